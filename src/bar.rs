@@ -1,15 +1,35 @@
 use crate::config::{BarConfig, BarPosition, MarginConfig, ModuleConfig};
+#[cfg(feature = "ipc")]
+use crate::gtk_helpers::IronbarGtkExt;
+use crate::image::ImageProvider;
+#[cfg(feature = "ipc")]
+use crate::ipc::BarSection;
+#[cfg(feature = "ipc")]
+use crate::ironvar::MODE_KEY;
+#[cfg(feature = "ipc")]
+use crate::modules::FORCE_HIDDEN_TAG;
 use crate::modules::{BarModuleFactory, ModuleInfo, ModuleLocation};
 use crate::popup::Popup;
+#[cfg(feature = "ipc")]
+use crate::write_lock;
 use crate::Ironbar;
-use color_eyre::Result;
+use color_eyre::{Report, Result};
 use glib::Propagation;
 use gtk::gdk::Monitor;
 use gtk::prelude::*;
-use gtk::{Application, ApplicationWindow, IconTheme, Orientation, Window, WindowType};
+use gtk::{
+    Application, ApplicationWindow, IconTheme, Orientation, PolicyType, ScrolledWindow, ShadowType,
+    Window, WindowType,
+};
+#[cfg(feature = "ipc")]
+use gtk::{EventBox, Revealer};
 use gtk_layer_shell::LayerShell;
+#[cfg(feature = "ipc")]
+use std::cell::Cell;
 use std::rc::Rc;
 use std::time::Duration;
+#[cfg(feature = "ipc")]
+use tokio::sync::broadcast::error::RecvError;
 use tracing::{debug, info};
 
 #[derive(Debug, Clone)]
@@ -58,6 +78,11 @@ impl Bar {
 
         window.set_widget_name(&name);
 
+        // use the output name as bars are matched to monitors by it
+        window
+            .style_context()
+            .add_class(&format!("monitor-{monitor_name}"));
+
         let position = config.position;
         let orientation = position.orientation();
 
@@ -80,7 +105,41 @@ impl Bar {
         let center = create_container("center", orientation);
         let end = create_container("end", orientation);
 
-        window.add(&content);
+        let max_size = if orientation == Orientation::Horizontal {
+            config.max_height
+        } else {
+            config.max_width
+        };
+
+        if let Some(max_size) = max_size {
+            let scrolled = ScrolledWindow::builder()
+                .shadow_type(ShadowType::None)
+                .build();
+
+            // only allow scrolling along the bar's thickness,
+            // and size to the content until the maximum is reached.
+            if orientation == Orientation::Horizontal {
+                scrolled.set_policy(PolicyType::Never, PolicyType::Automatic);
+                scrolled.set_propagate_natural_height(true);
+                scrolled.set_max_content_height(max_size);
+            } else {
+                scrolled.set_policy(PolicyType::Automatic, PolicyType::Never);
+                scrolled.set_propagate_natural_width(true);
+                scrolled.set_max_content_width(max_size);
+            }
+
+            scrolled.add(&content);
+            window.add(&scrolled);
+
+            // `show_all` cannot be used here as it would show hidden modules,
+            // so show the scrolled window and its generated viewport directly.
+            scrolled.show();
+            if let Some(viewport) = content.parent() {
+                viewport.show();
+            }
+        } else {
+            window.add(&content);
+        }
 
         window.connect_destroy_event(|_, _| {
             info!("Shutting down");
@@ -132,9 +191,13 @@ impl Bar {
             monitor,
         );
 
-        if let Some(autohide) = config.autohide {
+        let reveal_on_edge = config
+            .reveal_on_edge
+            .unwrap_or_else(|| config.autohide.is_some());
+
+        let hotspot_window = reveal_on_edge.then(|| {
             let hotspot_window = Window::new(WindowType::Toplevel);
-            Self::setup_autohide(&self.window, &hotspot_window, autohide);
+            Self::setup_hotspot(&self.window, &hotspot_window, self.position.orientation());
             self.setup_layer_shell(
                 &hotspot_window,
                 false,
@@ -147,10 +210,19 @@ impl Bar {
             if start_hidden {
                 hotspot_window.show();
             }
+
+            hotspot_window
+        });
+
+        if let Some(autohide) = config.autohide {
+            Self::setup_autohide(&self.window, autohide);
         }
 
         let load_result = self.load_modules(config, monitor, self.monitor_size)?;
 
+        #[cfg(feature = "ipc")]
+        self.setup_mode();
+
         self.show(!start_hidden);
 
         self.inner = Inner::Loaded {
@@ -211,38 +283,102 @@ impl Bar {
         );
     }
 
-    fn setup_autohide(window: &ApplicationWindow, hotspot_window: &Window, timeout: u64) {
+    /// Hides the bar after the cursor leaves it.
+    fn setup_autohide(window: &ApplicationWindow, timeout: u64) {
+        window.connect_leave_notify_event(move |win, _| {
+            let win = win.clone();
+
+            glib::timeout_add_local_once(Duration::from_millis(timeout), move || {
+                win.hide();
+            });
+            Propagation::Proceed
+        });
+    }
+
+    /// Sets up an invisible single-pixel window along the bar's edge,
+    /// which reveals the bar when the cursor enters it.
+    ///
+    /// The hotspot is shown whenever the bar is hidden,
+    /// whether by autohide or over IPC.
+    fn setup_hotspot(
+        window: &ApplicationWindow,
+        hotspot_window: &Window,
+        orientation: Orientation,
+    ) {
         hotspot_window.hide();
 
         hotspot_window.set_opacity(0.0);
         hotspot_window.set_decorated(false);
-        hotspot_window.set_size_request(0, 1);
+
+        if orientation == Orientation::Horizontal {
+            hotspot_window.set_size_request(0, 1);
+        } else {
+            hotspot_window.set_size_request(1, 0);
+        }
 
         {
             let hotspot_window = hotspot_window.clone();
-
-            window.connect_leave_notify_event(move |win, _| {
-                let win = win.clone();
-                let hotspot_window = hotspot_window.clone();
-
-                glib::timeout_add_local_once(Duration::from_millis(timeout), move || {
-                    win.hide();
+            window.connect_hide(move |win| {
+                if !win.in_destruction() {
                     hotspot_window.show();
-                });
-                Propagation::Proceed
+                }
             });
         }
 
         {
-            let win = window.clone();
-
-            hotspot_window.connect_enter_notify_event(move |hotspot_win, _| {
-                hotspot_win.hide();
-                win.show();
+            let hotspot_window = hotspot_window.clone();
+            window.connect_show(move |_| hotspot_window.hide());
+        }
 
-                Propagation::Proceed
-            });
+        {
+            let hotspot_window = hotspot_window.clone();
+            window.connect_destroy(move |_| hotspot_window.close());
         }
+
+        let win = window.clone();
+
+        hotspot_window.connect_enter_notify_event(move |_, _| {
+            win.show();
+            Propagation::Proceed
+        });
+    }
+
+    /// Adds a `mode-<name>` class to the bar window
+    /// while the mode ironvar is set, replacing the class of the previous mode.
+    ///
+    /// The current mode is applied immediately,
+    /// so this also covers bars created after the mode was set.
+    ///
+    /// The subscription is stopped when the window is destroyed,
+    /// so closed bars are not kept alive across reloads.
+    #[cfg(feature = "ipc")]
+    fn setup_mode(&self) {
+        let mut rx = write_lock!(Ironbar::variable_manager()).subscribe(MODE_KEY.into());
+
+        let window = self.window.clone();
+        let mut current_class = None;
+
+        let handle = glib::spawn_future_local(async move {
+            loop {
+                let mode = match rx.recv().await {
+                    Ok(mode) => mode,
+                    Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => break,
+                };
+
+                if let Some(class) = current_class.take() {
+                    window.style_context().remove_class(&class);
+                }
+
+                if let Some(mode) = mode.filter(|mode| !mode.is_empty()) {
+                    let class = format!("mode-{mode}");
+                    window.style_context().add_class(&class);
+                    current_class = Some(class);
+                }
+            }
+        });
+
+        self.window.connect_destroy(move |_| handle.abort());
     }
 
     /// Loads the configured modules onto a bar.
@@ -257,6 +393,10 @@ impl Bar {
             icon_theme.set_custom_theme(Some(theme));
         }
 
+        // cached icons are keyed by their resolved file,
+        // so only need clearing if the theme's files change on disk.
+        icon_theme.connect_changed(|_| ImageProvider::clear_cache());
+
         let app = &self.window.application().expect("to exist");
 
         macro_rules! info {
@@ -278,6 +418,7 @@ impl Bar {
             &info!(ModuleLocation::Left),
             output_size,
             config.popup_gap,
+            config.popup_layer,
         );
         let popup = Rc::new(popup);
 
@@ -356,6 +497,111 @@ impl Bar {
             self.window.set_exclusive_zone(0);
         }
     }
+
+    /// Finds the container of the module with the configured `widget_name`.
+    ///
+    /// Modules are wrapped in an event box and revealer (see `wrap_widget`),
+    /// so this returns the outer event box.
+    #[cfg(feature = "ipc")]
+    fn find_module(&self, widget_name: &str) -> Option<EventBox> {
+        [&self.start, &self.center, &self.end]
+            .into_iter()
+            .flat_map(ContainerExt::children)
+            .filter_map(|container| container.downcast::<EventBox>().ok())
+            .find(|container| {
+                module_revealer(container)
+                    .and_then(|revealer| revealer.child())
+                    .is_some_and(|widget| widget.widget_name().as_str() == widget_name)
+            })
+    }
+
+    /// Gets whether the module with the configured `widget_name` is currently revealed.
+    #[cfg(feature = "ipc")]
+    pub fn module_visible(&self, widget_name: &str) -> Result<bool> {
+        self.find_module(widget_name)
+            .and_then(|container| module_revealer(&container))
+            .map(|revealer| revealer.reveals_child())
+            .ok_or_else(|| Report::msg("Invalid module name"))
+    }
+
+    /// Shows or hides the module with the configured `widget_name`,
+    /// using its configured transition.
+    #[cfg(feature = "ipc")]
+    pub fn set_module_visible(&self, widget_name: &str, visible: bool) -> Result<()> {
+        let container = self
+            .find_module(widget_name)
+            .ok_or_else(|| Report::msg("Invalid module name"))?;
+
+        let revealer =
+            module_revealer(&container).ok_or_else(|| Report::msg("Invalid module name"))?;
+
+        // `show_if` hides the container once its revealer closes
+        if visible {
+            container.show_all();
+        }
+
+        if let Some(force_hidden) = container.get_tag::<Rc<Cell<bool>>>(FORCE_HIDDEN_TAG) {
+            force_hidden.set(!visible);
+        }
+
+        revealer.set_reveal_child(visible);
+
+        Ok(())
+    }
+
+    /// Moves the module with the configured `widget_name` into `section`.
+    /// If `position` is not set, the module is added to the end of the section.
+    #[cfg(feature = "ipc")]
+    pub fn move_module(
+        &self,
+        widget_name: &str,
+        section: BarSection,
+        position: Option<i32>,
+    ) -> Result<()> {
+        let container = self
+            .find_module(widget_name)
+            .ok_or_else(|| Report::msg("Invalid module name"))?;
+
+        let target = match section {
+            BarSection::Start => &self.start,
+            BarSection::Center => &self.center,
+            BarSection::End => &self.end,
+        };
+
+        // sections without any configured modules are not added to the bar
+        if target.parent().is_none() {
+            match section {
+                BarSection::Start => self.content.add(target),
+                BarSection::Center => self.content.set_center_widget(Some(target)),
+                BarSection::End => self.content.pack_end(target, false, true, 0),
+            }
+
+            target.show();
+        }
+
+        if container.parent().as_ref() != Some(target.upcast_ref()) {
+            if let Some(parent) = container
+                .parent()
+                .and_then(|parent| parent.downcast::<gtk::Container>().ok())
+            {
+                parent.remove(&container);
+            }
+
+            target.add(&container);
+        }
+
+        target.reorder_child(&container, position.unwrap_or(-1));
+
+        Ok(())
+    }
+}
+
+/// Gets the revealer inside a `wrap_widget` container.
+#[cfg(feature = "ipc")]
+fn module_revealer(container: &EventBox) -> Option<Revealer> {
+    container
+        .child()
+        .and_then(|child| child.downcast::<Revealer>().ok())
 }
 
 /// Creates a `gtk::Box` container to place widgets inside.
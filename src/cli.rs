@@ -16,6 +16,12 @@ pub struct Args {
     #[arg(long("print-schema"))]
     pub print_schema: bool,
 
+    /// Reads the config file, upgrades any options
+    /// which have changed since it was written,
+    /// and prints the result to `stdout` as JSON.
+    #[arg(long("migrate-config"))]
+    pub migrate_config: bool,
+
     /// Print debug information to stderr
     /// TODO: Make bar follow this too
     #[arg(long)]
@@ -46,12 +52,23 @@ pub fn handle_response(response: Response, format: Format) {
             Response::Ok => println!("ok"),
             Response::OkValue { value } => println!("{value}"),
             Response::Multi { values } => println!("{}", values.join("\n")),
+            Response::OkMap { values } => {
+                for (key, value) in values {
+                    println!("{key}={value}");
+                }
+            }
             Response::Err { message } => eprintln!("error\n{}", message.unwrap_or_default()),
         },
-        Format::Json => println!(
-            "{}",
-            serde_json::to_string(&response).expect("to be valid json")
-        ),
+        Format::Json => match response {
+            Response::OkMap { values } => println!(
+                "{}",
+                serde_json::to_string_pretty(&values).expect("to be valid json")
+            ),
+            response => println!(
+                "{}",
+                serde_json::to_string(&response).expect("to be valid json")
+            ),
+        },
     }
 
     if is_err {
@@ -0,0 +1,149 @@
+use crate::clients::LazyClient;
+use crate::{arc_mut, lock, register_client, send, spawn};
+use color_eyre::{Report, Result};
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio::time::sleep;
+use tracing::{debug, error};
+
+const BACKLIGHT_DIR: &str = "/sys/class/backlight";
+
+/// How often to check devices for brightness changes.
+/// The kernel does not reliably emit change events for sysfs backlight files.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+#[zbus::dbus_proxy(
+    interface = "org.freedesktop.login1.Session",
+    default_service = "org.freedesktop.login1",
+    default_path = "/org/freedesktop/login1/session/auto"
+)]
+trait Session {
+    /// SetBrightness method
+    fn set_brightness(&self, subsystem: &str, name: &str, brightness: u32) -> zbus::Result<()>;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Device {
+    pub name: String,
+    pub brightness: u32,
+    pub max_brightness: u32,
+}
+
+impl Device {
+    /// Gets the brightness as a percentage of the maximum.
+    pub fn percentage(&self) -> f64 {
+        if self.max_brightness == 0 {
+            0.0
+        } else {
+            f64::from(self.brightness) / f64::from(self.max_brightness) * 100.0
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Client {
+    session: SessionProxy<'static>,
+    devices: Arc<Mutex<Vec<Device>>>,
+
+    tx: broadcast::Sender<Device>,
+    _rx: broadcast::Receiver<Device>,
+}
+
+impl Client {
+    pub async fn new() -> Result<Self> {
+        let devices = read_devices()?;
+        if devices.is_empty() {
+            return Err(Report::msg("No backlight devices found"));
+        }
+
+        let dbus = Box::pin(zbus::Connection::system()).await?;
+        let session = SessionProxy::new(&dbus).await?;
+
+        let devices = arc_mut!(devices);
+        let (tx, rx) = broadcast::channel(16);
+
+        {
+            let devices = devices.clone();
+            let tx = tx.clone();
+
+            spawn(async move {
+                loop {
+                    sleep(POLL_INTERVAL).await;
+
+                    let new_devices = match read_devices() {
+                        Ok(devices) => devices,
+                        Err(err) => {
+                            error!("{err:?}");
+                            continue;
+                        }
+                    };
+
+                    let mut devices = lock!(devices);
+                    for device in &new_devices {
+                        if !devices.contains(device) {
+                            debug!("Backlight device changed: {device:?}");
+                            send!(tx, device.clone());
+                        }
+                    }
+
+                    *devices = new_devices;
+                }
+            });
+        }
+
+        Ok(Self {
+            session,
+            devices,
+            tx,
+            _rx: rx,
+        })
+    }
+
+    /// Gets the current state of all devices.
+    pub fn devices(&self) -> Vec<Device> {
+        lock!(self.devices).clone()
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<Device> {
+        self.tx.subscribe()
+    }
+
+    /// Sets the raw brightness value of a device.
+    ///
+    /// This is done through logind,
+    /// which does not require write access to the device.
+    pub async fn set_brightness(&self, device: &str, brightness: u32) -> Result<()> {
+        self.session
+            .set_brightness("backlight", device, brightness)
+            .await?;
+        Ok(())
+    }
+}
+
+/// Reads all backlight devices from sysfs, sorted by name.
+fn read_devices() -> Result<Vec<Device>> {
+    let read_value = |path: &Path, file: &str| -> Result<u32> {
+        Ok(fs::read_to_string(path.join(file))?.trim().parse()?)
+    };
+
+    let mut devices = fs::read_dir(BACKLIGHT_DIR)?
+        .flatten()
+        .map(|entry| {
+            let path = entry.path();
+
+            Ok(Device {
+                name: entry.file_name().to_string_lossy().to_string(),
+                brightness: read_value(&path, "brightness")?,
+                max_brightness: read_value(&path, "max_brightness")?,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    devices.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(devices)
+}
+
+register_client!(LazyClient<Client>, backlight);
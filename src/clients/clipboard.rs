@@ -11,6 +11,8 @@ pub enum ClipboardEvent {
     Add(ClipboardItem),
     Remove(usize),
     Activate(usize),
+    /// Item was moved to the position of the target item.
+    Move(usize, usize),
 }
 
 type EventSender = mpsc::Sender<ClipboardEvent>;
@@ -143,6 +145,19 @@ impl Client {
             try_send!(tx, ClipboardEvent::Remove(id));
         }
     }
+
+    /// Moves the item with `id` to the position of the item with `target_id`.
+    pub fn move_item(&self, id: usize, target_id: usize) {
+        if !lock!(self.cache).move_item(id, target_id) {
+            return;
+        }
+
+        let senders = lock!(self.senders);
+        let iter = senders.iter();
+        for (tx, _) in iter {
+            try_send!(tx, ClipboardEvent::Move(id, target_id));
+        }
+    }
 }
 
 /// Shared clipboard item cache.
@@ -209,6 +224,23 @@ impl ClipboardCache {
         }
     }
 
+    /// Moves the entry with key `id` to the position of the entry with key `target_id`,
+    /// shifting the entries in between.
+    ///
+    /// Returns whether the entry was moved.
+    fn move_item(&mut self, id: usize, target_id: usize) -> bool {
+        match (
+            self.cache.get_index_of(&id),
+            self.cache.get_index_of(&target_id),
+        ) {
+            (Some(from), Some(to)) if from != to => {
+                self.cache.move_index(from, to);
+                true
+            }
+            _ => false,
+        }
+    }
+
     /// Checks if an item with matching mime type and value
     /// already exists in the cache.
     fn contains(&self, item: &ClipboardItem) -> Option<usize> {
@@ -0,0 +1,126 @@
+use crate::{lock, register_client, spawn};
+use chrono::{DateTime, Local, Timelike};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio::time::sleep;
+use tracing::debug;
+
+/// How often a clock needs to tick
+/// for its output to stay accurate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Resolution {
+    Second,
+    Minute,
+}
+
+impl Resolution {
+    /// Checks the format string for any specifiers
+    /// which change more often than once a minute.
+    pub fn from_format(format: &str) -> Self {
+        let mut chars = format.chars();
+
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                continue;
+            }
+
+            // skip padding, precision and colon modifiers, eg `%-S`, `%.3f` or `%:z`
+            let specifier = chars.find(|c| !matches!(c, '-' | '_' | '.' | ':' | '#' | '0'..='9'));
+
+            // seconds, fractional seconds, unix timestamp,
+            // and composite formats which include seconds
+            if matches!(
+                specifier,
+                Some('S' | 'f' | 's' | 'T' | 'X' | 'r' | 'c' | '+')
+            ) {
+                return Self::Second;
+            }
+        }
+
+        Self::Minute
+    }
+
+    /// Gets the time remaining from `now` until the start of the next second or minute.
+    pub fn until_next_tick(self, now: DateTime<Local>) -> Duration {
+        // nanoseconds can exceed one second during a leap second
+        let nanos = Duration::from_nanos(u64::from(now.nanosecond().min(999_999_999)));
+
+        match self {
+            Self::Second => Duration::from_secs(1) - nanos,
+            Self::Minute => Duration::from_secs(u64::from(60 - now.second())) - nanos,
+        }
+    }
+}
+
+/// Shared wall-clock ticks for clock modules.
+///
+/// Every consumer at the same resolution is woken by a single timer,
+/// aligned to the start of each second or minute.
+#[derive(Debug)]
+pub struct Client {
+    ticks: Mutex<HashMap<Resolution, broadcast::Sender<DateTime<Local>>>>,
+}
+
+impl Client {
+    pub fn new() -> Self {
+        Self {
+            ticks: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Gets a receiver which is sent the current time on each tick.
+    /// The timer for `resolution` is started on its first subscription.
+    pub fn subscribe(&self, resolution: Resolution) -> broadcast::Receiver<DateTime<Local>> {
+        lock!(self.ticks)
+            .entry(resolution)
+            .or_insert_with(|| Self::spawn_timer(resolution))
+            .subscribe()
+    }
+
+    fn spawn_timer(resolution: Resolution) -> broadcast::Sender<DateTime<Local>> {
+        debug!("Starting clock timer with {resolution:?} resolution");
+
+        let (tx, _rx) = broadcast::channel(4);
+
+        {
+            let tx = tx.clone();
+
+            spawn(async move {
+                loop {
+                    sleep(resolution.until_next_tick(Local::now())).await;
+
+                    // no receivers is not an error, as consumers may come and go.
+                    tx.send(Local::now()).ok();
+                }
+            });
+        }
+
+        tx
+    }
+}
+
+register_client!(Client, clock);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolution_minutes() {
+        assert_eq!(
+            Resolution::from_format("%d/%m/%Y %H:%M"),
+            Resolution::Minute
+        );
+        assert_eq!(Resolution::from_format("%R %:z %%S"), Resolution::Minute);
+    }
+
+    #[test]
+    fn resolution_seconds() {
+        assert_eq!(Resolution::from_format("%H:%M:%S"), Resolution::Second);
+        assert_eq!(Resolution::from_format("%T"), Resolution::Second);
+        assert_eq!(Resolution::from_format("%-S"), Resolution::Second);
+        assert_eq!(Resolution::from_format("%.3f"), Resolution::Second);
+    }
+}
@@ -1,13 +1,24 @@
-use super::{Visibility, Workspace, WorkspaceClient, WorkspaceUpdate};
+use super::{
+    CompositorCapabilities, Visibility, WindowTarget, Workspace, WorkspaceClient, WorkspaceUpdate,
+};
 use crate::{arc_mut, lock, send, spawn_blocking};
-use color_eyre::Result;
-use hyprland::data::{Workspace as HWorkspace, Workspaces};
-use hyprland::dispatch::{Dispatch, DispatchType, WorkspaceIdentifierWithSpecial};
+use color_eyre::{Report, Result};
+use hyprland::data::{Clients, Workspace as HWorkspace, Workspaces};
+use hyprland::dispatch::{
+    Dispatch, DispatchType, WindowIdentifier, WorkspaceIdentifierWithSpecial,
+};
 use hyprland::event_listener::EventListener;
 use hyprland::prelude::*;
 use hyprland::shared::{HyprDataVec, WorkspaceType};
+use std::thread;
+use std::time::Duration;
 use tokio::sync::broadcast::{channel, Receiver, Sender};
-use tracing::{debug, error, info};
+use tracing::{debug, error, info, warn};
+
+/// Delay before the first reconnection attempt after the event socket drops.
+const RECONNECT_INITIAL_DELAY: Duration = Duration::from_millis(500);
+/// Maximum delay between reconnection attempts.
+const RECONNECT_MAX_DELAY: Duration = Duration::from_secs(30);
 
 #[derive(Debug)]
 pub struct Client {
@@ -33,183 +44,251 @@ impl Client {
 
         let tx = self.workspace_tx.clone();
 
-        spawn_blocking(move || {
-            let mut event_listener = EventListener::new();
+        spawn_blocking(move || loop {
+            match Self::run_event_listener(tx.clone()) {
+                Ok(()) => warn!("Hyprland event listener stopped"),
+                Err(err) => error!("{:?}", err.wrap_err("Hyprland event listener failed")),
+            }
 
-            // we need a lock to ensure events don't run at the same time
-            let lock = arc_mut!(());
+            send!(tx, WorkspaceUpdate::Disconnected);
 
-            // cache the active workspace since Hyprland doesn't give us the prev active
-            let active = Self::get_active_workspace().expect("Failed to get active workspace");
-            let active = arc_mut!(Some(active));
+            let workspaces = Self::wait_for_reconnect();
+            send!(tx, WorkspaceUpdate::Init(workspaces));
+        });
+    }
 
-            {
-                let tx = tx.clone();
-                let lock = lock.clone();
-                let active = active.clone();
+    /// Listens to the Hyprland event socket,
+    /// blocking until the connection is lost.
+    fn run_event_listener(tx: Sender<WorkspaceUpdate>) -> Result<()> {
+        let mut event_listener = EventListener::new();
 
-                event_listener.add_workspace_added_handler(move |workspace_type| {
-                    let _lock = lock!(lock);
-                    debug!("Added workspace: {workspace_type:?}");
+        // we need a lock to ensure events don't run at the same time
+        let lock = arc_mut!(());
 
-                    let workspace_name = get_workspace_name(workspace_type);
-                    let prev_workspace = lock!(active);
+        // cache the active workspace since Hyprland doesn't give us the prev active
+        let active = Self::get_active_workspace()?;
+        let active = arc_mut!(Some(active));
 
-                    let workspace = Self::get_workspace(&workspace_name, prev_workspace.as_ref());
+        {
+            let tx = tx.clone();
+            let lock = lock.clone();
+            let active = active.clone();
 
-                    if let Some(workspace) = workspace {
-                        send!(tx, WorkspaceUpdate::Add(workspace));
-                    }
-                });
-            }
+            event_listener.add_workspace_added_handler(move |workspace_type| {
+                let _lock = lock!(lock);
+                debug!("Added workspace: {workspace_type:?}");
 
-            {
-                let tx = tx.clone();
-                let lock = lock.clone();
-                let active = active.clone();
+                let workspace_name = get_workspace_name(workspace_type);
+                let prev_workspace = lock!(active);
 
-                event_listener.add_workspace_change_handler(move |workspace_type| {
-                    let _lock = lock!(lock);
+                let workspace = Self::get_workspace(&workspace_name, prev_workspace.as_ref());
 
-                    let mut prev_workspace = lock!(active);
+                if let Some(workspace) = workspace {
+                    send!(tx, WorkspaceUpdate::Add(workspace));
+                }
+            });
+        }
 
-                    debug!(
-                        "Received workspace change: {:?} -> {workspace_type:?}",
-                        prev_workspace.as_ref().map(|w| &w.id)
-                    );
+        {
+            let tx = tx.clone();
+            let lock = lock.clone();
+            let active = active.clone();
 
-                    let workspace_name = get_workspace_name(workspace_type);
-                    let workspace = Self::get_workspace(&workspace_name, prev_workspace.as_ref());
+            event_listener.add_workspace_change_handler(move |workspace_type| {
+                let _lock = lock!(lock);
 
-                    workspace.map_or_else(
-                        || {
-                            error!("Unable to locate workspace");
-                        },
-                        |workspace| {
-                            // there may be another type of update so dispatch that regardless of focus change
-                            if !workspace.visibility.is_focused() {
-                                Self::send_focus_change(&mut prev_workspace, workspace, &tx);
-                            }
-                        },
-                    );
-                });
-            }
+                let mut prev_workspace = lock!(active);
 
-            {
-                let tx = tx.clone();
-                let lock = lock.clone();
-                let active = active.clone();
+                debug!(
+                    "Received workspace change: {:?} -> {workspace_type:?}",
+                    prev_workspace.as_ref().map(|w| &w.id)
+                );
 
-                event_listener.add_active_monitor_change_handler(move |event_data| {
-                    let _lock = lock!(lock);
-                    let workspace_type = event_data.workspace;
+                let workspace_name = get_workspace_name(workspace_type);
+                let workspace = Self::get_workspace(&workspace_name, prev_workspace.as_ref());
 
-                    let mut prev_workspace = lock!(active);
+                workspace.map_or_else(
+                    || {
+                        error!("Unable to locate workspace");
+                    },
+                    |workspace| {
+                        // there may be another type of update so dispatch that regardless of focus change
+                        if !workspace.visibility.is_focused() {
+                            Self::send_focus_change(&mut prev_workspace, workspace, &tx);
+                        }
+                    },
+                );
+            });
+        }
 
-                    debug!(
-                        "Received active monitor change: {:?} -> {workspace_type:?}",
-                        prev_workspace.as_ref().map(|w| &w.name)
-                    );
+        {
+            let tx = tx.clone();
+            let lock = lock.clone();
+            let active = active.clone();
 
-                    let workspace_name = get_workspace_name(workspace_type);
-                    let workspace = Self::get_workspace(&workspace_name, prev_workspace.as_ref());
+            event_listener.add_active_monitor_change_handler(move |event_data| {
+                let _lock = lock!(lock);
+                let workspace_type = event_data.workspace;
 
-                    if let Some((false, workspace)) =
-                        workspace.map(|w| (w.visibility.is_focused(), w))
-                    {
-                        Self::send_focus_change(&mut prev_workspace, workspace, &tx);
-                    } else {
-                        error!("unable to locate workspace: {workspace_name}");
-                    }
-                });
-            }
+                let mut prev_workspace = lock!(active);
 
-            {
-                let tx = tx.clone();
-                let lock = lock.clone();
+                debug!(
+                    "Received active monitor change: {:?} -> {workspace_type:?}",
+                    prev_workspace.as_ref().map(|w| &w.name)
+                );
 
-                event_listener.add_workspace_moved_handler(move |event_data| {
-                    let _lock = lock!(lock);
-                    let workspace_type = event_data.workspace;
-                    debug!("Received workspace move: {workspace_type:?}");
+                let workspace_name = get_workspace_name(workspace_type);
+                let workspace = Self::get_workspace(&workspace_name, prev_workspace.as_ref());
 
-                    let mut prev_workspace = lock!(active);
+                if let Some((false, workspace)) = workspace.map(|w| (w.visibility.is_focused(), w))
+                {
+                    Self::send_focus_change(&mut prev_workspace, workspace, &tx);
+                } else {
+                    error!("unable to locate workspace: {workspace_name}");
+                }
+            });
+        }
 
-                    let workspace_name = get_workspace_name(workspace_type);
-                    let workspace = Self::get_workspace(&workspace_name, prev_workspace.as_ref());
+        {
+            let tx = tx.clone();
+            let lock = lock.clone();
 
-                    if let Some(workspace) = workspace {
-                        send!(tx, WorkspaceUpdate::Move(workspace.clone()));
+            event_listener.add_workspace_moved_handler(move |event_data| {
+                let _lock = lock!(lock);
+                let workspace_type = event_data.workspace;
+                debug!("Received workspace move: {workspace_type:?}");
 
-                        if !workspace.visibility.is_focused() {
-                            Self::send_focus_change(&mut prev_workspace, workspace, &tx);
-                        }
+                let mut prev_workspace = lock!(active);
+
+                let workspace_name = get_workspace_name(workspace_type);
+                let workspace = Self::get_workspace(&workspace_name, prev_workspace.as_ref());
+
+                if let Some(workspace) = workspace {
+                    send!(tx, WorkspaceUpdate::Move(workspace.clone()));
+
+                    if !workspace.visibility.is_focused() {
+                        Self::send_focus_change(&mut prev_workspace, workspace, &tx);
                     }
-                });
-            }
+                }
+            });
+        }
 
-            {
-                let tx = tx.clone();
-                let lock = lock.clone();
+        {
+            let tx = tx.clone();
+            let lock = lock.clone();
+
+            event_listener.add_workspace_rename_handler(move |data| {
+                let _lock = lock!(lock);
+                debug!("Received workspace rename: {data:?}");
+
+                send!(
+                    tx,
+                    WorkspaceUpdate::Rename {
+                        id: data.workspace_id as i64,
+                        name: data.workspace_name
+                    }
+                );
+            });
+        }
 
-                event_listener.add_workspace_rename_handler(move |data| {
-                    let _lock = lock!(lock);
-                    debug!("Received workspace rename: {data:?}");
+        {
+            let tx = tx.clone();
+            let lock = lock.clone();
+
+            event_listener.add_workspace_destroy_handler(move |data| {
+                let _lock = lock!(lock);
+                debug!("Received workspace destroy: {data:?}");
+                send!(tx, WorkspaceUpdate::Remove(data.workspace_id as i64));
+            });
+        }
 
-                    send!(
-                        tx,
-                        WorkspaceUpdate::Rename {
-                            id: data.workspace_id as i64,
-                            name: data.workspace_name
-                        }
-                    );
-                });
-            }
+        {
+            let tx = tx.clone();
+            let lock = lock.clone();
+
+            event_listener.add_window_open_handler(move |_| {
+                let _lock = lock!(lock);
+                debug!("Received window open");
+                Self::send_window_counts(&tx);
+            });
+        }
 
-            {
-                let tx = tx.clone();
-                let lock = lock.clone();
+        {
+            let tx = tx.clone();
+            let lock = lock.clone();
+
+            event_listener.add_window_close_handler(move |_| {
+                let _lock = lock!(lock);
+                debug!("Received window close");
+                Self::send_window_counts(&tx);
+            });
+        }
 
-                event_listener.add_workspace_destroy_handler(move |data| {
-                    let _lock = lock!(lock);
-                    debug!("Received workspace destroy: {data:?}");
-                    send!(tx, WorkspaceUpdate::Remove(data.workspace_id as i64));
-                });
-            }
+        {
+            let tx = tx.clone();
+            let lock = lock.clone();
+
+            event_listener.add_window_moved_handler(move |_| {
+                let _lock = lock!(lock);
+                debug!("Received window move");
+                Self::send_window_counts(&tx);
+            });
+        }
 
-            {
-                event_listener.add_urgent_state_handler(move |address| {
-                    let _lock = lock!(lock);
-                    debug!("Received urgent state: {address:?}");
+        {
+            event_listener.add_urgent_state_handler(move |address| {
+                let _lock = lock!(lock);
+                debug!("Received urgent state: {address:?}");
+
+                let clients = match hyprland::data::Clients::get() {
+                    Ok(clients) => clients,
+                    Err(err) => {
+                        error!("Failed to get clients: {err}");
+                        return;
+                    }
+                };
+                clients.iter().find(|c| c.address == address).map_or_else(
+                    || {
+                        error!("Unable to locate client");
+                    },
+                    |c| {
+                        send!(
+                            tx,
+                            WorkspaceUpdate::Urgent {
+                                id: c.workspace.id as i64,
+                                urgent: true,
+                            }
+                        );
+                    },
+                );
+            });
+        }
 
-                    let clients = match hyprland::data::Clients::get() {
-                        Ok(clients) => clients,
-                        Err(err) => {
-                            error!("Failed to get clients: {err}");
-                            return;
-                        }
-                    };
-                    clients.iter().find(|c| c.address == address).map_or_else(
-                        || {
-                            error!("Unable to locate client");
-                        },
-                        |c| {
-                            send!(
-                                tx,
-                                WorkspaceUpdate::Urgent {
-                                    id: c.workspace.id as i64,
-                                    urgent: true,
-                                }
-                            );
-                        },
-                    );
-                });
-            }
+        event_listener.start_listener()?;
+        Ok(())
+    }
 
-            event_listener
-                .start_listener()
-                .expect("Failed to start listener");
-        });
+    /// Blocks until the Hyprland socket is available again,
+    /// retrying with an exponential backoff.
+    ///
+    /// Returns the current list of workspaces.
+    fn wait_for_reconnect() -> Vec<Workspace> {
+        let mut delay = RECONNECT_INITIAL_DELAY;
+
+        loop {
+            thread::sleep(delay);
+
+            match Self::get_workspaces() {
+                Ok(workspaces) => {
+                    info!("Reconnected to Hyprland");
+                    return workspaces;
+                }
+                Err(err) => {
+                    delay = (delay * 2).min(RECONNECT_MAX_DELAY);
+                    debug!("Failed to reconnect to Hyprland, retrying in {delay:?}: {err}");
+                }
+            }
+        }
     }
 
     /// Sends a `WorkspaceUpdate::Focus` event
@@ -238,6 +317,27 @@ impl Client {
         prev_workspace.replace(workspace);
     }
 
+    /// Sends a `WorkspaceUpdate::WindowCount` event for every workspace.
+    fn send_window_counts(tx: &Sender<WorkspaceUpdate>) {
+        let workspaces = match Workspaces::get() {
+            Ok(workspaces) => workspaces,
+            Err(err) => {
+                error!("Failed to get workspaces: {err}");
+                return;
+            }
+        };
+
+        for workspace in workspaces {
+            send!(
+                tx,
+                WorkspaceUpdate::WindowCount {
+                    id: workspace.id as i64,
+                    count: u32::from(workspace.windows),
+                }
+            );
+        }
+    }
+
     /// Gets a workspace by name from the server, given the active workspace if known.
     fn get_workspace(name: &str, active: Option<&Workspace>) -> Option<Workspace> {
         Workspaces::get()
@@ -256,6 +356,23 @@ impl Client {
             })
     }
 
+    /// Gets all workspaces from the server.
+    fn get_workspaces() -> Result<Vec<Workspace>> {
+        let active_id = HWorkspace::get_active().ok().map(|active| active.name);
+        let is_visible = create_is_visible();
+
+        let workspaces = Workspaces::get()?
+            .into_iter()
+            .map(|w| {
+                let vis = Visibility::from((&w, active_id.as_deref(), &is_visible));
+
+                Workspace::from((vis, w))
+            })
+            .collect();
+
+        Ok(workspaces)
+    }
+
     /// Gets the active workspace from the server.
     fn get_active_workspace() -> Result<Workspace> {
         let w = HWorkspace::get_active().map(|w| Workspace::from((Visibility::focused(), w)))?;
@@ -264,6 +381,13 @@ impl Client {
 }
 
 impl WorkspaceClient for Client {
+    fn capabilities(&self) -> CompositorCapabilities {
+        CompositorCapabilities::FOCUS_WORKSPACE
+            | CompositorCapabilities::MOVE_WINDOW
+            | CompositorCapabilities::FOCUS_WINDOW_WORKSPACE
+            | CompositorCapabilities::FOCUS_LAST_WINDOW
+    }
+
     fn focus(&self, id: String) -> Result<()> {
         let identifier = id.parse::<i32>().map_or_else(
             |_| WorkspaceIdentifierWithSpecial::Name(&id),
@@ -274,25 +398,64 @@ impl WorkspaceClient for Client {
         Ok(())
     }
 
+    fn move_window_to(&self, window: &WindowTarget, workspace: String) -> Result<()> {
+        let client = find_window(window)?;
+
+        if client.workspace.name == workspace {
+            debug!(
+                "Window '{}' is already on workspace '{workspace}'",
+                window.title
+            );
+            return Ok(());
+        }
+
+        let identifier = workspace.parse::<i32>().map_or_else(
+            |_| WorkspaceIdentifierWithSpecial::Name(&workspace),
+            WorkspaceIdentifierWithSpecial::Id,
+        );
+
+        Dispatch::call(DispatchType::MoveToWorkspaceSilent(
+            identifier,
+            Some(WindowIdentifier::Address(client.address)),
+        ))?;
+        Ok(())
+    }
+
+    fn focus_window_workspace(&self, window: &WindowTarget) -> Result<()> {
+        let client = find_window(window)?;
+
+        if HWorkspace::get_active()?.id != client.workspace.id {
+            Dispatch::call(DispatchType::Workspace(WorkspaceIdentifierWithSpecial::Id(
+                client.workspace.id,
+            )))?;
+        }
+
+        Ok(())
+    }
+
+    fn focus_last_window(&self, workspace: String) -> Result<()> {
+        // a lower focus history ID means the window was focused more recently
+        let client = Clients::get()?
+            .into_iter()
+            .filter(|client| client.workspace.name == workspace)
+            .min_by_key(|client| client.focus_history_id);
+
+        if let Some(client) = client {
+            Dispatch::call(DispatchType::FocusWindow(WindowIdentifier::Address(
+                client.address,
+            )))?;
+        }
+
+        Ok(())
+    }
+
     fn subscribe_workspace_change(&self) -> Receiver<WorkspaceUpdate> {
         let rx = self.workspace_tx.subscribe();
 
         {
             let tx = self.workspace_tx.clone();
 
-            let active_id = HWorkspace::get_active().ok().map(|active| active.name);
-            let is_visible = create_is_visible();
-
-            let workspaces = Workspaces::get()
-                .expect("Failed to get workspaces")
-                .into_iter()
-                .map(|w| {
-                    let vis = Visibility::from((&w, active_id.as_deref(), &is_visible));
-
-                    Workspace::from((vis, w))
-                })
-                .collect();
-
+            let workspaces = Self::get_workspaces().expect("Failed to get workspaces");
             send!(tx, WorkspaceUpdate::Init(workspaces));
         }
 
@@ -300,6 +463,14 @@ impl WorkspaceClient for Client {
     }
 }
 
+/// Finds the Hyprland client for the window.
+fn find_window(window: &WindowTarget) -> Result<hyprland::data::Client> {
+    Clients::get()?
+        .into_iter()
+        .find(|client| client.class == window.app_id && client.title == window.title)
+        .ok_or_else(|| Report::msg(format!("Could not find window '{}'", window.title)))
+}
+
 fn get_workspace_name(name: WorkspaceType) -> String {
     match name {
         WorkspaceType::Regular(name) => name,
@@ -324,6 +495,7 @@ impl From<(Visibility, HWorkspace)> for Workspace {
             name: workspace.name,
             monitor: workspace.monitor,
             visibility,
+            windows: Some(u32::from(workspace.windows)),
         }
     }
 }
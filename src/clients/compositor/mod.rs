@@ -2,12 +2,15 @@ use crate::register_fallible_client;
 use cfg_if::cfg_if;
 use color_eyre::{Help, Report, Result};
 use std::fmt::{Debug, Display, Formatter};
+use std::ops::BitOr;
 use std::sync::Arc;
 use tokio::sync::broadcast;
 use tracing::debug;
 
 #[cfg(feature = "workspaces+hyprland")]
 pub mod hyprland;
+#[cfg(feature = "workspaces+river")]
+pub mod river;
 #[cfg(feature = "workspaces+sway")]
 pub mod sway;
 
@@ -16,6 +19,8 @@ pub enum Compositor {
     Sway,
     #[cfg(feature = "workspaces+hyprland")]
     Hyprland,
+    #[cfg(feature = "workspaces+river")]
+    River,
     Unsupported,
 }
 
@@ -29,6 +34,8 @@ impl Display for Compositor {
                 Self::Sway => "Sway",
                 #[cfg(feature = "workspaces+hyprland")]
                 Self::Hyprland => "Hyprland",
+                #[cfg(feature = "workspaces+river")]
+                Self::River => "River",
                 Self::Unsupported => "Unsupported",
             }
         )
@@ -49,11 +56,29 @@ impl Compositor {
                 if #[cfg(feature = "workspaces+hyprland")] { Self::Hyprland }
                 else { tracing::error!("Not compiled with Hyprland support"); Self::Unsupported }
             }
+        } else if Self::is_river() {
+            cfg_if! {
+                if #[cfg(feature = "workspaces+river")] { Self::River }
+                else { tracing::error!("Not compiled with River support"); Self::Unsupported }
+            }
         } else {
             Self::Unsupported
         }
     }
 
+    /// River does not set its own env var,
+    /// so this relies on the desktop set by the session.
+    fn is_river() -> bool {
+        ["XDG_CURRENT_DESKTOP", "XDG_SESSION_DESKTOP"]
+            .into_iter()
+            .filter_map(|key| std::env::var(key).ok())
+            .any(|desktop| {
+                desktop
+                    .split(':')
+                    .any(|name| name.eq_ignore_ascii_case("river"))
+            })
+    }
+
     /// Creates a new instance of
     /// the workspace client for the current compositor.
     pub fn create_workspace_client(
@@ -68,8 +93,11 @@ impl Compositor {
                 .map(|client| client as Arc<dyn WorkspaceClient + Send + Sync>),
             #[cfg(feature = "workspaces+hyprland")]
             Self::Hyprland => Ok(Arc::new(hyprland::Client::new())),
+            #[cfg(feature = "workspaces+river")]
+            Self::River => river::Client::new()
+                .map(|client| Arc::new(client) as Arc<dyn WorkspaceClient + Send + Sync>),
             Self::Unsupported => Err(Report::msg("Unsupported compositor")
-                .note("Currently workspaces are only supported by Sway and Hyprland")),
+                .note("Currently workspaces are only supported by Sway, Hyprland and River")),
         }
     }
 }
@@ -84,6 +112,9 @@ pub struct Workspace {
     pub monitor: String,
     /// How visible the workspace is
     pub visibility: Visibility,
+    /// Number of windows on the workspace,
+    /// if reported by the compositor.
+    pub windows: Option<u32>,
 }
 
 /// Indicates workspace visibility.
@@ -137,6 +168,17 @@ pub enum WorkspaceUpdate {
         urgent: bool,
     },
 
+    /// The number of windows on a workspace changed.
+    /// This is sent for every workspace whenever a window opens, closes or moves.
+    WindowCount {
+        id: i64,
+        count: u32,
+    },
+
+    /// The connection to the compositor was lost.
+    /// A fresh `Init` is sent once the client reconnects.
+    Disconnected,
+
     /// An update was triggered by the compositor but this was not mapped by Ironbar.
     ///
     /// This is purely used for ergonomics within the compositor clients
@@ -144,10 +186,84 @@ pub enum WorkspaceUpdate {
     Unknown,
 }
 
+/// Describes a window to act on.
+///
+/// Ironbar's window IDs are not shared with the compositor,
+/// so windows are matched using the properties
+/// exposed over the foreign toplevel protocol.
+#[derive(Debug, Clone)]
+pub struct WindowTarget {
+    /// The window's app ID, or class for XWayland windows.
+    pub app_id: String,
+    /// The window's title.
+    pub title: String,
+}
+
+/// Set of optional features supported by a compositor client.
+///
+/// Modules should check these before rendering controls
+/// which depend on them, rather than relying on requests failing.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CompositorCapabilities(u8);
+
+impl CompositorCapabilities {
+    /// Workspaces can be focused by name.
+    pub const FOCUS_WORKSPACE: Self = Self(1);
+    /// Windows can be moved between workspaces.
+    pub const MOVE_WINDOW: Self = Self(1 << 1);
+    /// The workspace containing a window can be focused.
+    pub const FOCUS_WINDOW_WORKSPACE: Self = Self(1 << 2);
+    /// The most recently focused window on a workspace can be focused.
+    pub const FOCUS_LAST_WINDOW: Self = Self(1 << 3);
+
+    /// Checks whether all capabilities in `other` are supported.
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl BitOr for CompositorCapabilities {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self::Output {
+        Self(self.0 | rhs.0)
+    }
+}
+
 pub trait WorkspaceClient: Debug + Send + Sync {
+    /// Gets the set of optional features supported by this client.
+    fn capabilities(&self) -> CompositorCapabilities;
+
     /// Requests the workspace with this name is focused.
     fn focus(&self, name: String) -> Result<()>;
 
+    /// Requests the workspace with this name on `output` is focused.
+    ///
+    /// Only compositors where each output has its own workspaces
+    /// with the same names need to implement this.
+    /// Others focus the workspace by name alone.
+    fn focus_on_output(&self, name: String, _output: &str) -> Result<()> {
+        self.focus(name)
+    }
+
+    /// Requests the window is moved to the workspace with this name.
+    ///
+    /// Errors if the window cannot be found.
+    /// If the window is already on the workspace, this is a no-op.
+    fn move_window_to(&self, window: &WindowTarget, workspace: String) -> Result<()>;
+
+    /// Requests the workspace containing the window is focused.
+    ///
+    /// Errors if the window cannot be found.
+    /// If the workspace is already focused, this is a no-op.
+    fn focus_window_workspace(&self, window: &WindowTarget) -> Result<()>;
+
+    /// Requests the most recently focused window
+    /// on the workspace with this name is focused.
+    ///
+    /// If the workspace has no windows, this is a no-op.
+    fn focus_last_window(&self, workspace: String) -> Result<()>;
+
     /// Creates a new to workspace event receiver.
     fn subscribe_workspace_change(&self) -> broadcast::Receiver<WorkspaceUpdate>;
 }
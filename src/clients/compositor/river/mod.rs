@@ -0,0 +1,473 @@
+mod protocol;
+
+use super::{
+    CompositorCapabilities, Visibility, WindowTarget, Workspace, WorkspaceClient, WorkspaceUpdate,
+};
+use crate::{arc_mut, lock, send, spawn_blocking};
+use color_eyre::{Report, Result};
+use protocol::control::zriver_command_callback_v1::{self, ZriverCommandCallbackV1};
+use protocol::control::zriver_control_v1::ZriverControlV1;
+use protocol::status::zriver_output_status_v1::{self, ZriverOutputStatusV1};
+use protocol::status::zriver_status_manager_v1::ZriverStatusManagerV1;
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast::{channel, Receiver, Sender};
+use tracing::{debug, error, info, warn};
+use wayland_client::globals::{registry_queue_init, GlobalListContents};
+use wayland_client::protocol::wl_output::{self, WlOutput};
+use wayland_client::protocol::wl_registry::{self, WlRegistry};
+use wayland_client::protocol::wl_seat::WlSeat;
+use wayland_client::{Connection, Dispatch, Proxy, QueueHandle};
+
+/// River supports 32 tags, one per bit.
+const NUM_TAGS: u32 = 32;
+
+#[derive(Debug)]
+pub struct Client {
+    connection: Connection,
+    queue_handle: QueueHandle<State>,
+    control: ZriverControlV1,
+    seat: WlSeat,
+
+    workspace_tx: Sender<WorkspaceUpdate>,
+    _workspace_rx: Receiver<WorkspaceUpdate>,
+
+    /// Snapshot of all active tags across all outputs,
+    /// used to send the initial state to new subscribers.
+    workspaces: Arc<Mutex<Vec<Workspace>>>,
+}
+
+impl Client {
+    pub(crate) fn new() -> Result<Self> {
+        info!("Connecting to River");
+
+        let (workspace_tx, workspace_rx) = channel(32);
+        let workspaces = arc_mut!(vec![]);
+
+        let connection = Connection::connect_to_env()?;
+        let (globals, mut event_queue) = registry_queue_init::<State>(&connection)?;
+        let queue_handle = event_queue.handle();
+
+        let status_manager: ZriverStatusManagerV1 = globals.bind(&queue_handle, 1..=4, ())?;
+        let control: ZriverControlV1 = globals.bind(&queue_handle, 1..=1, ())?;
+        let seat: WlSeat = globals.bind(&queue_handle, 1..=1, ())?;
+
+        let mut state = State {
+            status_manager,
+            outputs: vec![],
+            tx: workspace_tx.clone(),
+            workspaces: workspaces.clone(),
+        };
+
+        let output_globals = globals.contents().with_list(|list| {
+            list.iter()
+                .filter(|global| global.interface == WlOutput::interface().name)
+                .map(|global| (global.name, global.version))
+                .collect::<Vec<_>>()
+        });
+
+        for (name, version) in output_globals {
+            state.add_output(globals.registry(), name, version, &queue_handle);
+        }
+
+        // receive output names and initial tag states
+        event_queue.roundtrip(&mut state)?;
+
+        spawn_blocking(move || loop {
+            if let Err(err) = event_queue.blocking_dispatch(&mut state) {
+                error!("{err:?}");
+                break;
+            }
+        });
+
+        Ok(Self {
+            connection,
+            queue_handle,
+            control,
+            seat,
+            workspace_tx,
+            _workspace_rx: workspace_rx,
+            workspaces,
+        })
+    }
+}
+
+impl Client {
+    /// Runs the commands to focus the tag with this name,
+    /// focusing `output` first if set.
+    fn focus_tag(&self, name: &str, output: Option<String>) -> Result<()> {
+        let tag = name
+            .parse::<u32>()
+            .ok()
+            .filter(|tag| (1..=NUM_TAGS).contains(tag))
+            .ok_or_else(|| Report::msg(format!("Invalid River tag: '{name}'")))?;
+
+        if let Some(output) = output {
+            self.control.add_argument("focus-output".to_string());
+            self.control.add_argument(output);
+            self.control.run_command(&self.seat, &self.queue_handle, ());
+        }
+
+        self.control.add_argument("set-focused-tags".to_string());
+        self.control.add_argument((1u32 << (tag - 1)).to_string());
+        self.control.run_command(&self.seat, &self.queue_handle, ());
+
+        self.connection.flush()?;
+        Ok(())
+    }
+}
+
+impl WorkspaceClient for Client {
+    fn capabilities(&self) -> CompositorCapabilities {
+        CompositorCapabilities::FOCUS_WORKSPACE
+    }
+
+    /// Focuses the tag with this name.
+    /// Tags are named from `1` to `32`.
+    ///
+    /// Every output has its own set of tags,
+    /// so the first output the tag is shown on is focused first.
+    /// If the tag is not active on any output,
+    /// it is focused on the seat's focused output.
+    fn focus(&self, name: String) -> Result<()> {
+        let output = lock!(self.workspaces)
+            .iter()
+            .find(|workspace| workspace.name == name && !workspace.monitor.is_empty())
+            .map(|workspace| workspace.monitor.clone());
+
+        self.focus_tag(&name, output)
+    }
+
+    /// Focuses `output`, then the tag with this name on it.
+    fn focus_on_output(&self, name: String, output: &str) -> Result<()> {
+        self.focus_tag(&name, Some(output.to_string()))
+    }
+
+    fn move_window_to(&self, _window: &WindowTarget, _workspace: String) -> Result<()> {
+        Err(Report::msg("Moving windows is not supported on River"))
+    }
+
+    fn focus_window_workspace(&self, _window: &WindowTarget) -> Result<()> {
+        Err(Report::msg(
+            "Focusing window tags is not supported on River",
+        ))
+    }
+
+    fn focus_last_window(&self, _workspace: String) -> Result<()> {
+        Err(Report::msg("Focusing windows is not supported on River"))
+    }
+
+    fn subscribe_workspace_change(&self) -> Receiver<WorkspaceUpdate> {
+        let rx = self.workspace_tx.subscribe();
+
+        let workspaces = lock!(self.workspaces).clone();
+        send!(self.workspace_tx, WorkspaceUpdate::Init(workspaces));
+
+        rx
+    }
+}
+
+/// Tag bitmasks for a single output.
+#[derive(Debug, Default, Clone, Copy)]
+struct Tags {
+    focused: u32,
+    occupied: u32,
+    urgent: u32,
+}
+
+impl Tags {
+    /// Tags which should be shown as a workspace.
+    const fn active(self) -> u32 {
+        self.focused | self.occupied | self.urgent
+    }
+}
+
+#[derive(Debug)]
+struct Output {
+    global_name: u32,
+    output: WlOutput,
+    status: ZriverOutputStatusV1,
+    name: String,
+    tags: Tags,
+}
+
+impl Output {
+    /// Gets a unique ID for a tag on this output.
+    fn tag_id(&self, tag: u32) -> i64 {
+        (i64::from(self.global_name) << 5) | i64::from(tag)
+    }
+
+    fn workspace(&self, tag: u32) -> Workspace {
+        let bit = 1 << tag;
+
+        // multiple tags can be focused at once,
+        // so all of them are treated as focused.
+        let visibility = if self.tags.focused & bit == bit {
+            Visibility::focused()
+        } else {
+            Visibility::Hidden
+        };
+
+        Workspace {
+            id: self.tag_id(tag),
+            name: (tag + 1).to_string(),
+            monitor: self.name.clone(),
+            visibility,
+            windows: None,
+        }
+    }
+
+    fn workspaces(&self) -> impl Iterator<Item = Workspace> + '_ {
+        let active = self.tags.active();
+
+        (0..NUM_TAGS)
+            .filter(move |tag| active & (1 << tag) != 0)
+            .map(|tag| self.workspace(tag))
+    }
+}
+
+#[derive(Debug)]
+struct State {
+    status_manager: ZriverStatusManagerV1,
+    outputs: Vec<Output>,
+
+    tx: Sender<WorkspaceUpdate>,
+    workspaces: Arc<Mutex<Vec<Workspace>>>,
+}
+
+impl State {
+    fn add_output(
+        &mut self,
+        registry: &WlRegistry,
+        name: u32,
+        version: u32,
+        qh: &QueueHandle<Self>,
+    ) {
+        debug!("Adding output {name}");
+
+        // output names are only available from v4
+        let output: WlOutput = registry.bind(name, version.min(4), qh, name);
+        let status = self
+            .status_manager
+            .get_river_output_status(&output, qh, name);
+
+        self.outputs.push(Output {
+            global_name: name,
+            output,
+            status,
+            name: String::new(),
+            tags: Tags::default(),
+        });
+    }
+
+    fn remove_output(&mut self, name: u32) {
+        let Some(index) = self.outputs.iter().position(|o| o.global_name == name) else {
+            return;
+        };
+
+        debug!("Removing output {name}");
+
+        let output = self.outputs.remove(index);
+        for workspace in output.workspaces() {
+            send!(self.tx, WorkspaceUpdate::Remove(workspace.id));
+        }
+
+        output.status.destroy();
+        if output.output.version() >= 3 {
+            output.output.release();
+        }
+
+        self.update_snapshot();
+    }
+
+    /// Updates the tags for an output,
+    /// sending workspace updates for any changes.
+    fn update_tags(&mut self, name: u32, f: impl FnOnce(&mut Tags)) {
+        let Some(output) = self.outputs.iter_mut().find(|o| o.global_name == name) else {
+            return;
+        };
+
+        let old = output.tags;
+        f(&mut output.tags);
+        let new = output.tags;
+
+        let output = &*output;
+        let is_set = |tags: u32, tag: u32| tags & (1 << tag) != 0;
+
+        for tag in (0..NUM_TAGS).filter(|&tag| is_set(new.active() & !old.active(), tag)) {
+            send!(self.tx, WorkspaceUpdate::Add(output.workspace(tag)));
+        }
+
+        let mut gained = (0..NUM_TAGS).filter(|&tag| is_set(new.focused & !old.focused, tag));
+        let mut lost = (0..NUM_TAGS).filter(|&tag| is_set(old.focused & !new.focused, tag));
+        let remaining = (0..NUM_TAGS).find(|&tag| is_set(new.focused & old.focused, tag));
+
+        loop {
+            match (gained.next(), lost.next()) {
+                (Some(gained_tag), lost_tag) => send!(
+                    self.tx,
+                    WorkspaceUpdate::Focus {
+                        old: lost_tag.map(|tag| output.workspace(tag)),
+                        new: output.workspace(gained_tag),
+                    }
+                ),
+                (None, Some(lost_tag)) => {
+                    // focus was removed without moving,
+                    // so point at a tag which is still focused.
+                    if let Some(remaining_tag) = remaining {
+                        send!(
+                            self.tx,
+                            WorkspaceUpdate::Focus {
+                                old: Some(output.workspace(lost_tag)),
+                                new: output.workspace(remaining_tag),
+                            }
+                        );
+                    }
+                }
+                (None, None) => break,
+            }
+        }
+
+        for tag in (0..NUM_TAGS).filter(|&tag| is_set(new.urgent ^ old.urgent, tag)) {
+            send!(
+                self.tx,
+                WorkspaceUpdate::Urgent {
+                    id: output.tag_id(tag),
+                    urgent: is_set(new.urgent, tag),
+                }
+            );
+        }
+
+        for tag in (0..NUM_TAGS).filter(|&tag| is_set(old.active() & !new.active(), tag)) {
+            send!(self.tx, WorkspaceUpdate::Remove(output.tag_id(tag)));
+        }
+
+        self.update_snapshot();
+    }
+
+    fn update_snapshot(&self) {
+        let workspaces = self.outputs.iter().flat_map(Output::workspaces).collect();
+        *lock!(self.workspaces) = workspaces;
+    }
+}
+
+impl Dispatch<WlRegistry, GlobalListContents> for State {
+    fn event(
+        state: &mut Self,
+        registry: &WlRegistry,
+        event: wl_registry::Event,
+        _data: &GlobalListContents,
+        _conn: &Connection,
+        qh: &QueueHandle<Self>,
+    ) {
+        match event {
+            wl_registry::Event::Global {
+                name,
+                interface,
+                version,
+            } if interface == WlOutput::interface().name => {
+                state.add_output(registry, name, version, qh);
+            }
+            wl_registry::Event::GlobalRemove { name } => state.remove_output(name),
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<WlOutput, u32> for State {
+    fn event(
+        state: &mut Self,
+        _output: &WlOutput,
+        event: wl_output::Event,
+        global_name: &u32,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        if let wl_output::Event::Name { name } = event {
+            if let Some(output) = state
+                .outputs
+                .iter_mut()
+                .find(|o| o.global_name == *global_name)
+            {
+                output.name = name;
+
+                // tags may have been sent before the name was known
+                for workspace in output.workspaces() {
+                    send!(state.tx, WorkspaceUpdate::Move(workspace));
+                }
+            }
+
+            state.update_snapshot();
+        }
+    }
+}
+
+impl Dispatch<ZriverOutputStatusV1, u32> for State {
+    fn event(
+        state: &mut Self,
+        _status: &ZriverOutputStatusV1,
+        event: zriver_output_status_v1::Event,
+        global_name: &u32,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        match event {
+            zriver_output_status_v1::Event::FocusedTags { tags } => {
+                state.update_tags(*global_name, |t| t.focused = tags);
+            }
+            zriver_output_status_v1::Event::ViewTags { tags } => {
+                // array of native-endian `u32` tag masks, one per view
+                let occupied = tags
+                    .chunks_exact(4)
+                    .map(|bytes| u32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+                    .fold(0, |acc, tags| acc | tags);
+
+                state.update_tags(*global_name, |t| t.occupied = occupied);
+            }
+            zriver_output_status_v1::Event::UrgentTags { tags } => {
+                state.update_tags(*global_name, |t| t.urgent = tags);
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<ZriverCommandCallbackV1, ()> for State {
+    fn event(
+        _state: &mut Self,
+        _callback: &ZriverCommandCallbackV1,
+        event: zriver_command_callback_v1::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        match event {
+            zriver_command_callback_v1::Event::Success { output } => {
+                debug!("River command succeeded: {output}");
+            }
+            zriver_command_callback_v1::Event::Failure { failure_message } => {
+                error!("River command failed: {failure_message}");
+            }
+            _ => warn!("Received unknown River command callback event"),
+        }
+    }
+}
+
+macro_rules! ignore_events {
+    ($($proxy:ty),+) => {
+        $(
+            impl Dispatch<$proxy, ()> for State {
+                fn event(
+                    _state: &mut Self,
+                    _proxy: &$proxy,
+                    _event: <$proxy as Proxy>::Event,
+                    _data: &(),
+                    _conn: &Connection,
+                    _qh: &QueueHandle<Self>,
+                ) {
+                }
+            }
+        )+
+    };
+}
+
+ignore_events!(ZriverStatusManagerV1, ZriverControlV1, WlSeat);
@@ -1,12 +1,103 @@
-use super::{Visibility, Workspace, WorkspaceClient, WorkspaceUpdate};
-use crate::{await_sync, send};
-use color_eyre::Result;
-use swayipc_async::{Node, WorkspaceChange, WorkspaceEvent};
+use super::{
+    CompositorCapabilities, Visibility, WindowTarget, Workspace, WorkspaceClient, WorkspaceUpdate,
+};
+use crate::{await_sync, send, spawn};
+use color_eyre::{Report, Result};
+use std::collections::HashMap;
+use swayipc_async::{Connection, Node, NodeType, WindowChange, WorkspaceChange, WorkspaceEvent};
 use tokio::sync::broadcast::{channel, Receiver};
+use tracing::{debug, error};
 
 use crate::clients::sway::Client;
 
+/// Finds the node for the window in the tree.
+///
+/// Returns the name of the workspace containing the window, and the window's node ID.
+fn find_window(tree: &Node, window: &WindowTarget) -> Result<(Option<String>, i64)> {
+    let is_target = |node: &Node| {
+        let class = node
+            .window_properties
+            .as_ref()
+            .and_then(|props| props.class.as_deref());
+
+        node.name.as_deref() == Some(window.title.as_str())
+            && (node.app_id.as_deref() == Some(window.app_id.as_str())
+                || class == Some(window.app_id.as_str()))
+    };
+
+    tree.iter()
+        .filter(|node| node.node_type == NodeType::Workspace)
+        .find_map(|ws| {
+            ws.find_as_ref(is_target)
+                .map(|node| (ws.name.clone(), node.id))
+        })
+        .ok_or_else(|| Report::msg(format!("Could not find window '{}'", window.title)))
+}
+
+/// Follows the focus stack down from the node
+/// to find its most recently focused window.
+///
+/// Returns the window's node ID.
+fn last_focused_window(node: &Node) -> Option<i64> {
+    let Some(&id) = node.focus.first() else {
+        return node.pid.map(|_| node.id);
+    };
+
+    node.nodes
+        .iter()
+        .chain(&node.floating_nodes)
+        .find(|child| child.id == id)
+        .and_then(last_focused_window)
+}
+
+/// Counts the windows inside a workspace node,
+/// including floating windows.
+fn count_windows(workspace: &Node) -> u32 {
+    workspace
+        .iter()
+        .filter(|node| {
+            matches!(node.node_type, NodeType::Con | NodeType::FloatingCon) && node.pid.is_some()
+        })
+        .count() as u32
+}
+
+/// Gets the number of windows on each workspace in the tree, by workspace ID.
+fn window_counts(tree: &Node) -> HashMap<i64, u32> {
+    tree.iter()
+        .filter(|node| node.node_type == NodeType::Workspace)
+        .map(|ws| (ws.id, count_windows(ws)))
+        .collect()
+}
+
+/// Gets the current list of workspaces, including their window counts.
+async fn get_workspaces(client: &mut Connection) -> Result<Vec<Workspace>> {
+    let workspaces = client.get_workspaces().await?;
+    let counts = client
+        .get_tree()
+        .await
+        .map(|tree| window_counts(&tree))
+        .unwrap_or_default();
+
+    let workspaces = workspaces
+        .into_iter()
+        .map(|workspace| {
+            let mut workspace = Workspace::from(workspace);
+            workspace.windows = counts.get(&workspace.id).copied();
+            workspace
+        })
+        .collect();
+
+    Ok(workspaces)
+}
+
 impl WorkspaceClient for Client {
+    fn capabilities(&self) -> CompositorCapabilities {
+        CompositorCapabilities::FOCUS_WORKSPACE
+            | CompositorCapabilities::MOVE_WINDOW
+            | CompositorCapabilities::FOCUS_WINDOW_WORKSPACE
+            | CompositorCapabilities::FOCUS_LAST_WINDOW
+    }
+
     fn focus(&self, id: String) -> Result<()> {
         await_sync(async move {
             let mut client = self.connection().lock().await;
@@ -15,28 +106,163 @@ impl WorkspaceClient for Client {
         Ok(())
     }
 
+    fn move_window_to(&self, window: &WindowTarget, workspace: String) -> Result<()> {
+        await_sync(async move {
+            let mut client = self.connection().lock().await;
+            let tree = client.get_tree().await?;
+
+            let (current_workspace, con_id) = find_window(&tree, window)?;
+
+            if current_workspace.as_deref() == Some(workspace.as_str()) {
+                debug!(
+                    "Window '{}' is already on workspace '{workspace}'",
+                    window.title
+                );
+                return Ok(());
+            }
+
+            client
+                .run_command(format!(
+                    "[con_id={con_id}] move container to workspace {workspace}"
+                ))
+                .await?;
+
+            Ok(())
+        })
+    }
+
+    fn focus_window_workspace(&self, window: &WindowTarget) -> Result<()> {
+        await_sync(async move {
+            let mut client = self.connection().lock().await;
+            let tree = client.get_tree().await?;
+
+            let Some(workspace) = find_window(&tree, window)?.0 else {
+                return Ok(());
+            };
+
+            let is_focused = client
+                .get_workspaces()
+                .await?
+                .into_iter()
+                .any(|ws| ws.focused && ws.name == workspace);
+
+            if !is_focused {
+                client.run_command(format!("workspace {workspace}")).await?;
+            }
+
+            Ok(())
+        })
+    }
+
+    fn focus_last_window(&self, workspace: String) -> Result<()> {
+        await_sync(async move {
+            let mut client = self.connection().lock().await;
+            let tree = client.get_tree().await?;
+
+            let con_id = tree
+                .iter()
+                .filter(|node| node.node_type == NodeType::Workspace)
+                .find(|ws| ws.name.as_deref() == Some(workspace.as_str()))
+                .and_then(last_focused_window);
+
+            if let Some(con_id) = con_id {
+                client
+                    .run_command(format!("[con_id={con_id}] focus"))
+                    .await?;
+            }
+
+            Ok(())
+        })
+    }
+
     fn subscribe_workspace_change(&self) -> Receiver<WorkspaceUpdate> {
         let (tx, rx) = channel(16);
 
-        let client = self.connection().clone();
+        // the initial state is sent once ready,
+        // rather than blocking the caller while the IPC requests complete.
+        let client = self.clone();
+
+        spawn(async move {
+            let workspaces = get_workspaces(&mut *client.connection().lock().await).await;
+
+            match workspaces {
+                Ok(workspaces) => send!(tx, WorkspaceUpdate::Init(workspaces)),
+                Err(err) => error!("Failed to get workspaces: {err:?}"),
+            }
+
+            // resync the full state after the IPC socket reconnects
+            {
+                let tx = tx.clone();
+                let connection = client.connection().clone();
+                let mut connected = client.subscribe_connected();
+
+                spawn(async move {
+                    while connected.changed().await.is_ok() {
+                        let is_connected = *connected.borrow_and_update();
+                        if !is_connected {
+                            send!(tx, WorkspaceUpdate::Disconnected);
+                            continue;
+                        }
+
+                        let workspaces = get_workspaces(&mut *connection.lock().await).await;
 
-        await_sync(async {
-            let mut client = client.lock().await;
-            let workspaces = client.get_workspaces().await.expect("to get workspaces");
+                        match workspaces {
+                            Ok(workspaces) => {
+                                send!(tx, WorkspaceUpdate::Init(workspaces));
+                            }
+                            Err(err) => error!("Failed to get workspaces: {err:?}"),
+                        }
+                    }
+                });
+            }
 
-            let event =
-                WorkspaceUpdate::Init(workspaces.into_iter().map(Workspace::from).collect());
+            {
+                let tx = tx.clone();
 
-            send!(tx, event);
+                let res = client
+                    .add_listener::<swayipc_async::WorkspaceEvent>(move |event| {
+                        let update = WorkspaceUpdate::from(event.clone());
+                        send!(tx, update);
+                    })
+                    .await;
 
-            drop(client);
+                if let Err(err) = res {
+                    error!("{:?}", err.wrap_err("Failed to add workspace listener"));
+                }
+            }
 
-            self.add_listener::<swayipc_async::WorkspaceEvent>(move |event| {
-                let update = WorkspaceUpdate::from(event.clone());
-                send!(tx, update);
-            })
-            .await
-            .expect("to add listener");
+            let connection = client.connection().clone();
+
+            let res = client
+                .add_listener::<swayipc_async::WindowEvent>(move |event| {
+                    if !matches!(
+                        event.change,
+                        WindowChange::New | WindowChange::Close | WindowChange::Move
+                    ) {
+                        return;
+                    }
+
+                    let tx = tx.clone();
+                    let connection = connection.clone();
+
+                    spawn(async move {
+                        let tree = connection.lock().await.get_tree().await;
+
+                        match tree {
+                            Ok(tree) => {
+                                for (id, count) in window_counts(&tree) {
+                                    send!(tx, WorkspaceUpdate::WindowCount { id, count });
+                                }
+                            }
+                            Err(err) => error!("Failed to get tree: {err:?}"),
+                        }
+                    });
+                })
+                .await;
+
+            if let Err(err) = res {
+                error!("{:?}", err.wrap_err("Failed to add window listener"));
+            }
         });
 
         rx
@@ -46,12 +272,14 @@ impl WorkspaceClient for Client {
 impl From<Node> for Workspace {
     fn from(node: Node) -> Self {
         let visibility = Visibility::from(&node);
+        let windows = count_windows(&node);
 
         Self {
             id: node.id,
             name: node.name.unwrap_or_default(),
             monitor: node.output.unwrap_or_default(),
             visibility,
+            windows: Some(windows),
         }
     }
 }
@@ -65,6 +293,7 @@ impl From<swayipc_async::Workspace> for Workspace {
             name: workspace.name,
             monitor: workspace.output,
             visibility,
+            windows: None,
         }
     }
 }
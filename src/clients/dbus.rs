@@ -0,0 +1,27 @@
+use crate::clients::ClientResult;
+use serde::Deserialize;
+use std::sync::Arc;
+use zbus::Connection;
+
+/// A D-Bus message bus.
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum Bus {
+    /// The system-wide bus.
+    System,
+    /// The current user's session bus.
+    #[default]
+    Session,
+}
+
+/// Opens a connection to the bus.
+/// A single connection is shared by all modules using the same bus.
+pub async fn connect(bus: Bus) -> ClientResult<Connection> {
+    let connection = match bus {
+        Bus::System => Connection::system().await?,
+        Bus::Session => Connection::session().await?,
+    };
+
+    Ok(Arc::new(connection))
+}
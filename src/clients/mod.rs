@@ -1,14 +1,23 @@
-use crate::await_sync;
+use crate::{await_sync, spawn};
 use color_eyre::Result;
 use std::collections::HashMap;
+use std::future::Future;
 use std::path::Path;
 use std::rc::Rc;
 use std::sync::Arc;
+use tokio::sync::watch;
+use tracing::{debug, error};
 
+#[cfg(feature = "backlight")]
+pub mod backlight;
 #[cfg(feature = "clipboard")]
 pub mod clipboard;
+#[cfg(feature = "clock")]
+pub mod clock;
 #[cfg(feature = "workspaces")]
 pub mod compositor;
+#[cfg(feature = "dbus")]
+pub mod dbus;
 #[cfg(feature = "keys")]
 pub mod libinput;
 #[cfg(feature = "cairo")]
@@ -21,6 +30,8 @@ pub mod networkmanager;
 pub mod sway;
 #[cfg(feature = "notifications")]
 pub mod swaync;
+#[cfg(feature = "sys_info")]
+pub mod sys_info;
 #[cfg(feature = "tray")]
 pub mod tray;
 #[cfg(feature = "upower")]
@@ -34,12 +45,18 @@ pub mod wayland;
 #[derive(Debug, Default)]
 pub struct Clients {
     wayland: Option<Arc<wayland::Client>>,
+    #[cfg(feature = "backlight")]
+    backlight: Option<Arc<LazyClient<backlight::Client>>>,
     #[cfg(feature = "workspaces")]
     workspaces: Option<Arc<dyn compositor::WorkspaceClient>>,
     #[cfg(feature = "sway")]
     sway: Option<Arc<sway::Client>>,
     #[cfg(feature = "clipboard")]
     clipboard: Option<Arc<clipboard::Client>>,
+    #[cfg(feature = "clock")]
+    clock: Option<Arc<clock::Client>>,
+    #[cfg(feature = "dbus")]
+    dbus: HashMap<dbus::Bus, Arc<LazyClient<zbus::Connection>>>,
     #[cfg(feature = "keys")]
     libinput: HashMap<Box<str>, Arc<libinput::Client>>,
     #[cfg(feature = "cairo")]
@@ -49,13 +66,15 @@ pub struct Clients {
     #[cfg(feature = "network_manager")]
     network_manager: Option<Arc<networkmanager::Client>>,
     #[cfg(feature = "notifications")]
-    notifications: Option<Arc<swaync::Client>>,
+    notifications: Option<Arc<LazyClient<swaync::Client>>>,
+    #[cfg(feature = "sys_info")]
+    sys_info: Option<Arc<sys_info::Client>>,
     #[cfg(feature = "tray")]
-    tray: Option<Arc<tray::Client>>,
+    tray: Option<Arc<LazyClient<tray::Client>>>,
     #[cfg(feature = "upower")]
-    upower: Option<Arc<zbus::fdo::PropertiesProxy<'static>>>,
+    upower: Option<Arc<LazyClient<zbus::fdo::PropertiesProxy<'static>>>>,
     #[cfg(feature = "volume")]
-    volume: Option<Arc<volume::Client>>,
+    volume: Option<Arc<dyn volume::VolumeClient>>,
 }
 
 pub type ClientResult<T> = Result<Arc<T>>;
@@ -71,6 +90,17 @@ impl Clients {
             .clone()
     }
 
+    #[cfg(feature = "backlight")]
+    pub fn backlight(&mut self) -> Arc<LazyClient<backlight::Client>> {
+        self.backlight
+            .get_or_insert_with(|| {
+                LazyClient::spawn("backlight", async {
+                    Ok(Arc::new(backlight::Client::new().await?))
+                })
+            })
+            .clone()
+    }
+
     #[cfg(feature = "clipboard")]
     pub fn clipboard(&mut self) -> Arc<clipboard::Client> {
         let wayland = self.wayland();
@@ -80,6 +110,21 @@ impl Clients {
             .clone()
     }
 
+    #[cfg(feature = "clock")]
+    pub fn clock(&mut self) -> Arc<clock::Client> {
+        self.clock
+            .get_or_insert_with(|| Arc::new(clock::Client::new()))
+            .clone()
+    }
+
+    #[cfg(feature = "dbus")]
+    pub fn dbus(&mut self, bus: dbus::Bus) -> Arc<LazyClient<zbus::Connection>> {
+        self.dbus
+            .entry(bus)
+            .or_insert_with(|| LazyClient::spawn("dbus", dbus::connect(bus)))
+            .clone()
+    }
+
     #[cfg(feature = "workspaces")]
     pub fn workspaces(&mut self) -> ClientResult<dyn compositor::WorkspaceClient> {
         let client = if let Some(workspaces) = &self.workspaces {
@@ -142,54 +187,96 @@ impl Clients {
     }
 
     #[cfg(feature = "notifications")]
-    pub fn notifications(&mut self) -> ClientResult<swaync::Client> {
-        let client = if let Some(client) = &self.notifications {
-            client.clone()
-        } else {
-            let client = await_sync(async { swaync::Client::new().await })?;
-            let client = Arc::new(client);
-            self.notifications.replace(client.clone());
-            client
-        };
+    pub fn notifications(&mut self) -> Arc<LazyClient<swaync::Client>> {
+        self.notifications
+            .get_or_insert_with(|| {
+                LazyClient::spawn("notifications", async {
+                    Ok(Arc::new(swaync::Client::new().await?))
+                })
+            })
+            .clone()
+    }
 
-        Ok(client)
+    #[cfg(feature = "sys_info")]
+    pub fn sys_info(&mut self) -> Arc<sys_info::Client> {
+        self.sys_info
+            .get_or_insert_with(|| Arc::new(sys_info::Client::new()))
+            .clone()
     }
 
     #[cfg(feature = "tray")]
-    pub fn tray(&mut self) -> ClientResult<tray::Client> {
-        let client = if let Some(client) = &self.tray {
-            client.clone()
-        } else {
-            let client = await_sync(async { tray::Client::new().await })?;
-            let client = Arc::new(client);
-            self.tray.replace(client.clone());
-            client
-        };
-
-        Ok(client)
+    pub fn tray(&mut self) -> Arc<LazyClient<tray::Client>> {
+        self.tray
+            .get_or_insert_with(|| {
+                LazyClient::spawn("tray", async { Ok(Arc::new(tray::Client::new().await?)) })
+            })
+            .clone()
     }
 
     #[cfg(feature = "upower")]
-    pub fn upower(&mut self) -> ClientResult<zbus::fdo::PropertiesProxy<'static>> {
-        let client = if let Some(client) = &self.upower {
-            client.clone()
-        } else {
-            let client = await_sync(async { upower::create_display_proxy().await })?;
-            self.upower.replace(client.clone());
-            client
-        };
-
-        Ok(client)
+    pub fn upower(&mut self) -> Arc<LazyClient<zbus::fdo::PropertiesProxy<'static>>> {
+        self.upower
+            .get_or_insert_with(|| LazyClient::spawn("upower", upower::create_display_proxy()))
+            .clone()
     }
 
     #[cfg(feature = "volume")]
-    pub fn volume(&mut self) -> Arc<volume::Client> {
+    pub fn volume(&mut self) -> Arc<dyn volume::VolumeClient> {
         self.volume
             .get_or_insert_with(volume::create_client)
             .clone()
     }
 }
 
+/// A client which connects in the background.
+///
+/// Clients connecting over D-Bus are wrapped in this
+/// so that requesting them never blocks the UI thread,
+/// even if the bus or the service is slow to respond.
+/// Modules can create their widgets immediately,
+/// and wait for the client to become ready inside their controller.
+#[derive(Debug)]
+pub struct LazyClient<T: ?Sized> {
+    rx: watch::Receiver<Option<Arc<T>>>,
+}
+
+impl<T: ?Sized + Send + Sync + 'static> LazyClient<T> {
+    /// Starts connecting the client in the background.
+    /// Failures are logged.
+    fn spawn<F>(name: &'static str, connect: F) -> Arc<Self>
+    where
+        F: Future<Output = ClientResult<T>> + Send + 'static,
+    {
+        let (tx, rx) = watch::channel(None);
+
+        spawn(async move {
+            match connect.await {
+                Ok(client) => {
+                    debug!("{name} client ready");
+                    tx.send_replace(Some(client));
+                }
+                Err(err) => error!(
+                    "{:?}",
+                    err.wrap_err(format!("Failed to connect {name} client"))
+                ),
+            }
+        });
+
+        Arc::new(Self { rx })
+    }
+
+    /// Waits for the client to finish connecting.
+    ///
+    /// Returns `None` if the client failed to connect.
+    pub async fn ready(&self) -> Option<Arc<T>> {
+        let mut rx = self.rx.clone();
+        rx.wait_for(Option::is_some)
+            .await
+            .ok()
+            .and_then(|client| client.clone())
+    }
+}
+
 /// Types implementing this trait
 /// indicate that they provide a singleton client instance of type `T`.
 pub trait ProvidesClient<T: ?Sized> {
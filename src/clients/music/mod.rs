@@ -31,6 +31,10 @@ pub struct Track {
     pub disc: Option<u64>,
     pub genre: Option<String>,
     pub track: Option<u64>,
+    pub length: Option<Duration>,
+    pub url: Option<String>,
+    pub file: Option<String>,
+    pub album_artist: Option<String>,
     pub cover_path: Option<String>,
 }
 
@@ -65,18 +69,42 @@ pub trait MusicClient: Debug + Send + Sync {
     fn set_volume_percent(&self, vol: u8) -> Result<()>;
     fn seek(&self, duration: Duration) -> Result<()>;
 
+    /// Brings the player's window to the front.
+    fn raise(&self) -> Result<()>;
+
     fn subscribe_change(&self) -> broadcast::Receiver<PlayerUpdate>;
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
 pub enum ClientType {
-    Mpd { host: String, music_dir: PathBuf },
-    Mpris,
+    Mpd {
+        host: String,
+        music_dir: PathBuf,
+        max_reconnect_delay: Duration,
+    },
+    Mpris {
+        filter: PlayerFilter,
+    },
+}
+
+/// Filters which MPRIS players are considered,
+/// by their identity or D-Bus name.
+/// Patterns support `*` and `?` wildcards.
+#[derive(Debug, Clone, Default, Eq, PartialEq, Hash)]
+pub struct PlayerFilter {
+    /// If not empty, only players matching one of these are considered.
+    pub allow: Vec<String>,
+    /// Players matching any of these are never considered.
+    pub deny: Vec<String>,
 }
 
 pub fn create_client(client_type: ClientType) -> Arc<dyn MusicClient> {
     match client_type {
-        ClientType::Mpd { host, music_dir } => Arc::new(mpd::Client::new(host, music_dir)),
-        ClientType::Mpris => Arc::new(mpris::Client::new()),
+        ClientType::Mpd {
+            host,
+            music_dir,
+            max_reconnect_delay,
+        } => Arc::new(mpd::Client::new(host, music_dir, max_reconnect_delay)),
+        ClientType::Mpris { filter } => Arc::new(mpris::Client::new(filter)),
     }
 }
@@ -6,6 +6,7 @@ use color_eyre::Report;
 use color_eyre::Result;
 use mpd_client::client::{ConnectionEvent, Subsystem};
 use mpd_client::commands::{self, SeekMode};
+use mpd_client::protocol::Command as RawCommand;
 use mpd_client::responses::{PlayState, Song};
 use mpd_client::tag::Tag;
 use mpd_utils::{mpd_client, PersistentClient};
@@ -13,8 +14,13 @@ use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::RecvError;
 use tokio::time::sleep;
-use tracing::debug;
+use tracing::{debug, info, warn};
+
+/// Initial delay between connection checks after the server disconnects.
+/// This is doubled after each failed check, up to the configured maximum.
+const RECONNECT_INITIAL_DELAY: Duration = Duration::from_secs(1);
 
 macro_rules! command {
     ($self:ident, $command:expr) => {
@@ -31,7 +37,7 @@ pub struct Client {
 }
 
 impl Client {
-    pub fn new(host: String, music_dir: PathBuf) -> Self {
+    pub fn new(host: String, music_dir: PathBuf, max_reconnect_delay: Duration) -> Self {
         let client = Arc::new(PersistentClient::new(host, Duration::from_secs(5)));
         let mut client_rx = client.subscribe();
 
@@ -50,15 +56,41 @@ impl Client {
                     .await
                     .expect("Failed to send update");
 
-                while let Ok(change) = client_rx.recv().await {
+                loop {
+                    let change = match client_rx.recv().await {
+                        Ok(change) => change,
+                        Err(RecvError::Lagged(count)) => {
+                            warn!("Missed {count} MPD state changes, refreshing");
+                            Self::send_update(&client, &tx, &music_dir)
+                                .await
+                                .expect("Failed to send update");
+                            continue;
+                        }
+                        Err(RecvError::Closed) => break,
+                    };
+
                     debug!("Received state change: {change:?}");
-                    if let ConnectionEvent::SubsystemChange(
-                        Subsystem::Player | Subsystem::Queue | Subsystem::Mixer,
-                    ) = *change
-                    {
-                        Self::send_update(&client, &tx, &music_dir)
-                            .await
-                            .expect("Failed to send update");
+                    match *change {
+                        ConnectionEvent::SubsystemChange(
+                            Subsystem::Player | Subsystem::Queue | Subsystem::Mixer,
+                        ) => {
+                            Self::send_update(&client, &tx, &music_dir)
+                                .await
+                                .expect("Failed to send update");
+                        }
+                        ConnectionEvent::ConnectionClosed(ref err) => {
+                            warn!("Lost connection to MPD server: {err}");
+
+                            // clear the stale track while the server is unavailable
+                            send!(tx, PlayerUpdate::Update(Box::new(None), Status::default()));
+
+                            Self::wait_for_reconnect(&client, max_reconnect_delay).await;
+
+                            Self::send_update(&client, &tx, &music_dir)
+                                .await
+                                .expect("Failed to send update");
+                        }
+                        _ => {}
                     }
                 }
             });
@@ -76,6 +108,24 @@ impl Client {
             });
         }
 
+        {
+            let client = client.clone();
+
+            Ironbar::on_shutdown(move || {
+                if !client.is_connected() {
+                    return;
+                }
+
+                debug!("Closing MPD connection");
+
+                // the server closes the connection without responding,
+                // so the resulting error is expected.
+                await_sync(client.with_client(|client| async move {
+                    client.raw_command(RawCommand::new("close")).await.ok();
+                }));
+            });
+        }
+
         Self {
             client,
             tx,
@@ -84,6 +134,28 @@ impl Client {
         }
     }
 
+    /// Waits until the server responds to commands again,
+    /// checking with an exponential backoff capped at `max_delay`.
+    ///
+    /// `max_delay` is never shorter than `RECONNECT_INITIAL_DELAY`,
+    /// so a zero delay cannot cause a busy loop.
+    async fn wait_for_reconnect(client: &PersistentClient, max_delay: Duration) {
+        let max_delay = max_delay.max(RECONNECT_INITIAL_DELAY);
+        let mut delay = RECONNECT_INITIAL_DELAY;
+
+        loop {
+            sleep(delay).await;
+
+            if client.command(commands::Status).await.is_ok() {
+                info!("Reconnected to MPD server");
+                break;
+            }
+
+            delay = (delay * 2).min(max_delay);
+            debug!("MPD server still unavailable, retrying in {delay:?}");
+        }
+    }
+
     async fn send_update(
         client: &PersistentClient,
         tx: &broadcast::Sender<PlayerUpdate>,
@@ -144,13 +216,25 @@ impl MusicClient for Client {
         command!(self, commands::Seek(SeekMode::Absolute(duration)))
     }
 
+    fn raise(&self) -> Result<()> {
+        Err(Report::msg("MPD does not have a player window"))
+    }
+
     fn subscribe_change(&self) -> broadcast::Receiver<PlayerUpdate> {
         let rx = self.tx.subscribe();
-        await_sync(async move {
-            Self::send_update(&self.client, &self.tx, &self.music_dir)
+
+        // the server may be remote,
+        // so the initial state is sent in the background.
+        let client = self.client.clone();
+        let tx = self.tx.clone();
+        let music_dir = self.music_dir.clone();
+
+        spawn(async move {
+            Self::send_update(&client, &tx, &music_dir)
                 .await
                 .expect("to be able to send update");
         });
+
         rx
     }
 }
@@ -177,6 +261,14 @@ fn convert_song(song: &Song, music_dir: &Path) -> Track {
         genre: try_get_first_tag(song, &Tag::Genre).map(ToString::to_string),
         disc: Some(disc),
         track: Some(track),
+        length: song.duration,
+        url: Some(song.url.clone()),
+        file: music_dir
+            .join(song.file_path())
+            .into_os_string()
+            .into_string()
+            .ok(),
+        album_artist: try_get_first_tag(song, &Tag::AlbumArtist).map(ToString::to_string),
         cover_path,
     }
 }
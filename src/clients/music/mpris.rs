@@ -1,4 +1,6 @@
-use super::{MusicClient, PlayerState, PlayerUpdate, Status, Track, TICK_INTERVAL_MS};
+use super::{
+    MusicClient, PlayerFilter, PlayerState, PlayerUpdate, Status, Track, TICK_INTERVAL_MS,
+};
 use crate::clients::music::ProgressTick;
 use crate::{arc_mut, lock, send, spawn_blocking};
 use color_eyre::Result;
@@ -23,8 +25,34 @@ const NO_REPLY: &str = "org.freedesktop.DBus.Error.NoReply";
 const NO_SERVICE: &str = "org.freedesktop.DBus.Error.ServiceUnknown";
 const NO_METHOD: &str = "org.freedesktop.DBus.Error.UnknownMethod";
 
+/// Prefix shared by the D-Bus names of all MPRIS players.
+const BUS_NAME_PREFIX: &str = "org.mpris.MediaPlayer2.";
+
+impl PlayerFilter {
+    /// Checks whether the player should be considered.
+    ///
+    /// Patterns are matched against the player's identity
+    /// and its D-Bus name, with or without the MPRIS prefix.
+    fn allows(&self, player: &Player) -> bool {
+        let bus_name = player.bus_name();
+        let names = [
+            player.identity(),
+            bus_name,
+            bus_name.trim_start_matches(BUS_NAME_PREFIX),
+        ];
+
+        let matches = |patterns: &[String]| {
+            patterns
+                .iter()
+                .any(|pattern| names.iter().any(|name| glob_match(pattern, name)))
+        };
+
+        (self.allow.is_empty() || matches(&self.allow)) && !matches(&self.deny)
+    }
+}
+
 impl Client {
-    pub(crate) fn new() -> Self {
+    pub(crate) fn new(filter: PlayerFilter) -> Self {
         let (tx, rx) = broadcast::channel(32);
 
         let current_player = arc_mut!(None);
@@ -55,12 +83,33 @@ impl Client {
                         }
                     });
 
+                    let players = players
+                        .into_iter()
+                        .filter(|player| filter.allows(player))
+                        .collect::<Vec<_>>();
+
                     // Acquire the lock of current_player before players to avoid deadlock.
                     // There are places where we lock on current_player and players, but we always lock on current_player first.
                     // This is because we almost never need to lock on players without locking on current_player.
                     {
                         let mut current_player_lock = lock!(current_player);
 
+                        // fall back to the next allowed player
+                        // if the active one is no longer found or allowed.
+                        let is_current_missing =
+                            current_player_lock.as_ref().is_some_and(|identity| {
+                                !players.iter().any(|player| player.identity() == identity)
+                            });
+
+                        if is_current_missing {
+                            debug!("Active player is no longer available");
+                            current_player_lock.take();
+
+                            if players.is_empty() {
+                                send!(tx, PlayerUpdate::Update(Box::new(None), Status::default()));
+                            }
+                        }
+
                         let mut players_list_val = lock!(players_list);
                         for player in players {
                             let identity = player.identity();
@@ -304,6 +353,11 @@ impl MusicClient for Client {
         Ok(())
     }
 
+    fn raise(&self) -> Result<()> {
+        command!(self, raise);
+        Ok(())
+    }
+
     fn subscribe_change(&self) -> broadcast::Receiver<PlayerUpdate> {
         debug!("Creating new subscription");
         let rx = self.tx.subscribe();
@@ -354,6 +408,16 @@ impl From<Metadata> for Track {
                 .and_then(mpris::MetadataValue::as_str_array)
                 .and_then(|arr| arr.first().map(|val| (*val).to_string())),
             track: value.track_number().map(|track| track as u64),
+            length: value.length(),
+            url: value.url().map(ToString::to_string),
+            file: value
+                .url()
+                .and_then(|url| glib::filename_from_uri(url).ok())
+                .map(|(path, _)| path.to_string_lossy().to_string()),
+            album_artist: value
+                .album_artists()
+                .map(|artists| artists.join(", "))
+                .and_then(replace_empty_none),
             cover_path: value.art_url().map(ToString::to_string),
         }
     }
@@ -376,3 +440,62 @@ fn replace_empty_none(string: String) -> Option<String> {
         Some(string)
     }
 }
+
+/// Checks whether the text matches the glob pattern, ignoring case.
+/// `*` matches any number of characters, and `?` matches exactly one.
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern = pattern.to_lowercase().chars().collect::<Vec<_>>();
+    let text = text.to_lowercase().chars().collect::<Vec<_>>();
+
+    let (mut p, mut t) = (0, 0);
+
+    // the position of the last `*` in the pattern,
+    // and the position in the text it has matched up to.
+    let mut backtrack = None;
+
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((star, matched)) => {
+                    backtrack = Some((star, matched + 1));
+                    p = star + 1;
+                    t = matched + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("spotify", "Spotify"));
+        assert!(glob_match(
+            "*firefox*",
+            "org.mpris.MediaPlayer2.firefox.instance_1_42"
+        ));
+        assert!(glob_match("chrom?um", "Chromium"));
+        assert!(glob_match("*", ""));
+    }
+
+    #[test]
+    fn test_glob_no_match() {
+        assert!(!glob_match("firefox", "firefox.instance_1_42"));
+        assert!(!glob_match("chrom?um", "Chrome"));
+        assert!(!glob_match("a*b", "ac"));
+    }
+}
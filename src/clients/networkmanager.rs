@@ -1,14 +1,17 @@
+use std::collections::{HashMap, HashSet};
+use std::net::Ipv6Addr;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 
 use color_eyre::Result;
 use futures_signals::signal::{Mutable, MutableSignalCloned};
-use tracing::error;
-use zbus::blocking::fdo::PropertiesProxy;
-use zbus::blocking::Connection;
+use tracing::{error, warn};
+use zbus::blocking::{Connection, MessageIterator};
+use zbus::fdo::PropertiesChanged;
 use zbus::{
     dbus_proxy,
-    names::InterfaceName,
-    zvariant::{ObjectPath, Str},
+    zvariant::{ObjectPath, OwnedObjectPath, OwnedValue, Str},
+    MatchRule, MessageType,
 };
 
 use crate::{register_fallible_client, spawn_blocking};
@@ -17,12 +20,30 @@ const DBUS_BUS: &str = "org.freedesktop.NetworkManager";
 const DBUS_PATH: &str = "/org/freedesktop/NetworkManager";
 const DBUS_INTERFACE: &str = "org.freedesktop.NetworkManager";
 
+/// `NM_ACTIVE_CONNECTION_STATE_ACTIVATED`
+const ACTIVE_CONNECTION_ACTIVATED: u32 = 2;
+
+/// Root properties which affect the displayed connection.
+const ROOT_PROPERTIES: &[&str] = &[
+    "ActiveConnections",
+    "Connectivity",
+    "PrimaryConnection",
+    "PrimaryConnectionType",
+    "WirelessEnabled",
+];
+
+/// Properties of the displayed connection which affect its details.
+const ACTIVE_CONNECTION_PROPERTIES: &[&str] =
+    &["Ip4Config", "Ip6Config", "SpecificObject", "State"];
+const ACCESS_POINT_PROPERTIES: &[&str] = &["Ssid", "Strength"];
+const IP_CONFIG_PROPERTIES: &[&str] = &["AddressData"];
+
 #[derive(Debug)]
 pub struct Client {
-    client_state: Mutable<ClientState>,
-    interface_name: InterfaceName<'static>,
+    client_state: Mutable<NetworkState>,
     dbus_connection: Connection,
-    props_proxy: PropertiesProxy<'static>,
+    /// Whether the connectivity check result has been requested by a consumer.
+    connectivity_enabled: AtomicBool,
 }
 
 #[derive(Clone, Debug)]
@@ -36,6 +57,72 @@ pub enum ClientState {
     Unknown,
 }
 
+/// NetworkManager's internet connectivity check result.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Connectivity {
+    Unknown,
+    /// Not connected to any network.
+    None,
+    /// Behind a captive portal.
+    Portal,
+    /// Connected to a network, but without internet access.
+    Limited,
+    Full,
+}
+
+impl From<u32> for Connectivity {
+    fn from(value: u32) -> Self {
+        match value {
+            1 => Self::None,
+            2 => Self::Portal,
+            3 => Self::Limited,
+            4 => Self::Full,
+            _ => Self::Unknown,
+        }
+    }
+}
+
+impl Connectivity {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Unknown => "unknown",
+            Self::None => "none",
+            Self::Portal => "portal",
+            Self::Limited => "limited",
+            Self::Full => "full",
+        }
+    }
+}
+
+/// The state of the displayed connection,
+/// along with its details where available.
+#[derive(Clone, Debug)]
+pub struct NetworkState {
+    pub state: ClientState,
+    pub connectivity: Connectivity,
+    /// The SSID of the access point, for wifi connections.
+    pub ssid: Option<String>,
+    /// The signal strength percentage, for wifi connections.
+    pub strength: Option<u8>,
+    /// The first IPv4 address of the connection.
+    pub ipv4: Option<String>,
+    /// The first global-scope IPv6 address of the connection.
+    pub ipv6: Option<String>,
+}
+
+impl NetworkState {
+    const fn new(state: ClientState) -> Self {
+        Self {
+            state,
+            connectivity: Connectivity::Unknown,
+            ssid: None,
+            strength: None,
+            ipv4: None,
+            ipv6: None,
+        }
+    }
+}
+
 #[dbus_proxy(
     default_service = "org.freedesktop.NetworkManager",
     interface = "org.freedesktop.NetworkManager",
@@ -45,6 +132,9 @@ trait NetworkManagerDbus {
     #[dbus_proxy(property)]
     fn active_connections(&self) -> Result<Vec<ObjectPath>>;
 
+    #[dbus_proxy(property)]
+    fn connectivity(&self) -> Result<u32>;
+
     #[dbus_proxy(property)]
     fn devices(&self) -> Result<Vec<ObjectPath>>;
 
@@ -61,72 +151,311 @@ trait NetworkManagerDbus {
     fn wireless_enabled(&self) -> Result<bool>;
 }
 
+#[dbus_proxy(
+    default_service = "org.freedesktop.NetworkManager",
+    interface = "org.freedesktop.NetworkManager.Connection.Active"
+)]
+trait ActiveConnectionDbus {
+    #[dbus_proxy(property)]
+    fn ip4_config(&self) -> Result<OwnedObjectPath>;
+
+    #[dbus_proxy(property)]
+    fn ip6_config(&self) -> Result<OwnedObjectPath>;
+
+    #[dbus_proxy(property)]
+    fn specific_object(&self) -> Result<OwnedObjectPath>;
+
+    #[dbus_proxy(property)]
+    fn state(&self) -> Result<u32>;
+
+    #[dbus_proxy(property, name = "Type")]
+    fn type_(&self) -> Result<String>;
+}
+
+#[dbus_proxy(
+    default_service = "org.freedesktop.NetworkManager",
+    interface = "org.freedesktop.NetworkManager.AccessPoint"
+)]
+trait AccessPointDbus {
+    #[dbus_proxy(property)]
+    fn ssid(&self) -> Result<Vec<u8>>;
+
+    #[dbus_proxy(property)]
+    fn strength(&self) -> Result<u8>;
+}
+
+#[dbus_proxy(
+    default_service = "org.freedesktop.NetworkManager",
+    interface = "org.freedesktop.NetworkManager.IP4Config"
+)]
+trait Ip4ConfigDbus {
+    #[dbus_proxy(property)]
+    fn address_data(&self) -> Result<Vec<HashMap<String, OwnedValue>>>;
+}
+
+#[dbus_proxy(
+    default_service = "org.freedesktop.NetworkManager",
+    interface = "org.freedesktop.NetworkManager.IP6Config"
+)]
+trait Ip6ConfigDbus {
+    #[dbus_proxy(property)]
+    fn address_data(&self) -> Result<Vec<HashMap<String, OwnedValue>>>;
+}
+
 impl Client {
     fn new() -> Result<Self> {
-        let client_state = Mutable::new(ClientState::Unknown);
+        let client_state = Mutable::new(NetworkState::new(ClientState::Unknown));
         let dbus_connection = Connection::system()?;
-        let interface_name = InterfaceName::from_static_str(DBUS_INTERFACE)?;
-        let props_proxy = PropertiesProxy::builder(&dbus_connection)
-            .destination(DBUS_BUS)?
-            .path(DBUS_PATH)?
-            .build()?;
 
         Ok(Self {
             client_state,
-            interface_name,
             dbus_connection,
-            props_proxy,
+            connectivity_enabled: AtomicBool::new(false),
         })
     }
 
     fn run(&self) -> Result<()> {
         let proxy = NetworkManagerDbusProxyBlocking::new(&self.dbus_connection)?;
 
-        let mut primary_connection = proxy.primary_connection()?;
-        let mut primary_connection_type = proxy.primary_connection_type()?;
-        let mut wireless_enabled = proxy.wireless_enabled()?;
+        // paths of the objects making up the displayed connection,
+        // such as its access point and IP configs.
+        let mut watched = HashSet::new();
+        self.update_state(&proxy, &mut watched);
 
-        self.client_state.set(determine_state(
-            &primary_connection,
-            &primary_connection_type,
-            wireless_enabled,
-        ));
+        // listen to every NetworkManager object rather than just the root,
+        // so that details such as signal strength stay up to date while connected.
+        let rule = MatchRule::builder()
+            .msg_type(MessageType::Signal)
+            .sender(DBUS_BUS)?
+            .interface("org.freedesktop.DBus.Properties")?
+            .member("PropertiesChanged")?
+            .path_namespace(DBUS_PATH)?
+            .build();
+
+        for message in MessageIterator::for_match_rule(rule, &self.dbus_connection, None)? {
+            let message = match message {
+                Ok(message) => message,
+                Err(err) => {
+                    error!("{err:?}");
+                    continue;
+                }
+            };
+
+            let is_watched = message
+                .path()
+                .is_some_and(|path| watched.contains(path.as_str()));
 
-        for change in self.props_proxy.receive_properties_changed()? {
-            let args = change.args()?;
-            if args.interface_name != self.interface_name {
+            let Some(args) =
+                PropertiesChanged::from_message(message).and_then(|change| change.args().ok())
+            else {
                 continue;
-            }
+            };
 
-            let changed_props = args.changed_properties;
-            let mut relevant_prop_changed = false;
+            let props = match args.interface_name.as_str() {
+                DBUS_INTERFACE => ROOT_PROPERTIES,
+                _ if !is_watched => continue,
+                "org.freedesktop.NetworkManager.Connection.Active" => ACTIVE_CONNECTION_PROPERTIES,
+                "org.freedesktop.NetworkManager.AccessPoint" => ACCESS_POINT_PROPERTIES,
+                "org.freedesktop.NetworkManager.IP4Config"
+                | "org.freedesktop.NetworkManager.IP6Config" => IP_CONFIG_PROPERTIES,
+                _ => continue,
+            };
 
-            if changed_props.contains_key("PrimaryConnection") {
-                primary_connection = proxy.primary_connection()?;
-                relevant_prop_changed = true;
-            }
-            if changed_props.contains_key("PrimaryConnectionType") {
-                primary_connection_type = proxy.primary_connection_type()?;
-                relevant_prop_changed = true;
-            }
-            if changed_props.contains_key("WirelessEnabled") {
-                wireless_enabled = proxy.wireless_enabled()?;
-                relevant_prop_changed = true;
+            if props
+                .iter()
+                .filter(|&&prop| prop != "Connectivity" || self.connectivity_enabled())
+                .any(|prop| args.changed_properties.contains_key(prop))
+            {
+                self.update_state(&proxy, &mut watched);
             }
+        }
+
+        Ok(())
+    }
+
+    /// Re-reads the connection state and sends it to subscribers.
+    ///
+    /// Errors are logged rather than returned,
+    /// so a single failed read does not stop the client.
+    fn update_state(&self, proxy: &NetworkManagerDbusProxyBlocking, watched: &mut HashSet<String>) {
+        watched.clear();
+
+        match self.read_state(proxy, watched) {
+            Ok(state) => self.client_state.set(state),
+            Err(err) => error!("Failed to read network state: {err:?}"),
+        }
+    }
+
+    /// Reads the state of the connection to display.
+    ///
+    /// This is the primary connection,
+    /// unless an ethernet connection is active,
+    /// in which case that is preferred.
+    ///
+    /// The paths of the objects read are added to `watched`.
+    fn read_state(
+        &self,
+        proxy: &NetworkManagerDbusProxyBlocking,
+        watched: &mut HashSet<String>,
+    ) -> Result<NetworkState> {
+        let primary_connection = proxy.primary_connection()?;
+        let wireless_enabled = proxy.wireless_enabled()?;
+
+        let connectivity = if self.connectivity_enabled() {
+            proxy
+                .connectivity()
+                .map_or_else(read_connectivity_error, Connectivity::from)
+        } else {
+            Connectivity::Unknown
+        };
+
+        if primary_connection.as_str() == "/" {
+            let mut state = NetworkState::new(determine_state("/", "", wireless_enabled));
+            state.connectivity = connectivity;
+            return Ok(state);
+        }
 
-            if relevant_prop_changed {
-                self.client_state.set(determine_state(
-                    &primary_connection,
-                    &primary_connection_type,
-                    wireless_enabled,
-                ));
+        // connections can disappear while being read,
+        // so failures here are not fatal.
+        let ethernet = proxy
+            .active_connections()?
+            .into_iter()
+            .filter_map(|path| self.active_connection(path.into()).ok())
+            .find(|connection| {
+                connection.type_().is_ok_and(|ty| ty == "802-3-ethernet")
+                    && connection
+                        .state()
+                        .is_ok_and(|state| state == ACTIVE_CONNECTION_ACTIVATED)
+            });
+
+        let connection = match ethernet {
+            Some(connection) => connection,
+            None => self.active_connection(primary_connection.into())?,
+        };
+
+        let connection_type = connection.type_().unwrap_or_default();
+        watched.insert(connection.path().to_string());
+
+        let mut state = NetworkState::new(determine_state(
+            connection.path().as_str(),
+            &connection_type,
+            wireless_enabled,
+        ));
+
+        state.connectivity = connectivity;
+        state.ipv4 = self.read_ipv4(&connection, watched);
+        state.ipv6 = self.read_ipv6(&connection, watched);
+
+        if matches!(state.state, ClientState::WifiConnected) {
+            if let Some(access_point) = connection
+                .specific_object()
+                .ok()
+                .and_then(|path| self.access_point(path).ok())
+            {
+                watched.insert(access_point.path().to_string());
+
+                state.ssid = access_point
+                    .ssid()
+                    .ok()
+                    .map(|ssid| String::from_utf8_lossy(&ssid).into_owned());
+                state.strength = access_point.strength().ok();
             }
         }
 
-        Ok(())
+        Ok(state)
+    }
+
+    fn read_ipv4(
+        &self,
+        connection: &ActiveConnectionDbusProxyBlocking,
+        watched: &mut HashSet<String>,
+    ) -> Option<String> {
+        let path = connection.ip4_config().ok()?;
+        if path.as_str() == "/" {
+            return None;
+        }
+
+        watched.insert(path.to_string());
+
+        let config = Ip4ConfigDbusProxyBlocking::builder(&self.dbus_connection)
+            .path(path)
+            .ok()?
+            .build()
+            .ok()?;
+
+        addresses(config.address_data().ok()?).next()
     }
 
-    pub fn subscribe(&self) -> MutableSignalCloned<ClientState> {
+    fn read_ipv6(
+        &self,
+        connection: &ActiveConnectionDbusProxyBlocking,
+        watched: &mut HashSet<String>,
+    ) -> Option<String> {
+        let path = connection.ip6_config().ok()?;
+        if path.as_str() == "/" {
+            return None;
+        }
+
+        watched.insert(path.to_string());
+
+        let config = Ip6ConfigDbusProxyBlocking::builder(&self.dbus_connection)
+            .path(path)
+            .ok()?
+            .build()
+            .ok()?;
+
+        addresses(config.address_data().ok()?).find(|address| {
+            address
+                .parse::<Ipv6Addr>()
+                .is_ok_and(|address| is_global_ipv6(&address))
+        })
+    }
+
+    fn active_connection(
+        &self,
+        path: OwnedObjectPath,
+    ) -> Result<ActiveConnectionDbusProxyBlocking<'static>> {
+        let proxy = ActiveConnectionDbusProxyBlocking::builder(&self.dbus_connection)
+            .path(path)?
+            .build()?;
+
+        Ok(proxy)
+    }
+
+    fn access_point(&self, path: OwnedObjectPath) -> Result<AccessPointDbusProxyBlocking<'static>> {
+        let proxy = AccessPointDbusProxyBlocking::builder(&self.dbus_connection)
+            .path(path)?
+            .build()?;
+
+        Ok(proxy)
+    }
+
+    fn connectivity_enabled(&self) -> bool {
+        self.connectivity_enabled.load(Ordering::Relaxed)
+    }
+
+    /// Starts reading NetworkManager's internet connectivity check result.
+    /// This is disabled by default, and the connectivity is always `Unknown`.
+    pub fn enable_connectivity(&self) {
+        if self.connectivity_enabled.swap(true, Ordering::Relaxed) {
+            return;
+        }
+
+        // the state may already have been read without it
+        let dbus_connection = self.dbus_connection.clone();
+        let client_state = self.client_state.clone();
+
+        spawn_blocking(move || {
+            let connectivity = NetworkManagerDbusProxyBlocking::new(&dbus_connection)
+                .map_err(Into::into)
+                .and_then(|proxy| proxy.connectivity())
+                .map_or_else(read_connectivity_error, Connectivity::from);
+
+            client_state.lock_mut().connectivity = connectivity;
+        });
+    }
+
+    pub fn subscribe(&self) -> MutableSignalCloned<NetworkState> {
         self.client_state.signal_cloned()
     }
 }
@@ -144,6 +473,30 @@ pub fn create_client() -> Result<Arc<Client>> {
     Ok(client)
 }
 
+/// Logs a failed connectivity read, falling back to `Unknown`.
+fn read_connectivity_error(err: color_eyre::Report) -> Connectivity {
+    warn!("Failed to read network connectivity: {err:?}");
+    Connectivity::Unknown
+}
+
+/// Gets the address strings from an IP config's `AddressData` property.
+fn addresses(address_data: Vec<HashMap<String, OwnedValue>>) -> impl Iterator<Item = String> {
+    address_data
+        .into_iter()
+        .filter_map(|mut address| address.remove("address"))
+        .filter_map(|address| String::try_from(address).ok())
+}
+
+/// Checks the address is not loopback, link-local or unique local.
+fn is_global_ipv6(address: &Ipv6Addr) -> bool {
+    let first_segment = address.segments()[0];
+
+    !address.is_loopback()
+        && !address.is_unspecified()
+        && first_segment & 0xffc0 != 0xfe80
+        && first_segment & 0xfe00 != 0xfc00
+}
+
 fn determine_state(
     primary_connection: &str,
     primary_connection_type: &str,
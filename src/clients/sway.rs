@@ -2,22 +2,31 @@ use crate::spawn;
 use color_eyre::{Report, Result};
 use futures_lite::StreamExt;
 use std::sync::Arc;
+use std::time::Duration;
 use swayipc_async::{Connection, Event, EventType};
-use tokio::sync::Mutex;
-use tracing::{info, trace};
+use tokio::sync::{watch, Mutex};
+use tokio::time::sleep;
+use tracing::{debug, error, info, trace, warn};
+
+/// Delay before the first reconnection attempt after the IPC socket drops.
+const RECONNECT_INITIAL_DELAY: Duration = Duration::from_millis(500);
+/// Maximum delay between reconnection attempts.
+const RECONNECT_MAX_DELAY: Duration = Duration::from_secs(30);
 
 type SyncFn<T> = dyn Fn(&T) + Sync + Send;
 
 struct TaskState {
-    join_handle: Option<tokio::task::JoinHandle<Result<()>>>,
+    join_handle: Option<tokio::task::JoinHandle<()>>,
     // could have been a `HashMap<EventType, Vec<Box<dyn Fn(&Event) + Sync + Send>>>`, but we don't
     // expect enough listeners to justify the constant overhead of a hashmap.
     listeners: Arc<Vec<(EventType, Box<SyncFn<Event>>)>>,
 }
 
+#[derive(Clone)]
 pub struct Client {
     connection: Arc<Mutex<Connection>>,
-    task_state: Mutex<TaskState>,
+    task_state: Arc<Mutex<TaskState>>,
+    connected: Arc<watch::Sender<bool>>,
 }
 
 impl std::fmt::Debug for Client {
@@ -37,10 +46,11 @@ impl Client {
 
         Ok(Self {
             connection: client,
-            task_state: Mutex::new(TaskState {
+            task_state: Arc::new(Mutex::new(TaskState {
                 listeners: Arc::new(Vec::new()),
                 join_handle: None,
-            }),
+            })),
+            connected: Arc::new(watch::channel(true).0),
         })
     }
 
@@ -48,6 +58,14 @@ impl Client {
         &self.connection
     }
 
+    /// Subscribes to the connection state of the IPC socket.
+    ///
+    /// The value changes to `false` when the event subscription drops,
+    /// and back to `true` once both connections have been re-established.
+    pub fn subscribe_connected(&self) -> watch::Receiver<bool> {
+        self.connected.subscribe()
+    }
+
     pub async fn add_listener<T: SwayIpcEvent>(
         &self,
         f: impl Fn(&T) + Sync + Send + 'static,
@@ -91,27 +109,76 @@ impl Client {
         let event_types = listeners.iter().map(|(t, _)| *t).collect::<Vec<_>>();
         let listeners = listeners.clone();
 
+        let connection = self.connection.clone();
+        let connected = self.connected.clone();
+
         let handle = spawn(async move {
-            let mut events = client.subscribe(&event_types).await?;
-
-            while let Some(event) = events.next().await {
-                trace!("event: {:?}", event);
-                let event = event?;
-                let ty = sway_event_to_event_type(&event);
-                for (t, f) in listeners.iter() {
-                    if *t == ty {
-                        f(&event);
-                    }
+            let mut client = client;
+
+            loop {
+                match Self::listen(client, &event_types, &listeners).await {
+                    Ok(()) => warn!("Sway IPC event stream ended"),
+                    Err(err) => error!("{:?}", err.wrap_err("Sway IPC event stream failed")),
                 }
-            }
 
-            Ok::<(), Report>(())
+                connected.send_replace(false);
+                client = Self::reconnect(&connection).await;
+                connected.send_replace(true);
+            }
         });
 
         *join_handle = Some(handle);
 
         Ok(())
     }
+
+    /// Subscribes to the event types,
+    /// and passes each event to its listeners until the stream ends.
+    async fn listen(
+        client: Connection,
+        event_types: &[EventType],
+        listeners: &[(EventType, Box<SyncFn<Event>>)],
+    ) -> Result<()> {
+        let mut events = client.subscribe(event_types).await?;
+
+        while let Some(event) = events.next().await {
+            trace!("event: {:?}", event);
+            let event = event?;
+            let ty = sway_event_to_event_type(&event);
+            for (t, f) in listeners {
+                if *t == ty {
+                    f(&event);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Waits for the IPC socket to become available again,
+    /// retrying with an exponential backoff.
+    ///
+    /// The shared command connection is replaced,
+    /// and a new connection for the event subscription is returned.
+    async fn reconnect(connection: &Mutex<Connection>) -> Connection {
+        let mut delay = RECONNECT_INITIAL_DELAY;
+
+        loop {
+            sleep(delay).await;
+
+            match (Connection::new().await, Connection::new().await) {
+                (Ok(command_client), Ok(event_client)) => {
+                    *connection.lock().await = command_client;
+                    info!("Sway IPC client reconnected");
+                    return event_client;
+                }
+                (Err(err), _) | (_, Err(err)) => {
+                    delay = (delay * 2).min(RECONNECT_MAX_DELAY);
+                    debug!("Failed to reconnect to Sway IPC, retrying in {delay:?}: {err}");
+                }
+            }
+        }
+    }
 }
 
 fn sway_event_to_event_type(event: &Event) -> EventType {
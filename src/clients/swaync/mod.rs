@@ -1,6 +1,7 @@
 mod dbus;
 
-use crate::{register_fallible_client, send, spawn};
+use crate::clients::LazyClient;
+use crate::{register_client, send, spawn};
 use color_eyre::{Report, Result};
 use dbus::SwayNcProxy;
 use serde::Deserialize;
@@ -84,4 +85,4 @@ impl Client {
     }
 }
 
-register_fallible_client!(Client, notifications);
+register_client!(LazyClient<Client>, notifications);
@@ -0,0 +1,576 @@
+use crate::{arc_mut, lock, register_client, spawn};
+use std::collections::{HashMap, HashSet};
+use std::ffi::OsStr;
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use sysinfo::{ComponentExt, CpuExt, DiskExt, NetworkExt, RefreshKind, System, SystemExt};
+use tokio::sync::broadcast;
+use tokio::time::interval;
+use tracing::{debug, info, warn};
+
+/// The shortest allowed refresh interval,
+/// to avoid refreshing in a tight loop.
+const MIN_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Converts a configured refresh interval in seconds into a `Duration`.
+///
+/// Invalid values, and values shorter than `MIN_INTERVAL`,
+/// are clamped to `MIN_INTERVAL` with a warning.
+pub fn interval_from_secs(secs: f64) -> Duration {
+    match Duration::try_from_secs_f64(secs) {
+        Ok(interval) if interval >= MIN_INTERVAL => interval,
+        _ => {
+            warn!(
+                "Invalid refresh interval '{secs}', using minimum of {}s",
+                MIN_INTERVAL.as_secs_f64()
+            );
+            MIN_INTERVAL
+        }
+    }
+}
+
+/// Sensor labels or chip names used for the `temp_cpu` token,
+/// in order of preference.
+const CPU_SENSORS: [&str; 7] = [
+    "Tctl",
+    "Tdie",
+    "Package id 0",
+    "k10temp",
+    "coretemp",
+    "zenpower",
+    "cpu_thermal",
+];
+
+/// The type of system information to refresh.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RefreshType {
+    Memory,
+    Cpu,
+    Temps,
+    Disks,
+    Network,
+    System,
+}
+
+/// Snapshot of all formatting token values.
+pub type Values = Arc<HashMap<String, String>>;
+
+/// Disk I/O counters at the previous refresh,
+/// used to calculate read and write rates.
+#[derive(Debug, Clone)]
+struct DiskIo {
+    /// The kernel name of the disk's device.
+    device: String,
+    read: u64,
+    written: u64,
+}
+
+#[derive(Debug)]
+struct State {
+    sys: System,
+    values: HashMap<String, String>,
+    last_network_refresh: Instant,
+    last_disk_refresh: Instant,
+    /// I/O counters by mountpoint.
+    disk_io: HashMap<String, DiskIo>,
+    /// Mountpoints to expose disk tokens for.
+    /// If `None`, all mountpoints are exposed.
+    mounts: Option<HashSet<String>>,
+}
+
+impl State {
+    fn refresh(&mut self, refresh_type: RefreshType) {
+        let values = &mut self.values;
+        let sys = &mut self.sys;
+
+        match refresh_type {
+            RefreshType::Memory => refresh_memory_tokens(values, sys),
+            RefreshType::Cpu => refresh_cpu_tokens(values, sys),
+            RefreshType::Temps => refresh_temp_tokens(values, sys),
+            RefreshType::Disks => {
+                let elapsed = self
+                    .last_disk_refresh
+                    .elapsed()
+                    .max(MIN_INTERVAL)
+                    .as_secs_f64();
+                self.last_disk_refresh = Instant::now();
+
+                refresh_disk_tokens(
+                    values,
+                    sys,
+                    &mut self.disk_io,
+                    self.mounts.as_ref(),
+                    elapsed,
+                );
+            }
+            RefreshType::Network => {
+                let elapsed = self
+                    .last_network_refresh
+                    .elapsed()
+                    .max(MIN_INTERVAL)
+                    .as_secs_f64();
+                self.last_network_refresh = Instant::now();
+
+                refresh_network_tokens(values, sys, elapsed);
+            }
+            RefreshType::System => refresh_system_tokens(values, sys),
+        }
+    }
+}
+
+/// A timer shared by all consumers polling at the same interval.
+#[derive(Debug)]
+struct Schedule {
+    refresh_types: HashSet<RefreshType>,
+    tx: broadcast::Sender<Values>,
+}
+
+/// Shared poll scheduler for system information.
+///
+/// Rather than each module refreshing on its own timers,
+/// consumers register the data they need and how often.
+/// Consumers with the same interval tick together,
+/// and all refreshes are made against a single `System` instance.
+#[derive(Debug)]
+pub struct Client {
+    state: Arc<Mutex<State>>,
+    schedules: Arc<Mutex<HashMap<Duration, Schedule>>>,
+}
+
+impl Client {
+    pub fn new() -> Self {
+        let refresh_kind = RefreshKind::everything()
+            .without_processes()
+            .without_users_list();
+
+        let mut sys = System::new_with_specifics(refresh_kind);
+        sys.refresh_components_list();
+        sys.refresh_disks_list();
+
+        let sensors = sys
+            .components()
+            .iter()
+            .map(ComponentExt::label)
+            .collect::<Vec<_>>();
+        info!("Available temperature sensors: {sensors:?}");
+
+        sys.refresh_networks_list();
+
+        Self {
+            state: arc_mut!(State {
+                sys,
+                values: HashMap::new(),
+                last_network_refresh: Instant::now(),
+                last_disk_refresh: Instant::now(),
+                disk_io: HashMap::new(),
+                mounts: Some(HashSet::new()),
+            }),
+            schedules: arc_mut!(HashMap::new()),
+        }
+    }
+
+    /// Registers each refresh type to be refreshed every `interval`.
+    /// Intervals shorter than 100ms are raised to 100ms.
+    ///
+    /// Returns a receiver for each distinct interval,
+    /// which is sent a snapshot of all values each time that interval ticks.
+    /// Types registered against an already running interval
+    /// are first refreshed on its next tick.
+    pub fn subscribe(
+        &self,
+        refresh_types: impl IntoIterator<Item = (RefreshType, Duration)>,
+    ) -> Vec<broadcast::Receiver<Values>> {
+        let mut schedules = lock!(self.schedules);
+        let mut receivers = HashMap::new();
+
+        // all types are registered while holding the lock,
+        // so new schedules see every type on their first tick.
+        for (refresh_type, interval) in refresh_types {
+            let interval = interval.max(MIN_INTERVAL);
+
+            let schedule = schedules
+                .entry(interval)
+                .or_insert_with(|| self.spawn_schedule(interval));
+
+            schedule.refresh_types.insert(refresh_type);
+            receivers
+                .entry(interval)
+                .or_insert_with(|| schedule.tx.subscribe());
+        }
+
+        receivers.into_values().collect()
+    }
+
+    /// Exposes disk tokens for each of `mounts`.
+    /// If `mounts` is empty, tokens are exposed for all mountpoints.
+    ///
+    /// Mountpoints are shared between all consumers,
+    /// so tokens may also be available for mountpoints requested elsewhere.
+    pub fn expose_mounts(&self, mounts: &[String]) {
+        let mut state = lock!(self.state);
+
+        if mounts.is_empty() {
+            state.mounts = None;
+        } else if let Some(exposed) = &mut state.mounts {
+            exposed.extend(mounts.iter().cloned());
+        }
+    }
+
+    fn spawn_schedule(&self, interval_duration: Duration) -> Schedule {
+        debug!("Starting sysinfo schedule with {interval_duration:?} interval");
+
+        let (tx, _rx) = broadcast::channel(4);
+
+        let state = self.state.clone();
+        let schedules = self.schedules.clone();
+        let tx2 = tx.clone();
+
+        spawn(async move {
+            let mut timer = interval(interval_duration);
+
+            loop {
+                timer.tick().await;
+
+                let refresh_types = lock!(schedules)
+                    .get(&interval_duration)
+                    .map(|schedule| schedule.refresh_types.clone())
+                    .unwrap_or_default();
+
+                let values = {
+                    let mut state = lock!(state);
+                    for refresh_type in refresh_types {
+                        state.refresh(refresh_type);
+                    }
+
+                    Arc::new(state.values.clone())
+                };
+
+                // no receivers is not an error, as consumers may come and go.
+                tx2.send(values).ok();
+            }
+        });
+
+        Schedule {
+            refresh_types: HashSet::new(),
+            tx,
+        }
+    }
+}
+
+fn refresh_memory_tokens(format_info: &mut HashMap<String, String>, sys: &mut System) {
+    sys.refresh_memory();
+
+    let total_memory = sys.total_memory();
+    let available_memory = sys.available_memory();
+
+    let actual_used_memory = total_memory - available_memory;
+    let memory_percent = actual_used_memory as f64 / total_memory as f64 * 100.0;
+
+    format_info.insert(
+        String::from("memory_free"),
+        (bytes_to_gigabytes(available_memory)).to_string(),
+    );
+    format_info.insert(
+        String::from("memory_used"),
+        (bytes_to_gigabytes(actual_used_memory)).to_string(),
+    );
+    format_info.insert(
+        String::from("memory_total"),
+        (bytes_to_gigabytes(total_memory)).to_string(),
+    );
+    format_info.insert(
+        String::from("memory_percent"),
+        format!("{memory_percent:0>2.0}"),
+    );
+
+    let used_swap = sys.used_swap();
+    let total_swap = sys.total_swap();
+
+    format_info.insert(
+        String::from("swap_free"),
+        (bytes_to_gigabytes(sys.free_swap())).to_string(),
+    );
+    format_info.insert(
+        String::from("swap_used"),
+        (bytes_to_gigabytes(used_swap)).to_string(),
+    );
+    format_info.insert(
+        String::from("swap_total"),
+        (bytes_to_gigabytes(total_swap)).to_string(),
+    );
+    format_info.insert(
+        String::from("swap_percent"),
+        format!("{:0>2.0}", used_swap as f64 / total_swap as f64 * 100.0),
+    );
+}
+
+fn refresh_cpu_tokens(format_info: &mut HashMap<String, String>, sys: &mut System) {
+    sys.refresh_cpu();
+
+    let cpu_info = sys.global_cpu_info();
+    let cpu_percent = cpu_info.cpu_usage();
+
+    format_info.insert(String::from("cpu_percent"), format!("{cpu_percent:0>2.0}"));
+}
+
+fn refresh_temp_tokens(format_info: &mut HashMap<String, String>, sys: &mut System) {
+    sys.refresh_components();
+
+    let components = sys.components();
+    let mut short_names = HashSet::new();
+
+    for component in components {
+        let label = component.label();
+        let key = label.replace(' ', "-");
+        let temp = component.temperature();
+
+        format_info.insert(format!("temp_c:{key}"), format!("{temp:.0}"));
+        format_info.insert(format!("temp_f:{key}"), format!("{:.0}", c_to_f(temp)));
+
+        // labels are prefixed with the chip name,
+        // so sensors can be selected with or without it.
+        // where several chips share a sensor name, the first is used.
+        format_info.insert(format!("temp:{label}"), format!("{temp:.0}"));
+        if let Some((_, name)) = label.split_once(' ') {
+            if short_names.insert(name) {
+                format_info.insert(format!("temp:{name}"), format!("{temp:.0}"));
+            }
+        }
+    }
+
+    let cpu_temp = CPU_SENSORS.iter().find_map(|sensor| {
+        components.iter().find(|component| {
+            let label = component.label();
+            let (chip, name) = label.split_once(' ').unwrap_or((label, ""));
+            chip == *sensor || name == *sensor
+        })
+    });
+
+    if let Some(component) = cpu_temp {
+        format_info.insert(
+            String::from("temp_cpu"),
+            format!("{:.0}", component.temperature()),
+        );
+    }
+}
+
+/// Refreshes disk capacity and I/O rates,
+/// with rates averaged over the number of seconds since the previous refresh.
+///
+/// The disk list is refreshed each time,
+/// so removable drives are picked up as they are mounted.
+/// Disks are matched to their previous values by mountpoint.
+fn refresh_disk_tokens(
+    format_info: &mut HashMap<String, String>,
+    sys: &mut System,
+    disk_io: &mut HashMap<String, DiskIo>,
+    mounts: Option<&HashSet<String>>,
+    elapsed: f64,
+) {
+    sys.refresh_disks_list();
+
+    // clear all disk tokens so unmounted disks are removed
+    format_info.retain(|key, _| !key.starts_with("disk_"));
+
+    let stats = read_disk_stats();
+    let mut previous_io = std::mem::take(disk_io);
+
+    for disk in sys.disks() {
+        // replace braces to avoid conflict with token parsing
+        let key = disk
+            .mount_point()
+            .to_str()
+            .map(|s| s.replace(['{', '}'], ""));
+
+        let Some(key) = key else {
+            continue;
+        };
+
+        if mounts.is_some_and(|mounts| !mounts.contains(&key)) {
+            continue;
+        }
+
+        let total = disk.total_space();
+        let available = disk.available_space();
+        let used = total - available;
+        let used_percent = format!("{:0>2.0}", used as f64 / total as f64 * 100.0);
+
+        format_info.insert(
+            format!("disk_free:{key}"),
+            bytes_to_gigabytes(available).to_string(),
+        );
+
+        format_info.insert(
+            format!("disk_used:{key}"),
+            bytes_to_gigabytes(used).to_string(),
+        );
+
+        format_info.insert(
+            format!("disk_total:{key}"),
+            bytes_to_gigabytes(total).to_string(),
+        );
+
+        format_info.insert(format!("disk_percent:{key}"), used_percent.clone());
+        format_info.insert(format!("disk_used_percent:{key}"), used_percent);
+
+        let io = device_name(disk.name()).and_then(|device| {
+            stats.get(&device).map(|&(read, written)| DiskIo {
+                device,
+                read,
+                written,
+            })
+        });
+
+        if let Some(io) = io {
+            // a different device at the same mountpoint has no previous counters
+            let (read_rate, write_rate) = match previous_io.remove(&key) {
+                Some(previous) if previous.device == io.device => (
+                    io.read.saturating_sub(previous.read) as f64 / elapsed,
+                    io.written.saturating_sub(previous.written) as f64 / elapsed,
+                ),
+                _ => (0.0, 0.0),
+            };
+
+            format_info.insert(
+                format!("disk_read:{key}"),
+                format!("{}/s", format_bytes(read_rate)),
+            );
+
+            format_info.insert(
+                format!("disk_write:{key}"),
+                format!("{}/s", format_bytes(write_rate)),
+            );
+
+            disk_io.insert(key, io);
+        }
+    }
+}
+
+/// Gets the kernel name of a disk device,
+/// resolving symlinks such as `/dev/mapper/root` to `dm-0`.
+fn device_name(name: &OsStr) -> Option<String> {
+    let path = Path::new(name);
+    let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+
+    path.file_name()
+        .and_then(OsStr::to_str)
+        .map(ToString::to_string)
+}
+
+/// Reads the total bytes read and written for each device
+/// from `/proc/diskstats`.
+///
+/// Returns an empty map if the file cannot be read.
+fn read_disk_stats() -> HashMap<String, (u64, u64)> {
+    /// The kernel always reports sectors as 512 bytes.
+    const SECTOR_SIZE: u64 = 512;
+
+    let Ok(contents) = fs::read_to_string("/proc/diskstats") else {
+        return HashMap::new();
+    };
+
+    contents
+        .lines()
+        .filter_map(|line| {
+            let fields = line.split_whitespace().collect::<Vec<_>>();
+
+            let device = fields.get(2)?;
+            let read = fields.get(5)?.parse::<u64>().ok()?;
+            let written = fields.get(9)?.parse::<u64>().ok()?;
+
+            Some((
+                (*device).to_string(),
+                (read * SECTOR_SIZE, written * SECTOR_SIZE),
+            ))
+        })
+        .collect()
+}
+
+/// Refreshes network rates,
+/// averaged over the number of seconds since the previous refresh.
+fn refresh_network_tokens(
+    format_info: &mut HashMap<String, String>,
+    sys: &mut System,
+    elapsed: f64,
+) {
+    sys.refresh_networks();
+
+    for (iface, network) in sys.networks() {
+        format_info.insert(
+            format!("net_down:{iface}"),
+            format!("{:0>2.0}", bytes_to_megabits(network.received()) / elapsed),
+        );
+
+        format_info.insert(
+            format!("net_up:{iface}"),
+            format!(
+                "{:0>2.0}",
+                bytes_to_megabits(network.transmitted()) / elapsed
+            ),
+        );
+    }
+}
+
+fn refresh_system_tokens(format_info: &mut HashMap<String, String>, sys: &System) {
+    // no refresh required for these tokens
+
+    let load_average = sys.load_average();
+    format_info.insert(
+        String::from("load_average:1"),
+        format!("{:.2}", load_average.one),
+    );
+
+    format_info.insert(
+        String::from("load_average:5"),
+        format!("{:.2}", load_average.five),
+    );
+
+    format_info.insert(
+        String::from("load_average:15"),
+        format!("{:.2}", load_average.fifteen),
+    );
+
+    let uptime = Duration::from_secs(sys.uptime()).as_secs();
+    let hours = uptime / 3600;
+    format_info.insert(
+        String::from("uptime"),
+        format!("{:0>2}:{:0>2}", hours, (uptime % 3600) / 60),
+    );
+}
+
+/// Converts celsius to fahrenheit.
+fn c_to_f(c: f32) -> f32 {
+    c * 9.0 / 5.0 + 32.0
+}
+
+/// Formats a number of bytes using the largest fitting unit,
+/// for example `1.5 MB`.
+fn format_bytes(bytes: f64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+
+    let mut value = bytes;
+    let mut unit = 0;
+
+    while value >= 1000.0 && unit < UNITS.len() - 1 {
+        value /= 1000.0;
+        unit += 1;
+    }
+
+    format!("{value:.1} {}", UNITS[unit])
+}
+
+const fn bytes_to_gigabytes(b: u64) -> u64 {
+    const BYTES_IN_GIGABYTE: u64 = 1_000_000_000;
+    b / BYTES_IN_GIGABYTE
+}
+
+/// Converts bytes to megabits,
+/// keeping the fractional part so slow rates are not rounded down to zero.
+fn bytes_to_megabits(b: u64) -> f64 {
+    const BYTES_IN_MEGABIT: f64 = 125_000.0;
+    b as f64 / BYTES_IN_MEGABIT
+}
+
+register_client!(Client, sys_info);
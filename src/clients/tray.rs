@@ -1,4 +1,5 @@
-use crate::register_fallible_client;
+use crate::clients::LazyClient;
+use crate::register_client;
 pub use system_tray::client::Client;
 
-register_fallible_client!(Client, tray);
+register_client!(LazyClient<Client>, tray);
@@ -1,5 +1,5 @@
-use crate::clients::ClientResult;
-use crate::register_fallible_client;
+use crate::clients::{ClientResult, LazyClient};
+use crate::register_client;
 use std::sync::Arc;
 use upower_dbus::UPowerProxy;
 use zbus::fdo::PropertiesProxy;
@@ -25,4 +25,4 @@ pub async fn create_display_proxy() -> ClientResult<PropertiesProxy<'static>> {
     Ok(Arc::new(proxy))
 }
 
-register_fallible_client!(PropertiesProxy<'static>, upower);
+register_client!(LazyClient<PropertiesProxy<'static>>, upower);
@@ -1,3 +1,5 @@
+#[cfg(feature = "volume+pipewire")]
+mod pipewire;
 mod sink;
 mod sink_input;
 
@@ -30,6 +32,36 @@ pub enum Event {
     RemoveInput(u32),
 }
 
+/// Common interface for the audio server backends.
+///
+/// PulseAudio is used by default,
+/// with PipeWire used only if no PulseAudio server is available.
+pub trait VolumeClient: Debug + Send + Sync {
+    /// Gets the list of sinks (output devices).
+    fn sinks(&self) -> Arc<Mutex<Vec<Sink>>>;
+
+    /// Gets the list of sink inputs (application playback streams).
+    fn sink_inputs(&self) -> Arc<Mutex<Vec<SinkInput>>>;
+
+    /// Sets the default sink by name.
+    fn set_default_sink(&self, name: &str);
+
+    /// Sets the volume of the sink with this name, as a percentage.
+    fn set_sink_volume(&self, name: &str, volume_percent: f64);
+
+    /// Sets whether the sink with this name is muted.
+    fn set_sink_muted(&self, name: &str, muted: bool);
+
+    /// Sets the volume of the sink input with this index, as a percentage.
+    fn set_input_volume(&self, index: u32, volume_percent: f64);
+
+    /// Sets whether the sink input with this index is muted.
+    fn set_input_muted(&self, index: u32, muted: bool);
+
+    /// Gets an event receiver.
+    fn subscribe(&self) -> broadcast::Receiver<Event>;
+}
+
 #[derive(Debug)]
 pub struct Client {
     connection: Arc<Mutex<ConnectionState>>,
@@ -144,8 +176,51 @@ impl Client {
     }
 }
 
-/// Creates a new Pulse volume client.
-pub fn create_client() -> Arc<Client> {
+impl VolumeClient for Client {
+    fn sinks(&self) -> Arc<Mutex<Vec<Sink>>> {
+        Self::sinks(self)
+    }
+
+    fn sink_inputs(&self) -> Arc<Mutex<Vec<SinkInput>>> {
+        Self::sink_inputs(self)
+    }
+
+    fn set_default_sink(&self, name: &str) {
+        Self::set_default_sink(self, name);
+    }
+
+    fn set_sink_volume(&self, name: &str, volume_percent: f64) {
+        Self::set_sink_volume(self, name, volume_percent);
+    }
+
+    fn set_sink_muted(&self, name: &str, muted: bool) {
+        Self::set_sink_muted(self, name, muted);
+    }
+
+    fn set_input_volume(&self, index: u32, volume_percent: f64) {
+        Self::set_input_volume(self, index, volume_percent);
+    }
+
+    fn set_input_muted(&self, index: u32, muted: bool) {
+        Self::set_input_muted(self, index, muted);
+    }
+
+    fn subscribe(&self) -> broadcast::Receiver<Event> {
+        Self::subscribe(self)
+    }
+}
+
+/// Creates a new volume client.
+///
+/// This uses PulseAudio, unless no server is available
+/// and Ironbar is compiled with PipeWire support.
+pub fn create_client() -> Arc<dyn VolumeClient> {
+    #[cfg(feature = "volume+pipewire")]
+    if !is_pulse_available() && pipewire::is_available() {
+        info!("PulseAudio server not found, using PipeWire");
+        return pipewire::create_client();
+    }
+
     let client = Arc::new(Client::new());
 
     {
@@ -158,6 +233,14 @@ pub fn create_client() -> Arc<Client> {
     client
 }
 
+/// Checks whether a PulseAudio server socket exists.
+/// This includes `pipewire-pulse`.
+#[cfg(feature = "volume+pipewire")]
+fn is_pulse_available() -> bool {
+    std::env::var_os("PULSE_SERVER").is_some()
+        || dirs::runtime_dir().is_some_and(|dir| dir.join("pulse").join("native").exists())
+}
+
 fn on_state_change(context: &Arc<Mutex<Context>>, data: &Data, tx: &broadcast::Sender<Event>) {
     let Ok(state) = context.try_lock().map(|lock| lock.get_state()) else {
         return;
@@ -306,4 +389,4 @@ pub fn percent_to_volume(target_percent: f64) -> u32 {
     }
 }
 
-register_client!(Client, volume);
+register_client!(dyn VolumeClient, volume);
@@ -0,0 +1,546 @@
+use super::{ArcMutVec, Data, Event, Sink, SinkInput, VolumeClient};
+use crate::{lock, send, spawn_blocking};
+use pipewire as pw;
+use pw::context::Context;
+use pw::main_loop::MainLoop;
+use pw::metadata::{Metadata, MetadataListener};
+use pw::node::{Node, NodeListener};
+use pw::registry::{GlobalObject, Registry};
+use pw::spa::param::ParamType;
+use pw::spa::pod::deserialize::PodDeserializer;
+use pw::spa::pod::serialize::PodSerializer;
+use pw::spa::pod::{Object, Pod, Property, PropertyFlags, Value, ValueArray};
+use pw::spa::sys as spa_sys;
+use pw::spa::utils::dict::DictRef;
+use pw::types::ObjectType;
+use serde::Deserialize;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt::{Debug, Formatter};
+use std::io::Cursor;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
+use tracing::{debug, error, info};
+
+/// Name of the metadata object which stores the default devices.
+const DEFAULT_METADATA_NAME: &str = "default";
+/// Metadata key for the current default sink.
+const DEFAULT_SINK_KEY: &str = "default.audio.sink";
+/// Metadata key for the user-selected default sink.
+const CONFIGURED_SINK_KEY: &str = "default.configured.audio.sink";
+
+/// Commands sent to the PipeWire main loop thread.
+#[derive(Debug)]
+enum Command {
+    SetDefaultSink(String),
+    SetVolume(u32, f64),
+    SetMuted(u32, bool),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum NodeKind {
+    Sink,
+    Input,
+}
+
+impl NodeKind {
+    fn from_media_class(media_class: &str) -> Option<Self> {
+        match media_class {
+            "Audio/Sink" => Some(Self::Sink),
+            "Stream/Output/Audio" => Some(Self::Input),
+            _ => None,
+        }
+    }
+}
+
+/// A node proxy bound from the registry.
+struct BoundNode {
+    proxy: Node,
+    kind: NodeKind,
+    /// The number of channels, as of the last volume update.
+    channels: usize,
+    _listener: NodeListener,
+}
+
+/// Objects which must live on the main loop thread.
+#[derive(Default)]
+struct LoopState {
+    nodes: HashMap<u32, BoundNode>,
+    metadata: Option<(Metadata, MetadataListener)>,
+}
+
+/// JSON value stored in the default metadata.
+#[derive(Deserialize)]
+struct MetadataNode {
+    name: String,
+}
+
+/// Volume client using the native PipeWire API,
+/// for systems without `pipewire-pulse`.
+///
+/// PipeWire nodes are mapped onto the same sink
+/// and sink input types as the PulseAudio client.
+/// Node IDs are used as indexes.
+pub struct Client {
+    data: Data,
+    commands: Mutex<pw::channel::Sender<Command>>,
+
+    tx: broadcast::Sender<Event>,
+    _rx: broadcast::Receiver<Event>,
+}
+
+impl Debug for Client {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Client")
+            .field("data", &self.data)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Client {
+    fn send(&self, command: Command) {
+        if lock!(self.commands).send(command).is_err() {
+            error!("PipeWire main loop is not running");
+        }
+    }
+
+    fn sink_id(&self, name: &str) -> Option<u32> {
+        lock!(self.data.sinks)
+            .iter()
+            .find(|sink| sink.name == name)
+            .map(|sink| sink.index)
+    }
+}
+
+impl VolumeClient for Client {
+    fn sinks(&self) -> ArcMutVec<Sink> {
+        self.data.sinks.clone()
+    }
+
+    fn sink_inputs(&self) -> ArcMutVec<SinkInput> {
+        self.data.sink_inputs.clone()
+    }
+
+    fn set_default_sink(&self, name: &str) {
+        self.send(Command::SetDefaultSink(name.to_string()));
+    }
+
+    fn set_sink_volume(&self, name: &str, volume_percent: f64) {
+        if let Some(id) = self.sink_id(name) {
+            self.send(Command::SetVolume(id, volume_percent));
+        }
+    }
+
+    fn set_sink_muted(&self, name: &str, muted: bool) {
+        if let Some(id) = self.sink_id(name) {
+            self.send(Command::SetMuted(id, muted));
+        }
+    }
+
+    fn set_input_volume(&self, index: u32, volume_percent: f64) {
+        self.send(Command::SetVolume(index, volume_percent));
+    }
+
+    fn set_input_muted(&self, index: u32, muted: bool) {
+        self.send(Command::SetMuted(index, muted));
+    }
+
+    fn subscribe(&self) -> broadcast::Receiver<Event> {
+        self.tx.subscribe()
+    }
+}
+
+/// Checks whether a PipeWire server socket exists.
+pub fn is_available() -> bool {
+    std::env::var_os("PIPEWIRE_REMOTE").is_some()
+        || dirs::runtime_dir().is_some_and(|dir| dir.join("pipewire-0").exists())
+}
+
+/// Creates a new PipeWire volume client,
+/// and starts its main loop on a background thread.
+pub fn create_client() -> Arc<dyn VolumeClient> {
+    let (tx, rx) = broadcast::channel(32);
+    let (command_tx, command_rx) = pw::channel::channel();
+
+    let client = Client {
+        data: Data::default(),
+        commands: Mutex::new(command_tx),
+        tx,
+        _rx: rx,
+    };
+
+    {
+        let data = client.data.clone();
+        let tx = client.tx.clone();
+
+        spawn_blocking(move || {
+            if let Err(err) = run(&data, &tx, command_rx) {
+                error!("PipeWire client failed: {err}");
+            }
+        });
+    }
+
+    Arc::new(client)
+}
+
+/// Connects to the server and runs the main loop.
+/// This blocks until the loop exits.
+fn run(
+    data: &Data,
+    tx: &broadcast::Sender<Event>,
+    commands: pw::channel::Receiver<Command>,
+) -> Result<(), pw::Error> {
+    pw::init();
+
+    let mainloop = MainLoop::new(None)?;
+    let context = Context::new(&mainloop)?;
+    let core = context.connect(None)?;
+    let registry = Rc::new(core.get_registry()?);
+
+    let state = Rc::new(RefCell::new(LoopState::default()));
+
+    let _commands = commands.attach(mainloop.loop_(), {
+        let state = state.clone();
+        move |command| on_command(command, &state)
+    });
+
+    let _listener = registry
+        .add_listener_local()
+        .global({
+            let registry = registry.clone();
+            let state = state.clone();
+            let data = data.clone();
+            let tx = tx.clone();
+
+            move |global| on_global(global, &registry, &state, &data, &tx)
+        })
+        .global_remove({
+            let state = state.clone();
+            let data = data.clone();
+            let tx = tx.clone();
+
+            move |id| on_global_remove(id, &state, &data, &tx)
+        })
+        .register();
+
+    info!("connected to PipeWire");
+    mainloop.run();
+
+    Ok(())
+}
+
+fn on_command(command: Command, state: &Rc<RefCell<LoopState>>) {
+    let state = state.borrow();
+
+    match command {
+        Command::SetDefaultSink(name) => {
+            if let Some((metadata, _)) = &state.metadata {
+                let value = format!(r#"{{"name":"{name}"}}"#);
+                metadata.set_property(
+                    0,
+                    CONFIGURED_SINK_KEY,
+                    Some("Spa:String:JSON"),
+                    Some(&value),
+                );
+            }
+        }
+        Command::SetVolume(id, volume_percent) => {
+            if let Some(node) = state.nodes.get(&id) {
+                let volume = percent_to_volume(volume_percent);
+
+                set_prop(
+                    &node.proxy,
+                    spa_sys::SPA_PROP_channelVolumes,
+                    Value::ValueArray(ValueArray::Float(vec![volume; node.channels.max(1)])),
+                );
+            }
+        }
+        Command::SetMuted(id, muted) => {
+            if let Some(node) = state.nodes.get(&id) {
+                set_prop(&node.proxy, spa_sys::SPA_PROP_mute, Value::Bool(muted));
+            }
+        }
+    }
+}
+
+fn on_global(
+    global: &GlobalObject<&DictRef>,
+    registry: &Registry,
+    state: &Rc<RefCell<LoopState>>,
+    data: &Data,
+    tx: &broadcast::Sender<Event>,
+) {
+    let Some(props) = global.props else {
+        return;
+    };
+
+    match global.type_ {
+        ObjectType::Node => {
+            let Some(kind) = props
+                .get("media.class")
+                .and_then(NodeKind::from_media_class)
+            else {
+                return;
+            };
+
+            let proxy = match registry.bind::<Node, _>(global) {
+                Ok(proxy) => proxy,
+                Err(err) => {
+                    error!("Failed to bind PipeWire node: {err}");
+                    return;
+                }
+            };
+
+            let id = global.id;
+
+            let listener = proxy
+                .add_listener_local()
+                .param({
+                    let state = state.clone();
+                    let data = data.clone();
+                    let tx = tx.clone();
+
+                    move |_, _, _, _, param| on_param(id, param, &state, &data, &tx)
+                })
+                .register();
+
+            proxy.subscribe_params(&[ParamType::Props]);
+
+            let get = |key| props.get(key).map(ToString::to_string);
+
+            match kind {
+                NodeKind::Sink => {
+                    let name = get("node.name").unwrap_or_default();
+                    let active = lock!(data.default_sink_name).as_ref() == Some(&name);
+
+                    let sink = Sink {
+                        index: id,
+                        description: get("node.description")
+                            .or_else(|| get("node.nick"))
+                            .unwrap_or_else(|| name.clone()),
+                        name,
+                        volume: 0.0,
+                        muted: false,
+                        active,
+                    };
+
+                    debug!("new sink: {}", sink.name);
+                    lock!(data.sinks).push(sink.clone());
+                    send!(tx, Event::AddSink(sink));
+                }
+                NodeKind::Input => {
+                    let input = SinkInput {
+                        index: id,
+                        name: get("media.name")
+                            .or_else(|| get("node.name"))
+                            .unwrap_or_default(),
+                        volume: 0.0,
+                        muted: false,
+                        can_set_volume: true,
+                        app_name: get("application.name"),
+                        app_binary: get("application.process.binary"),
+                        app_icon: get("application.icon_name"),
+                    };
+
+                    debug!("new sink input: {}", input.name);
+                    lock!(data.sink_inputs).push(input.clone());
+                    send!(tx, Event::AddInput(input));
+                }
+            }
+
+            state.borrow_mut().nodes.insert(
+                id,
+                BoundNode {
+                    proxy,
+                    kind,
+                    channels: 0,
+                    _listener: listener,
+                },
+            );
+        }
+        ObjectType::Metadata if props.get("metadata.name") == Some(DEFAULT_METADATA_NAME) => {
+            let metadata = match registry.bind::<Metadata, _>(global) {
+                Ok(metadata) => metadata,
+                Err(err) => {
+                    error!("Failed to bind PipeWire metadata: {err}");
+                    return;
+                }
+            };
+
+            let listener = metadata
+                .add_listener_local()
+                .property({
+                    let data = data.clone();
+                    let tx = tx.clone();
+
+                    move |_, key, _, value| {
+                        if key == Some(DEFAULT_SINK_KEY) {
+                            on_default_sink(value, &data, &tx);
+                        }
+
+                        0
+                    }
+                })
+                .register();
+
+            state.borrow_mut().metadata = Some((metadata, listener));
+        }
+        _ => {}
+    }
+}
+
+fn on_global_remove(
+    id: u32,
+    state: &Rc<RefCell<LoopState>>,
+    data: &Data,
+    tx: &broadcast::Sender<Event>,
+) {
+    let Some(node) = state.borrow_mut().nodes.remove(&id) else {
+        return;
+    };
+
+    match node.kind {
+        NodeKind::Sink => {
+            let mut sinks = lock!(data.sinks);
+            if let Some(pos) = sinks.iter().position(|sink| sink.index == id) {
+                let sink = sinks.remove(pos);
+                send!(tx, Event::RemoveSink(sink.name));
+            }
+        }
+        NodeKind::Input => {
+            let mut inputs = lock!(data.sink_inputs);
+            if let Some(pos) = inputs.iter().position(|input| input.index == id) {
+                inputs.remove(pos);
+                send!(tx, Event::RemoveInput(id));
+            }
+        }
+    }
+}
+
+/// Updates the volume and mute state of a node
+/// from its `Props` param.
+fn on_param(
+    id: u32,
+    param: Option<&Pod>,
+    state: &Rc<RefCell<LoopState>>,
+    data: &Data,
+    tx: &broadcast::Sender<Event>,
+) {
+    let Some(param) = param else {
+        return;
+    };
+
+    let Ok((_, Value::Object(object))) = PodDeserializer::deserialize_any_from(param.as_bytes())
+    else {
+        return;
+    };
+
+    let mut volumes = None;
+    let mut muted = None;
+
+    for property in object.properties {
+        match (property.key, property.value) {
+            (spa_sys::SPA_PROP_channelVolumes, Value::ValueArray(ValueArray::Float(values))) => {
+                volumes = Some(values);
+            }
+            (spa_sys::SPA_PROP_mute, Value::Bool(value)) => muted = Some(value),
+            _ => {}
+        }
+    }
+
+    let kind = {
+        let mut state = state.borrow_mut();
+        let Some(node) = state.nodes.get_mut(&id) else {
+            return;
+        };
+
+        if let Some(volumes) = &volumes {
+            node.channels = volumes.len();
+        }
+
+        node.kind
+    };
+
+    let volume = volumes.as_deref().map(volume_to_percent);
+
+    match kind {
+        NodeKind::Sink => {
+            let mut sinks = lock!(data.sinks);
+            if let Some(sink) = sinks.iter_mut().find(|sink| sink.index == id) {
+                sink.volume = volume.unwrap_or(sink.volume);
+                sink.muted = muted.unwrap_or(sink.muted);
+                send!(tx, Event::UpdateSink(sink.clone()));
+            }
+        }
+        NodeKind::Input => {
+            let mut inputs = lock!(data.sink_inputs);
+            if let Some(input) = inputs.iter_mut().find(|input| input.index == id) {
+                input.volume = volume.unwrap_or(input.volume);
+                input.muted = muted.unwrap_or(input.muted);
+                send!(tx, Event::UpdateInput(input.clone()));
+            }
+        }
+    }
+}
+
+/// Marks the sink named in the metadata value as active,
+/// and all others as inactive.
+fn on_default_sink(value: Option<&str>, data: &Data, tx: &broadcast::Sender<Event>) {
+    let name = value
+        .and_then(|value| serde_json::from_str::<MetadataNode>(value).ok())
+        .map(|node| node.name);
+
+    debug!("default sink changed: {name:?}");
+
+    for sink in lock!(data.sinks).iter_mut() {
+        let active = name.as_ref() == Some(&sink.name);
+        if sink.active != active {
+            sink.active = active;
+            send!(tx, Event::UpdateSink(sink.clone()));
+        }
+    }
+
+    *lock!(data.default_sink_name) = name;
+}
+
+/// Sets a single property in a node's `Props` param.
+fn set_prop(node: &Node, key: u32, value: Value) {
+    let props = Value::Object(Object {
+        type_: spa_sys::SPA_TYPE_OBJECT_Props,
+        id: spa_sys::SPA_PARAM_Props,
+        properties: vec![Property {
+            key,
+            flags: PropertyFlags::empty(),
+            value,
+        }],
+    });
+
+    match PodSerializer::serialize(Cursor::new(Vec::new()), &props) {
+        Ok((cursor, _)) => {
+            let bytes = cursor.into_inner();
+            if let Some(pod) = Pod::from_bytes(&bytes) {
+                node.set_param(ParamType::Props, 0, pod);
+            }
+        }
+        Err(err) => error!("Failed to serialize PipeWire props: {err:?}"),
+    }
+}
+
+/// Converts PipeWire's linear channel volumes into a single percentage value,
+/// representing the average value across all channels.
+///
+/// PipeWire uses a cubic scale, which matches the percentages shown by PulseAudio.
+fn volume_to_percent(volumes: &[f32]) -> f64 {
+    if volumes.is_empty() {
+        return 0.0;
+    }
+
+    let avg = volumes.iter().sum::<f32>() / volumes.len() as f32;
+    (f64::from(avg).cbrt() * 100.0).round()
+}
+
+/// Converts a percentage volume into a linear PipeWire volume.
+fn percent_to_volume(volume_percent: f64) -> f32 {
+    (volume_percent.max(0.0) / 100.0).powi(3) as f32
+}
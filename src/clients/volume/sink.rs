@@ -11,7 +11,7 @@ use tracing::{debug, error};
 
 #[derive(Debug, Clone)]
 pub struct Sink {
-    index: u32,
+    pub(super) index: u32,
     pub name: String,
     pub description: String,
     pub volume: f64,
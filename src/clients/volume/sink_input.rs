@@ -4,6 +4,7 @@ use libpulse_binding::callbacks::ListResult;
 use libpulse_binding::context::introspect::SinkInputInfo;
 use libpulse_binding::context::subscribe::Operation;
 use libpulse_binding::context::Context;
+use libpulse_binding::proplist::{properties, Proplist};
 use std::sync::{mpsc, Arc, Mutex};
 use tokio::sync::broadcast;
 use tracing::{debug, error};
@@ -16,10 +17,27 @@ pub struct SinkInput {
     pub muted: bool,
 
     pub can_set_volume: bool,
+
+    /// The display name of the application which owns the stream.
+    pub app_name: Option<String>,
+    /// The binary of the application's process.
+    pub app_binary: Option<String>,
+    /// The icon name set by the application.
+    pub app_icon: Option<String>,
+}
+
+impl SinkInput {
+    /// Gets an identifier for the application which owns the stream,
+    /// or `None` if the application is unknown.
+    pub fn app_id(&self) -> Option<&str> {
+        self.app_binary.as_deref().or(self.app_name.as_deref())
+    }
 }
 
 impl From<&SinkInputInfo<'_>> for SinkInput {
     fn from(value: &SinkInputInfo) -> Self {
+        let prop = |key: &str| get_prop(&value.proplist, key);
+
         Self {
             index: value.index,
             name: value
@@ -27,6 +45,9 @@ impl From<&SinkInputInfo<'_>> for SinkInput {
                 .as_ref()
                 .map(ToString::to_string)
                 .unwrap_or_default(),
+            app_name: prop(properties::APPLICATION_NAME),
+            app_binary: prop(properties::APPLICATION_PROCESS_BINARY),
+            app_icon: prop(properties::APPLICATION_ICON_NAME),
             muted: value.mute,
             volume: volume_to_percent(value.volume),
             can_set_volume: value.has_volume && value.volume_writable,
@@ -34,6 +55,11 @@ impl From<&SinkInputInfo<'_>> for SinkInput {
     }
 }
 
+/// Gets a non-empty string property.
+fn get_prop(proplist: &Proplist, key: &str) -> Option<String> {
+    proplist.get_str(key).filter(|value| !value.is_empty())
+}
+
 impl Client {
     pub fn sink_inputs(&self) -> Arc<Mutex<Vec<SinkInput>>> {
         self.data.sink_inputs.clone()
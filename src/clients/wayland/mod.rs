@@ -35,6 +35,14 @@ cfg_if! {
     }
 }
 
+cfg_if! {
+    if #[cfg(feature = "launcher+thumbnails")] {
+        mod toplevel_capture;
+        use self::toplevel_capture::CaptureState;
+        pub use self::toplevel_capture::ToplevelImage;
+    }
+}
+
 cfg_if! {
     if #[cfg(feature = "clipboard")] {
         mod wlr_data_control;
@@ -69,7 +77,7 @@ pub enum Event {
 pub enum Request {
     Roundtrip,
 
-    #[cfg(feature = "ipc")]
+    #[cfg(any(feature = "ipc", feature = "output"))]
     OutputInfoAll,
 
     #[cfg(any(feature = "focused", feature = "launcher"))]
@@ -78,6 +86,8 @@ pub enum Request {
     ToplevelFocus(usize),
     #[cfg(feature = "launcher")]
     ToplevelMinimize(usize),
+    #[cfg(feature = "launcher+thumbnails")]
+    ToplevelCapture(usize, tokio::sync::oneshot::Sender<Option<ToplevelImage>>),
 
     #[cfg(feature = "clipboard")]
     CopyToClipboard(ClipboardItem),
@@ -90,7 +100,7 @@ pub enum Response {
     /// An empty success response
     Ok,
 
-    #[cfg(feature = "ipc")]
+    #[cfg(any(feature = "ipc", feature = "output"))]
     OutputInfoAll(Vec<smithay_client_toolkit::output::OutputInfo>),
 
     #[cfg(any(feature = "focused", feature = "launcher"))]
@@ -204,6 +214,10 @@ pub struct Environment {
     #[cfg(any(feature = "focused", feature = "launcher"))]
     handles: Vec<ToplevelHandle>,
 
+    // -- toplevel capture --
+    #[cfg(feature = "launcher+thumbnails")]
+    capture: Option<CaptureState>,
+
     // -- clipboard --
     #[cfg(feature = "clipboard")]
     data_control_device_manager_state: DataControlDeviceManagerState,
@@ -270,6 +284,9 @@ impl Environment {
         ToplevelManagerState::bind(&globals, &qh)
             .expect("to bind to wlr_foreign_toplevel_manager global");
 
+        #[cfg(feature = "launcher+thumbnails")]
+        let capture = CaptureState::bind(&globals, &qh);
+
         #[cfg(feature = "clipboard")]
         let data_control_device_manager_state = DataControlDeviceManagerState::bind(&globals, &qh)
             .expect("to bind to wlr_data_control_device_manager global");
@@ -286,6 +303,8 @@ impl Environment {
             response_tx,
             #[cfg(any(feature = "focused", feature = "launcher"))]
             handles: vec![],
+            #[cfg(feature = "launcher+thumbnails")]
+            capture,
 
             #[cfg(feature = "clipboard")]
             data_control_devices: vec![],
@@ -324,7 +343,7 @@ impl Environment {
                 debug!("received roundtrip request");
                 send!(env.response_tx, Response::Ok);
             }
-            #[cfg(feature = "ipc")]
+            #[cfg(any(feature = "ipc", feature = "output"))]
             Msg(Request::OutputInfoAll) => {
                 let infos = env.output_info_all();
                 send!(env.response_tx, Response::OutputInfoAll(infos));
@@ -365,6 +384,11 @@ impl Environment {
 
                 send!(env.response_tx, Response::Ok);
             }
+            #[cfg(feature = "launcher+thumbnails")]
+            Msg(Request::ToplevelCapture(id, tx)) => {
+                env.capture_toplevel(id, tx);
+                send!(env.response_tx, Response::Ok);
+            }
             #[cfg(feature = "clipboard")]
             Msg(Request::CopyToClipboard(item)) => {
                 env.copy_to_clipboard(item);
@@ -0,0 +1,520 @@
+//! One-shot capture of toplevel (window) contents,
+//! using the `ext-image-copy-capture` protocol.
+//!
+//! Capture sources can only be created from `ext-foreign-toplevel-list` handles,
+//! so toplevels are tracked a second time here.
+//! The two protocols share no identifier,
+//! so the first time a `wlr-foreign-toplevel-management` handle is captured,
+//! it is paired with an unpaired handle with the same app ID and title.
+//! Later captures use the compositor's identifier for the paired handle,
+//! so windows with duplicate titles, or which have since changed title,
+//! still capture the right window.
+
+use super::{Client, Environment, Request, Response, ToplevelHandle};
+use crate::lock;
+use smithay_client_toolkit::error::GlobalError;
+use smithay_client_toolkit::globals::ProvidesBoundGlobal;
+use smithay_client_toolkit::shm::raw::RawPool;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::oneshot;
+use tracing::{debug, error, trace};
+use wayland_client::globals::{BindError, GlobalList};
+use wayland_client::protocol::wl_buffer::WlBuffer;
+use wayland_client::protocol::wl_shm::{self, WlShm};
+use wayland_client::{event_created_child, Connection, Dispatch, Proxy, QueueHandle, WEnum};
+use wayland_protocols::ext::foreign_toplevel_list::v1::client::{
+    ext_foreign_toplevel_handle_v1::{self, ExtForeignToplevelHandleV1},
+    ext_foreign_toplevel_list_v1::{self, ExtForeignToplevelListV1},
+};
+use wayland_protocols::ext::image_capture_source::v1::client::{
+    ext_foreign_toplevel_image_capture_source_manager_v1::ExtForeignToplevelImageCaptureSourceManagerV1,
+    ext_image_capture_source_v1::ExtImageCaptureSourceV1,
+};
+use wayland_protocols::ext::image_copy_capture::v1::client::{
+    ext_image_copy_capture_frame_v1::{self, ExtImageCopyCaptureFrameV1},
+    ext_image_copy_capture_manager_v1::{ExtImageCopyCaptureManagerV1, Options},
+    ext_image_copy_capture_session_v1::{self, ExtImageCopyCaptureSessionV1},
+};
+
+/// The captured contents of a toplevel,
+/// as tightly packed 8-bit RGBA pixels.
+#[derive(Debug, Clone)]
+pub struct ToplevelImage {
+    pub width: i32,
+    pub height: i32,
+    pub data: Vec<u8>,
+}
+
+/// The globals required to capture toplevels.
+/// These are not provided by all compositors.
+#[derive(Debug)]
+pub struct CaptureState {
+    shm: WlShm,
+    source_manager: ExtForeignToplevelImageCaptureSourceManagerV1,
+    capture_manager: ExtImageCopyCaptureManagerV1,
+
+    /// Kept alive to continue receiving toplevels.
+    _list: ExtForeignToplevelListV1,
+    toplevels: Vec<ExtForeignToplevelHandleV1>,
+
+    /// Map of `wlr-foreign-toplevel-management` handle IDs
+    /// to the identifier of their `ext-foreign-toplevel-list` handle.
+    paired: HashMap<usize, String>,
+}
+
+impl CaptureState {
+    /// Binds the required globals,
+    /// returning `None` if any are unavailable.
+    pub fn bind(globals: &GlobalList, qh: &QueueHandle<Environment>) -> Option<Self> {
+        let bind = || -> Result<Self, BindError> {
+            Ok(Self {
+                shm: globals.bind(qh, 1..=1, ())?,
+                source_manager: globals.bind(qh, 1..=1, ())?,
+                capture_manager: globals.bind(qh, 1..=1, ())?,
+                _list: globals.bind(qh, 1..=1, ())?,
+                toplevels: vec![],
+                paired: HashMap::new(),
+            })
+        };
+
+        match bind() {
+            Ok(state) => {
+                debug!("Bound to toplevel capture globals");
+                Some(state)
+            }
+            Err(err) => {
+                debug!("Toplevel capture is not supported by the compositor: {err}");
+                None
+            }
+        }
+    }
+}
+
+impl ProvidesBoundGlobal<WlShm, 1> for CaptureState {
+    fn bound_global(&self) -> Result<WlShm, GlobalError> {
+        Ok(self.shm.clone())
+    }
+}
+
+/// Details of an `ext-foreign-toplevel-list` handle,
+/// used to match it to a `wlr-foreign-toplevel-management` handle.
+#[derive(Debug, Default)]
+pub struct ExtToplevelData {
+    inner: Mutex<ExtToplevelInfo>,
+}
+
+#[derive(Debug, Default)]
+struct ExtToplevelInfo {
+    /// Unique identifier assigned by the compositor.
+    identifier: String,
+    app_id: String,
+    title: String,
+}
+
+impl ExtToplevelData {
+    fn identifier(&self) -> String {
+        lock!(self.inner).identifier.clone()
+    }
+
+    fn matches(&self, app_id: &str, title: &str) -> bool {
+        let info = lock!(self.inner);
+        info.app_id == app_id && info.title == title
+    }
+}
+
+impl CaptureState {
+    /// Finds the `ext-foreign-toplevel-list` handle
+    /// for a `wlr-foreign-toplevel-management` handle,
+    /// pairing them if this has not been done yet.
+    fn find_toplevel(
+        &mut self,
+        handle_id: usize,
+        app_id: &str,
+        title: &str,
+    ) -> Option<ExtForeignToplevelHandleV1> {
+        let identifier = |handle: &ExtForeignToplevelHandleV1| {
+            handle
+                .data::<ExtToplevelData>()
+                .map(ExtToplevelData::identifier)
+        };
+
+        if let Some(paired) = self.paired.get(&handle_id) {
+            return self
+                .toplevels
+                .iter()
+                .find(|handle| identifier(handle).as_ref() == Some(paired))
+                .cloned();
+        }
+
+        let toplevel = self
+            .toplevels
+            .iter()
+            .filter(|handle| {
+                handle
+                    .data::<ExtToplevelData>()
+                    .is_some_and(|data| data.matches(app_id, title))
+            })
+            .find(|handle| {
+                identifier(handle).is_some_and(|id| !self.paired.values().any(|p| p == &id))
+            })
+            .cloned()?;
+
+        if let Some(id) = identifier(&toplevel) {
+            self.paired.insert(handle_id, id);
+        }
+
+        Some(toplevel)
+    }
+}
+
+/// State for a single in-progress capture.
+pub struct CaptureData {
+    inner: Mutex<CaptureDataInner>,
+}
+
+#[derive(Default)]
+struct CaptureDataInner {
+    tx: Option<oneshot::Sender<Option<ToplevelImage>>>,
+
+    source: Option<ExtImageCaptureSourceV1>,
+    session: Option<ExtImageCopyCaptureSessionV1>,
+    buffer: Option<WlBuffer>,
+    pool: Option<RawPool>,
+
+    width: u32,
+    height: u32,
+    format: Option<wl_shm::Format>,
+}
+
+impl CaptureDataInner {
+    /// Sends the result of the capture,
+    /// and releases its protocol objects.
+    fn finish(&mut self, image: Option<ToplevelImage>) {
+        if let Some(tx) = self.tx.take() {
+            // the receiver may have been dropped if the popup closed
+            let _ = tx.send(image);
+        }
+
+        if let Some(buffer) = self.buffer.take() {
+            buffer.destroy();
+        }
+
+        if let Some(session) = self.session.take() {
+            session.destroy();
+        }
+
+        if let Some(source) = self.source.take() {
+            source.destroy();
+        }
+
+        self.pool = None;
+    }
+
+    /// Reads the captured buffer, converting it to RGBA.
+    fn read_image(&mut self) -> Option<ToplevelImage> {
+        let format = self.format?;
+        let len = (self.width * self.height * 4) as usize;
+
+        let pool = self.pool.as_mut()?;
+        let bytes = pool.mmap().get(..len)?;
+
+        // shm formats are little-endian, so `argb8888` is stored as BGRA
+        let data = bytes
+            .chunks_exact(4)
+            .flat_map(|pixel| {
+                let alpha = if format == wl_shm::Format::Xrgb8888 {
+                    u8::MAX
+                } else {
+                    pixel[3]
+                };
+
+                [pixel[2], pixel[1], pixel[0], alpha]
+            })
+            .collect();
+
+        Some(ToplevelImage {
+            width: self.width as i32,
+            height: self.height as i32,
+            data,
+        })
+    }
+}
+
+impl Client {
+    /// Captures the current contents of the toplevel with the provided ID.
+    ///
+    /// Resolves to `None` if the compositor does not support capturing toplevels,
+    /// or the capture fails.
+    pub fn toplevel_capture(&self, handle_id: usize) -> oneshot::Receiver<Option<ToplevelImage>> {
+        let (tx, rx) = oneshot::channel();
+
+        match self.send_request(Request::ToplevelCapture(handle_id, tx)) {
+            Response::Ok => rx,
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl Environment {
+    /// Starts capturing the toplevel with the provided ID.
+    /// The result is sent on `tx` once the capture completes.
+    pub(super) fn capture_toplevel(
+        &mut self,
+        handle_id: usize,
+        tx: oneshot::Sender<Option<ToplevelImage>>,
+    ) {
+        let Some(capture) = &mut self.capture else {
+            let _ = tx.send(None);
+            return;
+        };
+
+        let toplevel = self
+            .handles
+            .iter()
+            .filter_map(ToplevelHandle::info)
+            .find(|info| info.id == handle_id)
+            .and_then(|info| capture.find_toplevel(handle_id, &info.app_id, &info.title));
+
+        let Some(toplevel) = toplevel else {
+            debug!("Could not find capturable toplevel for handle {handle_id}");
+            let _ = tx.send(None);
+            return;
+        };
+
+        trace!("Capturing toplevel {handle_id}");
+
+        let source = capture
+            .source_manager
+            .create_source(&toplevel, &self.queue_handle, ());
+
+        let data = Arc::new(CaptureData {
+            inner: Mutex::new(CaptureDataInner {
+                tx: Some(tx),
+                source: Some(source.clone()),
+                ..Default::default()
+            }),
+        });
+
+        let session = capture.capture_manager.create_session(
+            &source,
+            Options::empty(),
+            &self.queue_handle,
+            data.clone(),
+        );
+
+        lock!(data.inner).session = Some(session);
+    }
+}
+
+impl Dispatch<ExtForeignToplevelListV1, ()> for Environment {
+    event_created_child!(Environment, ExtForeignToplevelListV1, [
+        ext_foreign_toplevel_list_v1::EVT_TOPLEVEL_OPCODE => (ExtForeignToplevelHandleV1, ExtToplevelData::default())
+    ]);
+
+    fn event(
+        state: &mut Self,
+        _list: &ExtForeignToplevelListV1,
+        event: ext_foreign_toplevel_list_v1::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        if let ext_foreign_toplevel_list_v1::Event::Toplevel { toplevel } = event {
+            if let Some(capture) = &mut state.capture {
+                capture.toplevels.push(toplevel);
+            }
+        }
+    }
+}
+
+impl Dispatch<ExtForeignToplevelHandleV1, ExtToplevelData> for Environment {
+    fn event(
+        state: &mut Self,
+        handle: &ExtForeignToplevelHandleV1,
+        event: ext_foreign_toplevel_handle_v1::Event,
+        data: &ExtToplevelData,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        match event {
+            ext_foreign_toplevel_handle_v1::Event::Identifier { identifier } => {
+                lock!(data.inner).identifier = identifier;
+            }
+            ext_foreign_toplevel_handle_v1::Event::AppId { app_id } => {
+                lock!(data.inner).app_id = app_id;
+            }
+            ext_foreign_toplevel_handle_v1::Event::Title { title } => {
+                lock!(data.inner).title = title;
+            }
+            ext_foreign_toplevel_handle_v1::Event::Closed => {
+                if let Some(capture) = &mut state.capture {
+                    capture.toplevels.retain(|h| h != handle);
+
+                    let identifier = data.identifier();
+                    capture.paired.retain(|_, paired| paired != &identifier);
+                }
+
+                handle.destroy();
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<ExtImageCopyCaptureSessionV1, Arc<CaptureData>> for Environment {
+    fn event(
+        state: &mut Self,
+        session: &ExtImageCopyCaptureSessionV1,
+        event: ext_image_copy_capture_session_v1::Event,
+        data: &Arc<CaptureData>,
+        _conn: &Connection,
+        qh: &QueueHandle<Self>,
+    ) {
+        let mut inner = lock!(data.inner);
+
+        match event {
+            ext_image_copy_capture_session_v1::Event::BufferSize { width, height } => {
+                inner.width = width;
+                inner.height = height;
+            }
+            ext_image_copy_capture_session_v1::Event::ShmFormat {
+                format: WEnum::Value(format),
+            } if inner.format.is_none()
+                && matches!(format, wl_shm::Format::Argb8888 | wl_shm::Format::Xrgb8888) =>
+            {
+                inner.format = Some(format);
+            }
+            // constraints are re-sent if they change,
+            // so only the first set is used to capture a frame.
+            ext_image_copy_capture_session_v1::Event::Done if inner.pool.is_none() => {
+                let Some(format) = inner.format else {
+                    debug!("Compositor offered no supported formats for toplevel capture");
+                    inner.finish(None);
+                    return;
+                };
+
+                let Some(capture) = &state.capture else {
+                    inner.finish(None);
+                    return;
+                };
+
+                let (width, height) = (inner.width as i32, inner.height as i32);
+                let stride = width * 4;
+
+                let mut pool = match RawPool::new((stride * height) as usize, capture) {
+                    Ok(pool) => pool,
+                    Err(err) => {
+                        error!("Failed to create buffer for toplevel capture: {err:?}");
+                        inner.finish(None);
+                        return;
+                    }
+                };
+
+                let buffer = pool.create_buffer(0, width, height, stride, format, (), qh);
+
+                let frame = session.create_frame(qh, data.clone());
+                frame.attach_buffer(&buffer);
+                frame.damage_buffer(0, 0, width, height);
+                frame.capture();
+
+                inner.pool = Some(pool);
+                inner.buffer = Some(buffer);
+            }
+            ext_image_copy_capture_session_v1::Event::Stopped => {
+                debug!("Toplevel capture session stopped");
+                inner.finish(None);
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<ExtImageCopyCaptureFrameV1, Arc<CaptureData>> for Environment {
+    fn event(
+        _state: &mut Self,
+        frame: &ExtImageCopyCaptureFrameV1,
+        event: ext_image_copy_capture_frame_v1::Event,
+        data: &Arc<CaptureData>,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        let mut inner = lock!(data.inner);
+
+        match event {
+            ext_image_copy_capture_frame_v1::Event::Ready => {
+                let image = inner.read_image();
+                frame.destroy();
+                inner.finish(image);
+            }
+            ext_image_copy_capture_frame_v1::Event::Failed { reason } => {
+                debug!("Toplevel capture failed: {reason:?}");
+                frame.destroy();
+                inner.finish(None);
+            }
+            _ => {}
+        }
+    }
+}
+
+// the remaining objects do not send any events which need handling
+
+impl Dispatch<WlShm, ()> for Environment {
+    fn event(
+        _state: &mut Self,
+        _shm: &WlShm,
+        _event: wl_shm::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+impl Dispatch<WlBuffer, ()> for Environment {
+    fn event(
+        _state: &mut Self,
+        _buffer: &WlBuffer,
+        _event: <WlBuffer as Proxy>::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+impl Dispatch<ExtForeignToplevelImageCaptureSourceManagerV1, ()> for Environment {
+    fn event(
+        _state: &mut Self,
+        _manager: &ExtForeignToplevelImageCaptureSourceManagerV1,
+        _event: <ExtForeignToplevelImageCaptureSourceManagerV1 as Proxy>::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+impl Dispatch<ExtImageCaptureSourceV1, ()> for Environment {
+    fn event(
+        _state: &mut Self,
+        _source: &ExtImageCaptureSourceV1,
+        _event: <ExtImageCaptureSourceV1 as Proxy>::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+impl Dispatch<ExtImageCopyCaptureManagerV1, ()> for Environment {
+    fn event(
+        _state: &mut Self,
+        _manager: &ExtImageCopyCaptureManagerV1,
+        _event: <ExtImageCopyCaptureManagerV1 as Proxy>::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+    }
+}
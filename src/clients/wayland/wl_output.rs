@@ -21,7 +21,7 @@ pub enum OutputEventType {
 
 impl Client {
     /// Gets the information for all outputs.
-    #[cfg(feature = "ipc")]
+    #[cfg(any(feature = "ipc", feature = "output"))]
     pub fn output_info_all(&self) -> Vec<OutputInfo> {
         use super::{Request, Response};
         match self.send_request(Request::OutputInfoAll) {
@@ -37,7 +37,7 @@ impl Client {
 }
 
 impl Environment {
-    #[cfg(feature = "ipc")]
+    #[cfg(any(feature = "ipc", feature = "output"))]
     pub fn output_info_all(&mut self) -> Vec<OutputInfo> {
         self.output_state
             .outputs()
@@ -1,10 +1,13 @@
 use crate::dynamic_value::{dynamic_string, DynamicBool};
+use crate::rc_mut;
 use crate::script::{Script, ScriptInput};
-use glib::Propagation;
-use gtk::gdk::ScrollDirection;
+use glib::{Propagation, SourceId};
+use gtk::gdk::{EventType, ScrollDirection};
 use gtk::prelude::*;
 use gtk::{EventBox, Justification, Orientation, Revealer, RevealerTransitionType};
 use serde::Deserialize;
+use std::rc::Rc;
+use std::time::Duration;
 use tracing::trace;
 
 /// The following are module-level options which are present on **all** modules.
@@ -69,6 +72,28 @@ pub struct CommonConfig {
     /// **Default**: `250`
     pub transition_duration: Option<u32>,
 
+    /// The time in milliseconds after which to hide the module
+    /// if it has not updated or been hovered.
+    ///
+    /// The module is shown again on its next update,
+    /// or when the cursor enters it.
+    /// With slide transitions, a small strip is left in place of the module
+    /// for the cursor to enter. Use `crossfade` to keep its full space instead.
+    ///
+    /// This has no effect if `show_if` is configured.
+    ///
+    /// **Default**: `null`
+    pub idle_hide_ms: Option<u64>,
+
+    /// Whether the module should start hidden.
+    /// It can then be shown using the `module` [IPC command](controlling-ironbar#module).
+    ///
+    /// If `show_if` is configured, it takes over once it is first evaluated.
+    ///
+    /// **Default**: `false`
+    #[serde(default)]
+    pub start_hidden: bool,
+
     /// A [script](scripts) to run when the module is left-clicked.
     ///
     /// **Supported script types**: `oneshot`.
@@ -87,7 +112,8 @@ pub struct CommonConfig {
     /// **Supported script types**: `oneshot`.
     /// <br>
     /// **Default**: `null`
-    /// /// # Example
+    ///
+    /// # Example
     ///
     /// ```corn
     /// { on_click_right = "echo 'event' >> log.txt" }
@@ -106,6 +132,21 @@ pub struct CommonConfig {
     /// ```
     pub on_click_middle: Option<ScriptInput>,
 
+    /// A [script](scripts) to run when the module is double-clicked with the left button.
+    ///
+    /// When this is set, the `on_click_left` script is delayed
+    /// by the system double-click time, and does not run for double-clicks.
+    ///
+    /// **Supported script types**: `oneshot`.
+    /// <br>
+    /// **Default**: `null`
+    /// # Example
+    ///
+    /// ```corn
+    /// { on_double_click = "echo 'event' >> log.txt" }
+    /// ```
+    pub on_double_click: Option<ScriptInput>,
+
     /// A [script](scripts) to run when the module is scrolled up on.
     ///
     /// **Supported script types**: `oneshot`.
@@ -157,6 +198,64 @@ pub struct CommonConfig {
     /// Prevents the popup from opening on-click for this widget.
     #[serde(default)]
     pub disable_popup: bool,
+
+    /// The action which opens the module's popup.
+    /// This has no effect on modules without a popup.
+    ///
+    /// When set to `hover`, the popup opens when the cursor enters the module,
+    /// and closes shortly after the cursor leaves both the module and the popup.
+    ///
+    /// **Valid options**: `click`, `hover`
+    /// <br>
+    /// **Default**: `click`
+    #[serde(default)]
+    pub popup_trigger: PopupTrigger,
+
+    /// How the module's popup receives keyboard input while open.
+    /// This has no effect on modules without a popup.
+    ///
+    /// - `none` never receives keyboard focus.
+    /// - `on_demand` receives focus when clicked, without grabbing all input.
+    /// - `exclusive` takes all keyboard input while open.
+    ///
+    /// Popups containing text entries may need `exclusive`
+    /// on compositors which do not support `on_demand`.
+    ///
+    /// **Valid options**: `none`, `on_demand`, `exclusive`
+    /// <br>
+    /// **Default**: `on_demand`
+    #[serde(default)]
+    pub keyboard_interactivity: KeyboardInteractivity,
+}
+
+#[derive(Debug, Default, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum PopupTrigger {
+    #[default]
+    Click,
+    Hover,
+}
+
+#[derive(Debug, Default, Deserialize, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum KeyboardInteractivity {
+    None,
+    #[default]
+    #[serde(alias = "on-demand")]
+    OnDemand,
+    Exclusive,
+}
+
+impl From<KeyboardInteractivity> for gtk_layer_shell::KeyboardMode {
+    fn from(mode: KeyboardInteractivity) -> Self {
+        match mode {
+            KeyboardInteractivity::None => Self::None,
+            KeyboardInteractivity::OnDemand => Self::OnDemand,
+            KeyboardInteractivity::Exclusive => Self::Exclusive,
+        }
+    }
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -238,16 +337,56 @@ impl TransitionType {
 
 impl CommonConfig {
     /// Configures the module's container according to the common config options.
+    ///
+    /// Click scripts are connected to the outer container,
+    /// so a module can prevent them running for clicks it handles itself
+    /// by returning `Propagation::Stop` from its own handler.
     pub fn install_events(mut self, container: &EventBox, revealer: &Revealer) {
         self.install_show_if(container, revealer);
 
-        let left_click_script = self.on_click_left.map(Script::new_polling);
+        let left_click_script = self.on_click_left.map(Script::new_polling).map(Rc::new);
         let middle_click_script = self.on_click_middle.map(Script::new_polling);
         let right_click_script = self.on_click_right.map(Script::new_polling);
+        let double_click_script = self.on_double_click.map(Script::new_polling);
+
+        let pending_click = rc_mut!(None::<SourceId>);
 
         container.connect_button_press_event(move |_, event| {
+            if let (1, Some(double_click_script)) = (event.button(), &double_click_script) {
+                match event.event_type() {
+                    EventType::DoubleButtonPress => {
+                        if let Some(source) = pending_click.borrow_mut().take() {
+                            source.remove();
+                        }
+
+                        trace!("Running on-double-click script");
+                        double_click_script.run_as_oneshot(None);
+                    }
+                    EventType::ButtonPress => {
+                        if let Some(script) = left_click_script.clone() {
+                            let source = {
+                                let pending_click = pending_click.clone();
+                                glib::timeout_add_local_once(double_click_time(), move || {
+                                    pending_click.borrow_mut().take();
+
+                                    trace!("Running on-click script: 1");
+                                    script.run_as_oneshot(None);
+                                })
+                            };
+
+                            if let Some(source) = pending_click.borrow_mut().replace(source) {
+                                source.remove();
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+
+                return Propagation::Proceed;
+            }
+
             let script = match event.button() {
-                1 => left_click_script.as_ref(),
+                1 => left_click_script.as_deref(),
                 2 => middle_click_script.as_ref(),
                 3 => right_click_script.as_ref(),
                 _ => None,
@@ -269,10 +408,15 @@ impl CommonConfig {
                 ScrollDirection::Up => scroll_up_script.as_ref(),
                 ScrollDirection::Down => scroll_down_script.as_ref(),
                 ScrollDirection::Smooth => {
-                    if event.scroll_deltas().unwrap_or_default().1 > 0.0 {
+                    let dy = event.scroll_deltas().unwrap_or_default().1;
+
+                    // horizontal scrolling has no vertical delta
+                    if dy > 0.0 {
                         scroll_down_script.as_ref()
-                    } else {
+                    } else if dy < 0.0 {
                         scroll_up_script.as_ref()
+                    } else {
+                        None
                     }
                 }
                 _ => None,
@@ -337,3 +481,13 @@ impl CommonConfig {
         );
     }
 }
+
+/// Gets the maximum time between clicks for them to count as a double-click,
+/// as configured in the GTK settings.
+fn double_click_time() -> Duration {
+    let millis = gtk::Settings::default().map_or(400, |settings| {
+        settings.property::<i32>("gtk-double-click-time")
+    });
+
+    Duration::from_millis(u64::try_from(millis).unwrap_or_default())
+}
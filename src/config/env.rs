@@ -0,0 +1,189 @@
+//! Expands environment variables in the raw config value.
+//!
+//! Expansion is opt-in using `expand_env`.
+//! Variables can be written as `$VAR` or `${VAR}`.
+//! A literal `$` can be written as `$$`.
+
+use serde_json::Value;
+use std::env;
+use tracing::warn;
+
+/// The config key used to enable expansion.
+const EXPAND_KEY: &str = "expand_env";
+
+/// The config key used to keep undefined variables as written.
+const KEEP_UNDEFINED_KEY: &str = "keep_undefined_env";
+
+/// Expands environment variables in every string value in the config,
+/// if `expand_env` is set.
+///
+/// Undefined variables log a warning and are left as written,
+/// unless `keep_undefined_env` is disabled, in which case they expand to an empty string.
+pub fn expand_env(config: &mut Value) {
+    let enabled = config
+        .get(EXPAND_KEY)
+        .and_then(Value::as_bool)
+        .unwrap_or_default();
+
+    if !enabled {
+        return;
+    }
+
+    let keep_undefined = config
+        .get(KEEP_UNDEFINED_KEY)
+        .and_then(Value::as_bool)
+        .unwrap_or(true);
+
+    expand_value(config, keep_undefined);
+}
+
+/// Recursively expands every string inside the value.
+fn expand_value(value: &mut Value, keep_undefined: bool) {
+    match value {
+        Value::String(string) if string.contains('$') => {
+            *string = expand(string, keep_undefined, |name| env::var(name).ok());
+        }
+        Value::Array(values) => values
+            .iter_mut()
+            .for_each(|value| expand_value(value, keep_undefined)),
+        Value::Object(values) => values
+            .values_mut()
+            .for_each(|value| expand_value(value, keep_undefined)),
+        _ => {}
+    }
+}
+
+/// Expands each `$VAR` or `${VAR}` in the string
+/// using the value returned by `get`.
+///
+/// A `$` not followed by a valid variable name is left untouched.
+fn expand(string: &str, keep_undefined: bool, get: impl Fn(&str) -> Option<String>) -> String {
+    let mut output = String::with_capacity(string.len());
+    let mut rest = string;
+
+    while let Some(start) = rest.find('$') {
+        output.push_str(&rest[..start]);
+
+        let after = &rest[start + 1..];
+
+        if let Some(after) = after.strip_prefix('$') {
+            output.push('$');
+            rest = after;
+            continue;
+        }
+
+        let (name, len) = match after.strip_prefix('{') {
+            Some(braced) => match braced.find('}') {
+                Some(end) => (&braced[..end], end + 2),
+                None => ("", 0),
+            },
+            None => {
+                let end = after
+                    .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                    .unwrap_or(after.len());
+                (&after[..end], end)
+            }
+        };
+
+        if !is_valid_name(name) {
+            output.push('$');
+            rest = after;
+            continue;
+        }
+
+        match get(name) {
+            Some(value) => output.push_str(&value),
+            None => {
+                warn!("Environment variable '{name}' is not set");
+
+                if keep_undefined {
+                    output.push_str(&rest[start..=start + len]);
+                }
+            }
+        }
+
+        rest = &after[len..];
+    }
+
+    output.push_str(rest);
+    output
+}
+
+/// Checks the name is non-empty, does not start with a digit,
+/// and only contains alphanumerics or underscores.
+fn is_valid_name(name: &str) -> bool {
+    name.chars().next().is_some_and(|c| !c.is_ascii_digit())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get(name: &str) -> Option<String> {
+        match name {
+            "HOST" => Some("localhost".to_string()),
+            "PORT" => Some("6600".to_string()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_expand() {
+        assert_eq!(expand("$HOST:${PORT}", false, get), "localhost:6600");
+        assert_eq!(expand("${HOST}name", false, get), "localhostname");
+    }
+
+    #[test]
+    fn test_expand_escaped() {
+        assert_eq!(expand("$$HOST", false, get), "$HOST");
+        assert_eq!(expand("$$$HOST", false, get), "$localhost");
+    }
+
+    #[test]
+    fn test_expand_undefined() {
+        assert_eq!(expand("a${MISSING}b", false, get), "ab");
+        assert_eq!(
+            expand("a${MISSING}b $MISSING", true, get),
+            "a${MISSING}b $MISSING"
+        );
+    }
+
+    #[test]
+    fn test_expand_positional() {
+        assert_eq!(expand("echo $1 ${1}", false, get), "echo $1 ${1}");
+    }
+
+    #[test]
+    fn test_expand_env_disabled() {
+        let mut config = serde_json::json!({
+            "actions": { "kill": "kill $$", "col": "awk '{print $NF}'", "arg": "echo $1" }
+        });
+        let expected = config.clone();
+
+        expand_env(&mut config);
+        assert_eq!(config, expected);
+    }
+
+    #[test]
+    fn test_expand_env_keeps_undefined() {
+        let mut config = serde_json::json!({
+            "expand_env": true,
+            "host": "$$HOST ${IRONBAR_TEST_UNDEFINED} $IRONBAR_TEST_UNDEFINED"
+        });
+
+        expand_env(&mut config);
+        assert_eq!(
+            config["host"],
+            "$HOST ${IRONBAR_TEST_UNDEFINED} $IRONBAR_TEST_UNDEFINED"
+        );
+    }
+
+    #[test]
+    fn test_expand_invalid() {
+        assert_eq!(
+            expand("awk '{print $1}' $ ${", false, get),
+            "awk '{print $1}' $ ${"
+        );
+    }
+}
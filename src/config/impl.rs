@@ -1,7 +1,9 @@
 use super::{BarConfig, BarPosition, MonitorConfig};
 use color_eyre::{Help, Report};
 use gtk::Orientation;
+use serde::de::IgnoredAny;
 use serde::{Deserialize, Deserializer};
+use std::collections::HashMap;
 
 // Manually implement for better untagged enum error handling:
 // currently open pr: https://github.com/serde-rs/serde/pull/1544
@@ -13,28 +15,52 @@ impl<'de> Deserialize<'de> for MonitorConfig {
         let content =
             <serde::__private::de::Content as serde::Deserialize>::deserialize(deserializer)?;
 
-        match <BarConfig as serde::Deserialize>::deserialize(
-            serde::__private::de::ContentRefDeserializer::<D::Error>::new(&content),
-        ) {
+        match deserialize_bar::<D::Error>(&content) {
             Ok(config) => Ok(Self::Single(config)),
-            Err(outer) => match <Vec<BarConfig> as serde::Deserialize>::deserialize(
-                serde::__private::de::ContentRefDeserializer::<D::Error>::new(&content),
-            ) {
-                Ok(config) => Ok(Self::Multiple(config)),
-                Err(inner) => {
-                    let report = Report::msg(format!(" multi-bar (c): {inner}").replace("An error occurred when deserializing: ", ""))
+            Err(outer) => {
+                match <Vec<serde::__private::de::Content> as serde::Deserialize>::deserialize(
+                    serde::__private::de::ContentRefDeserializer::<D::Error>::new(&content),
+                )
+                .and_then(|configs| configs.iter().map(deserialize_bar).collect())
+                {
+                    Ok(config) => Ok(Self::Multiple(config)),
+                    Err(inner) => {
+                        let report = Report::msg(format!(" multi-bar (c): {inner}").replace("An error occurred when deserializing: ", ""))
                         .wrap_err(format!("single-bar (b): {outer}").replace("An error occurred when deserializing: ", ""))
                         .wrap_err("An invalid config was found. The following errors were encountered:")
                         .note("Both the single-bar (type b / error 1) and multi-bar (type c / error 2) config variants were tried. You can likely ignore whichever of these is not relevant to you.")
                         .suggestion("Please see https://github.com/JakeStanger/ironbar/wiki/configuration-guide#2-pick-your-use-case for more info on the above");
 
-                    Err(serde::de::Error::custom(format!("{report:?}")))
+                        Err(serde::de::Error::custom(format!("{report:?}")))
+                    }
                 }
-            },
+            }
         }
     }
 }
 
+/// Deserializes a single bar config.
+///
+/// If the bar inherits from the top-level bar,
+/// the keys set in the config are recorded so that they can be merged later.
+fn deserialize_bar<'de, E: serde::de::Error>(
+    content: &serde::__private::de::Content<'de>,
+) -> Result<BarConfig, E> {
+    let mut config = <BarConfig as serde::Deserialize>::deserialize(
+        serde::__private::de::ContentRefDeserializer::<E>::new(content),
+    )?;
+
+    if config.inherit {
+        config.keys = <HashMap<String, IgnoredAny> as serde::Deserialize>::deserialize(
+            serde::__private::de::ContentRefDeserializer::<E>::new(content),
+        )?
+        .into_keys()
+        .collect();
+    }
+
+    Ok(config)
+}
+
 pub fn deserialize_layer<'de, D>(deserializer: D) -> Result<gtk_layer_shell::Layer, D::Error>
 where
     D: Deserializer<'de>,
@@ -85,3 +111,44 @@ impl BarPosition {
         }
     }
 }
+
+impl BarConfig {
+    /// Applies the options set on this bar on top of `parent`.
+    ///
+    /// Each option set replaces the parent's value entirely,
+    /// including module lists and `margin`.
+    /// If this bar does not inherit, it is returned unchanged.
+    pub fn inherit_from(mut self, parent: &Self) -> Self {
+        if !self.inherit {
+            return self;
+        }
+
+        let mut config = parent.clone();
+
+        for key in &self.keys {
+            match key.as_str() {
+                "name" => config.name = self.name.take(),
+                "position" => config.position = self.position,
+                "anchor_to_edges" => config.anchor_to_edges = self.anchor_to_edges,
+                "height" => config.height = self.height,
+                "max_height" => config.max_height = self.max_height,
+                "max_width" => config.max_width = self.max_width,
+                "margin" => config.margin = self.margin,
+                "layer" => config.layer = self.layer,
+                "exclusive_zone" => config.exclusive_zone = self.exclusive_zone,
+                "popup_gap" => config.popup_gap = self.popup_gap,
+                "popup_layer" => config.popup_layer = self.popup_layer,
+                "start_hidden" => config.start_hidden = self.start_hidden,
+                "autohide" => config.autohide = self.autohide,
+                "reveal_on_edge" => config.reveal_on_edge = self.reveal_on_edge,
+                "icon_theme" => config.icon_theme = self.icon_theme.take(),
+                "start" => config.start = self.start.take(),
+                "center" => config.center = self.center.take(),
+                "end" => config.end = self.end.take(),
+                _ => {}
+            }
+        }
+
+        config
+    }
+}
@@ -0,0 +1,253 @@
+//! Upgrades configs written for older versions of Ironbar.
+//!
+//! Each migration is applied in order to the raw config value,
+//! and the config is stamped with the resulting version
+//! so that migrations are never applied twice.
+
+use serde_json::{Map, Value};
+
+/// The config key used to stamp the config version.
+const VERSION_KEY: &str = "version";
+
+/// Bar keys containing module lists.
+const SECTIONS: [&str; 3] = ["start", "center", "end"];
+
+/// A single config upgrade step.
+struct Migration {
+    /// Short description of the change, shown to the user when applied.
+    description: &'static str,
+    apply: fn(&mut Value),
+}
+
+/// All known migrations, in the order they were introduced.
+/// The index of each migration (plus one) is the config version it upgrades to.
+///
+/// New migrations must only ever be appended.
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        description: "renamed bar `left` and `right` to `start` and `end`",
+        apply: |config| {
+            for_each_bar(config, &mut |bar| {
+                rename_key(bar, "left", "start");
+                rename_key(bar, "right", "end");
+            });
+        },
+    },
+    Migration {
+        description: "renamed `mpd` module to `music`",
+        apply: |config| {
+            for_each_module(config, &mut |module| {
+                if module.get("type").and_then(Value::as_str) == Some("mpd") {
+                    module.insert("type".to_string(), Value::from("music"));
+                    module
+                        .entry("player_type")
+                        .or_insert_with(|| Value::from("mpd"));
+                }
+            });
+        },
+    },
+    Migration {
+        description: "renamed `sys-info` module to `sys_info`",
+        apply: |config| {
+            for_each_module(config, &mut |module| {
+                if module.get("type").and_then(Value::as_str) == Some("sys-info") {
+                    module.insert("type".to_string(), Value::from("sys_info"));
+                }
+            });
+        },
+    },
+    Migration {
+        description: "renamed module `on_click` to `on_click_left`",
+        apply: |config| {
+            for_each_module(config, &mut |module| {
+                // the music module's own click actions are not scripts
+                let is_music_action = module.get("type").and_then(Value::as_str) == Some("music")
+                    && matches!(
+                        module.get("on_click").and_then(Value::as_str),
+                        Some("popup" | "raise")
+                    );
+
+                if is_music_action {
+                    rename_key(module, "on_click", "click_action");
+                } else {
+                    rename_key(module, "on_click", "on_click_left");
+                }
+            });
+        },
+    },
+    Migration {
+        description: "moved module `icon_theme` to the bar",
+        apply: |config| {
+            for_each_bar(config, &mut |bar| {
+                let mut icon_theme = None;
+
+                for section in SECTIONS {
+                    let Some(modules) = bar.get_mut(section).and_then(Value::as_array_mut) else {
+                        continue;
+                    };
+
+                    for module in modules.iter_mut().filter_map(Value::as_object_mut) {
+                        if let Some(theme) = module.remove("icon_theme") {
+                            icon_theme.get_or_insert(theme);
+                        }
+                    }
+                }
+
+                if let Some(icon_theme) = icon_theme {
+                    bar.entry("icon_theme").or_insert(icon_theme);
+                }
+            });
+        },
+    },
+];
+
+/// The config version produced by applying all migrations.
+pub const CONFIG_VERSION: u64 = MIGRATIONS.len() as u64;
+
+/// Applies all migrations newer than the config's version,
+/// and stamps it with the latest version.
+///
+/// Returns the descriptions of the applied migrations.
+/// Running this on an already-migrated config is a no-op.
+pub fn migrate(config: &mut Value) -> Vec<&'static str> {
+    let version = config
+        .get(VERSION_KEY)
+        .and_then(Value::as_u64)
+        .unwrap_or_default();
+
+    let applied = MIGRATIONS
+        .iter()
+        .skip(usize::try_from(version).unwrap_or(usize::MAX))
+        .map(|migration| {
+            (migration.apply)(config);
+            migration.description
+        })
+        .collect();
+
+    if let Some(config) = config.as_object_mut() {
+        config.insert(VERSION_KEY.to_string(), Value::from(CONFIG_VERSION));
+    }
+
+    applied
+}
+
+/// Renames `from` to `to` in the object,
+/// unless `to` is already present.
+fn rename_key(object: &mut Map<String, Value>, from: &str, to: &str) {
+    if object.contains_key(to) {
+        return;
+    }
+
+    if let Some(value) = object.remove(from) {
+        object.insert(to.to_string(), value);
+    }
+}
+
+/// Runs `f` on the top-level bar and every bar inside `monitors`.
+fn for_each_bar(config: &mut Value, f: &mut impl FnMut(&mut Map<String, Value>)) {
+    let Some(root) = config.as_object_mut() else {
+        return;
+    };
+
+    f(root);
+
+    let Some(monitors) = root.get_mut("monitors").and_then(Value::as_object_mut) else {
+        return;
+    };
+
+    for monitor in monitors.values_mut() {
+        match monitor {
+            Value::Object(bar) => f(bar),
+            Value::Array(bars) => bars
+                .iter_mut()
+                .filter_map(Value::as_object_mut)
+                .for_each(&mut *f),
+            _ => {}
+        }
+    }
+}
+
+/// Runs `f` on every module in every bar.
+fn for_each_module(config: &mut Value, f: &mut impl FnMut(&mut Map<String, Value>)) {
+    for_each_bar(config, &mut |bar| {
+        for section in SECTIONS {
+            if let Some(modules) = bar.get_mut(section).and_then(Value::as_array_mut) {
+                modules
+                    .iter_mut()
+                    .filter_map(Value::as_object_mut)
+                    .for_each(&mut *f);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_migrate() {
+        let mut config = json!({
+            "left": [{ "type": "mpd", "on_click": "echo" }],
+            "monitors": {
+                "DP-1": [{ "right": [{ "type": "launcher", "icon_theme": "Paper" }] }]
+            }
+        });
+
+        let applied = migrate(&mut config);
+        assert_eq!(applied.len(), MIGRATIONS.len());
+
+        assert_eq!(
+            config,
+            json!({
+                "version": CONFIG_VERSION,
+                "start": [{ "type": "music", "player_type": "mpd", "on_click_left": "echo" }],
+                "monitors": {
+                    "DP-1": [{ "icon_theme": "Paper", "end": [{ "type": "launcher" }] }]
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn test_migrate_music_click_action() {
+        let mut config = json!({
+            "start": [
+                { "type": "music", "on_click": "raise" },
+                { "type": "music", "on_click": "playerctl next" },
+                { "type": "music", "click_action": "popup", "on_click_left": "echo" }
+            ]
+        });
+
+        migrate(&mut config);
+
+        assert_eq!(
+            config["start"],
+            json!([
+                { "type": "music", "click_action": "raise" },
+                { "type": "music", "on_click_left": "playerctl next" },
+                { "type": "music", "click_action": "popup", "on_click_left": "echo" }
+            ])
+        );
+    }
+
+    #[test]
+    fn test_migrate_idempotent() {
+        let mut config = json!({ "left": [{ "type": "sys-info" }] });
+        migrate(&mut config);
+
+        let migrated = config.clone();
+        assert!(migrate(&mut config).is_empty());
+        assert_eq!(config, migrated);
+    }
+
+    #[test]
+    fn test_migrate_keeps_new_key() {
+        let mut config = json!({ "left": [], "start": [{ "type": "clock" }] });
+        migrate(&mut config);
+
+        assert_eq!(config["start"], json!([{ "type": "clock" }]));
+        assert_eq!(config["left"], json!([]));
+    }
+}
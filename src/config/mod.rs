@@ -1,7 +1,15 @@
 mod common;
+mod env;
 mod r#impl;
+#[cfg(feature = "cli")]
+mod migrate;
+#[cfg(any(feature = "network_manager", feature = "upower", feature = "volume"))]
+mod ramp;
 mod truncate;
+mod validate;
 
+#[cfg(feature = "backlight")]
+use crate::modules::backlight::BacklightModule;
 #[cfg(feature = "cairo")]
 use crate::modules::cairo::CairoModule;
 #[cfg(feature = "clipboard")]
@@ -9,8 +17,12 @@ use crate::modules::clipboard::ClipboardModule;
 #[cfg(feature = "clock")]
 use crate::modules::clock::ClockModule;
 use crate::modules::custom::CustomModule;
+#[cfg(feature = "dbus")]
+use crate::modules::dbus::DbusModule;
 #[cfg(feature = "focused")]
 use crate::modules::focused::FocusedModule;
+#[cfg(feature = "ipc")]
+use crate::modules::ironvar::IronvarModule;
 #[cfg(feature = "keys")]
 use crate::modules::keys::KeysModule;
 use crate::modules::label::LabelModule;
@@ -22,7 +34,15 @@ use crate::modules::music::MusicModule;
 use crate::modules::networkmanager::NetworkManagerModule;
 #[cfg(feature = "notifications")]
 use crate::modules::notifications::NotificationsModule;
+#[cfg(feature = "output")]
+use crate::modules::output::OutputModule;
+#[cfg(feature = "power_menu")]
+use crate::modules::power_menu::PowerMenuModule;
 use crate::modules::script::ScriptModule;
+#[cfg(feature = "separator")]
+use crate::modules::separator::SeparatorModule;
+#[cfg(feature = "spacer")]
+use crate::modules::spacer::SpacerModule;
 #[cfg(feature = "sway")]
 use crate::modules::sway::mode::SwayModeModule;
 #[cfg(feature = "sys_info")]
@@ -33,6 +53,8 @@ use crate::modules::tray::TrayModule;
 use crate::modules::upower::UpowerModule;
 #[cfg(feature = "volume")]
 use crate::modules::volume::VolumeModule;
+#[cfg(feature = "weather")]
+use crate::modules::weather::WeatherModule;
 #[cfg(feature = "workspaces")]
 use crate::modules::workspaces::WorkspacesModule;
 
@@ -45,13 +67,24 @@ use std::collections::HashMap;
 #[cfg(feature = "schema")]
 use schemars::JsonSchema;
 
-pub use self::common::{CommonConfig, ModuleJustification, ModuleOrientation, TransitionType};
+pub use self::common::{
+    CommonConfig, KeyboardInteractivity, ModuleJustification, ModuleOrientation, PopupTrigger,
+    TransitionType,
+};
+pub use self::env::expand_env;
+#[cfg(feature = "cli")]
+pub use self::migrate::migrate;
+#[cfg(any(feature = "network_manager", feature = "upower", feature = "volume"))]
+pub use self::ramp::IconRamp;
 pub use self::truncate::{EllipsizeMode, TruncateMode};
+pub use self::validate::validate;
 
 #[derive(Debug, Deserialize, Clone)]
 #[serde(tag = "type", rename_all = "snake_case")]
 #[cfg_attr(feature = "schema", derive(JsonSchema))]
 pub enum ModuleConfig {
+    #[cfg(feature = "backlight")]
+    Backlight(Box<BacklightModule>),
     #[cfg(feature = "cairo")]
     Cairo(Box<CairoModule>),
     #[cfg(feature = "clipboard")]
@@ -59,8 +92,12 @@ pub enum ModuleConfig {
     #[cfg(feature = "clock")]
     Clock(Box<ClockModule>),
     Custom(Box<CustomModule>),
+    #[cfg(feature = "dbus")]
+    Dbus(Box<DbusModule>),
     #[cfg(feature = "focused")]
     Focused(Box<FocusedModule>),
+    #[cfg(feature = "ipc")]
+    Ironvar(Box<IronvarModule>),
     #[cfg(feature = "keys")]
     Keys(Box<KeysModule>),
     Label(Box<LabelModule>),
@@ -72,7 +109,15 @@ pub enum ModuleConfig {
     NetworkManager(Box<NetworkManagerModule>),
     #[cfg(feature = "notifications")]
     Notifications(Box<NotificationsModule>),
+    #[cfg(feature = "output")]
+    Output(Box<OutputModule>),
+    #[cfg(feature = "power_menu")]
+    PowerMenu(Box<PowerMenuModule>),
     Script(Box<ScriptModule>),
+    #[cfg(feature = "separator")]
+    Separator(Box<SeparatorModule>),
+    #[cfg(feature = "spacer")]
+    Spacer(Box<SpacerModule>),
     #[cfg(feature = "sys_info")]
     SysInfo(Box<SysInfoModule>),
     #[cfg(feature = "sway")]
@@ -83,6 +128,8 @@ pub enum ModuleConfig {
     Upower(Box<UpowerModule>),
     #[cfg(feature = "volume")]
     Volume(Box<VolumeModule>),
+    #[cfg(feature = "weather")]
+    Weather(Box<WeatherModule>),
     #[cfg(feature = "workspaces")]
     Workspaces(Box<WorkspacesModule>),
 }
@@ -101,6 +148,8 @@ impl ModuleConfig {
         }
 
         match self {
+            #[cfg(feature = "backlight")]
+            Self::Backlight(module) => create!(module),
             #[cfg(feature = "cairo")]
             Self::Cairo(module) => create!(module),
             #[cfg(feature = "clipboard")]
@@ -108,8 +157,12 @@ impl ModuleConfig {
             #[cfg(feature = "clock")]
             Self::Clock(module) => create!(module),
             Self::Custom(module) => create!(module),
+            #[cfg(feature = "dbus")]
+            Self::Dbus(module) => create!(module),
             #[cfg(feature = "focused")]
             Self::Focused(module) => create!(module),
+            #[cfg(feature = "ipc")]
+            Self::Ironvar(module) => create!(module),
             #[cfg(feature = "keys")]
             Self::Keys(module) => create!(module),
             Self::Label(module) => create!(module),
@@ -121,7 +174,15 @@ impl ModuleConfig {
             Self::NetworkManager(module) => create!(module),
             #[cfg(feature = "notifications")]
             Self::Notifications(module) => create!(module),
+            #[cfg(feature = "output")]
+            Self::Output(module) => create!(module),
+            #[cfg(feature = "power_menu")]
+            Self::PowerMenu(module) => create!(module),
             Self::Script(module) => create!(module),
+            #[cfg(feature = "separator")]
+            Self::Separator(module) => create!(module),
+            #[cfg(feature = "spacer")]
+            Self::Spacer(module) => create!(module),
             #[cfg(feature = "sys_info")]
             Self::SysInfo(module) => create!(module),
             #[cfg(feature = "sway")]
@@ -132,6 +193,8 @@ impl ModuleConfig {
             Self::Upower(module) => create!(module),
             #[cfg(feature = "volume")]
             Self::Volume(module) => create!(module),
+            #[cfg(feature = "weather")]
+            Self::Weather(module) => create!(module),
             #[cfg(feature = "workspaces")]
             Self::Workspaces(module) => create!(module),
         }
@@ -183,6 +246,20 @@ pub struct MarginConfig {
 #[derive(Debug, Deserialize, Clone)]
 #[cfg_attr(feature = "schema", derive(JsonSchema))]
 pub struct BarConfig {
+    /// Whether options not set on this bar should be inherited from the top-level bar config.
+    /// This only applies to bars inside the [monitors](#monitors) config.
+    ///
+    /// Each option set on this bar replaces the inherited value entirely.
+    /// This includes the `start`, `center` and `end` module lists, which are not appended to.
+    ///
+    /// **Default**: `false`
+    #[serde(default)]
+    pub inherit: bool,
+
+    /// The config keys set on this bar, used to apply inherited options.
+    #[serde(skip)]
+    pub(crate) keys: Vec<String>,
+
     /// A unique identifier for the bar, used for controlling it over IPC.
     /// If not set, uses a generated integer suffix.
     ///
@@ -214,6 +291,26 @@ pub struct BarConfig {
     #[serde(default = "default_bar_height")]
     pub height: i32,
 
+    /// The maximum height of horizontal bars in pixels.
+    ///
+    /// Content larger than this is clipped and can be scrolled,
+    /// instead of expanding the bar.
+    /// Leave unset to allow the bar to grow to fit its content.
+    ///
+    /// **Default**: `null`
+    #[serde(default)]
+    pub max_height: Option<i32>,
+
+    /// The maximum width of vertical bars in pixels.
+    ///
+    /// Content larger than this is clipped and can be scrolled,
+    /// instead of expanding the bar.
+    /// Leave unset to allow the bar to grow to fit its content.
+    ///
+    /// **Default**: `null`
+    #[serde(default)]
+    pub max_width: Option<i32>,
+
     /// The margin to use on each side of the bar, in pixels.
     /// Object which takes `top`, `bottom`, `left` and `right` keys.
     ///
@@ -271,6 +368,21 @@ pub struct BarConfig {
     #[serde(default = "default_popup_gap")]
     pub popup_gap: i32,
 
+    /// The layer-shell layer to place popup windows on,
+    /// independently of the bar's `layer`.
+    ///
+    /// Popups are still positioned relative to the bar.
+    ///
+    /// **Valid options**: `background`, `bottom`, `top`, `overlay`
+    /// <br>
+    /// **Default**: `overlay`
+    #[serde(
+        default = "default_popup_layer",
+        deserialize_with = "r#impl::deserialize_layer"
+    )]
+    #[cfg_attr(feature = "schema", schemars(schema_with = "r#impl::schema_layer"))]
+    pub popup_layer: gtk_layer_shell::Layer,
+
     /// Whether the bar should be hidden when Ironbar starts.
     ///
     /// **Default**: `false`, unless `autohide` is set.
@@ -284,6 +396,15 @@ pub struct BarConfig {
     #[serde(default)]
     pub autohide: Option<u64>,
 
+    /// Whether moving the cursor to the screen edge should reveal the bar while it is hidden.
+    ///
+    /// This places an invisible, single-pixel surface along the bar's edge.
+    /// The surface never reserves an exclusive zone.
+    ///
+    /// **Default**: `false`, unless `autohide` is set.
+    #[serde(default)]
+    pub reveal_on_edge: Option<bool>,
+
     /// The name of the GTK icon theme to use.
     /// Leave unset to use the default Adwaita theme.
     ///
@@ -329,14 +450,19 @@ impl Default for BarConfig {
         }
 
         Self {
+            inherit: false,
+            keys: vec![],
             position: BarPosition::default(),
             margin: MarginConfig::default(),
             name: None,
             layer: default_layer(),
             exclusive_zone: None,
             height: default_bar_height(),
+            max_height: None,
+            max_width: None,
             start_hidden: None,
             autohide: None,
+            reveal_on_edge: None,
             icon_theme: None,
             start: Some(vec![ModuleConfig::Label(
                 LabelModule::new("ℹ️ Using default config".to_string()).into(),
@@ -345,6 +471,7 @@ impl Default for BarConfig {
             end,
             anchor_to_edges: default_true(),
             popup_gap: default_popup_gap(),
+            popup_layer: default_popup_layer(),
         }
     }
 }
@@ -374,6 +501,58 @@ pub struct Config {
     /// ```
     pub ironvar_defaults: Option<HashMap<Box<str>, String>>,
 
+    /// A map of action names to [scripts](script).
+    /// Actions can be run by name using the `run` [IPC command](controlling-ironbar#run),
+    /// allowing compositor keybinds and modules to share the same commands.
+    ///
+    /// **Default**: `{}`
+    ///
+    /// # Example
+    ///
+    /// ```corn
+    /// { actions.lock = "swaylock -f" }
+    /// ```
+    ///
+    /// ```sh
+    /// $ ironbar run lock
+    /// ok
+    /// ```
+    #[cfg_attr(not(feature = "ipc"), allow(dead_code))]
+    #[serde(default)]
+    pub actions: HashMap<Box<str>, String>,
+
+    /// Whether an invalid module should fail the whole config.
+    ///
+    /// When disabled, invalid modules are skipped
+    /// and a warning is logged with their location.
+    ///
+    /// **Default**: `false`
+    // read from the raw config value during validation
+    #[allow(dead_code)]
+    #[serde(default)]
+    pub strict_config: bool,
+
+    /// Whether to expand environment variables
+    /// written as `$VAR` or `${VAR}` in string values.
+    ///
+    /// This is disabled by default so that shell snippets
+    /// such as `awk '{print $NF}'` are left untouched.
+    ///
+    /// **Default**: `false`
+    // read from the raw config value during expansion
+    #[allow(dead_code)]
+    #[serde(default)]
+    pub expand_env: bool,
+
+    /// Whether environment variables which are not set
+    /// should be left as written, rather than replaced with an empty string.
+    ///
+    /// **Default**: `true`
+    // read from the raw config value during expansion
+    #[allow(dead_code)]
+    #[serde(default = "default_true")]
+    pub keep_undefined_env: bool,
+
     /// The configuration for the bar.
     /// Setting through this will enable a single identical bar on each monitor.
     #[serde(flatten)]
@@ -402,6 +581,10 @@ const fn default_popup_gap() -> i32 {
     5
 }
 
+const fn default_popup_layer() -> gtk_layer_shell::Layer {
+    gtk_layer_shell::Layer::Overlay
+}
+
 pub const fn default_false() -> bool {
     false
 }
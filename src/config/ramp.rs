@@ -0,0 +1,89 @@
+use serde::Deserialize;
+
+/// Some modules provide options for picking an icon based on a level,
+/// such as volume or battery percentage.
+/// This is controlled using a common `IconRamp` type.
+///
+/// The option can be set to either a single icon, which is always used,
+/// or a list of icons ordered from lowest to highest.
+///
+/// The 0-100 range is split into equal buckets, one per icon.
+/// For example, with four icons, `0-24` uses the first,
+/// `25-49` the second, `50-74` the third and `75-100` the last.
+/// Values outside the range are clamped.
+///
+/// # Example
+///
+/// ```corn
+/// { icons = [ "󰁺" "󰁾" "󰂀" "󰁹" ] }
+/// ```
+#[derive(Debug, Deserialize, Clone)]
+#[serde(untagged)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum IconRamp {
+    /// A single icon, used for all levels.
+    Single(String),
+    /// A list of icons, ordered from lowest to highest level.
+    Ramp(Vec<String>),
+}
+
+impl IconRamp {
+    /// Gets the icon for the given percentage.
+    ///
+    /// Returns an empty string if the ramp has no icons.
+    pub fn get(&self, percent: f64) -> &str {
+        match self {
+            Self::Single(icon) => icon,
+            Self::Ramp(icons) if icons.is_empty() => "",
+            Self::Ramp(icons) => {
+                let percent = if percent.is_nan() {
+                    0.0
+                } else {
+                    percent.clamp(0.0, 100.0)
+                };
+
+                let index = (percent / 100.0 * icons.len() as f64) as usize;
+                &icons[index.min(icons.len() - 1)]
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ramp() -> IconRamp {
+        IconRamp::Ramp(vec![
+            "a".to_string(),
+            "b".to_string(),
+            "c".to_string(),
+            "d".to_string(),
+        ])
+    }
+
+    #[test]
+    fn buckets() {
+        let ramp = ramp();
+        assert_eq!(ramp.get(0.0), "a");
+        assert_eq!(ramp.get(24.9), "a");
+        assert_eq!(ramp.get(25.0), "b");
+        assert_eq!(ramp.get(74.9), "c");
+        assert_eq!(ramp.get(75.0), "d");
+        assert_eq!(ramp.get(100.0), "d");
+    }
+
+    #[test]
+    fn out_of_range() {
+        let ramp = ramp();
+        assert_eq!(ramp.get(-10.0), "a");
+        assert_eq!(ramp.get(150.0), "d");
+        assert_eq!(ramp.get(f64::NAN), "a");
+    }
+
+    #[test]
+    fn single_and_empty() {
+        assert_eq!(IconRamp::Single("a".to_string()).get(50.0), "a");
+        assert_eq!(IconRamp::Ramp(vec![]).get(50.0), "");
+    }
+}
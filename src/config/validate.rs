@@ -0,0 +1,144 @@
+//! Checks each module in the raw config value individually,
+//! so that errors can point at the offending module
+//! rather than failing somewhere inside the whole config.
+
+use super::ModuleConfig;
+use color_eyre::{Report, Result};
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use tracing::warn;
+
+/// The config key used to enable strict validation.
+const STRICT_KEY: &str = "strict_config";
+
+/// Bar keys containing module lists.
+const SECTIONS: [&str; 3] = ["start", "center", "end"];
+
+/// Deserializes each module in every bar on its own.
+///
+/// By default, invalid modules are removed from the config
+/// and a warning is logged for each.
+/// If `strict_config` is set, the first invalid module is returned as an error instead.
+pub fn validate(config: &mut Value) -> Result<()> {
+    let strict = config
+        .get(STRICT_KEY)
+        .and_then(Value::as_bool)
+        .unwrap_or_default();
+
+    let Some(root) = config.as_object_mut() else {
+        return Ok(());
+    };
+
+    validate_bar(root, "top-level bar", strict)?;
+
+    if let Some(monitors) = root.get_mut("monitors") {
+        validate_monitors(monitors, strict)?;
+    }
+
+    Ok(())
+}
+
+/// Validates every bar inside the `monitors` map.
+fn validate_monitors(monitors: &mut Value, strict: bool) -> Result<()> {
+    let Some(monitors) = monitors.as_object_mut() else {
+        return Ok(());
+    };
+
+    for (monitor, config) in monitors {
+        match config {
+            Value::Object(bar) => validate_bar(bar, &format!("monitor '{monitor}'"), strict)?,
+            Value::Array(bars) => {
+                for (index, bar) in bars.iter_mut().enumerate() {
+                    if let Some(bar) = bar.as_object_mut() {
+                        let name = format!("monitor '{monitor}' bar {index}");
+                        validate_bar(bar, &name, strict)?;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+/// Validates each module list in a single bar.
+/// The bar's `name` is used to describe it when set.
+fn validate_bar(bar: &mut Map<String, Value>, name: &str, strict: bool) -> Result<()> {
+    let name = bar
+        .get("name")
+        .and_then(Value::as_str)
+        .map_or_else(|| name.to_string(), |name| format!("bar '{name}'"));
+
+    for section in SECTIONS {
+        let Some(modules) = bar.get_mut(section).and_then(Value::as_array_mut) else {
+            continue;
+        };
+
+        let mut invalid = vec![];
+
+        for (index, module) in modules.iter().enumerate() {
+            let Err(err) = ModuleConfig::deserialize(module) else {
+                continue;
+            };
+
+            let module_type = module
+                .get("type")
+                .and_then(Value::as_str)
+                .unwrap_or("unknown");
+
+            let location = format!("{name}, `{section}` module {index} (type `{module_type}`)");
+
+            if strict {
+                return Err(Report::new(err).wrap_err(format!("Invalid module in {location}")));
+            }
+
+            warn!("Skipping invalid module in {location}: {err}");
+            invalid.push(index);
+        }
+
+        for index in invalid.into_iter().rev() {
+            modules.remove(index);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_validate_skips_invalid() {
+        let mut config = json!({
+            "start": [{ "type": "label", "label": "a" }, { "type": "label" }],
+            "monitors": {
+                "DP-1": [{ "end": [{ "type": "not_a_module" }, { "type": "label", "label": "b" }] }]
+            }
+        });
+
+        validate(&mut config).expect("to be valid");
+
+        assert_eq!(config["start"], json!([{ "type": "label", "label": "a" }]));
+        assert_eq!(
+            config["monitors"]["DP-1"][0]["end"],
+            json!([{ "type": "label", "label": "b" }])
+        );
+    }
+
+    #[test]
+    fn test_validate_strict() {
+        let mut config = json!({
+            "strict_config": true,
+            "name": "main",
+            "start": [{ "type": "label", "label": "a" }, { "type": "label" }]
+        });
+
+        let err = validate(&mut config).expect_err("to be invalid");
+        assert!(err
+            .to_string()
+            .contains("bar 'main', `start` module 1 (type `label`)"));
+    }
+}
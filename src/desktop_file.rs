@@ -2,19 +2,36 @@ use std::collections::{HashMap, HashSet};
 use std::env;
 use std::fs;
 use std::path::{Path, PathBuf};
-use std::sync::{Mutex, OnceLock};
-use tracing::warn;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tracing::{debug, warn};
 use walkdir::{DirEntry, WalkDir};
 
 use crate::lock;
 
 type DesktopFile = HashMap<String, Vec<String>>;
 
+/// Minimum time between rescans triggered by lookup misses.
+const RESCAN_INTERVAL: Duration = Duration::from_secs(10);
+
 fn desktop_files() -> &'static Mutex<HashMap<PathBuf, DesktopFile>> {
     static DESKTOP_FILES: OnceLock<Mutex<HashMap<PathBuf, DesktopFile>>> = OnceLock::new();
     DESKTOP_FILES.get_or_init(|| Mutex::new(HashMap::new()))
 }
 
+/// Paths of all known desktop files,
+/// or `None` if the application directories have not been scanned yet.
+fn desktop_file_paths() -> &'static Mutex<Option<Arc<Vec<PathBuf>>>> {
+    static DESKTOP_FILE_PATHS: OnceLock<Mutex<Option<Arc<Vec<PathBuf>>>>> = OnceLock::new();
+    DESKTOP_FILE_PATHS.get_or_init(|| Mutex::new(None))
+}
+
+/// Time of the last scan of the application directories.
+fn last_scan() -> &'static Mutex<Option<Instant>> {
+    static LAST_SCAN: OnceLock<Mutex<Option<Instant>>> = OnceLock::new();
+    LAST_SCAN.get_or_init(|| Mutex::new(None))
+}
+
 fn desktop_files_look_out_keys() -> &'static HashSet<&'static str> {
     static DESKTOP_FILES_LOOK_OUT_KEYS: OnceLock<HashSet<&'static str>> = OnceLock::new();
     DESKTOP_FILES_LOOK_OUT_KEYS
@@ -61,13 +78,72 @@ fn find_desktop_files() -> Vec<PathBuf> {
         .collect()
 }
 
+/// Gets the cached list of desktop files,
+/// scanning the application directories if they have not been yet.
+fn cached_desktop_files() -> Arc<Vec<PathBuf>> {
+    if let Some(files) = lock!(desktop_file_paths()).as_ref() {
+        return files.clone();
+    }
+
+    scan_desktop_files()
+}
+
+/// Scans the application directories for desktop files,
+/// replacing the cached list.
+///
+/// Parsed files which still exist are kept.
+fn scan_desktop_files() -> Arc<Vec<PathBuf>> {
+    // scan without holding the lock, as this can be slow
+    let files = Arc::new(find_desktop_files());
+
+    lock!(last_scan()).replace(Instant::now());
+    lock!(desktop_file_paths()).replace(files.clone());
+
+    let paths = files.iter().collect::<HashSet<_>>();
+    lock!(desktop_files()).retain(|path, _| paths.contains(path));
+
+    files
+}
+
+/// Rescans the application directories,
+/// unless they were scanned within the last `RESCAN_INTERVAL`.
+///
+/// Returns `None` if the scan was skipped.
+fn rescan_desktop_files() -> Option<Arc<Vec<PathBuf>>> {
+    let recent = lock!(last_scan()).is_some_and(|last| last.elapsed() < RESCAN_INTERVAL);
+    if recent {
+        None
+    } else {
+        Some(scan_desktop_files())
+    }
+}
+
+/// Scans and parses all desktop files into the cache.
+///
+/// This is slow, so should be called off the UI thread at startup
+/// to avoid the first icon lookups blocking.
+pub fn preload() {
+    let files = scan_desktop_files();
+
+    let parsed = files
+        .iter()
+        .filter_map(|file| parse_desktop_file(file).map(|parsed| (file.clone(), parsed)))
+        .collect::<Vec<_>>();
+
+    debug!("Loaded {} desktop files", parsed.len());
+    lock!(desktop_files()).extend(parsed);
+}
+
 /// Attempts to locate a `.desktop` file for an app id
 pub fn find_desktop_file(app_id: &str) -> Option<PathBuf> {
-    // this is necessary to invalidate the cache
-    let files = find_desktop_files();
+    let find = |files: &[PathBuf]| {
+        find_desktop_file_by_filename(app_id, files)
+            .or_else(|| find_desktop_file_by_filedata(app_id, files))
+    };
 
-    find_desktop_file_by_filename(app_id, &files)
-        .or_else(|| find_desktop_file_by_filedata(app_id, &files))
+    // the app may have been installed since the last scan,
+    // so rescan before giving up.
+    find(&cached_desktop_files()).or_else(|| rescan_desktop_files().and_then(|files| find(&files)))
 }
 
 /// Finds the correct desktop file using a simple condition check
@@ -110,9 +186,15 @@ fn find_desktop_file_by_filedata(app_id: &str, files: &[PathBuf]) -> Option<Path
     let files = files
         .iter()
         .filter_map(|file| {
-            let parsed_desktop_file = parse_desktop_file(file)?;
+            let parsed_desktop_file = match desktop_files_cache.get(file) {
+                Some(desktop_file) => desktop_file.clone(),
+                None => {
+                    let desktop_file = parse_desktop_file(file)?;
+                    desktop_files_cache.insert(file.clone(), desktop_file.clone());
+                    desktop_file
+                }
+            };
 
-            desktop_files_cache.insert(file.clone(), parsed_desktop_file.clone());
             Some((file.clone(), parsed_desktop_file))
         })
         .collect::<Vec<_>>();
@@ -76,6 +76,6 @@ impl DynamicBool {
 ///
 /// This loosely follows the common JavaScript cases.
 #[cfg(feature = "ipc")]
-fn is_truthy(string: &str) -> bool {
+pub(super) fn is_truthy(string: &str) -> bool {
     !(string.is_empty() || string == "0" || string == "false")
 }
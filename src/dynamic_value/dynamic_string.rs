@@ -2,17 +2,56 @@ use crate::script::{OutputStream, Script};
 #[cfg(feature = "ipc")]
 use crate::Ironbar;
 use crate::{arc_mut, glib_recv_mpsc, lock, spawn, try_send};
+#[cfg(feature = "ipc")]
+use std::collections::{HashMap, HashSet};
 use tokio::sync::mpsc;
 
+#[cfg(feature = "ipc")]
+use super::dynamic_bool::is_truthy;
+
 /// A segment of a dynamic string,
-/// containing either a static string
-/// or a script.
+/// containing either a static string,
+/// a script, a variable or a conditional block.
 #[derive(Debug)]
 enum DynamicStringSegment {
     Static(String),
     Script(Script),
     #[cfg(feature = "ipc")]
     Variable(Box<str>),
+    /// Renders `then` if the variable is truthy,
+    /// or `otherwise` if not.
+    #[cfg(feature = "ipc")]
+    Conditional {
+        variable: Box<str>,
+        negate: bool,
+        then: Vec<DynamicStringSegment>,
+        otherwise: Vec<DynamicStringSegment>,
+    },
+}
+
+/// A token produced while parsing,
+/// which is either a segment or a conditional block directive.
+#[derive(Debug)]
+enum Token {
+    Segment(DynamicStringSegment),
+    #[cfg(feature = "ipc")]
+    If {
+        variable: Box<str>,
+        negate: bool,
+    },
+    #[cfg(feature = "ipc")]
+    Else,
+    #[cfg(feature = "ipc")]
+    EndIf,
+}
+
+/// The latest values of each script and variable.
+#[derive(Debug, Default)]
+struct State {
+    /// Script outputs, in the order the scripts appear in the input.
+    scripts: Vec<String>,
+    #[cfg(feature = "ipc")]
+    variables: HashMap<Box<str>, String>,
 }
 
 /// Creates a new dynamic string, based off the input template.
@@ -29,79 +68,183 @@ pub fn dynamic_string<F>(input: &str, mut f: F)
 where
     F: FnMut(String) + 'static,
 {
-    let (tokens, is_static) = parse_input(input);
+    let segments = parse_input(input);
 
-    let label_parts = arc_mut!(vec![]);
+    let state = arc_mut!(State::default());
     let (tx, rx) = mpsc::channel(32);
 
-    for (i, segment) in tokens.into_iter().enumerate() {
-        match segment {
-            DynamicStringSegment::Static(str) => {
-                lock!(label_parts).push(str);
-            }
-            DynamicStringSegment::Script(script) => {
-                let tx = tx.clone();
-                let label_parts = label_parts.clone();
-
-                // insert blank value to preserve segment order
-                lock!(label_parts).push(String::new());
-
-                spawn(async move {
-                    script
-                        .run(None, |out, _| {
-                            if let OutputStream::Stdout(out) = out {
-                                let mut label_parts = lock!(label_parts);
-
-                                let _: String = std::mem::replace(&mut label_parts[i], out);
-
-                                let string = label_parts.join("");
-                                try_send!(tx, string);
-                            }
-                        })
-                        .await;
-                });
-            }
-            #[cfg(feature = "ipc")]
-            DynamicStringSegment::Variable(name) => {
-                let tx = tx.clone();
-                let label_parts = label_parts.clone();
+    let mut scripts = vec![];
+    collect_scripts(&segments, &mut scripts);
 
-                // insert blank value to preserve segment order
-                lock!(label_parts).push(String::new());
+    // scripts trigger the first render once they output,
+    // but static text and unset variables need rendering straight away.
+    let render_initial = scripts.is_empty();
 
-                spawn(async move {
-                    let variable_manager = Ironbar::variable_manager();
-                    let mut rx = crate::write_lock!(variable_manager).subscribe(name);
+    lock!(state).scripts = vec![String::new(); scripts.len()];
 
-                    while let Ok(value) = rx.recv().await {
-                        if let Some(value) = value {
-                            let mut label_parts = lock!(label_parts);
+    for (i, script) in scripts.into_iter().enumerate() {
+        let tx = tx.clone();
+        let state = state.clone();
 
-                            let _: String = std::mem::replace(&mut label_parts[i], value);
-
-                            let string = label_parts.join("");
-                            try_send!(tx, string);
-                        }
+        spawn(async move {
+            script
+                .run(None, |out, _| {
+                    if let OutputStream::Stdout(out) = out {
+                        lock!(state).scripts[i] = out;
+                        try_send!(tx, ());
                     }
-                });
-            }
+                })
+                .await;
+        });
+    }
+
+    #[cfg(feature = "ipc")]
+    {
+        let mut variables = HashSet::new();
+        collect_variables(&segments, &mut variables);
+
+        for name in variables {
+            let tx = tx.clone();
+            let state = state.clone();
+
+            spawn(async move {
+                let variable_manager = Ironbar::variable_manager();
+                let mut rx = crate::write_lock!(variable_manager).subscribe(name.clone());
+
+                while let Ok(value) = rx.recv().await {
+                    let mut state = lock!(state);
+                    match value {
+                        Some(value) => state.variables.insert(name.clone(), value),
+                        None => state.variables.remove(&name),
+                    };
+                    drop(state);
+
+                    try_send!(tx, ());
+                }
+            });
         }
     }
 
-    glib_recv_mpsc!(rx , val => f(val));
+    glib_recv_mpsc!(rx, _ev => {
+        let string = render(&segments, &lock!(state));
+        f(string);
+    });
 
     // initialize
-    if is_static {
-        let label_parts = lock!(label_parts).join("");
-        try_send!(tx, label_parts);
+    if render_initial {
+        try_send!(tx, ());
+    }
+}
+
+/// Clones each script in the segments, in order of appearance.
+fn collect_scripts(segments: &[DynamicStringSegment], scripts: &mut Vec<Script>) {
+    for segment in segments {
+        match segment {
+            DynamicStringSegment::Script(script) => scripts.push(script.clone()),
+            #[cfg(feature = "ipc")]
+            DynamicStringSegment::Conditional {
+                then, otherwise, ..
+            } => {
+                collect_scripts(then, scripts);
+                collect_scripts(otherwise, scripts);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Gets the name of each variable used in the segments,
+/// including those used in conditions.
+#[cfg(feature = "ipc")]
+fn collect_variables(segments: &[DynamicStringSegment], variables: &mut HashSet<Box<str>>) {
+    for segment in segments {
+        match segment {
+            DynamicStringSegment::Variable(name) => {
+                variables.insert(name.clone());
+            }
+            DynamicStringSegment::Conditional {
+                variable,
+                then,
+                otherwise,
+                ..
+            } => {
+                variables.insert(variable.clone());
+                collect_variables(then, variables);
+                collect_variables(otherwise, variables);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Compiles the segments into a string using the current state.
+fn render(segments: &[DynamicStringSegment], state: &State) -> String {
+    let mut output = String::new();
+    render_into(segments, state, &mut 0, true, &mut output);
+    output
+}
+
+/// Writes the segments into `output`.
+///
+/// Hidden segments are still walked,
+/// so that script indexes stay in step with `State::scripts`.
+fn render_into(
+    segments: &[DynamicStringSegment],
+    state: &State,
+    script_index: &mut usize,
+    visible: bool,
+    output: &mut String,
+) {
+    for segment in segments {
+        match segment {
+            DynamicStringSegment::Static(str) => {
+                if visible {
+                    output.push_str(str);
+                }
+            }
+            DynamicStringSegment::Script(_) => {
+                if visible {
+                    output.push_str(&state.scripts[*script_index]);
+                }
+                *script_index += 1;
+            }
+            #[cfg(feature = "ipc")]
+            DynamicStringSegment::Variable(name) => {
+                if let Some(value) = state.variables.get(name).filter(|_| visible) {
+                    output.push_str(value);
+                }
+            }
+            #[cfg(feature = "ipc")]
+            DynamicStringSegment::Conditional {
+                variable,
+                negate,
+                then,
+                otherwise,
+            } => {
+                let value = state
+                    .variables
+                    .get(variable)
+                    .is_some_and(|value| is_truthy(value));
+                let condition = value != *negate;
+
+                render_into(then, state, script_index, visible && condition, output);
+                render_into(
+                    otherwise,
+                    state,
+                    script_index,
+                    visible && !condition,
+                    output,
+                );
+            }
+        }
     }
 }
 
 /// Parses the input string into static and dynamic segments
-fn parse_input(input: &str) -> (Vec<DynamicStringSegment>, bool) {
+fn parse_input(input: &str) -> Vec<DynamicStringSegment> {
     // short-circuit parser if it's all static
     if !input.contains("{{") && !input.contains('#') {
-        return (vec![DynamicStringSegment::Static(input.to_string())], true);
+        return vec![DynamicStringSegment::Static(input.to_string())];
     }
 
     let mut tokens = vec![];
@@ -116,7 +259,10 @@ fn parse_input(input: &str) -> (Vec<DynamicStringSegment>, bool) {
 
         let (token, skip) = match char_pair {
             Some(['{', '{']) => parse_script(&chars),
-            Some(['#', '#']) => (DynamicStringSegment::Static("#".to_string()), 2),
+            Some(['#', '#']) => (
+                Token::Segment(DynamicStringSegment::Static("#".to_string())),
+                2,
+            ),
             #[cfg(feature = "ipc")]
             Some(['#', _]) => parse_variable(&chars),
             _ => parse_static(&chars),
@@ -129,10 +275,84 @@ fn parse_input(input: &str) -> (Vec<DynamicStringSegment>, bool) {
         chars.drain(..skip);
     }
 
-    (tokens, false)
+    build_segments(tokens)
+}
+
+/// Nests the segments between conditional directives into blocks.
+///
+/// Unterminated blocks are closed at the end of the input,
+/// and stray `else`/`/if` directives are kept as static text.
+#[cfg(feature = "ipc")]
+fn build_segments(tokens: Vec<Token>) -> Vec<DynamicStringSegment> {
+    struct Block {
+        variable: Box<str>,
+        negate: bool,
+        then: Vec<DynamicStringSegment>,
+        otherwise: Option<Vec<DynamicStringSegment>>,
+    }
+
+    impl Block {
+        fn into_segment(self) -> DynamicStringSegment {
+            DynamicStringSegment::Conditional {
+                variable: self.variable,
+                negate: self.negate,
+                then: self.then,
+                otherwise: self.otherwise.unwrap_or_default(),
+            }
+        }
+    }
+
+    /// Gets the list new segments should be added to.
+    fn current<'a>(
+        root: &'a mut Vec<DynamicStringSegment>,
+        stack: &'a mut [Block],
+    ) -> &'a mut Vec<DynamicStringSegment> {
+        match stack.last_mut() {
+            Some(block) => block.otherwise.as_mut().unwrap_or(&mut block.then),
+            None => root,
+        }
+    }
+
+    let mut root = vec![];
+    let mut stack = vec![];
+
+    for token in tokens {
+        match token {
+            Token::Segment(segment) => current(&mut root, &mut stack).push(segment),
+            Token::If { variable, negate } => stack.push(Block {
+                variable,
+                negate,
+                then: vec![],
+                otherwise: None,
+            }),
+            Token::Else => match stack.last_mut() {
+                Some(block) if block.otherwise.is_none() => block.otherwise = Some(vec![]),
+                _ => current(&mut root, &mut stack)
+                    .push(DynamicStringSegment::Static("{{else}}".to_string())),
+            },
+            Token::EndIf => match stack.pop() {
+                Some(block) => current(&mut root, &mut stack).push(block.into_segment()),
+                None => root.push(DynamicStringSegment::Static("{{/if}}".to_string())),
+            },
+        }
+    }
+
+    while let Some(block) = stack.pop() {
+        current(&mut root, &mut stack).push(block.into_segment());
+    }
+
+    root
+}
+
+#[cfg(not(feature = "ipc"))]
+fn build_segments(tokens: Vec<Token>) -> Vec<DynamicStringSegment> {
+    tokens
+        .into_iter()
+        .map(|Token::Segment(segment)| segment)
+        .collect()
 }
 
-fn parse_script(chars: &[char]) -> (DynamicStringSegment, usize) {
+fn parse_script(chars: &[char]) -> (Token, usize) {
     const SKIP_BRACKETS: usize = 4; // two braces either side
 
     let str = chars
@@ -143,13 +363,42 @@ fn parse_script(chars: &[char]) -> (DynamicStringSegment, usize) {
         .collect::<String>();
 
     let len = str.chars().count() + SKIP_BRACKETS;
+
+    #[cfg(feature = "ipc")]
+    if let Some(directive) = parse_directive(&str) {
+        return (directive, len);
+    }
+
     let script = Script::from(str.as_str());
 
-    (DynamicStringSegment::Script(script), len)
+    (Token::Segment(DynamicStringSegment::Script(script)), len)
 }
 
+/// Parses a conditional block directive:
+/// `#if name`, `#if !name`, `else` or `/if`.
 #[cfg(feature = "ipc")]
-fn parse_variable(chars: &[char]) -> (DynamicStringSegment, usize) {
+fn parse_directive(str: &str) -> Option<Token> {
+    match str {
+        "else" => Some(Token::Else),
+        "/if" => Some(Token::EndIf),
+        _ => {
+            let condition = str.strip_prefix("#if ")?.trim();
+            let (negate, variable) = condition
+                .strip_prefix('!')
+                .map_or((false, condition), |variable| (true, variable));
+
+            let variable = variable.strip_prefix('#').unwrap_or(variable);
+
+            (!variable.is_empty()).then(|| Token::If {
+                variable: variable.into(),
+                negate,
+            })
+        }
+    }
+}
+
+#[cfg(feature = "ipc")]
+fn parse_variable(chars: &[char]) -> (Token, usize) {
     const SKIP_HASH: usize = 1;
 
     let str = chars
@@ -161,10 +410,10 @@ fn parse_variable(chars: &[char]) -> (DynamicStringSegment, usize) {
     let len = str.chars().count() + SKIP_HASH;
     let value = str.into();
 
-    (DynamicStringSegment::Variable(value), len)
+    (Token::Segment(DynamicStringSegment::Variable(value)), len)
 }
 
-fn parse_static(chars: &[char]) -> (DynamicStringSegment, usize) {
+fn parse_static(chars: &[char]) -> (Token, usize) {
     let mut str = chars
         .windows(2)
         .take_while(|&win| win != ['{', '{'] && win[0] != '#')
@@ -180,7 +429,10 @@ fn parse_static(chars: &[char]) -> (DynamicStringSegment, usize) {
         char_count += 1;
     }
 
-    (DynamicStringSegment::Static(str), char_count)
+    (
+        Token::Segment(DynamicStringSegment::Static(str)),
+        char_count,
+    )
 }
 
 #[cfg(test)]
@@ -190,7 +442,7 @@ mod tests {
     #[test]
     fn test_static() {
         const INPUT: &str = "hello world";
-        let (tokens, _) = parse_input(INPUT);
+        let tokens = parse_input(INPUT);
 
         assert_eq!(tokens.len(), 1);
         assert!(matches!(&tokens[0], DynamicStringSegment::Static(value) if value == INPUT))
@@ -199,7 +451,7 @@ mod tests {
     #[test]
     fn test_static_odd_char_count() {
         const INPUT: &str = "hello";
-        let (tokens, _) = parse_input(INPUT);
+        let tokens = parse_input(INPUT);
 
         assert_eq!(tokens.len(), 1);
         assert!(matches!(&tokens[0], DynamicStringSegment::Static(value) if value == INPUT))
@@ -208,7 +460,7 @@ mod tests {
     #[test]
     fn test_script() {
         const INPUT: &str = "{{echo hello}}";
-        let (tokens, _) = parse_input(INPUT);
+        let tokens = parse_input(INPUT);
 
         assert_eq!(tokens.len(), 1);
         assert!(
@@ -219,7 +471,7 @@ mod tests {
     #[test]
     fn test_variable() {
         const INPUT: &str = "#variable";
-        let (tokens, _) = parse_input(INPUT);
+        let tokens = parse_input(INPUT);
 
         assert_eq!(tokens.len(), 1);
         assert!(
@@ -230,7 +482,7 @@ mod tests {
     #[test]
     fn test_static_script() {
         const INPUT: &str = "hello {{echo world}}";
-        let (tokens, _) = parse_input(INPUT);
+        let tokens = parse_input(INPUT);
 
         assert_eq!(tokens.len(), 2);
         assert!(matches!(&tokens[0], DynamicStringSegment::Static(str) if str == "hello "));
@@ -242,7 +494,7 @@ mod tests {
     #[test]
     fn test_static_variable() {
         const INPUT: &str = "hello #subject";
-        let (tokens, _) = parse_input(INPUT);
+        let tokens = parse_input(INPUT);
 
         assert_eq!(tokens.len(), 2);
         assert!(matches!(&tokens[0], DynamicStringSegment::Static(str) if str == "hello "));
@@ -254,7 +506,7 @@ mod tests {
     #[test]
     fn test_static_script_static() {
         const INPUT: &str = "hello {{echo world}} foo";
-        let (tokens, _) = parse_input(INPUT);
+        let tokens = parse_input(INPUT);
 
         assert_eq!(tokens.len(), 3);
         assert!(matches!(&tokens[0], DynamicStringSegment::Static(str) if str == "hello "));
@@ -267,7 +519,7 @@ mod tests {
     #[test]
     fn test_static_variable_static() {
         const INPUT: &str = "hello #subject foo";
-        let (tokens, _) = parse_input(INPUT);
+        let tokens = parse_input(INPUT);
 
         assert_eq!(tokens.len(), 3);
         assert!(matches!(&tokens[0], DynamicStringSegment::Static(str) if str == "hello "));
@@ -280,7 +532,7 @@ mod tests {
     #[test]
     fn test_static_script_variable() {
         const INPUT: &str = "hello {{echo world}} #foo";
-        let (tokens, _) = parse_input(INPUT);
+        let tokens = parse_input(INPUT);
 
         assert_eq!(tokens.len(), 4);
         assert!(matches!(&tokens[0], DynamicStringSegment::Static(str) if str == "hello "));
@@ -296,7 +548,7 @@ mod tests {
     #[test]
     fn test_escape_hash() {
         const INPUT: &str = "number ###num";
-        let (tokens, _) = parse_input(INPUT);
+        let tokens = parse_input(INPUT);
 
         assert_eq!(tokens.len(), 3);
         assert!(matches!(&tokens[0], DynamicStringSegment::Static(str) if str == "number "));
@@ -309,7 +561,7 @@ mod tests {
     #[test]
     fn test_script_with_hash() {
         const INPUT: &str = "{{echo #hello}}";
-        let (tokens, _) = parse_input(INPUT);
+        let tokens = parse_input(INPUT);
 
         assert_eq!(tokens.len(), 1);
         assert!(
@@ -320,7 +572,7 @@ mod tests {
     #[test]
     fn test_pango_attribute() {
         const INPUT: &str = "<span color='#color'>hello</span>";
-        let (tokens, _) = parse_input(INPUT);
+        let tokens = parse_input(INPUT);
 
         assert_eq!(tokens.len(), 3);
 
@@ -330,4 +582,74 @@ mod tests {
         );
         assert!(matches!(&tokens[2], DynamicStringSegment::Static(str) if str == "'>hello</span>"))
     }
+
+    #[test]
+    fn test_conditional() {
+        const INPUT: &str = "{{#if charging}}⚡{{/if}}#percent";
+        let tokens = parse_input(INPUT);
+
+        assert_eq!(tokens.len(), 2);
+        assert!(matches!(
+            &tokens[0],
+            DynamicStringSegment::Conditional { variable, negate: false, then, otherwise }
+                if variable.as_ref() == "charging"
+                    && matches!(then.as_slice(), [DynamicStringSegment::Static(str)] if str == "⚡")
+                    && otherwise.is_empty()
+        ));
+        assert!(
+            matches!(&tokens[1], DynamicStringSegment::Variable(name) if name.as_ref() == "percent")
+        );
+    }
+
+    #[test]
+    fn test_conditional_else() {
+        const INPUT: &str = "{{#if !muted}}on{{else}}{{echo off}}{{/if}}";
+        let tokens = parse_input(INPUT);
+
+        assert_eq!(tokens.len(), 1);
+        assert!(matches!(
+            &tokens[0],
+            DynamicStringSegment::Conditional { variable, negate: true, then, otherwise }
+                if variable.as_ref() == "muted"
+                    && matches!(then.as_slice(), [DynamicStringSegment::Static(str)] if str == "on")
+                    && matches!(otherwise.as_slice(), [DynamicStringSegment::Script(script)] if script.cmd == "echo off")
+        ));
+    }
+
+    #[test]
+    fn test_render_conditional() {
+        const INPUT: &str = "{{#if a}}{{echo a}}{{else}}{{echo b}}{{/if}}{{echo c}}";
+        let tokens = parse_input(INPUT);
+
+        let mut state = State {
+            scripts: vec!["a".to_string(), "b".to_string(), "c".to_string()],
+            ..State::default()
+        };
+
+        assert_eq!(render(&tokens, &state), "bc");
+
+        state.variables.insert("a".into(), "1".to_string());
+        assert_eq!(render(&tokens, &state), "ac");
+    }
+
+    #[test]
+    fn test_render_unset_variables() {
+        const INPUT: &str = "{{#if muted}}muted{{/if}}{{#if !muted}}on{{/if}}#volume";
+        let tokens = parse_input(INPUT);
+
+        let mut scripts = vec![];
+        collect_scripts(&tokens, &mut scripts);
+        assert!(scripts.is_empty());
+
+        assert_eq!(render(&tokens, &State::default()), "on");
+    }
+
+    #[test]
+    fn test_stray_end_if() {
+        const INPUT: &str = "hello{{/if}}";
+        let tokens = parse_input(INPUT);
+
+        assert_eq!(tokens.len(), 2);
+        assert!(matches!(&tokens[1], DynamicStringSegment::Static(str) if str == "{{/if}}"));
+    }
 }
@@ -4,6 +4,8 @@ pub enum ExitCode {
     CreateBars = 2,
     IpcResponseError = 3,
     WaylandDispatchError = 4,
+    #[cfg(feature = "cli")]
+    Config = 5,
 }
 
 pub const ERR_MUTEX_LOCK: &str = "Failed to get lock on Mutex";
@@ -0,0 +1,292 @@
+//! Shared `{token}` replacement for module format strings.
+//!
+//! Tokens can optionally include a format spec after a colon,
+//! loosely following Rust's `format!` syntax:
+//!
+//! ```text
+//! {token:[[fill]align][0][width][.precision][type]}
+//! ```
+//!
+//! - `align` is one of `<` (left), `>` (right) or `^` (centre).
+//!   Numbers are right-aligned by default, and everything else left-aligned.
+//! - `fill` is the character to pad with. Defaults to a space.
+//! - `0` pads numbers with zeros after the sign.
+//! - `width` is the minimum number of characters.
+//! - `precision` is the number of decimal places for numbers,
+//!   or the maximum number of characters for text.
+//! - `type` is one of `x` (lowercase hex), `X` (uppercase hex) or `b` (binary),
+//!   and applies to integers only.
+//!
+//! Invalid specs, or specs which cannot apply to the value,
+//! fall back to inserting the value unchanged.
+
+/// Replaces each `{token}` or `{token:spec}` in the format string
+/// with the value returned by `get` for that token.
+///
+/// If `get` returns `None`, the token is treated as unknown
+/// and left untouched.
+///
+/// Token names may themselves contain colons,
+/// so the full token name is tried before splitting off a spec.
+pub fn replace_tokens(format: &str, mut get: impl FnMut(&str) -> Option<String>) -> String {
+    let mut output = String::with_capacity(format.len());
+    let mut rest = format;
+
+    while let Some(start) = rest.find('{') {
+        output.push_str(&rest[..start]);
+
+        let after = &rest[start + 1..];
+        let Some(end) = after
+            .find(['{', '}'])
+            .filter(|&end| after[end..].starts_with('}'))
+        else {
+            output.push('{');
+            rest = after;
+            continue;
+        };
+
+        let inner = &after[..end];
+
+        let value = get(inner).or_else(|| {
+            let (token, spec) = inner.rsplit_once(':')?;
+            let value = get(token)?;
+
+            Some(FormatSpec::parse(spec).map_or_else(|| value.clone(), |spec| spec.apply(&value)))
+        });
+
+        match value {
+            Some(value) => {
+                output.push_str(&value);
+                rest = &after[end + 1..];
+            }
+            None => {
+                output.push('{');
+                rest = after;
+            }
+        }
+    }
+
+    output.push_str(rest);
+    output
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Align {
+    Left,
+    Right,
+    Center,
+}
+
+impl Align {
+    const fn from_char(c: char) -> Option<Self> {
+        match c {
+            '<' => Some(Self::Left),
+            '>' => Some(Self::Right),
+            '^' => Some(Self::Center),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Radix {
+    Binary,
+    LowerHex,
+    UpperHex,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FormatSpec {
+    fill: char,
+    align: Option<Align>,
+    zero: bool,
+    width: usize,
+    precision: Option<usize>,
+    radix: Option<Radix>,
+}
+
+impl FormatSpec {
+    /// Parses a spec, returning `None` if it is invalid.
+    fn parse(spec: &str) -> Option<Self> {
+        let mut chars = spec.chars().peekable();
+
+        let mut fill = ' ';
+        let mut align = None;
+
+        let mut lookahead = spec.chars();
+        match (lookahead.next(), lookahead.next()) {
+            (Some(c), Some(a)) if Align::from_char(a).is_some() => {
+                fill = c;
+                align = Align::from_char(a);
+                chars.nth(1);
+            }
+            (Some(a), _) if Align::from_char(a).is_some() => {
+                align = Align::from_char(a);
+                chars.next();
+            }
+            _ => {}
+        }
+
+        let zero = chars.next_if_eq(&'0').is_some();
+
+        let mut width = String::new();
+        while let Some(c) = chars.next_if(char::is_ascii_digit) {
+            width.push(c);
+        }
+
+        let precision = if chars.next_if_eq(&'.').is_some() {
+            let mut precision = String::new();
+            while let Some(c) = chars.next_if(char::is_ascii_digit) {
+                precision.push(c);
+            }
+
+            Some(precision.parse().ok()?)
+        } else {
+            None
+        };
+
+        let radix = match chars.next() {
+            Some('b') => Some(Radix::Binary),
+            Some('x') => Some(Radix::LowerHex),
+            Some('X') => Some(Radix::UpperHex),
+            Some(_) => return None,
+            None => None,
+        };
+
+        if chars.next().is_some() {
+            return None;
+        }
+
+        Some(Self {
+            fill,
+            align,
+            zero,
+            width: if width.is_empty() {
+                0
+            } else {
+                width.parse().ok()?
+            },
+            precision,
+            radix,
+        })
+    }
+
+    /// Formats the value according to this spec.
+    fn apply(&self, value: &str) -> String {
+        let number = value.trim().parse::<f64>().ok();
+
+        let body = match (self.radix, self.precision, number) {
+            (Some(radix), _, _) => match value.trim().parse::<i64>() {
+                Ok(n) => match radix {
+                    Radix::Binary => format!("{n:b}"),
+                    Radix::LowerHex => format!("{n:x}"),
+                    Radix::UpperHex => format!("{n:X}"),
+                },
+                Err(_) => return value.to_string(),
+            },
+            (None, Some(precision), Some(n)) => format!("{n:.precision$}"),
+            (None, Some(precision), None) => value.chars().take(precision).collect(),
+            (None, None, _) => value.to_string(),
+        };
+
+        let len = body.chars().count();
+        if len >= self.width {
+            return body;
+        }
+
+        let padding = self.width - len;
+
+        if self.zero && self.align.is_none() && number.is_some() {
+            let (sign, digits) = body
+                .strip_prefix('-')
+                .map_or(("", body.as_str()), |digits| ("-", digits));
+
+            return format!("{sign}{}{digits}", "0".repeat(padding));
+        }
+
+        let align = self.align.unwrap_or(if number.is_some() {
+            Align::Right
+        } else {
+            Align::Left
+        });
+
+        let fill = |n: usize| self.fill.to_string().repeat(n);
+
+        match align {
+            Align::Left => format!("{body}{}", fill(padding)),
+            Align::Right => format!("{}{body}", fill(padding)),
+            Align::Center => format!("{}{body}{}", fill(padding / 2), fill(padding - padding / 2)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get(token: &str) -> Option<String> {
+        match token {
+            "percent" => Some("7".to_string()),
+            "temp" => Some("41.567".to_string()),
+            "name" => Some("ironbar".to_string()),
+            "disk:/" => Some("12".to_string()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn plain() {
+        assert_eq!(replace_tokens("{percent}%", get), "7%");
+    }
+
+    #[test]
+    fn unknown_token() {
+        assert_eq!(replace_tokens("{unknown} {percent}", get), "{unknown} 7");
+    }
+
+    #[test]
+    fn unclosed_brace() {
+        assert_eq!(replace_tokens("{ {percent}", get), "{ 7");
+    }
+
+    #[test]
+    fn width() {
+        assert_eq!(replace_tokens("{percent:3}", get), "  7");
+        assert_eq!(replace_tokens("{name:9}", get), "ironbar  ");
+    }
+
+    #[test]
+    fn align_and_fill() {
+        assert_eq!(replace_tokens("{percent:<3}", get), "7  ");
+        assert_eq!(replace_tokens("{percent:*^5}", get), "**7**");
+    }
+
+    #[test]
+    fn zero_pad() {
+        assert_eq!(replace_tokens("{percent:03}", get), "007");
+    }
+
+    #[test]
+    fn precision() {
+        assert_eq!(replace_tokens("{temp:.1}", get), "41.6");
+        assert_eq!(replace_tokens("{name:.4}", get), "iron");
+    }
+
+    #[test]
+    fn radix() {
+        assert_eq!(replace_tokens("{percent:b}", get), "111");
+        assert_eq!(replace_tokens("{disk:/:04X}", get), "000C");
+    }
+
+    #[test]
+    fn token_with_colon() {
+        assert_eq!(replace_tokens("{disk:/}", get), "12");
+        assert_eq!(replace_tokens("{disk:/:>4}", get), "  12");
+    }
+
+    #[test]
+    fn invalid_spec() {
+        assert_eq!(replace_tokens("{percent:abc}", get), "7");
+        assert_eq!(replace_tokens("{name:x}", get), "ironbar");
+    }
+}
@@ -0,0 +1,50 @@
+use gtk::gdk_pixbuf::Pixbuf;
+use indexmap::IndexMap;
+use std::cell::RefCell;
+use std::path::PathBuf;
+
+/// Maximum number of decoded images to keep at once.
+/// The least recently used image is evicted first.
+const MAX_ENTRIES: usize = 256;
+
+/// Identifies a decoded image by its resolved file path and pixel size.
+///
+/// Icon files are resolved through the icon theme before lookup,
+/// so the path is specific to the theme the icon came from.
+pub type Key = (PathBuf, i32);
+
+thread_local! {
+    static CACHE: RefCell<IndexMap<Key, Pixbuf>> = RefCell::new(IndexMap::new());
+}
+
+/// Gets a previously decoded image,
+/// marking it as recently used.
+pub fn get(key: &Key) -> Option<Pixbuf> {
+    CACHE.with(|cache| {
+        let mut cache = cache.borrow_mut();
+
+        let pixbuf = cache.shift_remove(key)?;
+        cache.insert(key.clone(), pixbuf.clone());
+
+        Some(pixbuf)
+    })
+}
+
+/// Stores a decoded image,
+/// evicting the least recently used image if the cache is full.
+pub fn insert(key: Key, pixbuf: Pixbuf) {
+    CACHE.with(|cache| {
+        let mut cache = cache.borrow_mut();
+
+        if cache.len() >= MAX_ENTRIES {
+            cache.shift_remove_index(0);
+        }
+
+        cache.insert(key, pixbuf);
+    });
+}
+
+/// Removes all decoded images.
+pub fn clear() {
+    CACHE.with(|cache| cache.borrow_mut().clear());
+}
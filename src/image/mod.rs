@@ -1,7 +1,8 @@
+mod cache;
 #[cfg(any(feature = "music", feature = "workspaces", feature = "clipboard"))]
 mod gtk;
 mod provider;
 
 #[cfg(any(feature = "music", feature = "workspaces"))]
 pub use self::gtk::*;
-pub use provider::ImageProvider;
+pub use provider::{ImageFit, ImageProvider, LoadGuard};
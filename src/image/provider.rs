@@ -1,3 +1,4 @@
+use super::cache;
 use crate::desktop_file::get_desktop_icon_name;
 #[cfg(feature = "http")]
 use crate::{glib_recv_mpsc, send_async, spawn};
@@ -5,13 +6,17 @@ use cfg_if::cfg_if;
 use color_eyre::{Help, Report, Result};
 use gtk::cairo::Surface;
 use gtk::gdk::ffi::gdk_cairo_surface_create_from_pixbuf;
-use gtk::gdk_pixbuf::Pixbuf;
+use gtk::gdk_pixbuf::{InterpType, Pixbuf};
+use gtk::glib::SignalHandlerId;
 use gtk::prelude::*;
 use gtk::{IconLookupFlags, IconTheme};
+use serde::Deserialize;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
 #[cfg(feature = "http")]
 use tokio::sync::mpsc;
-use tracing::{debug, warn};
+use tracing::{debug, trace, warn};
 
 cfg_if!(
     if #[cfg(feature = "http")] {
@@ -20,11 +25,11 @@ cfg_if!(
     }
 );
 
-#[derive(Debug)]
-enum ImageLocation<'a> {
+#[derive(Debug, Clone)]
+enum ImageLocation {
     Icon {
         name: String,
-        theme: &'a IconTheme,
+        theme: IconTheme,
     },
     Local(PathBuf),
     Steam(String),
@@ -32,22 +37,91 @@ enum ImageLocation<'a> {
     Remote(reqwest::Url),
 }
 
-pub struct ImageProvider<'a> {
-    location: ImageLocation<'a>,
+/// Tracks the latest image load into a widget,
+/// so that a slow load cannot overwrite a newer image once it completes.
+#[derive(Debug, Clone, Default)]
+pub struct LoadGuard(Arc<AtomicUsize>);
+
+impl LoadGuard {
+    /// Starts a new load, invalidating any loads still in progress.
+    pub fn next(&self) -> LoadToken {
+        let generation = self.0.fetch_add(1, Ordering::Relaxed) + 1;
+
+        LoadToken {
+            latest: self.0.clone(),
+            generation,
+        }
+    }
+}
+
+/// Identifies a single image load started from a `LoadGuard`.
+///
+/// Only remote images are loaded asynchronously,
+/// so the token is not checked otherwise.
+#[derive(Debug, Clone)]
+#[cfg_attr(not(feature = "http"), allow(dead_code))]
+pub struct LoadToken {
+    latest: Arc<AtomicUsize>,
+    generation: usize,
+}
+
+#[cfg_attr(not(feature = "http"), allow(dead_code))]
+impl LoadToken {
+    /// Whether no newer load has been started since this one.
+    fn is_current(&self) -> bool {
+        self.latest.load(Ordering::Relaxed) == self.generation
+    }
+}
+
+/// Key for the scale factor change handler stored on an image,
+/// so that it can be replaced each time a new image is loaded.
+const SCALE_HANDLER_KEY: &str = "ironbar-scale-handler";
+
+/// How images loaded from files are fitted to the requested size.
+/// Icons are always loaded at the requested size.
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(not(feature = "music"), allow(dead_code))]
+pub enum ImageFit {
+    /// Scale the image to fit inside the size,
+    /// preserving aspect ratio.
+    #[default]
+    Contain,
+    /// Scale the image to fill the size,
+    /// preserving aspect ratio and cropping the longer side.
+    Cover,
+}
+
+#[derive(Debug, Clone)]
+pub struct ImageProvider {
+    location: ImageLocation,
     size: i32,
+    fit: ImageFit,
 }
 
-impl<'a> ImageProvider<'a> {
+impl ImageProvider {
     /// Attempts to parse the image input to find its location.
     /// Errors if no valid location type can be found.
     ///
     /// Note this checks that icons exist in theme, or files exist on disk
     /// but no other check is performed.
-    pub fn parse(input: &str, theme: &'a IconTheme, use_fallback: bool, size: i32) -> Option<Self> {
+    pub fn parse(input: &str, theme: &IconTheme, use_fallback: bool, size: i32) -> Option<Self> {
         let location = Self::get_location(input, theme, size, use_fallback, 0)?;
         debug!("Resolved {input} --> {location:?} (size: {size})");
 
-        Some(Self { location, size })
+        Some(Self {
+            location,
+            size,
+            fit: ImageFit::default(),
+        })
+    }
+
+    /// Sets how images loaded from files are fitted to the size.
+    #[cfg_attr(not(feature = "music"), allow(dead_code))]
+    pub fn with_fit(mut self, fit: ImageFit) -> Self {
+        self.fit = fit;
+        self
     }
 
     /// Returns true if the input starts with a prefix
@@ -63,11 +137,11 @@ impl<'a> ImageProvider<'a> {
 
     fn get_location(
         input: &str,
-        theme: &'a IconTheme,
+        theme: &IconTheme,
         size: i32,
         use_fallback: bool,
         recurse_depth: usize,
-    ) -> Option<ImageLocation<'a>> {
+    ) -> Option<ImageLocation> {
         macro_rules! fallback {
             () => {
                 if use_fallback {
@@ -89,7 +163,7 @@ impl<'a> ImageProvider<'a> {
         match input_type {
             Some(input_type) if input_type == "icon" => Some(ImageLocation::Icon {
                 name: input_name.to_string(),
-                theme,
+                theme: theme.clone(),
             }),
             Some(input_type) if input_type == "file" => Some(ImageLocation::Local(PathBuf::from(
                 input_name[2..].to_string(),
@@ -107,7 +181,7 @@ impl<'a> ImageProvider<'a> {
             {
                 Some(ImageLocation::Icon {
                     name: input_name.to_string(),
-                    theme,
+                    theme: theme.clone(),
                 })
             }
             Some(input_type) => {
@@ -142,42 +216,75 @@ impl<'a> ImageProvider<'a> {
     /// Attempts to fetch the image from the location
     /// and load it into the provided `GTK::Image` widget.
     pub fn load_into_image(&self, image: &gtk::Image) -> Result<()> {
+        self.load(image, None)
+    }
+
+    /// Attempts to fetch the image from the location
+    /// and load it into the provided `GTK::Image` widget,
+    /// unless a newer load has been started from the same guard by the time it completes.
+    pub fn load_into_image_guarded(&self, image: &gtk::Image, token: LoadToken) -> Result<()> {
+        self.load(image, Some(token))
+    }
+
+    #[cfg_attr(not(feature = "http"), allow(unused_variables))]
+    fn load(&self, image: &gtk::Image, token: Option<LoadToken>) -> Result<()> {
         // handle remote locations async to avoid blocking UI thread while downloading
         #[cfg(feature = "http")]
         if let ImageLocation::Remote(url) = &self.location {
+            Self::disconnect_scale_handler(image);
+
             let url = url.clone();
             let (tx, rx) = mpsc::channel(64);
 
-            spawn(async move {
-                let bytes = Self::get_bytes_from_http(url).await;
-                if let Ok(bytes) = bytes {
-                    send_async!(tx, bytes);
-                }
-            });
+            let is_current = move || token.as_ref().map_or(true, LoadToken::is_current);
+
+            {
+                let is_current = is_current.clone();
+                spawn(async move {
+                    let bytes = Self::get_bytes_from_http(url).await;
+                    if let Ok(bytes) = bytes {
+                        if is_current() {
+                            send_async!(tx, bytes);
+                        } else {
+                            debug!("Discarding outdated image download");
+                        }
+                    }
+                });
+            }
 
             {
                 let size = self.size;
+                let fit = self.fit;
                 let image = image.clone();
                 glib_recv_mpsc!(rx, bytes => {
+                    if !is_current() {
+                        debug!("Discarding outdated image");
+                        continue;
+                    }
+
                     let stream = MemoryInputStream::from_bytes(&bytes);
 
                     let scale = image.scale_factor();
                     let scaled_size = size * scale;
 
-                    let pixbuf = Pixbuf::from_stream_at_scale(
-                        &stream,
-                        scaled_size,
-                        scaled_size,
-                        true,
-                        Some(&Cancellable::new()),
-                    );
-
-                    // Different error types makes this a bit awkward
-                    match pixbuf.map(|pixbuf| Self::create_and_load_surface(&pixbuf, &image))
+                    let pixbuf = match fit {
+                        ImageFit::Contain => Pixbuf::from_stream_at_scale(
+                            &stream,
+                            scaled_size,
+                            scaled_size,
+                            true,
+                            Some(&Cancellable::new()),
+                        )
+                        .map_err(Report::new),
+                        ImageFit::Cover => Pixbuf::from_stream(&stream, Some(&Cancellable::new()))
+                            .map_err(Report::new)
+                            .and_then(|pixbuf| Self::crop_to_fill(&pixbuf, scaled_size)),
+                    };
+
+                    if let Err(err) =
+                        pixbuf.and_then(|pixbuf| Self::create_and_load_surface(&pixbuf, &image))
                     {
-                        Ok(Err(err)) => error!("{err:?}"),
-                        Err(err) => error!("{err:?}"),
-                        _ => {}
+                        error!("{err:?}");
                     }
                 });
             }
@@ -193,7 +300,53 @@ impl<'a> ImageProvider<'a> {
 
     /// Attempts to synchronously fetch an image from location
     /// and load into into the image.
+    ///
+    /// The image is reloaded if its scale factor later changes.
     fn load_into_image_sync(&self, image: &gtk::Image) -> Result<()> {
+        self.reload_on_scale_change(image);
+        self.load_at_current_scale(image)
+    }
+
+    /// Reloads the image whenever its scale factor changes.
+    ///
+    /// Images created before being added to a bar take the scale
+    /// of the first monitor, which may differ from the bar's output,
+    /// so would otherwise be rendered at the wrong size.
+    ///
+    /// Any handler from a previous load into the same image is replaced.
+    fn reload_on_scale_change(&self, image: &gtk::Image) {
+        Self::disconnect_scale_handler(image);
+
+        let provider = self.clone();
+        let handler = image.connect_scale_factor_notify(move |image| {
+            trace!(
+                "Scale factor changed to {}, reloading image",
+                image.scale_factor()
+            );
+            if let Err(err) = provider.load_at_current_scale(image) {
+                warn!("{err:?}");
+            }
+        });
+
+        // SAFETY: the key is only ever used to store a `SignalHandlerId`
+        unsafe {
+            image.set_data(SCALE_HANDLER_KEY, handler);
+        }
+    }
+
+    /// Removes the scale factor change handler from a previous load, if any.
+    fn disconnect_scale_handler(image: &gtk::Image) {
+        // SAFETY: the key is only ever used to store a `SignalHandlerId`
+        let handler = unsafe { image.steal_data::<SignalHandlerId>(SCALE_HANDLER_KEY) };
+
+        if let Some(handler) = handler {
+            image.disconnect(handler);
+        }
+    }
+
+    /// Fetches the image at the image's current scale factor
+    /// and loads it into the image.
+    fn load_at_current_scale(&self, image: &gtk::Image) -> Result<()> {
         let scale = image.scale_factor();
 
         let pixbuf = match &self.location {
@@ -228,24 +381,77 @@ impl<'a> ImageProvider<'a> {
     }
 
     /// Attempts to get a `Pixbuf` from the GTK icon theme.
+    ///
+    /// Decoded icons are cached by their resolved file,
+    /// so repeated lookups of the same icon skip decoding.
     fn get_from_icon(&self, name: &str, theme: &IconTheme, scale: i32) -> Result<Pixbuf> {
-        let pixbuf =
-            match theme.lookup_icon_for_scale(name, self.size, scale, IconLookupFlags::empty()) {
-                Some(_) => theme.load_icon(name, self.size * scale, IconLookupFlags::FORCE_SIZE),
-                None => Ok(None),
-            }?;
-
-        pixbuf.map_or_else(
-            || Err(Report::msg("Icon theme does not contain icon '{name}'")),
-            Ok,
-        )
+        let Some(icon_info) =
+            theme.lookup_icon_for_scale(name, self.size, scale, IconLookupFlags::empty())
+        else {
+            return Err(Report::msg(format!(
+                "Icon theme does not contain icon '{name}'"
+            )));
+        };
+
+        let key = icon_info.filename().map(|path| (path, self.size * scale));
+
+        if let Some(pixbuf) = key.as_ref().and_then(cache::get) {
+            trace!("Icon cache hit: {name} (size: {})", self.size * scale);
+            return Ok(pixbuf);
+        }
+
+        trace!("Icon cache miss: {name} (size: {})", self.size * scale);
+
+        let pixbuf = theme
+            .load_icon(name, self.size * scale, IconLookupFlags::FORCE_SIZE)?
+            .ok_or_else(|| Report::msg(format!("Icon theme does not contain icon '{name}'")))?;
+
+        if let Some(key) = key {
+            cache::insert(key, pixbuf.clone());
+        }
+
+        Ok(pixbuf)
+    }
+
+    /// Removes all cached icons,
+    /// forcing them to be decoded again on next use.
+    pub fn clear_cache() {
+        debug!("Clearing icon cache");
+        cache::clear();
     }
 
     /// Attempts to get a `Pixbuf` from a local file.
     fn get_from_file(&self, path: &Path, scale: i32) -> Result<Pixbuf> {
         let scaled_size = self.size * scale;
-        let pixbuf = Pixbuf::from_file_at_scale(path, scaled_size, scaled_size, true)?;
-        Ok(pixbuf)
+
+        match self.fit {
+            ImageFit::Contain => {
+                let pixbuf = Pixbuf::from_file_at_scale(path, scaled_size, scaled_size, true)?;
+                Ok(pixbuf)
+            }
+            ImageFit::Cover => Self::crop_to_fill(&Pixbuf::from_file(path)?, scaled_size),
+        }
+    }
+
+    /// Scales the `Pixbuf` to fill a square of `size` pixels,
+    /// cropping the longer side equally from each end.
+    fn crop_to_fill(pixbuf: &Pixbuf, size: i32) -> Result<Pixbuf> {
+        let (width, height) = (pixbuf.width(), pixbuf.height());
+        let scale = f64::from(size) / f64::from(width.min(height).max(1));
+
+        let scaled_width = ((f64::from(width) * scale).round() as i32).max(size);
+        let scaled_height = ((f64::from(height) * scale).round() as i32).max(size);
+
+        let scaled = pixbuf
+            .scale_simple(scaled_width, scaled_height, InterpType::Bilinear)
+            .ok_or_else(|| Report::msg("Failed to scale image"))?;
+
+        Ok(scaled.new_subpixbuf(
+            (scaled_width - size) / 2,
+            (scaled_height - size) / 2,
+            size,
+            size,
+        ))
     }
 
     /// Attempts to get a `Pixbuf` from a local file,
@@ -280,10 +486,10 @@ impl<'a> ImageProvider<'a> {
         }
     }
 
-    fn get_fallback_icon(theme: &'a IconTheme) -> ImageLocation<'a> {
+    fn get_fallback_icon(theme: &IconTheme) -> ImageLocation {
         ImageLocation::Icon {
             name: "dialog-question-symbolic".to_string(),
-            theme,
+            theme: theme.clone(),
         }
     }
 }
@@ -2,13 +2,36 @@ use super::Ipc;
 use crate::ipc::{Command, Response};
 use color_eyre::Result;
 use color_eyre::{Help, Report};
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWriteExt, BufReader};
 use tokio::net::UnixStream;
 
 impl Ipc {
     /// Sends a command to the IPC server.
     /// The server response is returned.
     pub async fn send(&self, command: Command, debug: bool) -> Result<Response> {
+        let stream = self.connect(&command, debug).await?;
+        read_response(stream).await
+    }
+
+    /// Sends a streaming command to the IPC server.
+    /// `on_response` is called for each newline-delimited response
+    /// until the server closes the connection.
+    pub async fn watch<F>(&self, command: Command, debug: bool, mut on_response: F) -> Result<()>
+    where
+        F: FnMut(Response),
+    {
+        let stream = self.connect(&command, debug).await?;
+        let mut lines = BufReader::new(stream).lines();
+
+        while let Some(line) = lines.next_line().await? {
+            on_response(serde_json::from_str(&line)?);
+        }
+
+        Ok(())
+    }
+
+    /// Connects to the IPC server and writes the command.
+    async fn connect(&self, command: &Command, debug: bool) -> Result<UnixStream> {
         let mut stream = match UnixStream::connect(&self.path).await {
             Ok(stream) => Ok(stream),
             Err(err) => Err(Report::new(err)
@@ -16,18 +39,50 @@ impl Ipc {
                 .suggestion("Is Ironbar running?")),
         }?;
 
-        let write_buffer = serde_json::to_vec(&command)?;
+        let write_buffer = serde_json::to_vec(command)?;
 
         if debug {
-            eprintln!("REQUEST JSON: {}", serde_json::to_string(&command)?);
+            eprintln!("REQUEST JSON: {}", serde_json::to_string(command)?);
         }
 
         stream.write_all(&write_buffer).await?;
 
-        let mut read_buffer = vec![0; 1024];
-        let bytes = stream.read(&mut read_buffer).await?;
+        Ok(stream)
+    }
+}
+
+/// Reads the response message.
+/// The server closes the connection once it has been written.
+async fn read_response<R: AsyncRead + Unpin>(mut stream: R) -> Result<Response> {
+    let mut read_buffer = vec![];
+    stream.read_to_end(&mut read_buffer).await?;
+
+    let response = serde_json::from_slice(&read_buffer)?;
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    #[tokio::test]
+    async fn test_read_large_response() {
+        let values = (0..100)
+            .map(|i| (format!("key{i}").into(), "x".repeat(50)))
+            .collect::<BTreeMap<_, _>>();
+
+        let message = serde_json::to_vec(&Response::OkMap { values }).expect("to serialize");
+        assert!(message.len() > 1024);
+
+        let (client, mut server) = tokio::io::duplex(256);
+
+        tokio::spawn(async move {
+            server.write_all(&message).await.expect("to write");
+            server.shutdown().await.expect("to shut down");
+        });
 
-        let response = serde_json::from_slice(&read_buffer[..bytes])?;
-        Ok(response)
+        let response = read_response(client).await.expect("to read");
+        assert!(matches!(response, Response::OkMap { values } if values.len() == 100));
     }
 }
@@ -1,7 +1,7 @@
 use clap::ArgAction;
 use std::path::PathBuf;
 
-use clap::{Args, Subcommand};
+use clap::{Args, Subcommand, ValueEnum};
 use serde::{Deserialize, Serialize};
 
 #[derive(Subcommand, Debug, Serialize, Deserialize)]
@@ -13,22 +13,69 @@ pub enum Command {
     /// Open the GTK inspector.
     Inspect,
 
-    /// Reload the config.
+    /// Reload the config and stylesheet, and recreate all bars.
+    ///
+    /// If the new config fails to load,
+    /// the existing bars are kept running.
     Reload,
 
-    /// Load an additional CSS stylesheet.
+    /// Load an additional CSS stylesheet, or directory of stylesheets.
     /// The sheet is automatically hot-reloaded.
     LoadCss {
-        /// The path to the sheet.
+        /// The path to the sheet or directory.
         path: PathBuf,
     },
 
+    /// Set a colour which stylesheets can reference using `@name`.
+    /// Omit the value to remove it.
+    SetStyle {
+        /// The colour name. Can contain alphanumeric characters, `-` and `_`.
+        #[clap(long = "var")]
+        name: String,
+        /// Any valid GTK CSS colour.
+        value: Option<String>,
+    },
+
+    /// Set the bar mode, which adds a `mode-<mode>` class to every bar.
+    /// Omit the mode to clear it.
+    SetMode {
+        /// The mode name. Can contain alphanumeric characters, `-` and `_`.
+        mode: Option<String>,
+    },
+
     /// Get and set reactive Ironvar values.
     #[command(subcommand)]
     Var(IronvarCommand),
 
+    /// Set multiple `ironvar` values in a single call.
+    /// Each variable is set in turn, and fires its own change event.
+    SetMany {
+        /// Variables to set, each as a `key=value` pair.
+        #[arg(value_parser = parse_key_value, required = true)]
+        values: Vec<(Box<str>, String)>,
+    },
+
+    /// Get the current value of every `ironvar`.
+    Dump,
+
+    /// List all bars and their current visibility state.
+    ListBars,
+
+    /// Run a named action from the config's `actions` map.
+    Run {
+        /// The action name.
+        name: String,
+        /// Arguments to pass to the action's script.
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+        #[serde(default)]
+        args: Vec<String>,
+    },
+
     /// Interact with a specific bar.
     Bar(BarCommand),
+
+    /// Show or hide a specific module on every bar.
+    Module(ModuleCommand),
 }
 
 #[derive(Subcommand, Debug, Serialize, Deserialize)]
@@ -53,6 +100,14 @@ pub enum IronvarCommand {
 
     /// Gets the current value of all `ironvar`s.
     List,
+
+    /// Watch an `ironvar`, printing its value each time it changes.
+    /// The current value is printed immediately.
+    /// This creates the variable if it does not already exist.
+    Watch {
+        /// Variable key.
+        key: Box<str>,
+    },
 }
 
 #[derive(Args, Debug, Serialize, Deserialize)]
@@ -118,6 +173,12 @@ pub enum BarCommandType {
     },
     /// Get the popup's current visibility state.
     GetPopupVisible,
+    /// Toggle a popup open/closed, opening it at the pointer position instead of next to the module.
+    /// If opening this popup, and a different popup on the same bar is already open, the other is closed.
+    TogglePopupAtPointer {
+        /// The configured name of the widget.
+        widget_name: String,
+    },
 
     // == Exclusivity == \\
     /// Set whether the bar reserves an exclusive zone.
@@ -129,4 +190,59 @@ pub enum BarCommandType {
         )]
         exclusive: bool,
     },
+
+    // == Layout == \\
+    /// Move a module to a different section of the bar, or a different position in its section.
+    /// This is not persisted, and is reset when the config is reloaded.
+    MoveModule {
+        /// The configured name of the widget.
+        widget_name: String,
+        /// The bar section to move the module into.
+        #[clap(value_enum)]
+        section: BarSection,
+        /// The position to move the module to within the section, starting from 0.
+        /// If not set, the module is moved to the end of the section.
+        position: Option<i32>,
+    },
+}
+
+#[derive(Args, Debug, Serialize, Deserialize)]
+pub struct ModuleCommand {
+    /// The configured name of the widget.
+    pub widget_name: String,
+
+    #[command(subcommand)]
+    #[serde(flatten)]
+    pub subcommand: ModuleCommandType,
+}
+
+#[derive(Subcommand, Debug, Serialize, Deserialize)]
+#[serde(tag = "subcommand", rename_all = "snake_case")]
+pub enum ModuleCommandType {
+    /// Force the module to be shown, regardless of current visibility state.
+    Show,
+    /// Force the module to be hidden, regardless of current visibility state.
+    Hide,
+    /// Toggle the current visibility state between shown and hidden.
+    Toggle,
+    /// Get the module's visibility state.
+    GetVisible,
+}
+
+/// One of the three sections of modules on a bar.
+#[derive(ValueEnum, Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BarSection {
+    Start,
+    Center,
+    End,
+}
+
+/// Parses a `key=value` CLI argument.
+/// Only the first `=` is used to split the pair,
+/// so values may contain further `=` characters.
+fn parse_key_value(arg: &str) -> Result<(Box<str>, String), String> {
+    arg.split_once('=')
+        .map(|(key, value)| (key.into(), value.to_string()))
+        .ok_or_else(|| format!("invalid `key=value` pair: no `=` found in `{arg}`"))
 }
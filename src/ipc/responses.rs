@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
@@ -6,6 +7,7 @@ pub enum Response {
     Ok,
     OkValue { value: String },
     Multi { values: Vec<String> },
+    OkMap { values: BTreeMap<Box<str>, String> },
     Err { message: Option<String> },
 }
 
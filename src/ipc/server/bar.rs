@@ -40,6 +40,13 @@ pub fn handle_command(command: BarCommand, ironbar: &Rc<Ironbar>) -> Response {
                 };
                 Response::Ok
             }
+            TogglePopupAtPointer { widget_name } => {
+                if bar.popup().visible() {
+                    hide_popup(&bar)
+                } else {
+                    show_popup_at_pointer(&bar, widget_name)
+                }
+            }
             GetPopupVisible => Response::OkValue {
                 value: bar.popup().visible().to_string(),
             },
@@ -47,8 +54,18 @@ pub fn handle_command(command: BarCommand, ironbar: &Rc<Ironbar>) -> Response {
                 bar.set_exclusive(*exclusive);
                 Response::Ok
             }
+            MoveModule {
+                widget_name,
+                section,
+                position,
+            } => match bar.move_module(widget_name, *section, *position) {
+                Ok(()) => Response::Ok,
+                Err(err) => Response::error(&err.to_string()),
+            },
         })
         .reduce(|acc, rsp| match (acc, rsp) {
+            // If any response is an error, return the first error.
+            (err @ Response::Err { .. }, _) | (_, err @ Response::Err { .. }) => err,
             // If all responses are Ok, return one Ok. We assume we'll never mix Ok and OkValue.
             (Response::Ok, _) => Response::Ok,
             // Two or more OkValues create a multi:
@@ -64,6 +81,17 @@ pub fn handle_command(command: BarCommand, ironbar: &Rc<Ironbar>) -> Response {
         .unwrap_or(Response::error("Invalid bar name"))
 }
 
+/// Gets a map of every bar's name to its visibility.
+pub fn list_bars(ironbar: &Rc<Ironbar>) -> Response {
+    let values = ironbar
+        .bars()
+        .iter()
+        .map(|bar| (bar.name().into(), bar.visible().to_string()))
+        .collect();
+
+    Response::OkMap { values }
+}
+
 fn set_visible(bar: &Bar, visible: bool) -> Response {
     bar.set_visible(visible);
     Response::Ok
@@ -94,6 +122,25 @@ fn show_popup(bar: &Bar, widget_name: &str) -> Response {
     }
 }
 
+fn show_popup_at_pointer(bar: &Bar, widget_name: &str) -> Response {
+    let popup = bar.popup();
+
+    let id = popup
+        .container_cache
+        .borrow()
+        .iter()
+        .find(|(_, value)| value.name == widget_name)
+        .map(|(id, _)| *id);
+
+    match id {
+        Some(id) => {
+            popup.show_at_pointer(id);
+            Response::Ok
+        }
+        None => Response::error("Invalid module name"),
+    }
+}
+
 fn hide_popup(bar: &Bar) -> Response {
     let popup = bar.popup();
     popup.hide();
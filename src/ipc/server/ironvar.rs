@@ -1,6 +1,13 @@
 use crate::ipc::commands::IronvarCommand;
 use crate::ipc::Response;
+use crate::ironvar::{VariableManager, MODE_KEY};
 use crate::{read_lock, write_lock, Ironbar};
+use color_eyre::Result;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::UnixStream;
+use tokio::select;
+use tokio::sync::broadcast::error::RecvError;
+use tracing::debug;
 
 pub fn handle_command(command: IronvarCommand) -> Response {
     match command {
@@ -34,5 +41,78 @@ pub fn handle_command(command: IronvarCommand) -> Response {
 
             Response::OkValue { value }
         }
+        IronvarCommand::Watch { .. } => {
+            Response::error("Watch is only supported as a streaming connection")
+        }
+    }
+}
+
+/// Streams the value of an `ironvar` to the connection each time it changes,
+/// until the client disconnects.
+///
+/// Each value is written as an `ok_value` response followed by a newline.
+pub async fn watch(mut stream: UnixStream, key: Box<str>) -> Result<()> {
+    let mut rx = write_lock!(Ironbar::variable_manager()).subscribe(key.clone());
+
+    let (mut stream_read, mut stream_write) = stream.split();
+    let mut read_buffer = vec![0; 64];
+
+    loop {
+        select! {
+            value = rx.recv() => match value {
+                Ok(value) => {
+                    let res = Response::OkValue { value: value.unwrap_or_default() };
+
+                    let mut res = serde_json::to_vec(&res)?;
+                    res.push(b'\n');
+
+                    if stream_write.write_all(&res).await.is_err() {
+                        break;
+                    }
+                }
+                Err(RecvError::Lagged(_)) => {}
+                Err(RecvError::Closed) => break,
+            },
+            // the client does not write after the command,
+            // so any read completing means it has disconnected.
+            _ = stream_read.read(&mut read_buffer) => break,
+        }
+    }
+
+    debug!("Watch on ironvar '{key}' ended");
+
+    Ok(())
+}
+
+/// Sets each `key=value` pair in turn.
+pub fn set_many(values: Vec<(Box<str>, String)>) -> Response {
+    let variable_manager = Ironbar::variable_manager();
+    let mut variable_manager = write_lock!(variable_manager);
+    match variable_manager.set_many(values) {
+        Ok(()) => Response::Ok,
+        Err(err) => Response::error(&format!("{err:#}")),
+    }
+}
+
+/// Gets the current value of every ironvar.
+pub fn dump() -> Response {
+    let variable_manager = Ironbar::variable_manager();
+    let values = read_lock!(variable_manager).snapshot();
+
+    Response::OkMap { values }
+}
+
+/// Sets the bar mode ironvar, or clears it if no mode is given.
+pub fn set_mode(mode: Option<String>) -> Response {
+    let mode = mode.unwrap_or_default();
+    if !VariableManager::is_identifier(&mode) {
+        return Response::error("Invalid mode");
+    }
+
+    let variable_manager = Ironbar::variable_manager();
+    let mut variable_manager = write_lock!(variable_manager);
+    match variable_manager.set(MODE_KEY.into(), mode) {
+        Ok(()) => Response::Ok,
+        Err(err) => Response::error(&format!("{err}")),
     }
 }
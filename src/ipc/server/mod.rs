@@ -1,5 +1,6 @@
 mod bar;
 mod ironvar;
+mod module;
 
 use std::fs;
 use std::path::Path;
@@ -8,17 +9,22 @@ use std::rc::Rc;
 use color_eyre::{Report, Result};
 use gtk::prelude::*;
 use gtk::Application;
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
 use tokio::net::{UnixListener, UnixStream};
 use tokio::sync::mpsc::{self, Receiver, Sender};
 use tracing::{debug, error, info, warn};
 
+use crate::ipc::commands::IronvarCommand;
 use crate::ipc::{Command, Response};
+use crate::script::Script;
 use crate::style::load_css;
 use crate::{glib_recv_mpsc, send_async, spawn, try_send, Ironbar};
 
 use super::Ipc;
 
+/// The maximum size of a command message, in bytes.
+const MAX_COMMAND_SIZE: usize = 1024 * 1024;
+
 impl Ipc {
     /// Starts the IPC server on its socket.
     ///
@@ -51,9 +57,28 @@ impl Ipc {
 
             loop {
                 match listener.accept().await {
-                    Ok((stream, _addr)) => {
+                    Ok((mut stream, _addr)) => {
+                        let command = match Self::read_command(&mut stream).await {
+                            Ok(command) => command,
+                            Err(err) => {
+                                error!("{err:?}");
+                                continue;
+                            }
+                        };
+
+                        // watches stream until the client disconnects,
+                        // so are handled separately to avoid blocking other connections.
+                        if let Command::Var(IronvarCommand::Watch { key }) = command {
+                            spawn(async move {
+                                if let Err(err) = ironvar::watch(stream, key).await {
+                                    error!("{err:?}");
+                                }
+                            });
+                            continue;
+                        }
+
                         if let Err(err) =
-                            Self::handle_connection(stream, &cmd_tx, &mut res_rx).await
+                            Self::handle_connection(stream, command, &cmd_tx, &mut res_rx).await
                         {
                             error!("{err:?}");
                         }
@@ -72,25 +97,40 @@ impl Ipc {
         });
     }
 
-    /// Takes an incoming connections,
-    /// reads the command message, and sends the response.
+    /// Reads the command message from an incoming connection.
+    ///
+    /// Clients do not close their end after writing,
+    /// so the message is read until it forms a complete JSON value.
+    async fn read_command<R: AsyncRead + Unpin>(stream: &mut R) -> Result<Command> {
+        let mut message = vec![];
+        let mut read_buffer = vec![0; 1024];
+
+        let command = loop {
+            let bytes = stream.read(&mut read_buffer).await?;
+            message.extend_from_slice(&read_buffer[..bytes]);
+
+            match serde_json::from_slice::<Command>(&message) {
+                Ok(command) => break command,
+                Err(err) if err.is_eof() && bytes > 0 && message.len() < MAX_COMMAND_SIZE => {}
+                Err(err) => return Err(err.into()),
+            }
+        };
+
+        debug!("Received command: {command:?}");
+
+        Ok(command)
+    }
+
+    /// Takes an incoming connection and its command,
+    /// and sends the response.
     ///
     /// The connection is closed once the response has been written.
     async fn handle_connection(
         mut stream: UnixStream,
+        command: Command,
         cmd_tx: &Sender<Command>,
         res_rx: &mut Receiver<Response>,
     ) -> Result<()> {
-        let (mut stream_read, mut stream_write) = stream.split();
-
-        let mut read_buffer = vec![0; 1024];
-        let bytes = stream_read.read(&mut read_buffer).await?;
-
-        // FIXME: Error on invalid command
-        let command = serde_json::from_slice::<Command>(&read_buffer[..bytes])?;
-
-        debug!("Received command: {command:?}");
-
         send_async!(cmd_tx, command);
         let res = res_rx
             .recv()
@@ -98,8 +138,8 @@ impl Ipc {
             .unwrap_or(Response::Err { message: None });
         let res = serde_json::to_vec(&res)?;
 
-        stream_write.write_all(&res).await?;
-        stream_write.shutdown().await?;
+        stream.write_all(&res).await?;
+        stream.shutdown().await?;
 
         Ok(())
     }
@@ -119,6 +159,15 @@ impl Ipc {
                 Response::Ok
             }
             Command::Reload => {
+                info!("Reloading config");
+
+                // validate the new config before tearing anything down,
+                // so that a broken config leaves the running bars intact.
+                if let Err(err) = ironbar.reload_config() {
+                    error!("{err:?}");
+                    return Response::error(&format!("Failed to load config: {err}"));
+                }
+
                 info!("Closing existing bars");
                 ironbar.bars.borrow_mut().clear();
 
@@ -130,8 +179,6 @@ impl Ipc {
                 let wl = ironbar.clients.borrow_mut().wayland();
                 let outputs = wl.output_info_all();
 
-                ironbar.reload_config();
-
                 for output in outputs {
                     match crate::load_output_bars(ironbar, application, &output) {
                         Ok(mut bars) => ironbar.bars.borrow_mut().append(&mut bars),
@@ -139,18 +186,39 @@ impl Ipc {
                     }
                 }
 
+                ironbar.reload_css(application);
+
                 Response::Ok
             }
             Command::LoadCss { path } => {
                 if path.exists() {
-                    load_css(path, application.clone());
+                    load_css(vec![path], application.clone());
                     Response::Ok
                 } else {
                     Response::error("File not found")
                 }
             }
+            Command::SetStyle { name, value } => match ironbar.set_style_variable(name, value) {
+                Ok(()) => Response::Ok,
+                Err(err) => Response::error(&format!("{err:?}")),
+            },
+            Command::SetMode { mode } => ironvar::set_mode(mode),
             Command::Var(cmd) => ironvar::handle_command(cmd),
+            Command::SetMany { values } => ironvar::set_many(values),
+            Command::Dump => ironvar::dump(),
+            Command::ListBars => bar::list_bars(ironbar),
+            Command::Run { name, args } => {
+                let config = ironbar.config.borrow();
+                match config.actions.get(name.as_str()) {
+                    Some(action) => {
+                        Script::from(action.as_str()).run_as_oneshot(Some(&args));
+                        Response::Ok
+                    }
+                    None => Response::error(&format!("Unknown action '{name}'")),
+                }
+            }
             Command::Bar(cmd) => bar::handle_command(cmd, ironbar),
+            Command::Module(cmd) => module::handle_command(cmd, ironbar),
         }
     }
 
@@ -162,3 +230,31 @@ impl Ipc {
         fs::remove_file(&path).ok();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_read_large_command() {
+        let values = (0..100)
+            .map(|i| (format!("key{i}").into(), "x".repeat(50)))
+            .collect::<Vec<(Box<str>, String)>>();
+
+        let message = serde_json::to_vec(&Command::SetMany { values }).expect("to serialize");
+        assert!(message.len() > 1024);
+
+        let (mut client, mut server) = tokio::io::duplex(256);
+
+        // the client keeps its end open after writing
+        let write = tokio::spawn(async move {
+            client.write_all(&message).await.expect("to write");
+            client
+        });
+
+        let command = Ipc::read_command(&mut server).await.expect("to read");
+        assert!(matches!(command, Command::SetMany { values } if values.len() == 100));
+
+        write.await.expect("to finish");
+    }
+}
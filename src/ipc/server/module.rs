@@ -0,0 +1,46 @@
+use super::Response;
+use crate::bar::Bar;
+use crate::ipc::{ModuleCommand, ModuleCommandType};
+use crate::Ironbar;
+use color_eyre::Result;
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+/// Runs the command against the module on each bar it is present on.
+pub fn handle_command(command: ModuleCommand, ironbar: &Rc<Ironbar>) -> Response {
+    use ModuleCommandType::*;
+
+    let name = command.widget_name.as_str();
+
+    // bars without the module are skipped,
+    // so the command only fails if no bar has it.
+    let mut found = false;
+    let mut values = BTreeMap::new();
+
+    for bar in ironbar.bars().iter() {
+        let res = match command.subcommand {
+            Show => bar.set_module_visible(name, true),
+            Hide => bar.set_module_visible(name, false),
+            Toggle => toggle(bar, name),
+            GetVisible => bar.module_visible(name).map(|visible| {
+                values.insert(bar.name().into(), visible.to_string());
+            }),
+        };
+
+        found |= res.is_ok();
+    }
+
+    if !found {
+        return Response::error("Invalid module name");
+    }
+
+    match command.subcommand {
+        GetVisible => Response::OkMap { values },
+        _ => Response::Ok,
+    }
+}
+
+fn toggle(bar: &Bar, name: &str) -> Result<()> {
+    let visible = bar.module_visible(name)?;
+    bar.set_module_visible(name, !visible)
+}
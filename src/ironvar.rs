@@ -1,10 +1,15 @@
 #![doc = include_str!("../docs/Ironvars.md")]
 
 use crate::send;
+use color_eyre::eyre::WrapErr;
 use color_eyre::{Report, Result};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use tokio::sync::broadcast;
 
+/// The ironvar holding the current bar mode, set using the `set_mode` IPC command.
+/// Bars apply a `mode-<value>` class to themselves while this is set.
+pub const MODE_KEY: &str = "ironbar_mode";
+
 /// Global singleton manager for `IronVar` variables.
 pub struct VariableManager {
     variables: HashMap<Box<str>, IronVar>,
@@ -40,6 +45,21 @@ impl VariableManager {
         }
     }
 
+    /// Sets the value for each variable in turn,
+    /// creating any which do not exist.
+    ///
+    /// Each variable fires its own change event.
+    /// This stops at the first invalid key,
+    /// leaving any variables before it set.
+    pub fn set_many(&mut self, values: Vec<(Box<str>, String)>) -> Result<()> {
+        for (key, value) in values {
+            self.set(key.clone(), value)
+                .wrap_err_with(|| format!("Failed to set '{key}'"))?;
+        }
+
+        Ok(())
+    }
+
     /// Gets the current value of an `ironvar`.
     /// Prefer to use `subscribe` where possible.
     pub fn get(&self, key: &str) -> Option<String> {
@@ -50,6 +70,16 @@ impl VariableManager {
         &self.variables
     }
 
+    /// Takes a snapshot of the current value of every variable,
+    /// sorted by key.
+    /// Variables which have been subscribed to but never set are excluded.
+    pub fn snapshot(&self) -> BTreeMap<Box<str>, String> {
+        self.variables
+            .iter()
+            .filter_map(|(key, var)| var.get().map(|value| (key.clone(), value)))
+            .collect()
+    }
+
     /// Subscribes to an `ironvar`, creating it if it does not exist.
     /// Any time the var is set, its value is sent on the channel.
     pub fn subscribe(&mut self, key: Box<str>) -> broadcast::Receiver<Option<String>> {
@@ -60,10 +90,15 @@ impl VariableManager {
     }
 
     fn key_is_valid(key: &str) -> bool {
-        !key.is_empty()
-            && key
-                .chars()
-                .all(|char| char.is_alphanumeric() || char == '_' || char == '-')
+        !key.is_empty() && Self::is_identifier(key)
+    }
+
+    /// Checks whether the value only contains alphanumeric characters,
+    /// underscores and hyphens.
+    pub fn is_identifier(value: &str) -> bool {
+        value
+            .chars()
+            .all(|char| char.is_alphanumeric() || char == '_' || char == '-')
     }
 }
 
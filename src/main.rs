@@ -3,6 +3,7 @@
 use std::cell::RefCell;
 use std::env;
 use std::future::Future;
+use std::panic::Location;
 use std::path::PathBuf;
 use std::process::exit;
 use std::rc::Rc;
@@ -10,6 +11,8 @@ use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 #[cfg(feature = "ipc")]
 use std::sync::RwLock;
 use std::sync::{mpsc, Arc, Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
 
 use cfg_if::cfg_if;
 #[cfg(feature = "cli")]
@@ -20,6 +23,7 @@ use dirs::config_dir;
 use gtk::gdk::Display;
 use gtk::prelude::*;
 use gtk::Application;
+use serde::de::DeserializeOwned;
 use smithay_client_toolkit::output::OutputInfo;
 use tokio::runtime::Runtime;
 use tokio::task::{block_in_place, JoinHandle};
@@ -43,6 +47,7 @@ mod config;
 mod desktop_file;
 mod dynamic_value;
 mod error;
+mod format;
 mod gtk_helpers;
 mod image;
 #[cfg(feature = "ipc")]
@@ -59,6 +64,17 @@ mod style;
 pub const APP_ID: &str = "dev.jstanger.ironbar";
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 
+/// Maximum time to wait for shutdown hooks to complete
+/// before exiting anyway.
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Time the UI thread can be blocked for
+/// before a frame is dropped.
+pub const FRAME_BUDGET: Duration = Duration::from_millis(16);
+
+/// A cleanup closure run once on shutdown.
+type ShutdownHook = Box<dyn FnOnce() + Send>;
+
 fn main() {
     let _guard = logging::install_logging();
 
@@ -85,22 +101,51 @@ fn run_with_args() {
         return;
     }
 
+    if args.migrate_config {
+        migrate_config();
+        return;
+    }
+
     match args.command {
         Some(command) => {
             if args.debug {
                 eprintln!("REQUEST: {command:?}");
             }
 
+            let format = args.format.unwrap_or_default();
+
             let rt = create_runtime();
             rt.block_on(async move {
                 let ipc = ipc::Ipc::new();
+
+                if matches!(
+                    command,
+                    ipc::Command::Var(ipc::IronvarCommand::Watch { .. })
+                ) {
+                    let res = ipc
+                        .watch(command, args.debug, |res| {
+                            if args.debug {
+                                eprintln!("RESPONSE: {res:?}");
+                            }
+
+                            cli::handle_response(res, format);
+                        })
+                        .await;
+
+                    if let Err(err) = res {
+                        error!("{err:?}");
+                    }
+
+                    return;
+                }
+
                 match ipc.send(command, args.debug).await {
                     Ok(res) => {
                         if args.debug {
                             eprintln!("RESPONSE: {res:?}");
                         }
 
-                        cli::handle_response(res, args.format.unwrap_or_default());
+                        cli::handle_response(res, format);
                     }
                     Err(err) => error!("{err:?}"),
                 };
@@ -116,6 +161,12 @@ pub struct Ironbar {
     clients: Rc<RefCell<Clients>>,
     config: Rc<RefCell<Config>>,
     config_dir: PathBuf,
+    /// The main stylesheet paths and the provider they are loaded into.
+    #[cfg(feature = "ipc")]
+    style: RefCell<Option<(Vec<PathBuf>, gtk::CssProvider)>>,
+    /// Colours set at runtime over IPC, created on first use.
+    #[cfg(feature = "ipc")]
+    style_variables: RefCell<Option<style::StyleVariables>>,
 }
 
 impl Ironbar {
@@ -127,6 +178,10 @@ impl Ironbar {
             clients: Rc::new(RefCell::new(Clients::new())),
             config: Rc::new(RefCell::new(config)),
             config_dir,
+            #[cfg(feature = "ipc")]
+            style: RefCell::default(),
+            #[cfg(feature = "ipc")]
+            style_variables: RefCell::default(),
         }
     }
 
@@ -144,6 +199,10 @@ impl Ironbar {
         let instance = Rc::new(self);
         let instance2 = instance.clone();
 
+        // desktop files are used for icon lookups while creating bars,
+        // so start loading them in the background as early as possible.
+        spawn_blocking(desktop_file::preload);
+
         // force start wayland client ahead of ui
         let wl = instance.clients.borrow_mut().wayland();
         let mut rx_outputs = wl.subscribe_outputs();
@@ -164,7 +223,8 @@ impl Ironbar {
                 }
             }
 
-            let style_path = env::var("IRONBAR_CSS").ok().map_or_else(
+            // multiple files or directories can be given, separated like `PATH`
+            let style_paths = env::var_os("IRONBAR_CSS").map_or_else(
                 || {
                     config_dir().map_or_else(
                         || {
@@ -172,14 +232,50 @@ impl Ironbar {
                             error!("{:?}", report);
                             exit(ExitCode::CreateBars as i32);
                         },
-                        |dir| dir.join("ironbar").join("style.css"),
+                        |dir| {
+                            let dir = dir.join("ironbar");
+                            vec![dir.join("style.css"), dir.join("style.scss")]
+                        },
                     )
                 },
-                PathBuf::from,
+                |paths| env::split_paths(&paths).collect(),
             );
 
-            if style_path.exists() {
-                load_css(style_path, app.clone());
+            let style_paths = style_paths
+                .into_iter()
+                .filter(|path| path.exists())
+                .collect::<Vec<_>>();
+
+            if !style_paths.is_empty() {
+                cfg_if! {
+                    if #[cfg(feature = "ipc")] {
+                        let provider = load_css(style_paths.clone(), app.clone());
+                        instance.style.replace(Some((style_paths, provider)));
+                    } else {
+                        load_css(style_paths, app.clone());
+                    }
+                }
+            }
+
+            // popups must be hidden on the main thread,
+            // so the hook waits for the main loop to handle it.
+            {
+                let (hide_tx, hide_rx) = tokio::sync::mpsc::channel::<mpsc::Sender<()>>(1);
+                let instance = instance.clone();
+
+                glib_recv_mpsc!(hide_rx, done => {
+                    for bar in instance.bars.borrow().iter() {
+                        bar.popup().hide();
+                    }
+                    send!(done, ());
+                });
+
+                Ironbar::on_shutdown(move || {
+                    let (done_tx, done_rx) = mpsc::channel();
+                    if hide_tx.blocking_send(done_tx).is_ok() {
+                        done_rx.recv().ok();
+                    }
+                });
             }
 
             let (tx, rx) = mpsc::channel();
@@ -191,6 +287,8 @@ impl Ironbar {
 
                 info!("Shutting down");
 
+                Ironbar::run_shutdown_hooks(SHUTDOWN_TIMEOUT);
+
                 #[cfg(feature = "ipc")]
                 ipc::Ipc::shutdown(ipc_path);
 
@@ -257,6 +355,45 @@ impl Ironbar {
         COUNTER.fetch_add(1, Ordering::Relaxed)
     }
 
+    /// Registers a closure to run on shutdown, before the process exits.
+    ///
+    /// Hooks run once, in the order they were registered,
+    /// on a background thread.
+    /// They should be used to close connections and flush any state to disk.
+    pub fn on_shutdown<F>(hook: F)
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        lock!(Self::shutdown_hooks()).push(Box::new(hook));
+    }
+
+    /// Runs all registered shutdown hooks,
+    /// giving up if they have not completed within `timeout`.
+    fn run_shutdown_hooks(timeout: Duration) {
+        let hooks = std::mem::take(&mut *lock!(Self::shutdown_hooks()));
+        debug!("Running {} shutdown hooks", hooks.len());
+
+        let (tx, rx) = mpsc::channel();
+
+        thread::spawn(move || {
+            for hook in hooks {
+                hook();
+            }
+
+            tx.send(()).ok();
+        });
+
+        if rx.recv_timeout(timeout).is_err() {
+            warn!("Shutdown hooks did not complete within {timeout:?}, exiting anyway");
+        }
+    }
+
+    /// Gets the shutdown hooks singleton.
+    fn shutdown_hooks() -> &'static Mutex<Vec<ShutdownHook>> {
+        static SHUTDOWN_HOOKS: OnceLock<Mutex<Vec<ShutdownHook>>> = OnceLock::new();
+        SHUTDOWN_HOOKS.get_or_init(|| Mutex::new(vec![]))
+    }
+
     /// Gets the `Ironvar` manager singleton.
     #[cfg(feature = "ipc")]
     #[must_use]
@@ -267,6 +404,13 @@ impl Ironbar {
             .clone()
     }
 
+    /// Gets clones of all the currently loaded bars.
+    #[cfg(feature = "ipc")]
+    #[must_use]
+    pub fn bars(&self) -> Vec<Bar> {
+        self.bars.borrow().clone()
+    }
+
     /// Gets clones of bars by their name.
     ///
     /// Since the bars contain mostly GTK objects,
@@ -283,9 +427,32 @@ impl Ironbar {
 
     /// Re-reads the config file from disk and replaces the active config.
     /// Note this does *not* reload bars, which must be performed separately.
+    ///
+    /// If the new config fails to load, the active config is left untouched.
+    #[cfg(feature = "ipc")]
+    fn reload_config(&self) -> Result<()> {
+        let mut config = read_config().0.and_then(parse_config)?;
+        set_ironvar_defaults(&mut config);
+
+        self.config.replace(config);
+        Ok(())
+    }
+
+    /// Sets a style colour variable, or removes it if no value is given.
+    #[cfg(feature = "ipc")]
+    fn set_style_variable(&self, name: String, value: Option<String>) -> Result<()> {
+        self.style_variables
+            .borrow_mut()
+            .get_or_insert_with(style::StyleVariables::new)
+            .set(name, value)
+    }
+
+    /// Reloads the main stylesheet from disk, if one is loaded.
     #[cfg(feature = "ipc")]
-    fn reload_config(&self) {
-        self.config.replace(load_config().0);
+    fn reload_css(&self, application: &Application) {
+        if let Some((paths, provider)) = &*self.style.borrow() {
+            style::reload_css(provider, paths, application);
+        }
     }
 }
 
@@ -294,14 +461,17 @@ fn start_ironbar() {
     ironbar.start();
 }
 
-/// Loads the config file from disk.
-fn load_config() -> (Config, PathBuf) {
+/// Reads the config file from disk into `T`,
+/// using the path from `IRONBAR_CONFIG` if set.
+///
+/// Returns the config and its parent directory.
+fn read_config<T: DeserializeOwned>() -> (Result<T>, Result<PathBuf>) {
     let config_path = env::var("IRONBAR_CONFIG");
 
-    let (config, directory) = if let Ok(config_path) = config_path {
+    if let Ok(config_path) = config_path {
         let path = PathBuf::from(config_path);
         (
-            ConfigLoader::load(&path),
+            ConfigLoader::load(&path).map_err(Report::new),
             path.parent()
                 .map(PathBuf::from)
                 .ok_or_else(|| Report::msg("Specified path has no parent")),
@@ -309,13 +479,26 @@ fn load_config() -> (Config, PathBuf) {
     } else {
         let config_loader = ConfigLoader::new("ironbar");
         (
-            config_loader.find_and_load(),
+            config_loader.find_and_load().map_err(Report::new),
             config_loader.config_dir().map_err(Report::new),
         )
-    };
+    }
+}
+
+/// Expands environment variables and validates each module in the raw config,
+/// then deserializes it into the full config.
+fn parse_config(mut config: serde_json::Value) -> Result<Config> {
+    config::expand_env(&mut config);
+    config::validate(&mut config)?;
+    serde_json::from_value(config).map_err(Report::new)
+}
 
-    let mut config = config.unwrap_or_else(|err| {
-        error!("Failed to load config: {}", err);
+/// Loads the config file from disk.
+fn load_config() -> (Config, PathBuf) {
+    let (config, directory) = read_config();
+
+    let mut config = config.and_then(parse_config).unwrap_or_else(|err| {
+        error!("Failed to load config: {err:#}");
         warn!("Falling back to the default config");
         info!("If this is your first time using Ironbar, you should create a config in ~/.config/ironbar/");
         info!("More info here: https://github.com/JakeStanger/ironbar/wiki/configuration-guide");
@@ -330,6 +513,14 @@ fn load_config() -> (Config, PathBuf) {
     debug!("Loaded config file");
 
     #[cfg(feature = "ipc")]
+    set_ironvar_defaults(&mut config);
+
+    (config, directory)
+}
+
+/// Initializes ironvars from the config's `ironvar_defaults`.
+#[cfg(feature = "ipc")]
+fn set_ironvar_defaults(config: &mut Config) {
     if let Some(ironvars) = config.ironvar_defaults.take() {
         let variable_manager = Ironbar::variable_manager();
         for (k, v) in ironvars {
@@ -338,8 +529,30 @@ fn load_config() -> (Config, PathBuf) {
             }
         }
     }
+}
 
-    (config, directory)
+/// Upgrades the config file to the latest version,
+/// printing the result to `stdout`.
+///
+/// The config file on disk is not modified.
+#[cfg(feature = "cli")]
+fn migrate_config() {
+    let mut config = match read_config::<serde_json::Value>().0 {
+        Ok(config) => config,
+        Err(err) => {
+            error!("Failed to load config: {err:?}");
+            exit(ExitCode::Config as i32);
+        }
+    };
+
+    for description in config::migrate(&mut config) {
+        eprintln!("Migrated: {description}");
+    }
+
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&config).expect("to be serializable")
+    );
 }
 
 /// Gets the GDK `Display` instance.
@@ -400,25 +613,25 @@ fn load_output_bars(
         .as_ref()
         .and_then(|config| config.get(monitor_name))
     {
-        Some(MonitorConfig::Single(config)) => {
+        Some(MonitorConfig::Single(bar_config)) => {
             vec![create_bar(
                 app,
                 &monitor,
                 monitor_name.to_string(),
                 output_size,
-                config.clone(),
+                bar_config.clone().inherit_from(&config.bar),
                 ironbar.clone(),
             )?]
         }
-        Some(MonitorConfig::Multiple(configs)) => configs
+        Some(MonitorConfig::Multiple(bar_configs)) => bar_configs
             .iter()
-            .map(|config| {
+            .map(|bar_config| {
                 create_bar(
                     app,
                     &monitor,
                     monitor_name.to_string(),
                     output_size,
-                    config.clone(),
+                    bar_config.clone().inherit_from(&config.bar),
                     ironbar.clone(),
                 )
             })
@@ -469,6 +682,20 @@ where
 ///
 /// Use sparingly, as this risks blocking the UI thread!
 /// Prefer async functions wherever possible.
+///
+/// Calls which block the UI thread for longer than a frame
+/// are logged with the location of the caller.
+#[track_caller]
 pub fn await_sync<F: Future>(f: F) -> F::Output {
-    block_in_place(|| Ironbar::runtime().block_on(f))
+    let caller = Location::caller();
+    let start = Instant::now();
+
+    let output = block_in_place(|| Ironbar::runtime().block_on(f));
+
+    let elapsed = start.elapsed();
+    if elapsed > FRAME_BUDGET && gtk::is_initialized_main_thread() {
+        warn!("UI thread blocked for {elapsed:?} by `await_sync` at {caller}");
+    }
+
+    output
 }
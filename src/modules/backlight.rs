@@ -0,0 +1,415 @@
+use crate::clients::backlight::{self, Device};
+use crate::clients::LazyClient;
+use crate::config::CommonConfig;
+use crate::format;
+use crate::gtk_helpers::IronbarGtkExt;
+use crate::modules::{
+    Module, ModuleInfo, ModuleParts, ModulePopup, ModuleUpdateEvent, PopupButton, WidgetContext,
+};
+use crate::{glib_recv, module_impl, rc_mut, send_async, spawn, try_send};
+use color_eyre::Result;
+use glib::Propagation;
+use gtk::gdk::ScrollDirection;
+use gtk::prelude::*;
+use gtk::{Button, ComboBoxText, Label, Orientation, Scale};
+use serde::Deserialize;
+use tokio::select;
+use tokio::sync::{broadcast, mpsc};
+use tracing::error;
+
+#[derive(Debug, Clone, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct BacklightModule {
+    /// The name of the backlight device to control initially,
+    /// as listed under `/sys/class/backlight`.
+    ///
+    /// If not set, the first device is used.
+    ///
+    /// **Default**: `null`
+    device: Option<String>,
+
+    /// The format string to use for the widget button label.
+    /// For available tokens, see [below](#formatting-tokens).
+    ///
+    /// **Default**: `{percentage}%`
+    #[serde(default = "default_format")]
+    format: String,
+
+    /// The percentage to change the brightness by
+    /// when scrolling over the widget.
+    ///
+    /// **Default**: `5`
+    #[serde(default = "default_step")]
+    step: f64,
+
+    /// The range of brightness percentages to allow.
+    #[serde(flatten)]
+    range: BrightnessRange,
+
+    /// See [common options](module-level-options#common-options).
+    #[serde(flatten)]
+    pub common: Option<CommonConfig>,
+}
+
+fn default_format() -> String {
+    String::from("{percentage}%")
+}
+
+const fn default_step() -> f64 {
+    5.0
+}
+
+const fn default_min() -> f64 {
+    1.0
+}
+
+const fn default_max() -> f64 {
+    100.0
+}
+
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct BrightnessRange {
+    /// The minimum brightness percentage to allow.
+    /// Setting this to zero may turn the screen off entirely.
+    ///
+    /// **Default**: `1`
+    #[cfg_attr(feature = "schema", schemars(default = "default_min"))]
+    min: f64,
+
+    /// The maximum brightness percentage to allow.
+    /// Must not be lower than `min`.
+    ///
+    /// **Default**: `100`
+    #[cfg_attr(feature = "schema", schemars(default = "default_max"))]
+    max: f64,
+}
+
+impl<'de> Deserialize<'de> for BrightnessRange {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        struct Raw {
+            #[serde(default = "default_min")]
+            min: f64,
+            #[serde(default = "default_max")]
+            max: f64,
+        }
+
+        let Raw { min, max } = Raw::deserialize(deserializer)?;
+
+        // checked here, as `f64::clamp` panics on an invalid range
+        if !min.is_finite() || !max.is_finite() {
+            return Err(serde::de::Error::custom("`min` and `max` must be numbers"));
+        }
+
+        if min > max {
+            return Err(serde::de::Error::custom(format!(
+                "`min` ({min}) must not be greater than `max` ({max})"
+            )));
+        }
+
+        Ok(Self { min, max })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum BacklightEvent {
+    /// The names of all available devices.
+    Devices(Vec<String>),
+    /// The current state of the device being controlled.
+    Active(Device),
+}
+
+#[derive(Debug, Clone)]
+pub enum Update {
+    /// Changes the device being controlled.
+    Select(String),
+    /// Sets the brightness percentage.
+    Set(f64),
+    /// Adjusts the brightness percentage by the given amount.
+    Step(f64),
+}
+
+impl Module<Button> for BacklightModule {
+    type SendMessage = BacklightEvent;
+    type ReceiveMessage = Update;
+
+    const REPORTS_READY: bool = true;
+
+    module_impl!("backlight");
+
+    fn spawn_controller(
+        &self,
+        _info: &ModuleInfo,
+        context: &WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
+        mut rx: mpsc::Receiver<Self::ReceiveMessage>,
+    ) -> Result<()> {
+        let client = context.client::<LazyClient<backlight::Client>>();
+        let tx = context.tx.clone();
+
+        let device = self.device.clone();
+        let BrightnessRange { min, max } = self.range;
+
+        spawn(async move {
+            let Some(client) = client.ready().await else {
+                return;
+            };
+
+            let mut client_rx = client.subscribe();
+
+            let devices = client.devices();
+            let mut active = device
+                .filter(|name| devices.iter().any(|device| &device.name == name))
+                .or_else(|| devices.first().map(|device| device.name.clone()))
+                .unwrap_or_default();
+
+            let names = devices.iter().map(|device| device.name.clone()).collect();
+            send_async!(
+                tx,
+                ModuleUpdateEvent::Update(BacklightEvent::Devices(names))
+            );
+
+            if let Some(device) = devices.into_iter().find(|device| device.name == active) {
+                send_async!(
+                    tx,
+                    ModuleUpdateEvent::Update(BacklightEvent::Active(device))
+                );
+            }
+
+            send_async!(tx, ModuleUpdateEvent::Ready);
+
+            // the last percentage written to the active device.
+            // devices are only polled periodically,
+            // so this lets rapid steps build on each other
+            // until the next poll reports the new value.
+            let mut requested = None;
+
+            loop {
+                select! {
+                    Ok(device) = client_rx.recv() => {
+                        if device.name == active {
+                            requested = None;
+                            send_async!(tx, ModuleUpdateEvent::Update(BacklightEvent::Active(device)));
+                        }
+                    }
+                    Some(update) = rx.recv() => {
+                        let device = client.devices().into_iter().find(|device| match &update {
+                            Update::Select(name) => &device.name == name,
+                            _ => device.name == active,
+                        });
+
+                        let Some(device) = device else {
+                            continue;
+                        };
+
+                        let percentage = match update {
+                            Update::Select(name) => {
+                                active = name;
+                                requested = None;
+                                send_async!(tx, ModuleUpdateEvent::Update(BacklightEvent::Active(device)));
+                                continue;
+                            }
+                            Update::Set(percentage) => percentage,
+                            Update::Step(step) => {
+                                requested.unwrap_or_else(|| device.percentage()) + step
+                            }
+                        };
+
+                        let percentage = percentage.clamp(min, max);
+                        let brightness = (percentage / 100.0 * f64::from(device.max_brightness)).round() as u32;
+
+                        match client.set_brightness(&device.name, brightness).await {
+                            Ok(()) => requested = Some(percentage),
+                            Err(err) => error!("Failed to set brightness: {err:?}"),
+                        }
+                    }
+                    else => break,
+                }
+            }
+        });
+
+        Ok(())
+    }
+
+    fn into_widget(
+        self,
+        context: WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
+        info: &ModuleInfo,
+    ) -> Result<ModuleParts<Button>> {
+        let button = Button::new();
+        let label = Label::new(None);
+        button.add(&label);
+
+        {
+            let tx = context.tx.clone();
+
+            button.connect_clicked(move |button| {
+                try_send!(tx, ModuleUpdateEvent::TogglePopup(button.popup_id()));
+            });
+        }
+
+        {
+            let tx = context.controller_tx.clone();
+            let step = self.step;
+
+            button.connect_scroll_event(move |_, event| {
+                let step = match event.direction() {
+                    ScrollDirection::Up => step,
+                    ScrollDirection::Down => -step,
+                    ScrollDirection::Smooth => {
+                        if event.scroll_deltas().unwrap_or_default().1 > 0.0 {
+                            -step
+                        } else {
+                            step
+                        }
+                    }
+                    _ => return Propagation::Proceed,
+                };
+
+                try_send!(tx, Update::Step(step));
+                Propagation::Proceed
+            });
+        }
+
+        {
+            let rx = context.subscribe();
+            let format = self.format.clone();
+
+            glib_recv!(rx, event => {
+                if let BacklightEvent::Active(device) = event {
+                    let text = format::replace_tokens(&format, |token| match token {
+                        "percentage" => Some((device.percentage().round() as u32).to_string()),
+                        "name" => Some(device.name.clone()),
+                        _ => None,
+                    });
+
+                    label.set_label(&text);
+                }
+            });
+        }
+
+        let popup = self
+            .into_popup(
+                context.controller_tx.clone(),
+                context.subscribe(),
+                context,
+                info,
+            )
+            .into_popup_parts(vec![&button]);
+
+        Ok(ModuleParts::new(button, popup))
+    }
+
+    fn into_popup(
+        self,
+        tx: mpsc::Sender<Self::ReceiveMessage>,
+        rx: broadcast::Receiver<Self::SendMessage>,
+        _context: WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
+        _info: &ModuleInfo,
+    ) -> Option<gtk::Box> {
+        let container = gtk::Box::new(Orientation::Vertical, 5);
+
+        let selector = ComboBoxText::new();
+        selector.add_class("device-selector");
+        container.add(&selector);
+
+        let slider = Scale::builder()
+            .orientation(Orientation::Vertical)
+            .height_request(100)
+            .inverted(true)
+            .build();
+
+        slider.add_class("slider");
+        slider.set_range(self.range.min, self.range.max);
+        container.add(&slider);
+
+        container.show_all();
+
+        // only write the value once the slider is released,
+        // and avoid updates from the device moving the slider mid-drag.
+        let dragging = rc_mut!(false);
+
+        {
+            let dragging = dragging.clone();
+
+            slider.connect_button_press_event(move |scale, _| {
+                *dragging.borrow_mut() = true;
+                scale.add_class("dragging");
+
+                Propagation::Proceed
+            });
+        }
+
+        {
+            let tx = tx.clone();
+            let dragging = dragging.clone();
+
+            slider.connect_button_release_event(move |scale, _| {
+                *dragging.borrow_mut() = false;
+                scale.style_context().remove_class("dragging");
+
+                // GTK will send values outside min/max range
+                let val = scale.value().clamp(self.range.min, self.range.max);
+                try_send!(tx, Update::Set(val));
+
+                Propagation::Proceed
+            });
+        }
+
+        {
+            let selector = selector.clone();
+
+            glib_recv!(rx, event => {
+                match event {
+                    BacklightEvent::Devices(names) => {
+                        selector.remove_all();
+                        for name in &names {
+                            selector.append(Some(name), name);
+                        }
+
+                        selector.set_visible(names.len() > 1);
+                    }
+                    BacklightEvent::Active(device) => {
+                        if selector.active_id().as_deref() != Some(device.name.as_str()) {
+                            selector.set_active_id(Some(&device.name));
+                        }
+
+                        if !*dragging.borrow() {
+                            slider.set_value(device.percentage());
+                        }
+                    }
+                }
+            });
+        }
+
+        selector.connect_changed(move |selector| {
+            if let Some(name) = selector.active_id() {
+                try_send!(tx, Update::Select(name.into()));
+            }
+        });
+
+        Some(container)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_range_defaults() {
+        let range = BrightnessRange::deserialize(json!({})).expect("to be valid");
+        assert_eq!((range.min, range.max), (1.0, 100.0));
+    }
+
+    #[test]
+    fn test_range_min_above_max() {
+        let err = BrightnessRange::deserialize(json!({ "min": 80, "max": 20 }))
+            .expect_err("to be invalid");
+        assert!(err.to_string().contains("must not be greater than"));
+    }
+}
@@ -10,9 +10,11 @@ use mlua::{Error, Function, LightUserData};
 use notify::event::ModifyKind;
 use notify::{recommended_watcher, Event, EventKind, RecursiveMode, Watcher};
 use serde::Deserialize;
+use std::cell::Cell;
 use std::fs;
 use std::path::PathBuf;
-use std::time::Duration;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
 use tokio::sync::mpsc::Receiver;
 use tokio::time::sleep;
 use tracing::{debug, error};
@@ -34,6 +36,23 @@ pub struct CairoModule {
     #[serde(default = "default_frequency")]
     frequency: u64,
 
+    /// The maximum number of times per second to redraw.
+    /// This applies to all redraws, including those caused by script reloads.
+    /// Leave unset for no limit.
+    ///
+    /// **Default**: `null`
+    #[serde(default)]
+    max_fps: Option<u32>,
+
+    /// Whether to only redraw when the script changes,
+    /// rather than every `frequency` milliseconds.
+    ///
+    /// Use this for static content to keep CPU usage near zero.
+    ///
+    /// **Default**: `false`
+    #[serde(default = "crate::config::default_false")]
+    draw_on_change_only: bool,
+
     /// The canvas width in pixels.
     ///
     /// **Default**: `42`
@@ -59,6 +78,62 @@ const fn default_frequency() -> u64 {
     200
 }
 
+/// Limits how often the drawing area is repainted.
+///
+/// Requests made within the minimum interval of the last draw
+/// are merged into a single deferred draw.
+#[derive(Debug, Clone)]
+struct DrawThrottle {
+    area: DrawingArea,
+    min_interval: Duration,
+    last_draw: Rc<Cell<Option<Instant>>>,
+    pending: Rc<Cell<bool>>,
+}
+
+impl DrawThrottle {
+    fn new(area: DrawingArea, max_fps: Option<u32>) -> Self {
+        let min_interval =
+            max_fps.map_or(Duration::ZERO, |fps| Duration::from_secs(1) / fps.max(1));
+
+        Self {
+            area,
+            min_interval,
+            last_draw: Rc::new(Cell::new(None)),
+            pending: Rc::new(Cell::new(false)),
+        }
+    }
+
+    /// Queues a draw, or schedules one for later
+    /// if the area was drawn too recently.
+    fn request(&self) {
+        if self.pending.get() {
+            return;
+        }
+
+        let elapsed = self
+            .last_draw
+            .get()
+            .map_or(self.min_interval, |last_draw| last_draw.elapsed());
+
+        if elapsed >= self.min_interval {
+            self.draw();
+        } else {
+            self.pending.set(true);
+
+            let throttle = self.clone();
+            glib::timeout_add_local_once(self.min_interval - elapsed, move || {
+                throttle.pending.set(false);
+                throttle.draw();
+            });
+        }
+    }
+
+    fn draw(&self) {
+        self.last_draw.set(Some(Instant::now()));
+        self.area.queue_draw();
+    }
+}
+
 impl Module<gtk::Box> for CairoModule {
     type SendMessage = ();
     type ReceiveMessage = ();
@@ -180,12 +255,19 @@ impl Module<gtk::Box> for CairoModule {
         area.set_size_request(self.width as i32, self.height as i32);
         container.add(&area);
 
-        glib::spawn_future_local(async move {
-            loop {
-                area.queue_draw();
-                glib::timeout_future(Duration::from_millis(self.frequency)).await;
-            }
-        });
+        let throttle = DrawThrottle::new(area, self.max_fps);
+
+        if !self.draw_on_change_only {
+            let throttle = throttle.clone();
+            let frequency = self.frequency;
+
+            glib::spawn_future_local(async move {
+                loop {
+                    throttle.request();
+                    glib::timeout_future(Duration::from_millis(frequency)).await;
+                }
+            });
+        }
 
         glib_recv!(context.subscribe(), _ev => {
             let res = fs::read_to_string(&self.path)
@@ -194,7 +276,7 @@ impl Module<gtk::Box> for CairoModule {
             match res {
                 Ok(script) => {
                     match lua.load(&script).exec() {
-                        Ok(()) => {},
+                        Ok(()) => throttle.request(),
                         Err(Error::SyntaxError { message, ..}) => {
                             let message = message.split_once("]:").expect("to exist").1;
                             error!("[lua syntax error] {}:{message}", self.path.display());
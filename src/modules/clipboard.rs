@@ -1,22 +1,30 @@
 use crate::clients::clipboard::{self, ClipboardEvent};
 use crate::clients::wayland::{ClipboardItem, ClipboardValue};
 use crate::config::{CommonConfig, TruncateMode};
-use crate::gtk_helpers::IronbarLabelExt;
+use crate::gtk_helpers::{IronbarGtkExt, IronbarLabelExt};
 use crate::image::new_icon_button;
 use crate::modules::{
     Module, ModuleInfo, ModuleParts, ModulePopup, ModuleUpdateEvent, PopupButton, WidgetContext,
 };
-use crate::{glib_recv, module_impl, spawn, try_send};
+use crate::{glib_recv, module_impl, rc_mut, spawn, try_send};
 use glib::Propagation;
+use gtk::gdk::{DragAction, ModifierType};
 use gtk::gdk_pixbuf::Pixbuf;
 use gtk::gio::{Cancellable, MemoryInputStream};
 use gtk::prelude::*;
-use gtk::{Button, EventBox, Image, Label, Orientation, RadioButton, Widget};
+use gtk::{
+    Button, DestDefaults, EventBox, Image, Label, Orientation, RadioButton, SearchEntry,
+    TargetEntry, TargetFlags, Widget,
+};
 use serde::Deserialize;
 use std::collections::HashMap;
 use tokio::sync::{broadcast, mpsc};
 use tracing::{debug, error};
 
+/// Drag-and-drop target name for popup entries.
+/// The payload is the item ID.
+const DRAG_TARGET: &str = "ironbar/clipboard-item";
+
 #[derive(Debug, Deserialize, Clone)]
 #[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct ClipboardModule {
@@ -41,6 +49,14 @@ pub struct ClipboardModule {
     #[serde(default = "default_max_items")]
     max_items: usize,
 
+    /// Whether to show a search box at the top of the popup.
+    /// Typing filters the text items using fuzzy matching.
+    /// Image items are hidden while searching.
+    ///
+    /// **Default**: `true`
+    #[serde(default = "crate::config::default_true")]
+    show_search: bool,
+
     // -- Common --
     /// See [truncate options](module-level-options#truncate-mode).
     ///
@@ -70,12 +86,15 @@ pub enum ControllerEvent {
     Remove(usize),
     Activate(usize),
     Deactivate,
+    /// Item was moved to the position of the target item.
+    Move(usize, usize),
 }
 
 #[derive(Debug, Clone)]
 pub enum UIEvent {
     Copy(usize),
     Remove(usize),
+    Move(usize, usize),
 }
 
 impl Module<Button> for ClipboardModule {
@@ -116,6 +135,12 @@ impl Module<Button> for ClipboardModule {
                     ClipboardEvent::Activate(id) => {
                         try_send!(tx, ModuleUpdateEvent::Update(ControllerEvent::Activate(id)));
                     }
+                    ClipboardEvent::Move(id, target_id) => {
+                        try_send!(
+                            tx,
+                            ModuleUpdateEvent::Update(ControllerEvent::Move(id, target_id))
+                        );
+                    }
                 }
             }
 
@@ -130,6 +155,7 @@ impl Module<Button> for ClipboardModule {
                 match event {
                     UIEvent::Copy(id) => client.copy(id),
                     UIEvent::Remove(id) => client.remove(id),
+                    UIEvent::Move(id, target_id) => client.move_item(id, target_id),
                 }
             }
         });
@@ -170,16 +196,40 @@ impl Module<Button> for ClipboardModule {
     {
         let container = gtk::Box::new(Orientation::Vertical, 10);
 
+        let search = SearchEntry::new();
+        search.add_class("search");
+        container.add(&search);
+
         let entries = gtk::Box::new(Orientation::Vertical, 5);
         container.add(&entries);
 
         let hidden_option = RadioButton::new();
         entries.add(&hidden_option);
 
-        let mut items = HashMap::new();
+        // row, button and text value (for text items) for each item ID
+        let items = rc_mut!(HashMap::<usize, (gtk::Box, RadioButton, Option<String>)>::new());
+
+        {
+            let items = items.clone();
+
+            search.connect_search_changed(move |search| {
+                let query = search.text();
+                for (row, _, text) in items.borrow().values() {
+                    row.set_visible(matches_query(&query, text.as_deref()));
+                }
+            });
+        }
+
+        // reset the filter when the popup closes
+        {
+            let search = search.clone();
+            container.connect_unmap(move |_| search.set_text(""));
+        }
 
         {
             let hidden_option = hidden_option.clone();
+            let search = search.clone();
+
             glib_recv!(rx, event => {
                 match event {
                     ControllerEvent::Add(id, item) => {
@@ -241,6 +291,37 @@ impl Module<Button> for ClipboardModule {
                         button_wrapper.set_widget_name(&format!("copy-{id}"));
                         button_wrapper.set_above_child(true);
 
+                        // allow dragging entries onto each other to reorder them.
+                        // the drag source is set first so it sees presses before the copy handler.
+                        {
+                            let targets = [TargetEntry::new(DRAG_TARGET, TargetFlags::SAME_APP, 0)];
+
+                            button_wrapper.drag_source_set(
+                                ModifierType::BUTTON1_MASK,
+                                &targets,
+                                DragAction::MOVE,
+                            );
+                            button_wrapper.connect_drag_data_get(move |_, _, data, _, _| {
+                                data.set_text(&id.to_string());
+                            });
+
+                            row.drag_dest_set(DestDefaults::ALL, &targets, DragAction::MOVE);
+
+                            let tx = tx.clone();
+                            row.connect_drag_data_received(move |_, _, _, _, data, _, _| {
+                                // dropping onto itself is a no-op
+                                let dragged_id = data
+                                    .text()
+                                    .and_then(|id| id.parse::<usize>().ok())
+                                    .filter(|&dragged_id| dragged_id != id);
+
+                                if let Some(dragged_id) = dragged_id {
+                                    debug!("Moving item with id {dragged_id} to position of {id}");
+                                    try_send!(tx, UIEvent::Move(dragged_id, id));
+                                }
+                            });
+                        }
+
                         {
                             let tx = tx.clone();
                             button_wrapper.connect_button_press_event(
@@ -286,12 +367,19 @@ impl Module<Button> for ClipboardModule {
                         entries.reorder_child(&row, 0);
                         row.show_all();
 
-                        items.insert(id, (row, button));
+                        let text = match item.value.as_ref() {
+                            ClipboardValue::Text(value) => Some(value.clone()),
+                            _ => None,
+                        };
+
+                        row.set_visible(matches_query(&search.text(), text.as_deref()));
+
+                        items.borrow_mut().insert(id, (row, button, text));
                     }
                     ControllerEvent::Remove(id) => {
                         debug!("Removing option with ID {id}");
-                        let row = items.remove(&id);
-                        if let Some((row, button)) = row {
+                        let row = items.borrow_mut().remove(&id);
+                        if let Some((row, button, _)) = row {
                             if button.is_active() {
                                 hidden_option.set_active(true);
                             }
@@ -303,8 +391,7 @@ impl Module<Button> for ClipboardModule {
                         debug!("Activating option with ID {id}");
 
                         hidden_option.set_active(false);
-                        let row = items.get(&id);
-                        if let Some((_, button)) = row {
+                        if let Some((_, button, _)) = items.borrow().get(&id) {
                             button.set_active(true);
                         }
                     }
@@ -312,12 +399,24 @@ impl Module<Button> for ClipboardModule {
                         debug!("Deactivating current option");
                         hidden_option.set_active(true);
                     }
+                    ControllerEvent::Move(id, target_id) => {
+                        debug!("Moving option with ID {id} to position of {target_id}");
+
+                        let items = items.borrow();
+                        if let (Some((row, _, _)), Some((target_row, _, _))) =
+                            (items.get(&id), items.get(&target_id))
+                        {
+                            let position = entries.child_position(target_row);
+                            entries.reorder_child(row, position);
+                        }
+                    }
                 }
             });
         }
 
         container.show_all();
         hidden_option.hide();
+        search.set_visible(self.show_search);
 
         Some(container)
     }
@@ -336,3 +435,41 @@ where
         .split_once('-')
         .and_then(|(_, id)| id.parse().ok())
 }
+
+/// Checks whether an item matches the search query.
+///
+/// Text matches if it contains every character of the query in order,
+/// ignoring case. Non-text items only match an empty query.
+fn matches_query(query: &str, text: Option<&str>) -> bool {
+    if query.is_empty() {
+        return true;
+    }
+
+    let Some(text) = text else {
+        return false;
+    };
+
+    let mut chars = text.chars().flat_map(char::to_lowercase);
+    query
+        .chars()
+        .flat_map(char::to_lowercase)
+        .all(|query_char| chars.any(|char| char == query_char))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches_query_fuzzy() {
+        assert!(matches_query("hlo", Some("Hello world")));
+        assert!(matches_query("HW", Some("hello world")));
+        assert!(!matches_query("olh", Some("hello")));
+    }
+
+    #[test]
+    fn test_matches_query_non_text() {
+        assert!(matches_query("", None));
+        assert!(!matches_query("a", None));
+    }
+}
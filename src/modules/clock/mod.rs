@@ -0,0 +1,356 @@
+mod timer;
+
+use std::cell::{Cell, RefCell};
+use std::env;
+use std::rc::Rc;
+
+use chrono::{DateTime, Datelike, Local, Locale, NaiveDate, Utc};
+use chrono_tz::Tz;
+use color_eyre::Result;
+use gtk::prelude::*;
+use gtk::{Align, Button, Calendar, Label, Orientation};
+use serde::Deserialize;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::{broadcast, mpsc};
+use tracing::warn;
+
+use crate::clients::clock::{self, Resolution};
+use crate::config::{CommonConfig, ModuleJustification, ModuleOrientation};
+use crate::gtk_helpers::IronbarGtkExt;
+use crate::modules::{
+    Module, ModuleInfo, ModuleParts, ModulePopup, ModuleUpdateEvent, PopupButton, WidgetContext,
+};
+use crate::script::Script;
+use crate::{glib_recv, module_impl, send_async, spawn, try_send};
+
+#[derive(Debug, Deserialize, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ClockModule {
+    /// The format string to use for the date/time shown on the bar.
+    /// Pango markup is supported.
+    ///
+    /// Detail on available tokens can be found here:
+    /// <https://docs.rs/chrono/latest/chrono/format/strftime/index.html>
+    ///
+    /// The `{utc}` token can also be used to show the current UTC time.
+    /// This includes seconds if the rest of the format does.
+    ///
+    /// **Default**: `%d/%m/%Y %H:%M`
+    #[serde(default = "default_format")]
+    format: String,
+
+    /// The timezone to show the date/time on the bar in,
+    /// as an IANA name such as `Europe/London`.
+    /// Invalid names fall back to the local timezone.
+    ///
+    /// This does not affect the popup.
+    ///
+    /// **Default**: `null`
+    #[serde(default)]
+    timezone: Option<String>,
+
+    /// The format string to use for the date/time shown in the popup header.
+    /// Pango markup is supported.
+    ///
+    /// Detail on available tokens can be found here:
+    /// <https://docs.rs/chrono/latest/chrono/format/strftime/index.html>
+    ///
+    /// **Default**: `%H:%M:%S`
+    #[serde(default = "default_popup_format")]
+    format_popup: String,
+
+    /// The locale to use when formatting dates.
+    ///
+    /// Note this will not control the calendar -
+    /// for that you must set `LC_TIME`.
+    ///
+    /// **Valid options**: See [here](https://docs.rs/pure-rust-locales/0.8.1/pure_rust_locales/enum.Locale.html#variants)
+    /// <br>
+    /// **Default**: `$LC_TIME` or `$LANG` or `'POSIX'`
+    #[serde(default = "default_locale")]
+    locale: String,
+
+    /// The orientation to display the widget contents.
+    /// Setting to vertical will rotate text 90 degrees.
+    ///
+    /// **Valid options**: `horizontal`, `vertical`
+    /// <br>
+    /// **Default**: `horizontal`
+    #[serde(default)]
+    orientation: ModuleOrientation,
+
+    /// The justification (alignment) of the date/time shown on the bar.
+    ///
+    /// **Valid options**: `left`, `right`, `center`, `fill`
+    /// <br>
+    /// **Default**: `left`
+    #[serde(default)]
+    justify: ModuleJustification,
+
+    /// Whether to show a countdown timer and stopwatch in the popup.
+    ///
+    /// **Default**: `false`
+    #[serde(default)]
+    show_timer: bool,
+
+    /// A command to run when a day is clicked in the popup calendar.
+    /// The `{date}` token is replaced with the clicked date, in `YYYY-MM-DD` format.
+    ///
+    /// **Default**: `null`
+    #[serde(default)]
+    on_day_click: Option<String>,
+
+    /// Whether the popup calendar should stay on the last viewed month
+    /// when the popup is reopened, rather than resetting to the current month.
+    ///
+    /// **Default**: `false`
+    #[serde(default = "crate::config::default_false")]
+    calendar_sticky: bool,
+
+    /// See [common options](module-level-options#common-options).
+    #[serde(flatten)]
+    pub common: Option<CommonConfig>,
+}
+
+impl Default for ClockModule {
+    fn default() -> Self {
+        ClockModule {
+            format: default_format(),
+            format_popup: default_popup_format(),
+            timezone: None,
+            locale: default_locale(),
+            orientation: ModuleOrientation::Horizontal,
+            common: Some(CommonConfig::default()),
+            justify: ModuleJustification::Left,
+            show_timer: false,
+            on_day_click: None,
+            calendar_sticky: false,
+        }
+    }
+}
+
+fn default_format() -> String {
+    String::from("%d/%m/%Y %H:%M")
+}
+
+fn default_popup_format() -> String {
+    String::from("%H:%M:%S")
+}
+
+fn default_locale() -> String {
+    env::var("LC_TIME")
+        .or_else(|_| env::var("LANG"))
+        .map_or_else(|_| "POSIX".to_string(), strip_tail)
+}
+
+fn strip_tail(string: String) -> String {
+    string
+        .split_once('.')
+        .map(|(head, _)| head.to_string())
+        .unwrap_or(string)
+}
+
+impl Module<Button> for ClockModule {
+    type SendMessage = DateTime<Local>;
+    type ReceiveMessage = ();
+
+    module_impl!("clock");
+
+    fn spawn_controller(
+        &self,
+        _info: &ModuleInfo,
+        context: &WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
+        _rx: mpsc::Receiver<Self::ReceiveMessage>,
+    ) -> Result<()> {
+        let tx = context.tx.clone();
+        let resolution = Resolution::from_format(&self.format);
+
+        // ticks are shared with every other clock at the same resolution
+        let mut rx = context.client::<clock::Client>().subscribe(resolution);
+
+        spawn(async move {
+            send_async!(tx, ModuleUpdateEvent::Update(Local::now()));
+
+            loop {
+                match rx.recv().await {
+                    Ok(date) => send_async!(tx, ModuleUpdateEvent::Update(date)),
+                    Err(RecvError::Lagged(_)) => {}
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        });
+
+        Ok(())
+    }
+
+    fn into_widget(
+        self,
+        context: WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
+        info: &ModuleInfo,
+    ) -> Result<ModuleParts<Button>> {
+        let button = Button::new();
+        let label = Label::builder()
+            .angle(self.orientation.to_angle())
+            .use_markup(true)
+            .justify(self.justify.into())
+            .build();
+        button.add(&label);
+
+        let tx = context.tx.clone();
+        button.connect_clicked(move |button| {
+            try_send!(tx, ModuleUpdateEvent::TogglePopup(button.popup_id()));
+        });
+
+        let format = self.format.clone();
+        let locale = Locale::try_from(self.locale.as_str()).unwrap_or(Locale::POSIX);
+
+        let timezone = self.timezone.as_deref().and_then(|timezone| {
+            timezone
+                .parse::<Tz>()
+                .inspect_err(|err| warn!("Invalid timezone, using local time instead: {err}"))
+                .ok()
+        });
+
+        let utc_format = match Resolution::from_format(&format) {
+            Resolution::Second => "%H:%M:%S",
+            Resolution::Minute => "%H:%M",
+        };
+
+        let rx = context.subscribe();
+        glib_recv!(rx, date => {
+            let format = if format.contains("{utc}") {
+                let utc = date.with_timezone(&Utc).format(utc_format).to_string();
+                format.replace("{utc}", &utc)
+            } else {
+                format.clone()
+            };
+
+            let date_string = match timezone {
+                Some(timezone) => {
+                    format!("{}", date.with_timezone(&timezone).format_localized(&format, locale))
+                }
+                None => format!("{}", date.format_localized(&format, locale)),
+            };
+
+            label.set_label(&date_string);
+        });
+
+        let popup = self
+            .into_popup(
+                context.controller_tx.clone(),
+                context.subscribe(),
+                context,
+                info,
+            )
+            .into_popup_parts(vec![&button]);
+
+        Ok(ModuleParts::new(button, popup))
+    }
+
+    fn into_popup(
+        self,
+        _tx: mpsc::Sender<Self::ReceiveMessage>,
+        _rx: broadcast::Receiver<Self::SendMessage>,
+        _context: WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
+        _info: &ModuleInfo,
+    ) -> Option<gtk::Box> {
+        let container = gtk::Box::new(Orientation::Vertical, 0);
+
+        let clock = Label::builder()
+            .halign(Align::Center)
+            .use_markup(true)
+            .build();
+        clock.add_class("calendar-clock");
+
+        container.add(&clock);
+
+        let calendar = Calendar::new();
+        calendar.set_show_heading(true);
+        calendar.add_class("calendar");
+
+        // set while the selection is changed programmatically,
+        // to avoid treating it as a click.
+        let resetting = Rc::new(Cell::new(false));
+
+        if !self.calendar_sticky {
+            let calendar = calendar.clone();
+            let resetting = resetting.clone();
+
+            container.connect_map(move |_| {
+                let today = Local::now();
+
+                resetting.set(true);
+                calendar.select_month(today.month0(), today.year() as u32);
+                calendar.select_day(today.day());
+                resetting.set(false);
+            });
+        }
+
+        if let Some(cmd) = self.on_day_click {
+            // changing month also selects a day,
+            // so only selections within the displayed month count as clicks.
+            let (year, month, _) = calendar.date();
+            let displayed_month = Rc::new(Cell::new((year, month)));
+
+            calendar.connect_day_selected(move |calendar| {
+                let (year, month, day) = calendar.date();
+
+                if displayed_month.replace((year, month)) != (year, month) || resetting.get() {
+                    return;
+                }
+
+                let Some(date) = NaiveDate::from_ymd_opt(year as i32, month + 1, day) else {
+                    return;
+                };
+
+                let cmd = cmd.replace("{date}", &date.format("%Y-%m-%d").to_string());
+                Script::from(cmd.as_str()).run_as_oneshot(None);
+            });
+        }
+        container.add(&calendar);
+
+        if self.show_timer {
+            container.add(&timer::create_timer());
+        }
+
+        let format = self.format_popup;
+        let locale = Locale::try_from(self.locale.as_str()).unwrap_or(Locale::POSIX);
+        let resolution = Resolution::from_format(&format);
+
+        // The popup format may need more frequent updates than the bar,
+        // so it ticks on its own, but only while open.
+        let task = Rc::new(RefCell::new(None::<glib::JoinHandle<()>>));
+
+        {
+            let task = task.clone();
+            container.connect_map(move |_| {
+                let clock = clock.clone();
+                let format = format.clone();
+
+                let handle = glib::spawn_future_local(async move {
+                    loop {
+                        let date = Local::now();
+                        let date_string = format!("{}", date.format_localized(&format, locale));
+                        clock.set_label(&date_string);
+
+                        glib::timeout_future(resolution.until_next_tick(Local::now())).await;
+                    }
+                });
+
+                if let Some(old) = task.replace(Some(handle)) {
+                    old.abort();
+                }
+            });
+        }
+
+        container.connect_unmap(move |_| {
+            if let Some(handle) = task.take() {
+                handle.abort();
+            }
+        });
+
+        container.show_all();
+
+        Some(container)
+    }
+}
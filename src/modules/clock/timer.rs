@@ -0,0 +1,206 @@
+use crate::gtk_helpers::IronbarGtkExt;
+use crate::rc_mut;
+use glib::{ControlFlow, SourceId};
+use gtk::prelude::*;
+use gtk::{Adjustment, Button, Label, Orientation, SpinButton};
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+/// How often the timer label is refreshed while running.
+const TICK_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Default)]
+struct TimerState {
+    /// Time accumulated before the current run.
+    elapsed: Duration,
+    /// When the current run started, if running.
+    started: Option<Instant>,
+    /// Countdown length, or `None` for a stopwatch.
+    countdown: Option<Duration>,
+    tick: Option<SourceId>,
+}
+
+impl TimerState {
+    fn elapsed(&self) -> Duration {
+        self.elapsed + self.started.map(|s| s.elapsed()).unwrap_or_default()
+    }
+
+    /// Gets the time to display,
+    /// which counts down for a countdown and up for a stopwatch.
+    fn display_time(&self) -> Duration {
+        match self.countdown {
+            Some(countdown) => countdown.saturating_sub(self.elapsed()),
+            None => self.elapsed(),
+        }
+    }
+
+    fn is_finished(&self) -> bool {
+        self.countdown
+            .is_some_and(|countdown| self.elapsed() >= countdown)
+    }
+
+    fn stop(&mut self) {
+        self.elapsed = self.elapsed();
+        self.started = None;
+
+        if let Some(tick) = self.tick.take() {
+            tick.remove();
+        }
+    }
+}
+
+/// Creates the countdown timer and stopwatch shown in the popup.
+///
+/// Setting a number of minutes runs a countdown,
+/// otherwise the timer runs as a stopwatch.
+pub fn create_timer() -> gtk::Box {
+    let container = gtk::Box::new(Orientation::Vertical, 0);
+    container.add_class("timer");
+
+    let label = Label::new(Some(&format_duration(Duration::ZERO)));
+    label.add_class("timer-label");
+    container.add(&label);
+
+    let controls = gtk::Box::new(Orientation::Horizontal, 0);
+    controls.add_class("timer-controls");
+
+    let minutes = SpinButton::new(
+        Some(&Adjustment::new(0.0, 0.0, 999.0, 1.0, 5.0, 0.0)),
+        1.0,
+        0,
+    );
+    minutes.set_tooltip_text(Some("Countdown minutes (0 for stopwatch)"));
+    minutes.add_class("timer-minutes");
+    controls.add(&minutes);
+
+    let start = Button::with_label("Start");
+    start.add_class("timer-start");
+    controls.add(&start);
+
+    let reset = Button::with_label("Reset");
+    reset.add_class("timer-reset");
+    controls.add(&reset);
+
+    container.add(&controls);
+
+    let state = rc_mut!(TimerState::default());
+
+    {
+        let state = state.clone();
+        let label = label.clone();
+        let minutes = minutes.clone();
+
+        start.connect_clicked(move |start| {
+            let mut timer = state.borrow_mut();
+
+            if timer.started.is_some() {
+                timer.stop();
+                start.set_label("Start");
+                return;
+            }
+
+            // a new run after a reset or completion picks up the configured length
+            if timer.elapsed.is_zero() || timer.is_finished() {
+                let mins = u64::try_from(minutes.value_as_int()).unwrap_or_default();
+                timer.countdown = (mins > 0).then(|| Duration::from_secs(mins * 60));
+                timer.elapsed = Duration::ZERO;
+            }
+
+            label.remove_class("done");
+            timer.started = Some(Instant::now());
+            timer.tick = Some(start_tick(&state, &label, start));
+            start.set_label("Stop");
+        });
+    }
+
+    {
+        let label = label.clone();
+
+        reset.connect_clicked(move |_| {
+            let mut timer = state.borrow_mut();
+            timer.stop();
+            *timer = TimerState::default();
+
+            start.set_label("Start");
+            label.remove_class("done");
+            label.set_label(&format_duration(Duration::ZERO));
+        });
+    }
+
+    container
+}
+
+/// Starts refreshing the label while the timer runs,
+/// stopping the timer once a countdown completes.
+fn start_tick(state: &Rc<RefCell<TimerState>>, label: &Label, start: &Button) -> SourceId {
+    let state = state.clone();
+    let label = label.clone();
+    let start = start.clone();
+
+    glib::timeout_add_local(TICK_INTERVAL, move || {
+        let mut timer = state.borrow_mut();
+        label.set_label(&format_duration(timer.display_time()));
+
+        if timer.is_finished() {
+            // the source is removed by returning `Break`
+            timer.tick = None;
+            timer.stop();
+
+            start.set_label("Start");
+            label.add_class("done");
+            notify_done();
+
+            ControlFlow::Break
+        } else {
+            ControlFlow::Continue
+        }
+    })
+}
+
+/// Sends a desktop notification that the countdown has finished.
+fn notify_done() {
+    #[cfg(feature = "zbus")]
+    crate::spawn(async {
+        if let Err(err) = send_notification("Timer finished").await {
+            tracing::error!("Failed to send timer notification: {err:?}");
+        }
+    });
+}
+
+#[cfg(feature = "zbus")]
+async fn send_notification(summary: &str) -> zbus::Result<()> {
+    use std::collections::HashMap;
+
+    let dbus = Box::pin(zbus::Connection::session()).await?;
+
+    dbus.call_method(
+        Some("org.freedesktop.Notifications"),
+        "/org/freedesktop/Notifications",
+        Some("org.freedesktop.Notifications"),
+        "Notify",
+        &(
+            "Ironbar",
+            0u32,
+            "alarm-symbolic",
+            summary,
+            "",
+            Vec::<&str>::new(),
+            HashMap::<&str, zbus::zvariant::Value>::new(),
+            -1i32,
+        ),
+    )
+    .await?;
+
+    Ok(())
+}
+
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    format!(
+        "{:02}:{:02}:{:02}",
+        secs / 3600,
+        (secs / 60) % 60,
+        secs % 60
+    )
+}
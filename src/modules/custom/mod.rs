@@ -18,15 +18,23 @@ use crate::modules::{
 };
 use crate::script::Script;
 use crate::{module_impl, send_async, spawn};
-use color_eyre::Result;
+use color_eyre::{Report, Result};
 use gtk::prelude::*;
 use gtk::{Button, IconTheme, Orientation};
 use serde::Deserialize;
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::rc::Rc;
 use tokio::sync::{broadcast, mpsc};
 use tracing::{debug, error};
 
+/// The maximum number of custom modules which can be nested inside each other.
+const MAX_DEPTH: usize = 8;
+
+thread_local! {
+    /// The number of custom modules currently being built.
+    static DEPTH: Cell<usize> = const { Cell::new(0) };
+}
+
 #[derive(Debug, Deserialize, Clone)]
 #[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct CustomModule {
@@ -226,6 +234,17 @@ impl Module<gtk::Box> for CustomModule {
         mut context: WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
         info: &ModuleInfo,
     ) -> Result<ModuleParts<gtk::Box>> {
+        // native modules, including other custom modules, are built while building this one,
+        // so the depth is tracked to stop runaway nesting.
+        let depth = DEPTH.get();
+        if depth >= MAX_DEPTH {
+            return Err(Report::msg(format!(
+                "Custom modules cannot be nested more than {MAX_DEPTH} levels deep"
+            )));
+        }
+
+        DEPTH.set(depth + 1);
+
         let orientation = info.bar_position.orientation();
         let container = gtk::Box::builder().orientation(orientation).build();
 
@@ -265,6 +284,8 @@ impl Module<gtk::Box> for CustomModule {
             )
             .into_popup_parts_owned(popup_buttons.take());
 
+        DEPTH.set(depth);
+
         Ok(ModuleParts {
             widget: container,
             popup,
@@ -0,0 +1,285 @@
+use crate::clients::dbus::Bus;
+use crate::config::CommonConfig;
+use crate::format;
+use crate::gtk_helpers::IronbarLabelExt;
+use crate::modules::{Module, ModuleInfo, ModuleParts, ModuleUpdateEvent, WidgetContext};
+use crate::{glib_recv, module_impl, send_async, spawn};
+use color_eyre::Result;
+use futures_lite::StreamExt;
+use gtk::Label;
+use serde::Deserialize;
+use tokio::sync::mpsc;
+use tracing::{debug, error, warn};
+use zbus::fdo::PropertiesProxy;
+use zbus::names::InterfaceName;
+use zbus::zvariant::Value;
+use zbus::Connection;
+
+#[derive(Debug, Deserialize, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct DbusModule {
+    /// The bus to connect to.
+    ///
+    /// **Valid options**: `system`, `session`
+    /// <br>
+    /// **Default**: `session`
+    #[serde(default)]
+    bus: Bus,
+
+    /// The well-known name of the service which owns the object.
+    ///
+    /// **Required**
+    service: String,
+
+    /// The path of the object to read the property from.
+    ///
+    /// **Required**
+    path: String,
+
+    /// The interface the property belongs to.
+    ///
+    /// **Required**
+    interface: String,
+
+    /// The name of the property to display.
+    ///
+    /// **Required**
+    property: String,
+
+    /// The format string to use for the label.
+    /// The `{value}` token is replaced with the property value.
+    ///
+    /// **Default**: `{value}`
+    #[serde(default = "default_format")]
+    format: String,
+
+    /// The text to show when the property cannot be read,
+    /// for example if the service is not running.
+    ///
+    /// **Default**: `""`
+    #[serde(default)]
+    placeholder: String,
+
+    /// See [common options](module-level-options#common-options).
+    #[serde(flatten)]
+    pub common: Option<CommonConfig>,
+}
+
+fn default_format() -> String {
+    String::from("{value}")
+}
+
+impl Module<Label> for DbusModule {
+    /// The property value, or `None` if it could not be read.
+    type SendMessage = Option<String>;
+    type ReceiveMessage = ();
+
+    module_impl!("dbus");
+
+    fn spawn_controller(
+        &self,
+        _info: &ModuleInfo,
+        context: &WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
+        _rx: mpsc::Receiver<Self::ReceiveMessage>,
+    ) -> Result<()> {
+        let tx = context.tx.clone();
+        let module = self.clone();
+        let client = context.ironbar.clients.borrow_mut().dbus(self.bus);
+
+        spawn(async move {
+            let Some(connection) = client.ready().await else {
+                send_async!(tx, ModuleUpdateEvent::Update(None));
+                return;
+            };
+
+            if let Err(err) = watch_property(&connection, &module, &tx).await {
+                error!("{:?}", err.wrap_err("Failed to watch dbus property"));
+                send_async!(tx, ModuleUpdateEvent::Update(None));
+            }
+        });
+
+        Ok(())
+    }
+
+    fn into_widget(
+        self,
+        context: WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
+        _info: &ModuleInfo,
+    ) -> Result<ModuleParts<Label>> {
+        let label = Label::builder()
+            .label(&self.placeholder)
+            .use_markup(true)
+            .build();
+
+        {
+            let label = label.clone();
+            glib_recv!(context.subscribe(), value => {
+                match value {
+                    Some(value) => {
+                        let text = format::replace_tokens(&self.format, |token| {
+                            (token == "value").then(|| value.clone())
+                        });
+                        label.set_label_escaped(&text);
+                    }
+                    None => label.set_label_escaped(&self.placeholder),
+                }
+            });
+        }
+
+        Ok(ModuleParts {
+            widget: label,
+            popup: None,
+        })
+    }
+}
+
+/// Sends the property's current value,
+/// and then its new value each time it changes.
+async fn watch_property(
+    connection: &Connection,
+    module: &DbusModule,
+    tx: &mpsc::Sender<ModuleUpdateEvent<Option<String>>>,
+) -> Result<()> {
+    let interface = InterfaceName::try_from(module.interface.as_str())?;
+
+    let proxy = PropertiesProxy::builder(connection)
+        .destination(module.service.as_str())?
+        .path(module.path.as_str())?
+        .build()
+        .await?;
+
+    // subscribe before fetching the initial value to avoid missing updates
+    let mut stream = proxy.receive_properties_changed().await?;
+
+    let value = get_property(&proxy, &interface, &module.property).await;
+    send_async!(tx, ModuleUpdateEvent::Update(value));
+
+    while let Some(change) = stream.next().await {
+        let args = change.args()?;
+        if args.interface_name != interface {
+            continue;
+        }
+
+        let value = if let Some(value) = args.changed_properties.get(module.property.as_str()) {
+            Some(value_to_string(value))
+        } else if args
+            .invalidated_properties
+            .contains(&module.property.as_str())
+        {
+            get_property(&proxy, &interface, &module.property).await
+        } else {
+            continue;
+        };
+
+        send_async!(tx, ModuleUpdateEvent::Update(value));
+    }
+
+    Ok(())
+}
+
+/// Reads the current value of the property as a string.
+///
+/// Returns `None` if the property cannot be read.
+async fn get_property(
+    proxy: &PropertiesProxy<'_>,
+    interface: &InterfaceName<'_>,
+    property: &str,
+) -> Option<String> {
+    match proxy.get(interface.clone(), property).await {
+        Ok(value) => Some(value_to_string(&value)),
+        Err(err) => {
+            warn!("Failed to read dbus property '{property}': {err}");
+            None
+        }
+    }
+}
+
+/// Converts a D-Bus value into its display string.
+///
+/// - Strings, object paths and signatures are shown as-is.
+/// - Numbers and booleans use their standard representation.
+/// - Arrays and structs show their items, separated by commas.
+/// - Variants show their inner value.
+///
+/// Other types, such as dictionaries, are shown as an empty string.
+fn value_to_string(value: &Value) -> String {
+    let join = |values: &[Value]| {
+        values
+            .iter()
+            .map(value_to_string)
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+
+    match value {
+        Value::U8(value) => value.to_string(),
+        Value::Bool(value) => value.to_string(),
+        Value::I16(value) => value.to_string(),
+        Value::U16(value) => value.to_string(),
+        Value::I32(value) => value.to_string(),
+        Value::U32(value) => value.to_string(),
+        Value::I64(value) => value.to_string(),
+        Value::U64(value) => value.to_string(),
+        Value::F64(value) => value.to_string(),
+        Value::Str(value) => value.to_string(),
+        Value::Signature(value) => value.to_string(),
+        Value::ObjectPath(value) => value.to_string(),
+        Value::Value(value) => value_to_string(value),
+        Value::Array(array) => join(array.get()),
+        Value::Structure(structure) => join(structure.fields()),
+        value => {
+            debug!("Unsupported dbus value type: {}", value.value_signature());
+            String::new()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use zbus::zvariant::{ObjectPath, StructureBuilder};
+
+    #[test]
+    fn test_value_to_string_scalars() {
+        assert_eq!(value_to_string(&Value::from(42u32)), "42");
+        assert_eq!(value_to_string(&Value::from(-7i64)), "-7");
+        assert_eq!(value_to_string(&Value::from(1.5f64)), "1.5");
+        assert_eq!(value_to_string(&Value::from(true)), "true");
+        assert_eq!(value_to_string(&Value::from("hello")), "hello");
+    }
+
+    #[test]
+    fn test_value_to_string_object_path() {
+        let path = ObjectPath::try_from("/org/freedesktop/Foo").expect("valid path");
+        assert_eq!(value_to_string(&Value::from(path)), "/org/freedesktop/Foo");
+    }
+
+    #[test]
+    fn test_value_to_string_variant() {
+        let value = Value::Value(Box::new(Value::from(5u8)));
+        assert_eq!(value_to_string(&value), "5");
+    }
+
+    #[test]
+    fn test_value_to_string_array() {
+        let value = Value::from(vec!["a", "b", "c"]);
+        assert_eq!(value_to_string(&value), "a, b, c");
+    }
+
+    #[test]
+    fn test_value_to_string_structure() {
+        let structure = StructureBuilder::new()
+            .add_field(1u32)
+            .add_field("two")
+            .build();
+
+        assert_eq!(value_to_string(&Value::from(structure)), "1, two");
+    }
+
+    #[test]
+    fn test_value_to_string_unsupported() {
+        let dict = HashMap::from([("key", 1u32)]);
+        assert_eq!(value_to_string(&Value::from(dict)), "");
+    }
+}
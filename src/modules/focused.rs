@@ -6,6 +6,7 @@ use crate::image::ImageProvider;
 use crate::modules::{Module, ModuleInfo, ModuleParts, ModuleUpdateEvent, WidgetContext};
 use crate::{glib_recv, module_impl, send_async, spawn, try_send};
 use color_eyre::Result;
+use gtk::pango::EllipsizeMode;
 use gtk::prelude::*;
 use gtk::Label;
 use serde::Deserialize;
@@ -32,6 +33,22 @@ pub struct FocusedModule {
     #[serde(default = "default_icon_size")]
     icon_size: i32,
 
+    /// The minimum width of the title, in characters.
+    /// Shorter titles are padded to this width,
+    /// keeping the bar layout stable as the focused window changes.
+    ///
+    /// **Default**: `null`
+    #[serde(default)]
+    min_width: Option<i32>,
+
+    /// The maximum width of the title, in characters.
+    /// Longer titles are ellipsized at the end,
+    /// unless a `truncate` mode is also set.
+    ///
+    /// **Default**: `null`
+    #[serde(default)]
+    max_width: Option<i32>,
+
     // -- common --
     /// See [truncate options](module-level-options#truncate-mode).
     ///
@@ -49,6 +66,8 @@ impl Default for FocusedModule {
             show_icon: crate::config::default_true(),
             show_title: crate::config::default_true(),
             icon_size: default_icon_size(),
+            min_width: None,
+            max_width: None,
             truncate: None,
             common: Some(CommonConfig::default()),
         }
@@ -134,22 +153,42 @@ impl Module<gtk::Box> for FocusedModule {
     ) -> Result<ModuleParts<gtk::Box>> {
         let icon_theme = info.icon_theme;
 
-        let container = gtk::Box::new(info.bar_position.orientation(), 5);
+        // only space out the icon and title when both are shown
+        let spacing = if self.show_icon && self.show_title {
+            5
+        } else {
+            0
+        };
+
+        let container = gtk::Box::new(info.bar_position.orientation(), spacing);
 
         let icon = gtk::Image::new();
         if self.show_icon {
             icon.add_class("icon");
+            icon.set_size_request(self.icon_size, self.icon_size);
             container.add(&icon);
         }
 
         let label = Label::new(None);
-        label.add_class("label");
+        if self.show_title {
+            label.add_class("label");
 
-        if let Some(truncate) = self.truncate {
-            label.truncate(truncate);
-        }
+            if let Some(truncate) = self.truncate {
+                label.truncate(truncate);
+            } else if self.max_width.is_some() {
+                label.set_ellipsize(EllipsizeMode::End);
+            }
+
+            if let Some(min_width) = self.min_width {
+                label.set_width_chars(min_width);
+            }
 
-        container.add(&label);
+            if let Some(max_width) = self.max_width {
+                label.set_max_width_chars(max_width);
+            }
+
+            container.add(&label);
+        }
 
         {
             let icon_theme = icon_theme.clone();
@@ -0,0 +1,257 @@
+use crate::config::CommonConfig;
+use crate::gtk_helpers::IronbarGtkExt;
+use crate::modules::{Module, ModuleInfo, ModuleParts, ModuleUpdateEvent, WidgetContext};
+use crate::{glib_recv, module_impl, send_async, spawn, try_send, write_lock, Ironbar};
+use color_eyre::Result;
+use glib::{Propagation, SignalHandlerId};
+use gtk::prelude::*;
+use gtk::{Entry, Label, Orientation, Scale, Switch};
+use serde::Deserialize;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::mpsc;
+use tracing::error;
+
+#[derive(Debug, Deserialize, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct IronvarModule {
+    /// The variables to show controls for, in order.
+    /// See [below](#control) for options.
+    ///
+    /// **Required**
+    vars: Vec<VarControl>,
+
+    /// See [common options](module-level-options#common-options).
+    #[serde(flatten)]
+    pub common: Option<CommonConfig>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct VarControl {
+    /// The key of the `ironvar` to display and set.
+    ///
+    /// **Required**
+    key: Box<str>,
+
+    /// The text to show next to the control.
+    /// If not set, the key is shown.
+    ///
+    /// **Default**: `null`
+    label: Option<String>,
+
+    /// The type of input to use.
+    ///
+    /// **Valid options**: `text`, `toggle`, `slider`
+    /// <br>
+    /// **Default**: `text`
+    #[serde(default)]
+    control: ControlType,
+
+    /// The minimum slider value.
+    /// Only applies to `slider` controls.
+    ///
+    /// **Default**: `0`
+    #[serde(default)]
+    min: f64,
+
+    /// The maximum slider value.
+    /// Only applies to `slider` controls.
+    ///
+    /// **Default**: `100`
+    #[serde(default = "default_max")]
+    max: f64,
+
+    /// The amount the slider value changes by.
+    /// Only applies to `slider` controls.
+    ///
+    /// **Default**: `1`
+    #[serde(default = "default_step")]
+    step: f64,
+}
+
+const fn default_max() -> f64 {
+    100.0
+}
+
+const fn default_step() -> f64 {
+    1.0
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum ControlType {
+    /// A text entry.
+    /// The variable is set when `Enter` is pressed.
+    #[default]
+    Text,
+    /// A switch, which sets the variable to `true` or `false`.
+    Toggle,
+    /// A slider, which sets the variable to a number.
+    Slider,
+}
+
+/// A control widget,
+/// kept so it can be updated when its variable changes.
+#[derive(Debug, Clone)]
+enum Control {
+    Text(Entry),
+    /// The switch and its `state-set` handler,
+    /// which is blocked while the switch is updated from the variable.
+    Toggle(Switch, SignalHandlerId),
+    Slider(Scale),
+}
+
+impl Control {
+    /// Updates the widget to show the variable's new value.
+    fn set_value(&self, value: &str) {
+        match self {
+            Self::Text(entry) => {
+                if entry.text() != value {
+                    entry.set_text(value);
+                }
+            }
+            Self::Toggle(switch, handler) => {
+                let active = value == "true";
+                if switch.is_active() != active {
+                    switch.block_signal(handler);
+                    switch.set_active(active);
+                    switch.unblock_signal(handler);
+                }
+            }
+            Self::Slider(scale) => {
+                if let Ok(value) = value.parse() {
+                    scale.set_value(value);
+                }
+            }
+        }
+    }
+}
+
+impl Module<gtk::Box> for IronvarModule {
+    /// The index of the variable in the config, and its new value.
+    type SendMessage = (usize, Option<String>);
+    /// The key of the variable to set, and its value.
+    type ReceiveMessage = (Box<str>, String);
+
+    module_impl!("ironvar");
+
+    fn spawn_controller(
+        &self,
+        _info: &ModuleInfo,
+        context: &WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
+        mut rx: mpsc::Receiver<Self::ReceiveMessage>,
+    ) -> Result<()> {
+        let variable_manager = Ironbar::variable_manager();
+
+        for (index, var) in self.vars.iter().enumerate() {
+            let mut var_rx = write_lock!(variable_manager).subscribe(var.key.clone());
+            let tx = context.tx.clone();
+
+            spawn(async move {
+                loop {
+                    match var_rx.recv().await {
+                        Ok(value) => send_async!(tx, ModuleUpdateEvent::Update((index, value))),
+                        Err(RecvError::Lagged(_)) => {}
+                        Err(RecvError::Closed) => break,
+                    }
+                }
+            });
+        }
+
+        spawn(async move {
+            while let Some((key, value)) = rx.recv().await {
+                if let Err(err) = write_lock!(variable_manager).set(key, value) {
+                    error!("{err:?}");
+                }
+            }
+        });
+
+        Ok(())
+    }
+
+    fn into_widget(
+        self,
+        context: WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
+        info: &ModuleInfo,
+    ) -> Result<ModuleParts<gtk::Box>> {
+        let container = gtk::Box::new(info.bar_position.orientation(), 10);
+
+        let controls = self
+            .vars
+            .into_iter()
+            .map(|var| {
+                let row = gtk::Box::new(Orientation::Horizontal, 5);
+                row.add_class("control");
+
+                let label = Label::new(Some(var.label.as_deref().unwrap_or(&var.key)));
+                label.add_class("label");
+                row.add(&label);
+
+                let tx = context.controller_tx.clone();
+                let key = var.key;
+
+                let control = match var.control {
+                    ControlType::Text => {
+                        let entry = Entry::new();
+                        entry.add_class("text");
+
+                        entry.connect_activate(move |entry| {
+                            try_send!(tx, (key.clone(), entry.text().to_string()));
+                        });
+
+                        row.add(&entry);
+                        Control::Text(entry)
+                    }
+                    ControlType::Toggle => {
+                        let switch = Switch::new();
+                        switch.add_class("toggle");
+
+                        let handler = switch.connect_state_set(move |_, state| {
+                            try_send!(tx, (key.clone(), state.to_string()));
+                            Propagation::Proceed
+                        });
+
+                        row.add(&switch);
+                        Control::Toggle(switch, handler)
+                    }
+                    ControlType::Slider => {
+                        let scale =
+                            Scale::with_range(Orientation::Horizontal, var.min, var.max, var.step);
+                        scale.add_class("slider");
+                        scale.set_width_request(100);
+
+                        let (min, max, step) = (var.min, var.max, var.step);
+
+                        // only emitted by user interaction,
+                        // so updating the slider from the variable does not loop back.
+                        scale.connect_change_value(move |_, _, value| {
+                            // GTK will send values outside min/max range
+                            let value = ((value / step).round() * step).clamp(min, max);
+                            try_send!(tx, (key.clone(), value.to_string()));
+                            Propagation::Proceed
+                        });
+
+                        row.add(&scale);
+                        Control::Slider(scale)
+                    }
+                };
+
+                container.add(&row);
+                control
+            })
+            .collect::<Vec<_>>();
+
+        glib_recv!(context.subscribe(), update => {
+            let (index, value) = update;
+            if let Some(control) = controls.get(index) {
+                control.set_value(value.as_deref().unwrap_or_default());
+            }
+        });
+
+        Ok(ModuleParts {
+            widget: container,
+            popup: None,
+        })
+    }
+}
@@ -3,13 +3,13 @@ use crate::clients::wayland::ToplevelInfo;
 use crate::config::{BarPosition, TruncateMode};
 use crate::gtk_helpers::{IronbarGtkExt, IronbarLabelExt};
 use crate::image::ImageProvider;
-use crate::modules::launcher::{ItemEvent, LauncherUpdate};
+use crate::modules::launcher::{ItemEvent, LauncherUpdate, DRAG_TARGET};
 use crate::modules::ModuleUpdateEvent;
 use crate::{read_lock, try_send};
 use glib::Propagation;
-use gtk::gdk::{BUTTON_MIDDLE, BUTTON_PRIMARY};
+use gtk::gdk::{DragAction, ModifierType, BUTTON_MIDDLE, BUTTON_PRIMARY};
 use gtk::prelude::*;
-use gtk::{Button, IconTheme, Image, Label, Orientation};
+use gtk::{Button, IconTheme, Image, Label, Orientation, TargetEntry, TargetFlags};
 use indexmap::IndexMap;
 use std::ops::Deref;
 use std::rc::Rc;
@@ -252,6 +252,17 @@ impl ItemButton {
             });
         }
 
+        // allow dragging the item onto other widgets, such as workspace buttons
+        {
+            let targets = [TargetEntry::new(DRAG_TARGET, TargetFlags::SAME_APP, 0)];
+            button.drag_source_set(ModifierType::BUTTON1_MASK, &targets, DragAction::MOVE);
+
+            let app_id = item.app_id.clone();
+            button.connect_drag_data_get(move |_, _, data, _, _| {
+                data.set_text(&app_id);
+            });
+        }
+
         {
             let tx = tx.clone();
 
@@ -315,7 +326,7 @@ impl ItemButton {
 pub struct ImageTextButton {
     pub(crate) button: Button,
     pub(crate) label: Label,
-    image: Image,
+    pub(crate) image: Image,
 }
 
 impl ImageTextButton {
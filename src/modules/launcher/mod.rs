@@ -1,25 +1,47 @@
 mod item;
 mod open_state;
+mod sort;
 
 use self::item::{AppearanceOptions, Item, ItemButton, Window};
 use self::open_state::OpenState;
+use self::sort::{ItemSorter, SortOrder};
 use super::{Module, ModuleInfo, ModuleParts, ModulePopup, ModuleUpdateEvent, WidgetContext};
+#[cfg(feature = "workspaces")]
+use crate::clients::compositor::{CompositorCapabilities, WindowTarget, WorkspaceClient};
 use crate::clients::wayland::{self, ToplevelEvent};
 use crate::config::{CommonConfig, EllipsizeMode, TruncateMode};
 use crate::desktop_file::find_desktop_file;
 use crate::gtk_helpers::{IronbarGtkExt, IronbarLabelExt};
+use crate::image::ImageProvider;
 use crate::modules::launcher::item::ImageTextButton;
-use crate::{arc_mut, glib_recv, lock, module_impl, send_async, spawn, try_send, write_lock};
+use crate::{
+    arc_mut, glib_recv, lock, module_impl, rc_mut, send_async, spawn, try_send, write_lock,
+};
 use color_eyre::{Help, Report};
+use gtk::gdk::{DragAction, ModifierType};
+#[cfg(feature = "launcher+thumbnails")]
+use gtk::gdk_pixbuf::{Colorspace, InterpType, Pixbuf};
 use gtk::prelude::*;
-use gtk::{Button, Orientation};
+use gtk::{
+    DestDefaults, IconTheme, Orientation, PolicyType, ScrolledWindow, TargetEntry, TargetFlags,
+};
 use indexmap::IndexMap;
 use serde::Deserialize;
+use std::cell::RefCell;
 use std::process::{Command, Stdio};
+use std::rc::Rc;
 use std::sync::Arc;
 use tokio::sync::{broadcast, mpsc};
 use tracing::{debug, error, trace};
 
+/// Drag-and-drop target name for launcher items.
+/// The payload is the item's app ID.
+pub const DRAG_TARGET: &str = "ironbar/launcher-item";
+
+/// Drag-and-drop target name for popup window buttons.
+/// The payload is the window ID.
+const POPUP_DRAG_TARGET: &str = "ironbar/launcher-window";
+
 #[derive(Debug, Deserialize, Clone)]
 #[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct LauncherModule {
@@ -56,12 +78,36 @@ pub struct LauncherModule {
     #[serde(default = "crate::config::default_false")]
     reversed: bool,
 
+    /// The order to show items in.
+    /// Favorites are always shown first, in the order specified.
+    ///
+    /// - `added` shows items in the order they're opened.
+    /// - `alphabetical` sorts items by app ID.
+    /// - `recent` shows the most recently focused items first.
+    ///
+    /// **Valid options**: `added`, `alphabetical`, `recent`
+    /// <br>
+    /// **Default**: `added`
+    #[serde(default)]
+    sort: SortOrder,
+
     /// Whether to minimize a window if it is focused when clicked.
     ///
     /// **Default**: `true`
     #[serde(default = "crate::config::default_true")]
     minimize_focused: bool,
 
+    /// Whether to switch to the workspace containing a window when it is focused,
+    /// if it is on a different workspace.
+    ///
+    /// Most compositors do this automatically,
+    /// but some do not switch workspace when focusing a window from another one.
+    ///
+    /// **Default**: `true`
+    #[cfg(feature = "workspaces")]
+    #[serde(default = "crate::config::default_true")]
+    switch_workspace_on_focus: bool,
+
     // -- common --
     /// Truncate application names on the bar if they get too long.
     /// See [truncate options](module-level-options#truncate-mode).
@@ -77,6 +123,47 @@ pub struct LauncherModule {
     #[serde(default = "default_truncate_popup")]
     truncate_popup: TruncateMode,
 
+    /// The width of the popup in pixels.
+    /// The popup does not grow beyond this,
+    /// and window titles are truncated to fit.
+    ///
+    /// **Default**: `250`
+    #[serde(default = "default_popup_max_width")]
+    popup_max_width: i32,
+
+    /// The maximum height of the popup in pixels.
+    /// If there are more windows than fit, the popup can be scrolled.
+    /// Leave unset for no limit.
+    ///
+    /// **Default**: `null`
+    #[serde(default)]
+    popup_max_height: Option<i32>,
+
+    /// Size in pixels to render app icons at in the popup.
+    ///
+    /// **Default**: `24`
+    #[serde(default = "default_popup_icon_size")]
+    popup_icon_size: i32,
+
+    /// Whether to show a snapshot of each window in the popup,
+    /// in place of the app icon.
+    /// Snapshots are taken each time the popup opens.
+    ///
+    /// This requires compositor support for the `ext-image-copy-capture` protocol.
+    /// Where unsupported, the app icon is shown instead.
+    ///
+    /// **Default**: `false`
+    #[cfg(feature = "launcher+thumbnails")]
+    #[serde(default = "crate::config::default_false")]
+    popup_thumbnails: bool,
+
+    /// The maximum width or height of window snapshots in the popup, in pixels.
+    ///
+    /// **Default**: `128`
+    #[cfg(feature = "launcher+thumbnails")]
+    #[serde(default = "default_popup_thumbnail_size")]
+    popup_thumbnail_size: i32,
+
     /// See [common options](module-level-options#common-options).
     #[serde(flatten)]
     pub common: Option<CommonConfig>,
@@ -86,6 +173,19 @@ const fn default_icon_size() -> i32 {
     32
 }
 
+const fn default_popup_icon_size() -> i32 {
+    24
+}
+
+#[cfg(feature = "launcher+thumbnails")]
+const fn default_popup_thumbnail_size() -> i32 {
+    128
+}
+
+const fn default_popup_max_width() -> i32 {
+    250
+}
+
 const fn default_truncate_popup() -> TruncateMode {
     TruncateMode::Length {
         mode: EllipsizeMode::Middle,
@@ -299,6 +399,21 @@ impl Module<gtk::Box> for LauncherModule {
         // listen to ui events
         let minimize_focused = self.minimize_focused;
         let wl = context.client::<wayland::Client>();
+
+        #[cfg(feature = "workspaces")]
+        let workspaces = if self.switch_workspace_on_focus {
+            context
+                .try_client::<dyn WorkspaceClient>()
+                .ok()
+                .filter(|client| {
+                    client
+                        .capabilities()
+                        .contains(CompositorCapabilities::FOCUS_WINDOW_WORKSPACE)
+                })
+        } else {
+            None
+        };
+
         spawn(async move {
             while let Some(event) = rx.recv().await {
                 if let ItemEvent::OpenItem(app_id) = event {
@@ -343,6 +458,24 @@ impl Module<gtk::Box> for LauncherModule {
                     };
 
                     if let Some(id) = id {
+                        #[cfg(feature = "workspaces")]
+                        if let Some(workspaces) = &workspaces {
+                            if !(minimize_window && minimize_focused) {
+                                let target = lock!(items).iter().find_map(|(app_id, item)| {
+                                    item.windows.get(&id).map(|window| WindowTarget {
+                                        app_id: app_id.clone(),
+                                        title: window.name.clone(),
+                                    })
+                                });
+
+                                if let Some(target) = target {
+                                    if let Err(err) = workspaces.focus_window_workspace(&target) {
+                                        error!("{err:?}");
+                                    }
+                                }
+                            }
+                        }
+
                         if let Some(window) = lock!(items)
                             .iter()
                             .find_map(|(_, item)| item.windows.get(&id))
@@ -388,6 +521,7 @@ impl Module<gtk::Box> for LauncherModule {
             let bar_position = info.bar_position;
 
             let mut buttons = IndexMap::<String, ItemButton>::new();
+            let mut sorter = ItemSorter::new(self.sort);
 
             let tx = context.tx.clone();
             let rx = context.subscribe();
@@ -417,6 +551,8 @@ impl Module<gtk::Box> for LauncherModule {
 
                             buttons.insert(item.app_id, button);
                         }
+
+                        sorter.apply(&container, &buttons);
                     }
                     LauncherUpdate::AddWindow(app_id, win) => {
                         if let Some(button) = buttons.get(&app_id) {
@@ -438,6 +574,7 @@ impl Module<gtk::Box> for LauncherModule {
                             } else {
                                 container.remove(&button.button.button);
                                 buttons.shift_remove(&app_id);
+                                sorter.remove(&app_id);
                             }
                         }
                     }
@@ -457,6 +594,10 @@ impl Module<gtk::Box> for LauncherModule {
                         if let Some(button) = buttons.get(&app_id) {
                             button.set_focused(focus);
                         }
+
+                        if focus && sorter.focus(&app_id) {
+                            sorter.apply(&container, &buttons);
+                        }
                     }
                     LauncherUpdate::Title(app_id, _, name) => {
                         debug!("Updating title for item with id {}: {:?}", app_id, name);
@@ -487,19 +628,39 @@ impl Module<gtk::Box> for LauncherModule {
         self,
         controller_tx: mpsc::Sender<Self::ReceiveMessage>,
         rx: broadcast::Receiver<Self::SendMessage>,
-        _context: WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
-        _info: &ModuleInfo,
+        context: WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
+        info: &ModuleInfo,
     ) -> Option<gtk::Box> {
-        const MAX_WIDTH: i32 = 250;
+        let icon_theme = info.icon_theme.clone();
 
-        let container = gtk::Box::new(Orientation::Vertical, 0);
+        #[cfg(feature = "launcher+thumbnails")]
+        let wl = context.client::<wayland::Client>();
+        #[cfg(not(feature = "launcher+thumbnails"))]
+        let _ = context;
+
+        let popup = gtk::Box::new(Orientation::Vertical, 0);
+
+        // the requested width also stops the popup collapsing when empty.
+        // `External` stops wide content growing the popup past the maximum width,
+        // which `Never` allows as it always fits the content.
+        let scrolled = ScrolledWindow::builder()
+            .hscrollbar_policy(PolicyType::External)
+            .vscrollbar_policy(PolicyType::Automatic)
+            .propagate_natural_width(true)
+            .propagate_natural_height(true)
+            .width_request(self.popup_max_width)
+            .max_content_width(self.popup_max_width)
+            .build();
+
+        if let Some(max_height) = self.popup_max_height {
+            scrolled.set_max_content_height(max_height);
+        }
 
-        // we need some content to force the container to have a size
-        let placeholder = Button::with_label("PLACEHOLDER");
-        placeholder.set_width_request(MAX_WIDTH);
-        container.add(&placeholder);
+        let container = gtk::Box::new(Orientation::Vertical, 0);
+        scrolled.add(&container);
+        popup.add(&scrolled);
 
-        let mut buttons = IndexMap::<String, IndexMap<usize, ImageTextButton>>::new();
+        let buttons = rc_mut!(IndexMap::<String, IndexMap<usize, ImageTextButton>>::new());
 
         {
             let container = container.clone();
@@ -513,24 +674,19 @@ impl Module<gtk::Box> for LauncherModule {
                             .windows
                             .into_iter()
                             .map(|(_, win)| {
-                                // TODO: Currently has a useless image
-                                let button = ImageTextButton::new();
-                                button.set_height_request(40);
-                                button.label.set_label(&win.name);
-                                button.label.truncate(self.truncate_popup);
-
-                                {
-                                    let tx = controller_tx.clone();
-                                    button.connect_clicked(move |_| {
-                                        try_send!(tx, ItemEvent::FocusWindow(win.id));
-                                    });
-                                }
-
+                                let button = window_button(
+                                    &app_id,
+                                    &win,
+                                    &self,
+                                    &icon_theme,
+                                    &controller_tx,
+                                );
+                                connect_reorder(&button, &app_id, win.id, &buttons, &container);
                                 (win.id, button)
                             })
                             .collect();
 
-                        buttons.insert(app_id, window_buttons);
+                        buttons.borrow_mut().insert(app_id, window_buttons);
                     }
                     LauncherUpdate::AddWindow(app_id, win) => {
                         debug!(
@@ -538,28 +694,46 @@ impl Module<gtk::Box> for LauncherModule {
                             win.name, win.id
                         );
 
-                        if let Some(buttons) = buttons.get_mut(&app_id) {
-                            // TODO: Currently has a useless image
-                            let button = ImageTextButton::new();
-                            button.set_height_request(40);
-                            button.label.set_label(&win.name);
-                            button.label.truncate(self.truncate_popup);
+                        if buttons.borrow().contains_key(&app_id) {
+                            let button = window_button(
+                                &app_id,
+                                &win,
+                                &self,
+                                &icon_theme,
+                                &controller_tx,
+                            );
+                            connect_reorder(&button, &app_id, win.id, &buttons, &container);
 
-                            {
-                                let tx = controller_tx.clone();
-                                button.connect_clicked(move |_button| {
-                                    try_send!(tx, ItemEvent::FocusWindow(win.id));
-                                });
+                            if let Some(buttons) = buttons.borrow_mut().get_mut(&app_id) {
+                                buttons.insert(win.id, button);
                             }
-
-                            buttons.insert(win.id, button);
                         }
                     }
                     LauncherUpdate::RemoveWindow(app_id, win_id) => {
                         debug!("Removing window from popup for '{app_id}': {win_id}");
 
-                        if let Some(buttons) = buttons.get_mut(&app_id) {
-                            buttons.shift_remove(&win_id);
+                        let button = buttons
+                            .borrow_mut()
+                            .get_mut(&app_id)
+                            .and_then(|buttons| buttons.shift_remove(&win_id));
+
+                        if let Some(button) = button {
+                            // the window may be showing in the open popup
+                            if button.parent().is_some() {
+                                container.remove(&button.button);
+                            }
+                        }
+                    }
+                    LauncherUpdate::RemoveItem(app_id) => {
+                        debug!("Removing item from popup: '{app_id}'");
+
+                        let item_buttons = buttons.borrow_mut().shift_remove(&app_id);
+                        if let Some(buttons) = item_buttons {
+                            for button in buttons.values() {
+                                if button.parent().is_some() {
+                                    container.remove(&button.button);
+                                }
+                            }
                         }
                     }
                     LauncherUpdate::Title(app_id, win_id, title) => {
@@ -567,7 +741,7 @@ impl Module<gtk::Box> for LauncherModule {
                             "Updating window title on popup for '{app_id}'/{win_id} to '{title}'"
                         );
 
-                        if let Some(buttons) = buttons.get_mut(&app_id) {
+                        if let Some(buttons) = buttons.borrow().get(&app_id) {
                             if let Some(button) = buttons.get(&win_id) {
                                 button.label.set_label(&title);
                             }
@@ -580,14 +754,20 @@ impl Module<gtk::Box> for LauncherModule {
                         }
 
                         // add app's buttons
-                        if let Some(buttons) = buttons.get(&app_id) {
-                            for (_, button) in buttons {
-                                button.add_class("popup-item");
+                        if let Some(buttons) = buttons.borrow().get(&app_id) {
+                            for button in buttons.values() {
                                 container.add(&button.button);
                             }
 
                             container.show_all();
-                            container.set_width_request(MAX_WIDTH);
+
+                            #[cfg(feature = "launcher+thumbnails")]
+                            if self.popup_thumbnails {
+                                let size = self.popup_thumbnail_size;
+                                for (&id, button) in buttons {
+                                    load_thumbnail(&wl, id, &button.image, size);
+                                }
+                            }
                         }
                     }
                     _ => {}
@@ -595,6 +775,137 @@ impl Module<gtk::Box> for LauncherModule {
             });
         }
 
-        Some(container)
+        Some(popup)
     }
 }
+
+/// Creates a popup button for the window,
+/// which focuses the window when clicked.
+///
+/// The button shows the app's icon,
+/// or no image if the icon cannot be resolved.
+fn window_button(
+    app_id: &str,
+    win: &Window,
+    module: &LauncherModule,
+    icon_theme: &IconTheme,
+    tx: &mpsc::Sender<ItemEvent>,
+) -> ImageTextButton {
+    let button = ImageTextButton::new();
+    button.add_class("popup-item");
+    button.set_height_request(40);
+    button.label.set_label(&win.name);
+    button.label.truncate(module.truncate_popup);
+
+    let has_icon = ImageProvider::parse(app_id, icon_theme, false, module.popup_icon_size)
+        .is_some_and(|image| image.load_into_image(&button.image).is_ok());
+
+    if has_icon {
+        button.image.add_class("icon");
+    } else {
+        // stop `show_all` revealing the empty image
+        button.image.set_no_show_all(true);
+        button.image.hide();
+    }
+
+    let id = win.id;
+    let tx = tx.clone();
+    button.connect_clicked(move |_| {
+        try_send!(tx, ItemEvent::FocusWindow(id));
+    });
+
+    button
+}
+
+/// Allows the window button to be dragged onto
+/// another button in the popup, moving it to that position.
+///
+/// This only changes the order of the popup buttons.
+fn connect_reorder(
+    button: &ImageTextButton,
+    app_id: &str,
+    win_id: usize,
+    buttons: &Rc<RefCell<IndexMap<String, IndexMap<usize, ImageTextButton>>>>,
+    container: &gtk::Box,
+) {
+    let targets = [TargetEntry::new(
+        POPUP_DRAG_TARGET,
+        TargetFlags::SAME_APP,
+        0,
+    )];
+
+    button.drag_source_set(ModifierType::BUTTON1_MASK, &targets, DragAction::MOVE);
+    button.drag_dest_set(DestDefaults::ALL, &targets, DragAction::MOVE);
+
+    button.connect_drag_data_get(move |_, _, data, _, _| {
+        data.set_text(&win_id.to_string());
+    });
+
+    let app_id = app_id.to_string();
+    let buttons = buttons.clone();
+    let container = container.clone();
+
+    button.connect_drag_data_received(move |_, _, _, _, data, _, _| {
+        let Some(dragged_id) = data.text().and_then(|id| id.parse::<usize>().ok()) else {
+            return;
+        };
+
+        // dropped onto itself
+        if dragged_id == win_id {
+            return;
+        }
+
+        let mut buttons = buttons.borrow_mut();
+        let Some(buttons) = buttons.get_mut(&app_id) else {
+            return;
+        };
+
+        if let (Some(from), Some(to)) = (
+            buttons.get_index_of(&dragged_id),
+            buttons.get_index_of(&win_id),
+        ) {
+            buttons.move_index(from, to);
+
+            if let Some(button) = buttons.get(&dragged_id) {
+                container.reorder_child(&button.button, to as i32);
+            }
+        }
+    });
+}
+
+/// Captures a snapshot of the window,
+/// and loads it into the image once ready.
+///
+/// The image is left unchanged if the capture fails.
+#[cfg(feature = "launcher+thumbnails")]
+fn load_thumbnail(wl: &wayland::Client, window_id: usize, image: &gtk::Image, size: i32) {
+    let rx = wl.toplevel_capture(window_id);
+    let image = image.clone();
+
+    glib::spawn_future_local(async move {
+        let Ok(Some(capture)) = rx.await else {
+            return;
+        };
+
+        let scale = f64::from(size) / f64::from(capture.width.max(capture.height));
+        let width = (f64::from(capture.width) * scale).round().max(1.0) as i32;
+        let height = (f64::from(capture.height) * scale).round().max(1.0) as i32;
+
+        let row_stride = capture.width * 4;
+        let pixbuf = Pixbuf::from_bytes(
+            &glib::Bytes::from_owned(capture.data),
+            Colorspace::Rgb,
+            true,
+            8,
+            capture.width,
+            capture.height,
+            row_stride,
+        );
+
+        if let Some(pixbuf) = pixbuf.scale_simple(width, height, InterpType::Bilinear) {
+            image.set_from_pixbuf(Some(&pixbuf));
+            image.set_no_show_all(false);
+            image.show();
+        }
+    });
+}
@@ -0,0 +1,108 @@
+use super::item::ItemButton;
+use gtk::prelude::*;
+use indexmap::IndexMap;
+use serde::Deserialize;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+#[derive(Debug, Deserialize, Default, Clone, Copy, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum SortOrder {
+    /// Shows items in the order they're added.
+    #[default]
+    Added,
+
+    /// Shows items in alphabetical order of their app IDs.
+    Alphabetical,
+
+    /// Shows the most recently focused items first.
+    /// Items which have not been focused are added to the end.
+    Recent,
+}
+
+/// Keeps the item buttons in the configured order.
+///
+/// Favorites are always kept at the front, in the order they were added.
+#[derive(Debug)]
+pub struct ItemSorter {
+    sort: SortOrder,
+    /// Focus counter value each item was last focused at.
+    last_focused: HashMap<String, u64>,
+    focus_count: u64,
+    /// The currently applied order of app IDs.
+    order: Vec<String>,
+}
+
+impl ItemSorter {
+    pub fn new(sort: SortOrder) -> Self {
+        Self {
+            sort,
+            last_focused: HashMap::new(),
+            focus_count: 0,
+            order: vec![],
+        }
+    }
+
+    /// Records the item as focused.
+    ///
+    /// Returns `true` if this may affect the order.
+    pub fn focus(&mut self, app_id: &str) -> bool {
+        if self.sort != SortOrder::Recent {
+            return false;
+        }
+
+        self.focus_count += 1;
+        self.last_focused
+            .insert(app_id.to_string(), self.focus_count);
+
+        true
+    }
+
+    /// Forgets the item's focus history.
+    pub fn remove(&mut self, app_id: &str) {
+        self.last_focused.remove(app_id);
+    }
+
+    /// Reorders the buttons inside the container.
+    ///
+    /// The sort is stable, and the container is only
+    /// updated if the order has actually changed.
+    pub fn apply(&mut self, container: &gtk::Box, buttons: &IndexMap<String, ItemButton>) {
+        if self.sort == SortOrder::Added {
+            return;
+        }
+
+        let mut order = buttons.keys().cloned().collect::<Vec<_>>();
+
+        // favorites keep their configured order at the front
+        match self.sort {
+            SortOrder::Added => {}
+            SortOrder::Alphabetical => order.sort_by_cached_key(|app_id| {
+                if buttons[app_id].persistent {
+                    (false, String::new())
+                } else {
+                    (true, app_id.to_lowercase())
+                }
+            }),
+            SortOrder::Recent => order.sort_by_key(|app_id| {
+                if buttons[app_id].persistent {
+                    (false, Reverse(0))
+                } else {
+                    let last_focused = self.last_focused.get(app_id).copied();
+                    (true, Reverse(last_focused.unwrap_or_default()))
+                }
+            }),
+        }
+
+        if order == self.order {
+            return;
+        }
+
+        for (i, app_id) in order.iter().enumerate() {
+            container.reorder_child(&buttons[app_id].button.button, i as i32);
+        }
+
+        self.order = order;
+    }
+}
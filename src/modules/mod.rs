@@ -1,21 +1,25 @@
+use std::cell::Cell;
 use std::fmt::Debug;
 use std::rc::Rc;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use color_eyre::Result;
-use glib::IsA;
-use gtk::gdk::{EventMask, Monitor};
+use glib::{IsA, Propagation, SourceId};
+use gtk::gdk::{EventMask, Monitor, NotifyType};
 use gtk::prelude::*;
 use gtk::{Application, Button, EventBox, IconTheme, Orientation, Revealer, Widget};
 use tokio::sync::{broadcast, mpsc};
-use tracing::debug;
+use tracing::{debug, trace, warn};
 
 use crate::clients::{ClientResult, ProvidesClient, ProvidesFallibleClient};
-use crate::config::{BarPosition, CommonConfig, TransitionType};
+use crate::config::{BarPosition, CommonConfig, PopupTrigger, TransitionType};
 use crate::gtk_helpers::{IronbarGtkExt, WidgetGeometry};
 use crate::popup::Popup;
-use crate::{glib_recv_mpsc, send, Ironbar};
+use crate::{glib_recv, glib_recv_mpsc, rc_mut, send, Ironbar, FRAME_BUDGET};
 
+#[cfg(feature = "backlight")]
+pub mod backlight;
 #[cfg(feature = "cairo")]
 pub mod cairo;
 #[cfg(feature = "clipboard")]
@@ -29,8 +33,12 @@ pub mod clipboard;
 #[cfg(feature = "clock")]
 pub mod clock;
 pub mod custom;
+#[cfg(feature = "dbus")]
+pub mod dbus;
 #[cfg(feature = "focused")]
 pub mod focused;
+#[cfg(feature = "ipc")]
+pub mod ironvar;
 #[cfg(feature = "keys")]
 pub mod keys;
 pub mod label;
@@ -42,7 +50,15 @@ pub mod music;
 pub mod networkmanager;
 #[cfg(feature = "notifications")]
 pub mod notifications;
+#[cfg(feature = "output")]
+pub mod output;
+#[cfg(feature = "power_menu")]
+pub mod power_menu;
 pub mod script;
+#[cfg(feature = "separator")]
+pub mod separator;
+#[cfg(feature = "spacer")]
+pub mod spacer;
 #[cfg(feature = "sway")]
 pub mod sway;
 #[cfg(feature = "sys_info")]
@@ -53,6 +69,8 @@ pub mod tray;
 pub mod upower;
 #[cfg(feature = "volume")]
 pub mod volume;
+#[cfg(feature = "weather")]
+pub mod weather;
 #[cfg(feature = "workspaces")]
 pub mod workspaces;
 
@@ -87,6 +105,11 @@ pub enum ModuleUpdateEvent<T: Clone> {
     OpenPopupAt(WidgetGeometry),
     /// Force sets the popup closed.
     ClosePopup,
+    /// Marks the module as having loaded its initial data,
+    /// removing the `loading` class from its widget.
+    ///
+    /// This should be sent once by modules which set `REPORTS_READY`.
+    Ready,
 }
 
 pub struct WidgetContext<TSend, TReceive>
@@ -240,6 +263,13 @@ where
     type SendMessage;
     type ReceiveMessage;
 
+    /// Whether the module reports when its initial data has loaded.
+    ///
+    /// If set, the widget is given the `loading` class
+    /// until the controller sends [`ModuleUpdateEvent::Ready`].
+    /// This should be used by modules which depend on slow clients.
+    const REPORTS_READY: bool = false;
+
     fn name() -> &'static str;
 
     fn spawn_controller(
@@ -309,6 +339,8 @@ pub trait ModuleFactory {
             button_id: usize::MAX, // hack :(
         };
 
+        let start = Instant::now();
+
         module.spawn_controller(info, &context, controller_rx)?;
 
         let module_name = TModule::name();
@@ -318,23 +350,67 @@ pub trait ModuleFactory {
             .unwrap_or_else(|| module_name.to_string());
 
         let module_parts = module.into_widget(context, info)?;
+
+        // module init runs on the UI thread,
+        // so anything slow here delays the bar appearing.
+        let elapsed = start.elapsed();
+        if elapsed > FRAME_BUDGET {
+            warn!(
+                "Module {module_name} (id: {id}) blocked the UI thread for {elapsed:?} during init"
+            );
+        } else {
+            trace!("Module {module_name} (id: {id}) initialized in {elapsed:?}");
+        }
         module_parts.widget.add_class("widget");
         module_parts.widget.add_class(module_name);
 
+        if TModule::REPORTS_READY {
+            module_parts.widget.add_class("loading");
+        }
+
         if let Some(popup_content) = module_parts.popup.clone() {
             popup_content
                 .container
                 .style_context()
                 .add_class(&format!("popup-{module_name}"));
 
-            self.popup()
-                .register_content(id, instance_name, popup_content);
+            self.popup().register_content(
+                id,
+                instance_name,
+                popup_content,
+                common.keyboard_interactivity.into(),
+            );
         }
 
-        self.setup_receiver(tx, ui_rx, module_name, id, common.disable_popup);
+        let popup_trigger = (!common.disable_popup).then_some(common.popup_trigger);
+
+        let idle_hide = match (common.idle_hide_ms, &common.show_if) {
+            (Some(_), Some(_)) => {
+                warn!("`idle_hide_ms` has no effect on {module_name} as `show_if` is set");
+                None
+            }
+            (Some(ms), None) => Some((Duration::from_millis(ms), tx.subscribe())),
+            (None, _) => None,
+        };
+
+        self.setup_receiver(
+            tx,
+            ui_rx,
+            module_parts.widget.clone().upcast(),
+            module_name,
+            id,
+            popup_trigger,
+        );
 
         module_parts.setup_identifiers(&common);
 
+        let hover_button_id = module_parts
+            .popup
+            .as_ref()
+            .filter(|_| popup_trigger == Some(PopupTrigger::Hover))
+            .and_then(|popup| popup.buttons.first())
+            .map(PopupButton::popup_id);
+
         let ev_container = wrap_widget(
             &module_parts.widget,
             common,
@@ -342,16 +418,28 @@ pub trait ModuleFactory {
         );
         container.add(&ev_container);
 
+        if let Some((timeout, rx)) = idle_hide {
+            install_idle_hide(&ev_container, rx, timeout, info.bar_position.orientation());
+        }
+
+        if let Some(button_id) = hover_button_id {
+            self.popup().connect_hover(&ev_container, id, button_id);
+        }
+
         Ok(())
     }
 
+    /// Handles events sent from the module's UI.
+    ///
+    /// `popup_trigger` is `None` if the module's popup is disabled.
     fn setup_receiver<TSend>(
         &self,
         tx: broadcast::Sender<TSend>,
         rx: mpsc::Receiver<ModuleUpdateEvent<TSend>>,
+        widget: Widget,
         name: &'static str,
         id: usize,
-        disable_popup: bool,
+        popup_trigger: Option<PopupTrigger>,
     ) where
         TSend: Debug + Clone + Send + 'static;
 
@@ -376,9 +464,10 @@ impl ModuleFactory for BarModuleFactory {
         &self,
         tx: broadcast::Sender<TSend>,
         rx: mpsc::Receiver<ModuleUpdateEvent<TSend>>,
+        widget: Widget,
         name: &'static str,
         id: usize,
-        disable_popup: bool,
+        popup_trigger: Option<PopupTrigger>,
     ) where
         TSend: Debug + Clone + Send + 'static,
     {
@@ -388,30 +477,37 @@ impl ModuleFactory for BarModuleFactory {
                 ModuleUpdateEvent::Update(update) => {
                     send!(tx, update);
                 }
-                ModuleUpdateEvent::TogglePopup(button_id) if !disable_popup => {
+                ModuleUpdateEvent::TogglePopup(button_id) if popup_trigger.is_some() => {
                     debug!("Toggling popup for {} [#{}] (button id: {button_id})", name, id);
                     if popup.visible() && popup.current_widget().unwrap_or_default() == id {
-                        popup.hide();
+                        // hover popups are closed by moving the cursor away
+                        if popup_trigger == Some(PopupTrigger::Click) {
+                            popup.hide();
+                        }
                     } else {
                         popup.show(id, button_id);
                     }
                 }
-                ModuleUpdateEvent::OpenPopup(button_id) if !disable_popup => {
+                ModuleUpdateEvent::OpenPopup(button_id) if popup_trigger.is_some() => {
                     debug!("Opening popup for {} [#{}] (button id: {button_id})", name, id);
                     popup.hide();
                     popup.show(id, button_id);
                 }
                 #[cfg(feature = "launcher")]
-                ModuleUpdateEvent::OpenPopupAt(geometry) if !disable_popup => {
+                ModuleUpdateEvent::OpenPopupAt(geometry) if popup_trigger.is_some() => {
                     debug!("Opening popup for {} [#{}]", name, id);
 
                     popup.hide();
                     popup.show_at(id, geometry);
                 }
-                ModuleUpdateEvent::ClosePopup if !disable_popup => {
+                ModuleUpdateEvent::ClosePopup if popup_trigger.is_some() => {
                     debug!("Closing popup for {} [#{}]", name, id);
                     popup.hide();
                 },
+                ModuleUpdateEvent::Ready => {
+                    debug!("Module {} [#{}] is ready", name, id);
+                    widget.style_context().remove_class("loading");
+                }
                 _ => {}
             }
         });
@@ -448,9 +544,10 @@ impl ModuleFactory for PopupModuleFactory {
         &self,
         tx: broadcast::Sender<TSend>,
         rx: mpsc::Receiver<ModuleUpdateEvent<TSend>>,
+        widget: Widget,
         name: &'static str,
         id: usize,
-        disable_popup: bool,
+        popup_trigger: Option<PopupTrigger>,
     ) where
         TSend: Debug + Clone + Send + 'static,
     {
@@ -461,30 +558,37 @@ impl ModuleFactory for PopupModuleFactory {
                 ModuleUpdateEvent::Update(update) => {
                     send!(tx, update);
                 }
-                ModuleUpdateEvent::TogglePopup(_) if !disable_popup => {
+                ModuleUpdateEvent::TogglePopup(_) if popup_trigger.is_some() => {
                     debug!("Toggling popup for {} [#{}] (button id: {button_id})", name, id);
                     if popup.visible() && popup.current_widget().unwrap_or_default() == id {
-                        popup.hide();
+                        // hover popups are closed by moving the cursor away
+                        if popup_trigger == Some(PopupTrigger::Click) {
+                            popup.hide();
+                        }
                     } else {
                         popup.show(id, button_id);
                     }
                 }
-                ModuleUpdateEvent::OpenPopup(_) if !disable_popup => {
+                ModuleUpdateEvent::OpenPopup(_) if popup_trigger.is_some() => {
                     debug!("Opening popup for {} [#{}] (button id: {button_id})", name, id);
                     popup.hide();
                     popup.show(id, button_id);
                 }
                 #[cfg(feature = "launcher")]
-                ModuleUpdateEvent::OpenPopupAt(geometry) if !disable_popup => {
+                ModuleUpdateEvent::OpenPopupAt(geometry) if popup_trigger.is_some() => {
                     debug!("Opening popup for {} [#{}]", name, id);
 
                     popup.hide();
                     popup.show_at(id, geometry);
                 }
-                ModuleUpdateEvent::ClosePopup if !disable_popup => {
+                ModuleUpdateEvent::ClosePopup if popup_trigger.is_some() => {
                     debug!("Closing popup for {} [#{}]", name, id);
                     popup.hide();
                 },
+                ModuleUpdateEvent::Ready => {
+                    debug!("Module {} [#{}] is ready", name, id);
+                    widget.style_context().remove_class("loading");
+                }
                 _ => {}
             }
         });
@@ -510,15 +614,20 @@ impl ModuleFactory for AnyModuleFactory {
         &self,
         tx: broadcast::Sender<TSend>,
         rx: mpsc::Receiver<ModuleUpdateEvent<TSend>>,
+        widget: Widget,
         name: &'static str,
         id: usize,
-        disable_popup: bool,
+        popup_trigger: Option<PopupTrigger>,
     ) where
         TSend: Debug + Clone + Send + 'static,
     {
         match self {
-            AnyModuleFactory::Bar(bar) => bar.setup_receiver(tx, rx, name, id, disable_popup),
-            AnyModuleFactory::Popup(popup) => popup.setup_receiver(tx, rx, name, id, disable_popup),
+            AnyModuleFactory::Bar(bar) => {
+                bar.setup_receiver(tx, rx, widget, name, id, popup_trigger);
+            }
+            AnyModuleFactory::Popup(popup) => {
+                popup.setup_receiver(tx, rx, widget, name, id, popup_trigger);
+            }
         }
     }
 
@@ -549,6 +658,15 @@ impl From<PopupModuleFactory> for AnyModuleFactory {
     }
 }
 
+/// Tag on a `wrap_widget` container holding an `Rc<Cell<bool>>`,
+/// which is set while the module is hidden using `start_hidden` or IPC.
+///
+/// Idle-hide does not reveal the module while this is set.
+pub const FORCE_HIDDEN_TAG: &str = "force-hidden";
+
+/// Size in pixels of the area left to hover a module collapsed by idle-hide.
+const IDLE_HIT_AREA: i32 = 4;
+
 /// Takes a widget and adds it into a new `gtk::EventBox`.
 /// The event box container is returned.
 pub fn wrap_widget<W: IsA<Widget>>(
@@ -568,10 +686,11 @@ pub fn wrap_widget<W: IsA<Widget>>(
         .build();
 
     revealer.add(widget);
-    revealer.set_reveal_child(true);
+    revealer.set_reveal_child(!common.start_hidden);
 
     let container = EventBox::new();
     container.add_class("widget-container");
+    container.set_tag(FORCE_HIDDEN_TAG, Rc::new(Cell::new(common.start_hidden)));
 
     container.add_events(EventMask::SCROLL_MASK | EventMask::SMOOTH_SCROLL_MASK);
     container.add(&revealer);
@@ -580,3 +699,126 @@ pub fn wrap_widget<W: IsA<Widget>>(
 
     container
 }
+
+/// Collapses the revealer inside a `wrap_widget` container
+/// once `timeout` passes without an update on `rx` or the cursor hovering.
+///
+/// While collapsed, the container keeps a strip of `IDLE_HIT_AREA` pixels
+/// along the bar so that the cursor can still enter it.
+/// The revealer is shown again on the next update or when the cursor enters.
+fn install_idle_hide<T>(
+    container: &EventBox,
+    rx: broadcast::Receiver<T>,
+    timeout: Duration,
+    orientation: Orientation,
+) where
+    T: Clone + 'static,
+{
+    let Some(revealer) = container
+        .child()
+        .and_then(|child| child.downcast::<Revealer>().ok())
+    else {
+        return;
+    };
+
+    let pending = rc_mut!(None::<SourceId>);
+    let hovered = rc_mut!(false);
+
+    let force_hidden = container
+        .get_tag::<Rc<Cell<bool>>>(FORCE_HIDDEN_TAG)
+        .cloned()
+        .unwrap_or_default();
+
+    // drop the hit area whenever the module is shown,
+    // or hidden through IPC.
+    {
+        let container = container.clone();
+        let force_hidden = force_hidden.clone();
+
+        revealer.connect_reveal_child_notify(move |revealer| {
+            if revealer.reveals_child() || force_hidden.get() {
+                container.set_size_request(-1, -1);
+            }
+        });
+    }
+
+    let restart = {
+        let container = container.clone();
+        let revealer = revealer.clone();
+        let pending = pending.clone();
+
+        move || {
+            let source = {
+                let container = container.clone();
+                let revealer = revealer.clone();
+                let pending = pending.clone();
+
+                glib::timeout_add_local_once(timeout, move || {
+                    pending.borrow_mut().take();
+                    revealer.set_reveal_child(false);
+
+                    match orientation {
+                        Orientation::Horizontal => container.set_size_request(IDLE_HIT_AREA, -1),
+                        _ => container.set_size_request(-1, IDLE_HIT_AREA),
+                    }
+                })
+            };
+
+            if let Some(source) = pending.borrow_mut().replace(source) {
+                source.remove();
+            }
+        }
+    };
+
+    restart();
+
+    {
+        let revealer = revealer.clone();
+        let hovered = hovered.clone();
+        let restart = restart.clone();
+        let force_hidden = force_hidden.clone();
+
+        glib_recv!(rx, _ev => {
+            if !force_hidden.get() {
+                revealer.set_reveal_child(true);
+            }
+            if !*hovered.borrow() {
+                restart();
+            }
+        });
+    }
+
+    {
+        let pending = pending.clone();
+        let hovered = hovered.clone();
+
+        container.connect_enter_notify_event(move |_, _| {
+            *hovered.borrow_mut() = true;
+            if !force_hidden.get() {
+                revealer.set_reveal_child(true);
+            }
+
+            if let Some(source) = pending.borrow_mut().take() {
+                source.remove();
+            }
+
+            Propagation::Proceed
+        });
+    }
+
+    container.connect_leave_notify_event(move |_, event| {
+        // moving onto a child widget is still hovering the container
+        if event.detail() != NotifyType::Inferior {
+            *hovered.borrow_mut() = false;
+            restart();
+        }
+
+        Propagation::Proceed
+    });
+
+    container.connect_destroy(move |_| {
+        if let Some(source) = pending.borrow_mut().take() {
+            source.remove();
+        }
+    });
+}
@@ -1,4 +1,5 @@
 use crate::config::{CommonConfig, TruncateMode};
+use crate::image::ImageFit;
 use dirs::{audio_dir, home_dir};
 use serde::Deserialize;
 use std::path::PathBuf;
@@ -70,7 +71,7 @@ impl Default for Icons {
     }
 }
 
-#[derive(Debug, Deserialize, Clone, Copy)]
+#[derive(Debug, Deserialize, Clone, Copy, Eq, PartialEq)]
 #[serde(rename_all = "snake_case")]
 #[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub enum PlayerType {
@@ -84,6 +85,42 @@ impl Default for PlayerType {
     }
 }
 
+/// A playback control button in the popup.
+#[derive(Debug, Deserialize, Clone, Copy, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum PopupControl {
+    /// Skips to the previous track.
+    Prev,
+    /// Plays or pauses, depending on the player state.
+    PlayPause,
+    /// Skips to the next track.
+    Next,
+}
+
+/// The action to perform when the bar widget is clicked.
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(from = "String")]
+pub enum ClickAction {
+    /// Toggles the popup.
+    #[default]
+    Popup,
+    /// Raises the player window.
+    Raise,
+    /// Runs a shell command.
+    Command(String),
+}
+
+impl From<String> for ClickAction {
+    fn from(value: String) -> Self {
+        match value.as_str() {
+            "popup" => Self::Popup,
+            "raise" => Self::Raise,
+            _ => Self::Command(value),
+        }
+    }
+}
+
 #[derive(Debug, Deserialize, Clone)]
 #[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct MusicModule {
@@ -112,6 +149,32 @@ pub struct MusicModule {
     #[serde(default = "crate::config::default_true")]
     pub(crate) show_status_icon: bool,
 
+    /// The action to perform when the bar widget is clicked.
+    ///
+    /// This is one of `popup` to toggle the popup,
+    /// `raise` to bring the player window to the front,
+    /// or any other value to run it as a shell command.
+    ///
+    /// MPD has no player window, so `raise` toggles the popup instead.
+    ///
+    /// **Default**: `popup`
+    #[serde(default)]
+    #[cfg_attr(feature = "schema", schemars(with = "String"))]
+    pub(crate) click_action: ClickAction,
+
+    /// Whether right-clicking the bar widget toggles between playing and paused.
+    ///
+    /// **Default**: `false`
+    #[serde(default)]
+    pub(crate) right_click_play_pause: bool,
+
+    /// The percentage to change the player volume by
+    /// when scrolling over the bar widget.
+    /// Leave unset to disable scrolling.
+    ///
+    /// **Default**: `null`
+    pub(crate) scroll_volume_step: Option<u8>,
+
     /// Size to render the icons at, in pixels (image icons only).
     ///
     /// **Default** `32`
@@ -124,6 +187,45 @@ pub struct MusicModule {
     #[serde(default = "default_cover_image_size")]
     pub(crate) cover_image_size: i32,
 
+    /// How the album art is fitted to `cover_image_size`.
+    /// `contain` shows the whole image,
+    /// while `cover` fills the square and crops non-square art.
+    ///
+    /// **Valid options**: `contain`, `cover`
+    /// <br>
+    /// **Default**: `contain`
+    #[serde(default)]
+    pub(crate) cover_fit: ImageFit,
+
+    /// The playback controls to show in the popup, in order.
+    /// Controls not in the list are not shown,
+    /// and each control is only shown once.
+    ///
+    /// **Valid options**: `prev`, `play_pause`, `next`
+    /// <br>
+    /// **Default**: `["prev", "play_pause", "next"]`
+    #[serde(default = "default_controls")]
+    pub(crate) controls: Vec<PopupControl>,
+
+    /// Whether to show the volume slider in the popup.
+    ///
+    /// **Default**: `true`
+    #[serde(default = "crate::config::default_true")]
+    pub(crate) show_volume: bool,
+
+    /// Whether to show the track progress bar in the popup.
+    ///
+    /// **Default**: `true`
+    #[serde(default = "crate::config::default_true")]
+    pub(crate) show_progress: bool,
+
+    /// The number of seconds to seek by
+    /// when scrolling over the progress bar inside the popup.
+    ///
+    /// **Default**: `5`
+    #[serde(default = "default_seek_step_seconds")]
+    pub(crate) seek_step_seconds: f64,
+
     // -- MPD --
     /// *[MPD Only]*
     /// TCP or Unix socket address of the MPD server.
@@ -141,6 +243,35 @@ pub struct MusicModule {
     #[serde(default = "default_music_dir")]
     pub(crate) music_dir: PathBuf,
 
+    /// *[MPD Only]*
+    /// Maximum number of seconds to wait between reconnection attempts
+    /// after losing connection to the MPD server.
+    ///
+    /// The wait starts at one second and doubles after each failed attempt.
+    /// Values below one second are treated as one second.
+    ///
+    /// **Default**: `30`
+    #[serde(default = "default_max_reconnect_delay")]
+    pub(crate) max_reconnect_delay: u64,
+
+    // -- MPRIS --
+    /// *[MPRIS Only]*
+    /// Players to allow, by identity or D-Bus name.
+    /// Patterns can use `*` and `?` wildcards, and are not case-sensitive.
+    /// If empty, all players not in `player_deny` are allowed.
+    ///
+    /// **Default**: `[]`
+    #[serde(default)]
+    pub(crate) player_allow: Vec<String>,
+
+    /// *[MPRIS Only]*
+    /// Players to ignore, by identity or D-Bus name.
+    /// Patterns can use `*` and `?` wildcards, and are not case-sensitive.
+    ///
+    /// **Default**: `[]`
+    #[serde(default)]
+    pub(crate) player_deny: Vec<String>,
+
     // -- Common --
     /// See [truncate options](module-level-options#truncate-mode).
     ///
@@ -156,6 +287,10 @@ fn default_socket() -> String {
     String::from("localhost:6600")
 }
 
+const fn default_max_reconnect_delay() -> u64 {
+    30
+}
+
 fn default_format() -> String {
     String::from("{title} / {artist}")
 }
@@ -203,3 +338,15 @@ const fn default_icon_size() -> i32 {
 const fn default_cover_image_size() -> i32 {
     128
 }
+
+fn default_controls() -> Vec<PopupControl> {
+    vec![
+        PopupControl::Prev,
+        PopupControl::PlayPause,
+        PopupControl::Next,
+    ]
+}
+
+const fn default_seek_step_seconds() -> f64 {
+    5.0
+}
@@ -1,31 +1,32 @@
 use std::cell::RefMut;
-use std::path::PathBuf;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
 
 use color_eyre::Result;
 use glib::{Propagation, PropertySet};
+use gtk::gdk::ScrollDirection;
 use gtk::prelude::*;
 use gtk::{Button, IconTheme, Label, Orientation, Scale};
-use regex::Regex;
 use tokio::sync::{broadcast, mpsc};
-use tracing::error;
+use tracing::{error, warn};
 
 use crate::clients::music::{
     self, MusicClient, PlayerState, PlayerUpdate, ProgressTick, Status, Track,
 };
 use crate::clients::Clients;
+use crate::format;
 use crate::gtk_helpers::{IronbarGtkExt, IronbarLabelExt};
-use crate::image::{new_icon_button, IconLabel, ImageProvider};
+use crate::image::{new_icon_button, IconLabel, ImageProvider, LoadGuard};
 use crate::modules::PopupButton;
 use crate::modules::{
     Module, ModuleInfo, ModuleParts, ModulePopup, ModuleUpdateEvent, WidgetContext,
 };
-use crate::{glib_recv, module_impl, send_async, spawn, try_send};
+use crate::script::Script;
+use crate::{glib_recv, module_impl, rc_mut, send_async, spawn, try_send};
 
 pub use self::config::MusicModule;
-use self::config::PlayerType;
+use self::config::{ClickAction, PlayerType, PopupControl};
 
 mod config;
 
@@ -37,6 +38,7 @@ pub enum PlayerCommand {
     Next,
     Volume(u8),
     Seek(Duration),
+    Raise,
 }
 
 /// Formats a duration given in seconds
@@ -49,13 +51,6 @@ fn format_time(duration: Duration) -> String {
     format!("{minutes:0>2}:{seconds:0>2}")
 }
 
-/// Extracts the formatting tokens from a formatting string
-fn get_tokens(re: &Regex, format_string: &str) -> Vec<String> {
-    re.captures_iter(format_string)
-        .map(|caps| caps[1].to_string())
-        .collect::<Vec<_>>()
-}
-
 #[derive(Clone, Debug)]
 pub enum ControllerEvent {
     Update(Option<SongUpdate>),
@@ -69,15 +64,19 @@ pub struct SongUpdate {
     display_string: String,
 }
 
-fn get_client(
-    mut clients: RefMut<'_, Clients>,
-    player_type: PlayerType,
-    host: String,
-    music_dir: PathBuf,
-) -> Arc<dyn MusicClient> {
-    let client_type = match player_type {
-        PlayerType::Mpd => music::ClientType::Mpd { host, music_dir },
-        PlayerType::Mpris => music::ClientType::Mpris,
+fn get_client(mut clients: RefMut<'_, Clients>, config: &MusicModule) -> Arc<dyn MusicClient> {
+    let client_type = match config.player_type {
+        PlayerType::Mpd => music::ClientType::Mpd {
+            host: config.host.clone(),
+            music_dir: config.music_dir.clone(),
+            max_reconnect_delay: Duration::from_secs(config.max_reconnect_delay),
+        },
+        PlayerType::Mpris => music::ClientType::Mpris {
+            filter: music::PlayerFilter {
+                allow: config.player_allow.clone(),
+                deny: config.player_deny.clone(),
+            },
+        },
     };
 
     clients.music(client_type)
@@ -97,15 +96,7 @@ impl Module<Button> for MusicModule {
     ) -> Result<()> {
         let format = self.format.clone();
 
-        let re = Regex::new(r"\{([\w-]+)}")?;
-        let tokens = get_tokens(&re, self.format.as_str());
-
-        let client = get_client(
-            context.ironbar.clients.borrow_mut(),
-            self.player_type,
-            self.host.clone(),
-            self.music_dir.clone(),
-        );
+        let client = get_client(context.ironbar.clients.borrow_mut(), self);
 
         // receive player updates
         {
@@ -120,8 +111,7 @@ impl Module<Button> for MusicModule {
                         match update {
                             PlayerUpdate::Update(track, status) => match *track {
                                 Some(track) => {
-                                    let display_string =
-                                        replace_tokens(format.as_str(), &tokens, &track);
+                                    let display_string = replace_tokens(format.as_str(), &track);
 
                                     let update = SongUpdate {
                                         song: track,
@@ -164,6 +154,7 @@ impl Module<Button> for MusicModule {
                         PlayerCommand::Next => client.next(),
                         PlayerCommand::Volume(vol) => client.set_volume_percent(vol),
                         PlayerCommand::Seek(duration) => client.seek(duration),
+                        PlayerCommand::Raise => client.raise(),
                     };
 
                     if let Err(err) = res {
@@ -205,9 +196,89 @@ impl Module<Button> for MusicModule {
 
         {
             let tx = context.tx.clone();
+            let controller_tx = context.controller_tx.clone();
+
+            // MPD has no player window to raise
+            let click_action = match self.click_action.clone() {
+                ClickAction::Raise if self.player_type == PlayerType::Mpd => ClickAction::Popup,
+                click_action => click_action,
+            };
 
-            button.connect_clicked(move |button| {
-                try_send!(tx, ModuleUpdateEvent::TogglePopup(button.popup_id()));
+            button.connect_clicked(move |button| match &click_action {
+                ClickAction::Popup => {
+                    try_send!(tx, ModuleUpdateEvent::TogglePopup(button.popup_id()));
+                }
+                ClickAction::Raise => try_send!(controller_tx, PlayerCommand::Raise),
+                ClickAction::Command(cmd) => {
+                    let script = Script::from(cmd.as_str());
+                    spawn(async move {
+                        if let Err(err) = script.get_output(None).await {
+                            error!("{err:?}");
+                        }
+                    });
+                }
+            });
+        }
+
+        // last known player status, used to toggle playback and step the volume
+        let status = rc_mut!(Status::default());
+
+        if self.right_click_play_pause {
+            let tx = context.controller_tx.clone();
+            let status = status.clone();
+
+            button.connect_button_press_event(move |_, event| {
+                if event.button() != 3 {
+                    return Propagation::Proceed;
+                }
+
+                let command = match status.borrow().state {
+                    PlayerState::Playing => PlayerCommand::Pause,
+                    PlayerState::Paused | PlayerState::Stopped => PlayerCommand::Play,
+                };
+
+                try_send!(tx, command);
+
+                // allow `on_click_right` to run as well
+                Propagation::Proceed
+            });
+        }
+
+        if let Some(step) = self.scroll_volume_step {
+            let tx = context.controller_tx.clone();
+            let status = status.clone();
+
+            button.connect_scroll_event(move |_, event| {
+                let up = match event.direction() {
+                    ScrollDirection::Up => true,
+                    ScrollDirection::Down => false,
+                    ScrollDirection::Smooth => {
+                        let dy = event.scroll_deltas().unwrap_or_default().1;
+                        if dy == 0.0 {
+                            return Propagation::Proceed;
+                        }
+
+                        dy < 0.0
+                    }
+                    _ => return Propagation::Proceed,
+                };
+
+                let mut status = status.borrow_mut();
+                let Some(volume) = status.volume_percent else {
+                    return Propagation::Proceed;
+                };
+
+                let volume = if up {
+                    volume.saturating_add(step).min(100)
+                } else {
+                    volume.saturating_sub(step)
+                };
+
+                // update immediately so fast scrolling does not reuse a stale value
+                status.volume_percent = Some(volume);
+
+                try_send!(tx, PlayerCommand::Volume(volume));
+                Propagation::Stop
             });
         }
 
@@ -223,6 +294,8 @@ impl Module<Button> for MusicModule {
                 };
 
                 if let Some(event) = event.take() {
+                    *status.borrow_mut() = event.status;
+
                     label.set_label_escaped(&event.display_string);
 
                     button.show();
@@ -247,6 +320,8 @@ impl Module<Button> for MusicModule {
                         icon_play.hide();
                     }
                 } else {
+                    *status.borrow_mut() = Status::default();
+
                     button.hide();
                     try_send!(tx, ModuleUpdateEvent::ClosePopup);
                 }
@@ -273,9 +348,11 @@ impl Module<Button> for MusicModule {
         let container = gtk::Box::new(Orientation::Vertical, 10);
         let main_container = gtk::Box::new(Orientation::Horizontal, 10);
 
+        // the size is reserved even when art is smaller,
+        // so the popup does not resize between tracks.
         let album_image = gtk::Image::builder()
-            .width_request(128)
-            .height_request(128)
+            .width_request(self.cover_image_size)
+            .height_request(self.cover_image_size)
             .build();
         album_image.add_class("album-art");
 
@@ -309,12 +386,31 @@ impl Module<Button> for MusicModule {
         let btn_next = new_icon_button(&icons.next, icon_theme, self.icon_size);
         btn_next.add_class("btn-next");
 
-        controls_box.add(&btn_prev);
-        controls_box.add(&btn_play);
-        controls_box.add(&btn_pause);
-        controls_box.add(&btn_next);
+        // hidden controls are still created, but never added,
+        // so they can be updated without checking the config.
+        // a widget can only have one parent, so duplicates are skipped.
+        let mut added = Vec::with_capacity(self.controls.len());
+        for control in &self.controls {
+            if added.contains(control) {
+                warn!("Ignoring duplicate music control '{control:?}'");
+                continue;
+            }
+
+            added.push(*control);
+
+            match control {
+                PopupControl::Prev => controls_box.add(&btn_prev),
+                PopupControl::PlayPause => {
+                    controls_box.add(&btn_play);
+                    controls_box.add(&btn_pause);
+                }
+                PopupControl::Next => controls_box.add(&btn_next),
+            }
+        }
 
-        info_box.add(&controls_box);
+        if !self.controls.is_empty() {
+            info_box.add(&controls_box);
+        }
 
         let volume_box = gtk::Box::new(Orientation::Vertical, 5);
         volume_box.add_class("volume");
@@ -331,7 +427,9 @@ impl Module<Button> for MusicModule {
 
         main_container.add(&album_image);
         main_container.add(&info_box);
-        main_container.add(&volume_box);
+        if self.show_volume {
+            main_container.add(&volume_box);
+        }
         container.add(&main_container);
 
         let tx_prev = tx.clone();
@@ -375,7 +473,9 @@ impl Module<Button> for MusicModule {
 
         progress_box.add(&progress);
         progress_box.add(&progress_label);
-        container.add(&progress_box);
+        if self.show_progress {
+            container.add(&progress_box);
+        }
 
         let drag_lock = Arc::new(AtomicBool::new(false));
         {
@@ -386,6 +486,40 @@ impl Module<Button> for MusicModule {
             });
         }
 
+        {
+            let tx = tx.clone();
+            let drag_lock = drag_lock.clone();
+            let step = self.seek_step_seconds;
+
+            progress.connect_scroll_event(move |scale, event| {
+                if drag_lock.load(Ordering::Relaxed) {
+                    return Propagation::Stop;
+                }
+
+                let step = match event.direction() {
+                    ScrollDirection::Up | ScrollDirection::Right => step,
+                    ScrollDirection::Down | ScrollDirection::Left => -step,
+                    ScrollDirection::Smooth => {
+                        let (dx, dy) = event.scroll_deltas().unwrap_or_default();
+                        if dy > 0.0 || dx < 0.0 {
+                            -step
+                        } else {
+                            step
+                        }
+                    }
+                    _ => return Propagation::Proceed,
+                };
+
+                // the range upper bound is the track duration
+                let value = (scale.value() + step).clamp(0.0, scale.adjustment().upper());
+                scale.set_value(value);
+                try_send!(tx, PlayerCommand::Seek(Duration::from_secs_f64(value)));
+
+                // stop GTK moving the slider itself
+                Propagation::Stop
+            });
+        }
+
         {
             let drag_lock = drag_lock.clone();
             progress.connect_button_release_event(move |scale, _| {
@@ -402,20 +536,31 @@ impl Module<Button> for MusicModule {
         {
             let icon_theme = icon_theme.clone();
             let image_size = self.cover_image_size;
+            let image_fit = self.cover_fit;
 
             let mut prev_cover = None;
+            let cover_guard = LoadGuard::default();
+
             glib_recv!(rx, event =>  {
                 match event {
                     ControllerEvent::Update(Some(update)) => {
-                        // only update art when album changes
+                        // only update art when album changes.
+                        // players may re-emit identical metadata,
+                        // which must not trigger a reload.
                         let new_cover = update.song.cover_path;
                         if prev_cover != new_cover {
                             prev_cover.clone_from(&new_cover);
+
+                            // invalidate any in-progress load,
+                            // so art for a previous track cannot overwrite this one.
+                            let token = cover_guard.next();
+
                             let res = if let Some(image) = new_cover.and_then(|cover_path| {
                                 ImageProvider::parse(&cover_path, &icon_theme, false, image_size)
+                                    .map(|image| image.with_fit(image_fit))
                             }) {
                                 album_image.show();
-                                image.load_into_image(&album_image)
+                                image.load_into_image_guarded(&album_image, token)
                             } else {
                                 album_image.set_from_pixbuf(None);
                                 album_image.hide();
@@ -510,19 +655,16 @@ fn update_popup_metadata_label(text: Option<String>, label: &IconPrefixedLabel)
 
 /// Replaces each of the formatting tokens in the formatting string
 /// with actual data pulled from the music player
-fn replace_tokens(format_string: &str, tokens: &Vec<String>, song: &Track) -> String {
-    let mut compiled_string = format_string.to_string();
-    for token in tokens {
-        let value = get_token_value(song, token);
-        compiled_string = compiled_string.replace(format!("{{{token}}}").as_str(), value.as_str());
-    }
-    compiled_string
+fn replace_tokens(format_string: &str, song: &Track) -> String {
+    format::replace_tokens(format_string, |token| get_token_value(song, token))
 }
 
 /// Converts a string format token value
 /// into its respective value.
-fn get_token_value(song: &Track, token: &str) -> String {
-    match token {
+///
+/// Returns `None` for unknown tokens.
+fn get_token_value(song: &Track, token: &str) -> Option<String> {
+    let value = match token {
         "title" => song.title.clone(),
         "album" => song.album.clone(),
         "artist" => song.artist.clone(),
@@ -530,9 +672,14 @@ fn get_token_value(song: &Track, token: &str) -> String {
         "disc" => song.disc.map(|x| x.to_string()),
         "genre" => song.genre.clone(),
         "track" => song.track.map(|x| x.to_string()),
-        _ => Some(token.to_string()),
-    }
-    .unwrap_or_default()
+        "length" => song.length.map(format_time),
+        "url" => song.url.clone(),
+        "file" => song.file.clone(),
+        "album_artist" => song.album_artist.clone(),
+        _ => return None,
+    };
+
+    Some(value.unwrap_or_default())
 }
 
 #[derive(Clone, Debug)]
@@ -1,24 +1,61 @@
 use color_eyre::Result;
 use futures_lite::StreamExt;
 use futures_signals::signal::SignalExt;
-use gtk::prelude::ContainerExt;
-use gtk::{Box as GtkBox, Image};
+use gtk::prelude::*;
+use gtk::{Box as GtkBox, Button, Image, Label, Orientation};
 use serde::Deserialize;
-use tokio::sync::mpsc::Receiver;
+use tokio::sync::{broadcast, mpsc};
 
-use crate::clients::networkmanager::{Client, ClientState};
-use crate::config::CommonConfig;
-use crate::gtk_helpers::IronbarGtkExt;
+use crate::clients::networkmanager::{Client, ClientState, Connectivity, NetworkState};
+use crate::config::{CommonConfig, IconRamp};
+use crate::format;
+use crate::gtk_helpers::{IronbarGtkExt, IronbarLabelExt};
 use crate::image::ImageProvider;
-use crate::modules::{Module, ModuleInfo, ModuleParts, ModuleUpdateEvent, WidgetContext};
-use crate::{glib_recv, module_impl, send_async, spawn};
+use crate::modules::{
+    Module, ModuleInfo, ModuleParts, ModulePopup, ModuleUpdateEvent, PopupButton, WidgetContext,
+};
+use crate::{glib_recv, module_impl, send_async, spawn, try_send};
 
 #[derive(Debug, Deserialize, Clone)]
 #[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct NetworkManagerModule {
+    /// The size to render the icon at, in pixels.
+    ///
+    /// **Default**: `24`
     #[serde(default = "default_icon_size")]
     icon_size: i32,
 
+    /// Format strings for the label, per connection type.
+    /// See [below](#formatting-tokens) for available tokens.
+    #[serde(default)]
+    format: Formats,
+
+    /// Icons to use for the `{strength_icon}` token,
+    /// from weakest to strongest wifi signal.
+    ///
+    /// See [icon ramps](formatting-tokens#icon-ramps).
+    ///
+    /// **Default**: `null`
+    #[serde(default)]
+    strength_icons: Option<IconRamp>,
+
+    /// Whether to reflect NetworkManager's internet connectivity check.
+    /// When enabled, a `connectivity-<state>` class is added to the widget,
+    /// and the icon indicates when a connection has no internet access.
+    ///
+    /// **Default**: `false`
+    #[serde(default = "crate::config::default_false")]
+    show_connectivity: bool,
+
+    /// Whether to only show the icon on the bar.
+    /// Clicking the icon opens a popup containing the label instead.
+    ///
+    /// In this mode, the wifi icon also reflects the signal strength.
+    ///
+    /// **Default**: `false`
+    #[serde(default = "crate::config::default_false")]
+    compact: bool,
+
     #[serde(flatten)]
     pub common: Option<CommonConfig>,
 }
@@ -27,8 +64,47 @@ const fn default_icon_size() -> i32 {
     24
 }
 
+#[derive(Debug, Deserialize, Clone, Default)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Formats {
+    /// Format string to use for ethernet connections,
+    /// and other non-wireless connection types.
+    /// The label is hidden if empty.
+    ///
+    /// **Default**: `""`
+    #[serde(default)]
+    ethernet: String,
+
+    /// Format string to use for wifi connections.
+    /// The label is hidden if empty.
+    ///
+    /// **Default**: `""`
+    #[serde(default)]
+    wifi_connected: String,
+
+    /// Format string to use when disconnected.
+    /// The label is hidden if empty.
+    ///
+    /// **Default**: `""`
+    #[serde(default)]
+    disconnected: String,
+}
+
+impl Formats {
+    fn get(&self, state: &ClientState) -> &str {
+        match state {
+            ClientState::WifiConnected => &self.wifi_connected,
+            ClientState::WifiDisconnected | ClientState::Offline => &self.disconnected,
+            ClientState::WiredConnected
+            | ClientState::CellularConnected
+            | ClientState::VpnConnected
+            | ClientState::Unknown => &self.ethernet,
+        }
+    }
+}
+
 impl Module<GtkBox> for NetworkManagerModule {
-    type SendMessage = ClientState;
+    type SendMessage = NetworkState;
     type ReceiveMessage = ();
 
     module_impl!("network_manager");
@@ -36,10 +112,14 @@ impl Module<GtkBox> for NetworkManagerModule {
     fn spawn_controller(
         &self,
         _: &ModuleInfo,
-        context: &WidgetContext<ClientState, ()>,
-        _: Receiver<()>,
+        context: &WidgetContext<NetworkState, ()>,
+        _: mpsc::Receiver<()>,
     ) -> Result<()> {
         let client = context.try_client::<Client>()?;
+        if self.show_connectivity {
+            client.enable_connectivity();
+        }
+
         let mut client_signal = client.subscribe().to_stream();
         let widget_transmitter = context.tx.clone();
 
@@ -54,13 +134,37 @@ impl Module<GtkBox> for NetworkManagerModule {
 
     fn into_widget(
         self,
-        context: WidgetContext<ClientState, ()>,
+        context: WidgetContext<NetworkState, ()>,
         info: &ModuleInfo,
     ) -> Result<ModuleParts<GtkBox>> {
         let container = GtkBox::new(info.bar_position.orientation(), 0);
         let icon = Image::new();
         icon.add_class("icon");
-        container.add(&icon);
+
+        // in compact mode, the label is shown in the popup instead
+        let button = if self.compact {
+            let button = Button::new();
+            button.add(&icon);
+            container.add(&button);
+
+            let tx = context.tx.clone();
+            button.connect_clicked(move |button| {
+                try_send!(tx, ModuleUpdateEvent::TogglePopup(button.popup_id()));
+            });
+
+            Some(button)
+        } else {
+            container.add(&icon);
+            None
+        };
+
+        let label = (!self.compact).then(|| {
+            let label = Label::builder().use_markup(true).build();
+            label.add_class("label");
+            label.set_visible(false);
+            container.add(&label);
+            label
+        });
 
         let icon_theme = info.icon_theme.clone();
 
@@ -68,21 +172,143 @@ impl Module<GtkBox> for NetworkManagerModule {
         ImageProvider::parse(initial_icon_name, &icon_theme, false, self.icon_size)
             .map(|provider| provider.load_into_image(&icon));
 
-        let widget_receiver = context.subscribe();
-        glib_recv!(widget_receiver, state => {
-            let icon_name = match state {
-                ClientState::WiredConnected => "network-wired-symbolic",
-                ClientState::WifiConnected => "network-wireless-symbolic",
-                ClientState::CellularConnected => "network-cellular-symbolic",
-                ClientState::VpnConnected => "network-vpn-symbolic",
-                ClientState::WifiDisconnected => "network-wireless-acquiring-symbolic",
-                ClientState::Offline => "network-wireless-disabled-symbolic",
-                ClientState::Unknown => "dialog-question-symbolic",
-            };
-            ImageProvider::parse(icon_name, &icon_theme, false, self.icon_size)
-                .map(|provider| provider.load_into_image(&icon));
+        {
+            let container = container.clone();
+
+            let icon_size = self.icon_size;
+            let show_connectivity = self.show_connectivity;
+            let compact = self.compact;
+            let format = self.format.clone();
+            let strength_icons = self.strength_icons.clone();
+            let wifi_icons = wifi_strength_icons();
+
+            let mut connectivity_class = None::<String>;
+
+            let widget_receiver = context.subscribe();
+            glib_recv!(widget_receiver, network => {
+                let has_internet = !show_connectivity
+                    || matches!(network.connectivity, Connectivity::Full | Connectivity::Unknown);
+
+                let icon_name = match network.state {
+                    ClientState::WiredConnected if !has_internet => "network-wired-no-route-symbolic",
+                    ClientState::WifiConnected if !has_internet => "network-wireless-no-route-symbolic",
+                    ClientState::WiredConnected => "network-wired-symbolic",
+                    ClientState::WifiConnected if compact => network.strength.map_or(
+                        "network-wireless-symbolic",
+                        |strength| wifi_icons.get(f64::from(strength)),
+                    ),
+                    ClientState::WifiConnected => "network-wireless-symbolic",
+                    ClientState::CellularConnected => "network-cellular-symbolic",
+                    ClientState::VpnConnected => "network-vpn-symbolic",
+                    ClientState::WifiDisconnected => "network-wireless-acquiring-symbolic",
+                    ClientState::Offline => "network-wireless-disabled-symbolic",
+                    ClientState::Unknown => "dialog-question-symbolic",
+                };
+                ImageProvider::parse(icon_name, &icon_theme, false, icon_size)
+                    .map(|provider| provider.load_into_image(&icon));
+
+                if show_connectivity {
+                    let class = format!("connectivity-{}", network.connectivity.as_str());
+                    if connectivity_class.as_ref() != Some(&class) {
+                        if let Some(old_class) = connectivity_class.replace(class.clone()) {
+                            container.remove_class(&old_class);
+                        }
+                        container.add_class(&class);
+                    }
+                }
+
+                if let Some(label) = &label {
+                    let format = format.get(&network.state);
+                    label.set_visible(!format.is_empty());
+                    let text = format_network(format, &network, strength_icons.as_ref());
+                    label.set_label_escaped(&text);
+                }
+            });
+        }
+
+        let popup = match button {
+            Some(button) => self
+                .into_popup(
+                    context.controller_tx.clone(),
+                    context.subscribe(),
+                    context,
+                    info,
+                )
+                .into_popup_parts(vec![&button]),
+            None => None,
+        };
+
+        Ok(ModuleParts::new(container, popup))
+    }
+
+    fn into_popup(
+        self,
+        _tx: mpsc::Sender<Self::ReceiveMessage>,
+        rx: broadcast::Receiver<Self::SendMessage>,
+        _context: WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
+        _info: &ModuleInfo,
+    ) -> Option<GtkBox>
+    where
+        Self: Sized,
+    {
+        let container = GtkBox::new(Orientation::Vertical, 0);
+
+        let label = Label::builder().use_markup(true).build();
+        label.add_class("label");
+        container.add(&label);
+
+        glib_recv!(rx, network => {
+            let format = self.format.get(&network.state);
+            let text = format_network(format, &network, self.strength_icons.as_ref());
+            label.set_label_escaped(&text);
         });
 
-        Ok(ModuleParts::new(container, None))
+        container.show_all();
+
+        Some(container)
     }
 }
+
+/// The wifi icons used for the signal strength in compact mode,
+/// from weakest to strongest.
+fn wifi_strength_icons() -> IconRamp {
+    IconRamp::Ramp(
+        [
+            "network-wireless-signal-none-symbolic",
+            "network-wireless-signal-weak-symbolic",
+            "network-wireless-signal-ok-symbolic",
+            "network-wireless-signal-good-symbolic",
+            "network-wireless-signal-excellent-symbolic",
+        ]
+        .map(String::from)
+        .to_vec(),
+    )
+}
+
+/// Replaces the formatting tokens with the connection's details.
+/// Tokens for details which are not available are replaced with an empty string.
+fn format_network(
+    format: &str,
+    network: &NetworkState,
+    strength_icons: Option<&IconRamp>,
+) -> String {
+    let strength_icon = network
+        .strength
+        .zip(strength_icons)
+        .map(|(strength, icons)| icons.get(f64::from(strength)))
+        .unwrap_or_default();
+
+    format::replace_tokens(format, |token| match token {
+        "ssid" => Some(network.ssid.clone().unwrap_or_default()),
+        "strength" => Some(
+            network
+                .strength
+                .map(|strength| strength.to_string())
+                .unwrap_or_default(),
+        ),
+        "strength_icon" => Some(strength_icon.to_string()),
+        "ip" | "ipv4" => Some(network.ipv4.clone().unwrap_or_default()),
+        "ipv6" => Some(network.ipv6.clone().unwrap_or_default()),
+        _ => None,
+    })
+}
@@ -1,4 +1,4 @@
-use crate::clients::swaync;
+use crate::clients::{swaync, LazyClient};
 use crate::config::CommonConfig;
 use crate::gtk_helpers::IronbarGtkExt;
 use crate::modules::{Module, ModuleInfo, ModuleParts, ModuleUpdateEvent, WidgetContext};
@@ -131,6 +131,8 @@ impl Module<Overlay> for NotificationsModule {
     type SendMessage = swaync::Event;
     type ReceiveMessage = UiEvent;
 
+    const REPORTS_READY: bool = true;
+
     module_impl!("notifications");
 
     fn spawn_controller(
@@ -142,28 +144,34 @@ impl Module<Overlay> for NotificationsModule {
     where
         <Self as Module<Overlay>>::SendMessage: Clone,
     {
-        let client = context.try_client::<swaync::Client>()?;
+        let client = context.client::<LazyClient<swaync::Client>>();
+        let tx = context.tx.clone();
 
-        {
-            let client = client.clone();
-            let mut rx = client.subscribe();
-            let tx = context.tx.clone();
+        spawn(async move {
+            let Some(client) = client.ready().await else {
+                return;
+            };
 
-            spawn(async move {
-                let initial_state = client.state().await;
+            {
+                let client = client.clone();
+                let mut rx = client.subscribe();
 
-                match initial_state {
-                    Ok(ev) => send_async!(tx, ModuleUpdateEvent::Update(ev)),
-                    Err(err) => error!("{err:?}"),
-                };
+                spawn(async move {
+                    let initial_state = client.state().await;
 
-                while let Ok(ev) = rx.recv().await {
-                    send_async!(tx, ModuleUpdateEvent::Update(ev));
-                }
-            });
-        }
+                    match initial_state {
+                        Ok(ev) => send_async!(tx, ModuleUpdateEvent::Update(ev)),
+                        Err(err) => error!("{err:?}"),
+                    };
+
+                    send_async!(tx, ModuleUpdateEvent::Ready);
+
+                    while let Ok(ev) = rx.recv().await {
+                        send_async!(tx, ModuleUpdateEvent::Update(ev));
+                    }
+                });
+            }
 
-        spawn(async move {
             while let Some(event) = rx.recv().await {
                 match event {
                     UiEvent::ToggleVisibility => client.toggle_visibility().await,
@@ -0,0 +1,114 @@
+use crate::clients::wayland::{self, OutputEventType};
+use crate::config::CommonConfig;
+use crate::format;
+use crate::gtk_helpers::IronbarLabelExt;
+use crate::modules::{Module, ModuleInfo, ModuleParts, ModuleUpdateEvent, WidgetContext};
+use crate::{glib_recv, module_impl, send_async, spawn};
+use color_eyre::Result;
+use gtk::Label;
+use serde::Deserialize;
+use smithay_client_toolkit::output::OutputInfo;
+use tokio::sync::mpsc;
+
+#[derive(Debug, Deserialize, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct OutputModule {
+    /// The format string to use for the label.
+    /// For available tokens, see [below](#formatting-tokens).
+    ///
+    /// **Default**: `{resolution}@{refresh}Hz`
+    #[serde(default = "default_format")]
+    format: String,
+
+    /// See [common options](module-level-options#common-options).
+    #[serde(flatten)]
+    pub common: Option<CommonConfig>,
+}
+
+fn default_format() -> String {
+    String::from("{resolution}@{refresh}Hz")
+}
+
+impl Module<Label> for OutputModule {
+    type SendMessage = OutputInfo;
+    type ReceiveMessage = ();
+
+    module_impl!("output");
+
+    fn spawn_controller(
+        &self,
+        info: &ModuleInfo,
+        context: &WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
+        _rx: mpsc::Receiver<Self::ReceiveMessage>,
+    ) -> Result<()> {
+        let tx = context.tx.clone();
+        let wl = context.client::<wayland::Client>();
+        let output_name = info.output_name.to_string();
+
+        spawn(async move {
+            // subscribe before fetching the initial state to avoid missing updates
+            let mut rx = wl.subscribe_outputs();
+
+            let output = wl
+                .output_info_all()
+                .into_iter()
+                .find(|output| output.name.as_ref() == Some(&output_name));
+
+            if let Some(output) = output {
+                send_async!(tx, ModuleUpdateEvent::Update(output));
+            }
+
+            while let Ok(event) = rx.recv().await {
+                if event.event_type != OutputEventType::Destroyed
+                    && event.output.name.as_ref() == Some(&output_name)
+                {
+                    send_async!(tx, ModuleUpdateEvent::Update(event.output));
+                }
+            }
+        });
+
+        Ok(())
+    }
+
+    fn into_widget(
+        self,
+        context: WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
+        _info: &ModuleInfo,
+    ) -> Result<ModuleParts<Label>> {
+        let label = Label::builder().use_markup(true).build();
+
+        {
+            let label = label.clone();
+            glib_recv!(context.subscribe(), output => {
+                label.set_label_escaped(&format_output(&self.format, &output));
+            });
+        }
+
+        Ok(ModuleParts {
+            widget: label,
+            popup: None,
+        })
+    }
+}
+
+/// Replaces the formatting tokens with the output's details.
+fn format_output(format: &str, output: &OutputInfo) -> String {
+    let mode = output.modes.iter().find(|mode| mode.current);
+
+    let resolution = mode.map_or_else(String::new, |mode| {
+        format!("{}x{}", mode.dimensions.0, mode.dimensions.1)
+    });
+
+    // refresh rate is reported in mHz
+    let refresh = mode.map_or_else(String::new, |mode| {
+        format!("{:.0}", f64::from(mode.refresh_rate) / 1000.0)
+    });
+
+    format::replace_tokens(format, |token| match token {
+        "name" => Some(output.name.clone().unwrap_or_default()),
+        "resolution" => Some(resolution.clone()),
+        "refresh" => Some(refresh.clone()),
+        "scale" => Some(output.scale_factor.to_string()),
+        _ => None,
+    })
+}
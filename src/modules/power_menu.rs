@@ -0,0 +1,266 @@
+use crate::config::CommonConfig;
+use crate::gtk_helpers::IronbarGtkExt;
+use crate::image::ImageProvider;
+use crate::modules::{
+    Module, ModuleInfo, ModuleParts, ModulePopup, ModuleUpdateEvent, PopupButton, WidgetContext,
+};
+use crate::script::Script;
+use crate::{module_impl, send_async, spawn, try_send};
+use color_eyre::Result;
+use gtk::prelude::*;
+use gtk::{Button, Grid, Image, Label, Orientation};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use tokio::sync::{broadcast, mpsc};
+use tracing::{debug, error};
+
+#[derive(Debug, Deserialize, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct PowerMenuModule {
+    /// The icon to show on the bar button.
+    /// See [image](images) for supported formats.
+    ///
+    /// **Default**: `icon:system-shutdown-symbolic`
+    #[serde(default = "default_icon")]
+    icon: String,
+
+    /// The size to render icons at, in pixels.
+    ///
+    /// **Default**: `32`
+    #[serde(default = "default_icon_size")]
+    icon_size: i32,
+
+    /// The actions to show in the popup, in order.
+    ///
+    /// **Valid options**: `lock`, `logout`, `suspend`, `reboot`, `shutdown`
+    /// <br>
+    /// **Default**: `["lock", "logout", "suspend", "reboot", "shutdown"]`
+    #[serde(default = "default_actions")]
+    actions: Vec<PowerAction>,
+
+    /// Commands to run for each action, overriding the defaults.
+    /// Any actions not set here use the default command.
+    ///
+    /// **Default**: `{}`
+    #[serde(default)]
+    commands: HashMap<PowerAction, String>,
+
+    /// The number of buttons to show per row in the popup.
+    ///
+    /// **Default**: `3`
+    #[serde(default = "default_columns")]
+    columns: u32,
+
+    /// See [common options](module-level-options#common-options).
+    #[serde(flatten)]
+    pub common: Option<CommonConfig>,
+}
+
+fn default_icon() -> String {
+    String::from("icon:system-shutdown-symbolic")
+}
+
+const fn default_icon_size() -> i32 {
+    32
+}
+
+fn default_actions() -> Vec<PowerAction> {
+    vec![
+        PowerAction::Lock,
+        PowerAction::Logout,
+        PowerAction::Suspend,
+        PowerAction::Reboot,
+        PowerAction::Shutdown,
+    ]
+}
+
+const fn default_columns() -> u32 {
+    3
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum PowerAction {
+    Lock,
+    Logout,
+    Suspend,
+    Reboot,
+    Shutdown,
+}
+
+impl PowerAction {
+    const fn default_command(self) -> &'static str {
+        match self {
+            Self::Lock => "loginctl lock-session",
+            Self::Logout => "loginctl terminate-session \"$XDG_SESSION_ID\"",
+            Self::Suspend => "systemctl suspend",
+            Self::Reboot => "systemctl reboot",
+            Self::Shutdown => "systemctl poweroff",
+        }
+    }
+
+    const fn icon_name(self) -> &'static str {
+        match self {
+            Self::Lock => "system-lock-screen-symbolic",
+            Self::Logout => "system-log-out-symbolic",
+            Self::Suspend => "system-suspend-symbolic",
+            Self::Reboot => "system-reboot-symbolic",
+            Self::Shutdown => "system-shutdown-symbolic",
+        }
+    }
+
+    const fn class(self) -> &'static str {
+        match self {
+            Self::Lock => "lock",
+            Self::Logout => "logout",
+            Self::Suspend => "suspend",
+            Self::Reboot => "reboot",
+            Self::Shutdown => "shutdown",
+        }
+    }
+}
+
+impl Display for PowerAction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::Lock => "Lock",
+                Self::Logout => "Log out",
+                Self::Suspend => "Suspend",
+                Self::Reboot => "Reboot",
+                Self::Shutdown => "Shut down",
+            }
+        )
+    }
+}
+
+impl Module<Button> for PowerMenuModule {
+    type SendMessage = ();
+    type ReceiveMessage = PowerAction;
+
+    module_impl!("power_menu");
+
+    fn spawn_controller(
+        &self,
+        _info: &ModuleInfo,
+        context: &WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
+        mut rx: mpsc::Receiver<Self::ReceiveMessage>,
+    ) -> Result<()> {
+        let tx = context.tx.clone();
+        let commands = self.commands.clone();
+
+        spawn(async move {
+            while let Some(action) = rx.recv().await {
+                send_async!(tx, ModuleUpdateEvent::ClosePopup);
+
+                let cmd = commands
+                    .get(&action)
+                    .map_or(action.default_command(), String::as_str);
+
+                let script = Script::from(cmd);
+                debug!("executing command: '{}'", script.cmd);
+
+                if let Err(err) = script.get_output(None).await {
+                    error!("{err:?}");
+                }
+            }
+        });
+
+        Ok(())
+    }
+
+    fn into_widget(
+        self,
+        context: WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
+        info: &ModuleInfo,
+    ) -> Result<ModuleParts<Button>> {
+        let button = Button::new();
+
+        let icon = Image::new();
+        icon.add_class("icon");
+        ImageProvider::parse(&self.icon, info.icon_theme, false, self.icon_size)
+            .map(|provider| provider.load_into_image(&icon));
+        button.add(&icon);
+
+        let tx = context.tx.clone();
+        button.connect_clicked(move |button| {
+            try_send!(tx, ModuleUpdateEvent::TogglePopup(button.popup_id()));
+        });
+
+        let popup = self
+            .into_popup(
+                context.controller_tx.clone(),
+                context.subscribe(),
+                context,
+                info,
+            )
+            .into_popup_parts(vec![&button]);
+
+        Ok(ModuleParts::new(button, popup))
+    }
+
+    fn into_popup(
+        self,
+        tx: mpsc::Sender<Self::ReceiveMessage>,
+        _rx: broadcast::Receiver<Self::SendMessage>,
+        _context: WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
+        info: &ModuleInfo,
+    ) -> Option<gtk::Box>
+    where
+        Self: Sized,
+    {
+        let container = gtk::Box::new(Orientation::Vertical, 0);
+
+        let grid = Grid::builder()
+            .row_homogeneous(true)
+            .column_homogeneous(true)
+            .build();
+        grid.add_class("actions");
+
+        let columns = self.columns.max(1);
+
+        for (i, action) in (0..).zip(self.actions) {
+            let button = Button::new();
+            button.add_class("action");
+            button.add_class(action.class());
+
+            let content = gtk::Box::new(Orientation::Vertical, 5);
+
+            let icon = Image::new();
+            icon.add_class("icon");
+            ImageProvider::parse(
+                &format!("icon:{}", action.icon_name()),
+                info.icon_theme,
+                false,
+                self.icon_size,
+            )
+            .map(|provider| provider.load_into_image(&icon));
+            content.add(&icon);
+
+            let label = Label::new(Some(&action.to_string()));
+            label.add_class("label");
+            content.add(&label);
+
+            button.add(&content);
+
+            {
+                let tx = tx.clone();
+                button.connect_clicked(move |_| {
+                    try_send!(tx, action);
+                });
+            }
+
+            #[allow(clippy::cast_possible_wrap)]
+            grid.attach(&button, (i % columns) as i32, (i / columns) as i32, 1, 1);
+        }
+
+        container.add(&grid);
+        container.show_all();
+
+        Some(container)
+    }
+}
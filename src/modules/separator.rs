@@ -0,0 +1,51 @@
+use crate::config::CommonConfig;
+use crate::module_impl;
+use crate::modules::{Module, ModuleInfo, ModuleParts, WidgetContext};
+use color_eyre::Result;
+use gtk::Orientation;
+use gtk::Separator;
+use serde::Deserialize;
+use tokio::sync::mpsc;
+
+#[derive(Debug, Deserialize, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct SeparatorModule {
+    /// See [common options](module-level-options#common-options).
+    #[serde(flatten)]
+    pub common: Option<CommonConfig>,
+}
+
+impl Module<Separator> for SeparatorModule {
+    type SendMessage = ();
+    type ReceiveMessage = ();
+
+    module_impl!("separator");
+
+    fn spawn_controller(
+        &self,
+        _info: &ModuleInfo,
+        _context: &WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
+        _rx: mpsc::Receiver<Self::ReceiveMessage>,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    fn into_widget(
+        self,
+        _context: WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
+        info: &ModuleInfo,
+    ) -> Result<ModuleParts<Separator>> {
+        // the separator runs across the bar
+        let orientation = match info.bar_position.orientation() {
+            Orientation::Horizontal => Orientation::Vertical,
+            _ => Orientation::Horizontal,
+        };
+
+        let separator = Separator::new(orientation);
+
+        Ok(ModuleParts {
+            widget: separator,
+            popup: None,
+        })
+    }
+}
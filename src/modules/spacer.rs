@@ -0,0 +1,67 @@
+use crate::config::CommonConfig;
+use crate::module_impl;
+use crate::modules::{Module, ModuleInfo, ModuleParts, WidgetContext};
+use color_eyre::Result;
+use gtk::prelude::*;
+use gtk::Orientation;
+use serde::Deserialize;
+use tokio::sync::mpsc;
+
+#[derive(Debug, Deserialize, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct SpacerModule {
+    /// The size of the gap in pixels, along the bar.
+    ///
+    /// **Default**: `0`
+    #[serde(default)]
+    size: i32,
+
+    /// Whether the spacer should expand to fill all available space.
+    /// This can be used to push neighbouring modules apart.
+    ///
+    /// **Default**: `false`
+    #[serde(default)]
+    expand: bool,
+
+    /// See [common options](module-level-options#common-options).
+    #[serde(flatten)]
+    pub common: Option<CommonConfig>,
+}
+
+impl Module<gtk::Box> for SpacerModule {
+    type SendMessage = ();
+    type ReceiveMessage = ();
+
+    module_impl!("spacer");
+
+    fn spawn_controller(
+        &self,
+        _info: &ModuleInfo,
+        _context: &WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
+        _rx: mpsc::Receiver<Self::ReceiveMessage>,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    fn into_widget(
+        self,
+        _context: WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
+        info: &ModuleInfo,
+    ) -> Result<ModuleParts<gtk::Box>> {
+        let orientation = info.bar_position.orientation();
+        let spacer = gtk::Box::new(orientation, 0);
+
+        if orientation == Orientation::Horizontal {
+            spacer.set_size_request(self.size, -1);
+            spacer.set_hexpand(self.expand);
+        } else {
+            spacer.set_size_request(-1, self.size);
+            spacer.set_vexpand(self.expand);
+        }
+
+        Ok(ModuleParts {
+            widget: spacer,
+            popup: None,
+        })
+    }
+}
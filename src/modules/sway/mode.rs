@@ -1,14 +1,14 @@
 use crate::config::{CommonConfig, TruncateMode};
 use crate::gtk_helpers::IronbarLabelExt;
 use crate::modules::{Module, ModuleInfo, ModuleParts, ModuleUpdateEvent, WidgetContext};
-use crate::{await_sync, glib_recv, module_impl, try_send};
-use color_eyre::{Report, Result};
+use crate::{glib_recv, module_impl, spawn, try_send};
+use color_eyre::Result;
 use gtk::prelude::*;
 use gtk::Label;
 use serde::Deserialize;
 use swayipc_async::ModeEvent;
 use tokio::sync::mpsc;
-use tracing::{info, trace};
+use tracing::{error, info, trace};
 
 #[derive(Debug, Deserialize, Clone)]
 #[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
@@ -39,17 +39,22 @@ impl Module<Label> for SwayModeModule {
         info!("Sway Mode module started");
         let tx = context.tx.clone();
 
-        await_sync(async move {
-            let client = context.ironbar.clients.borrow_mut().sway()?;
-            client
+        let client = context.ironbar.clients.borrow_mut().sway()?;
+
+        // adding the listener reconnects the event stream,
+        // so is done in the background to avoid blocking the UI thread.
+        spawn(async move {
+            let res = client
                 .add_listener::<swayipc_async::ModeEvent>(move |mode| {
                     trace!("mode: {:?}", mode);
                     try_send!(tx, ModuleUpdateEvent::Update(mode.clone()));
                 })
-                .await?;
+                .await;
 
-            Ok::<(), Report>(())
-        })?;
+            if let Err(err) = res {
+                error!("{:?}", err.wrap_err("Failed to add sway mode listener"));
+            }
+        });
 
         Ok(())
     }
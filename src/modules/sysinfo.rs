@@ -1,17 +1,19 @@
+use crate::clients::sys_info::{self, RefreshType, Values};
 use crate::config::{CommonConfig, ModuleOrientation};
+use crate::format;
 use crate::gtk_helpers::{IronbarGtkExt, IronbarLabelExt};
-use crate::modules::{Module, ModuleInfo, ModuleParts, ModuleUpdateEvent, WidgetContext};
-use crate::{glib_recv, module_impl, send_async, spawn};
+use crate::modules::{
+    Module, ModuleInfo, ModuleParts, ModulePopup, ModuleUpdateEvent, PopupButton, WidgetContext,
+};
+use crate::{glib_recv, module_impl, rc_mut, send_async, spawn, try_send};
 use color_eyre::Result;
+use glib::Propagation;
 use gtk::prelude::*;
-use gtk::Label;
-use regex::{Captures, Regex};
+use gtk::{cairo, Button, DrawingArea, Label, Orientation, StateFlags};
 use serde::Deserialize;
-use std::collections::HashMap;
-use std::time::Duration;
-use sysinfo::{ComponentExt, CpuExt, DiskExt, NetworkExt, RefreshKind, System, SystemExt};
-use tokio::sync::mpsc;
-use tokio::time::sleep;
+use std::cell::Cell;
+use std::collections::VecDeque;
+use tokio::sync::{broadcast, mpsc};
 
 #[derive(Debug, Deserialize, Clone)]
 #[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
@@ -23,6 +25,7 @@ pub struct SysInfoModule {
     format: Vec<String>,
 
     /// Number of seconds between refresh.
+    /// Fractional values can be used for sub-second intervals.
     ///
     /// This can be set as a global interval,
     /// or passed as an object to customize the interval per-system.
@@ -46,6 +49,48 @@ pub struct SysInfoModule {
     /// **Default** : `horizontal`
     direction: Option<ModuleOrientation>,
 
+    /// The mountpoints to provide disk tokens for.
+    /// If empty, tokens are provided for all mountpoints.
+    ///
+    /// **Default**: `[]`
+    #[serde(default)]
+    mounts: Vec<String>,
+
+    /// Metrics to show history graphs for in a popup.
+    /// If empty, the module has no popup.
+    ///
+    /// **Valid options**: `cpu`, `memory`
+    /// <br>
+    /// **Default**: `[]`
+    #[serde(default)]
+    graphs: Vec<GraphMetric>,
+
+    /// The number of recent samples to show in each graph.
+    /// Samples are taken at the refresh interval of each metric.
+    ///
+    /// **Default**: `60`
+    #[serde(default = "default_history_length")]
+    history_length: usize,
+
+    /// The temperature sensor to compare against `temp_warm` and `temp_hot`.
+    /// Uses the same names as the `{temp:[sensor]}` token.
+    /// If not set, the CPU temperature is used.
+    ///
+    /// **Default**: `null`
+    temp_sensor: Option<String>,
+
+    /// The temperature in degrees C at or above which
+    /// the `.warm` class is added to the module.
+    ///
+    /// **Default**: `null`
+    temp_warm: Option<f32>,
+
+    /// The temperature in degrees C at or above which
+    /// the `.hot` class is added to the module.
+    ///
+    /// **Default**: `null`
+    temp_hot: Option<f32>,
+
     /// See [common options](module-level-options#common-options).
     #[serde(flatten)]
     pub common: Option<CommonConfig>,
@@ -58,44 +103,44 @@ pub struct Intervals {
     ///
     /// **Default**: `5`
     #[serde(default = "default_interval")]
-    memory: u64,
+    memory: f64,
 
     /// The number of seconds between refreshing CPU data.
     ///
     /// **Default**: `5`
     #[serde(default = "default_interval")]
-    cpu: u64,
+    cpu: f64,
 
     /// The number of seconds between refreshing temperature data.
     ///
     /// **Default**: `5`
     #[serde(default = "default_interval")]
-    temps: u64,
+    temps: f64,
 
     /// The number of seconds between refreshing disk data.
     ///
     /// **Default**: `5`
     #[serde(default = "default_interval")]
-    disks: u64,
+    disks: f64,
 
     /// The number of seconds between refreshing network data.
     ///
     /// **Default**: `5`
     #[serde(default = "default_interval")]
-    networks: u64,
+    networks: f64,
 
     /// The number of seconds between refreshing system data.
     ///
     /// **Default**: `5`
     #[serde(default = "default_interval")]
-    system: u64,
+    system: f64,
 }
 
 #[derive(Debug, Deserialize, Copy, Clone)]
 #[serde(untagged)]
 #[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub enum Interval {
-    All(u64),
+    All(f64),
     Individual(Intervals),
 }
 
@@ -106,42 +151,42 @@ impl Default for Interval {
 }
 
 impl Interval {
-    const fn memory(self) -> u64 {
+    const fn memory(self) -> f64 {
         match self {
             Self::All(n) => n,
             Self::Individual(intervals) => intervals.memory,
         }
     }
 
-    const fn cpu(self) -> u64 {
+    const fn cpu(self) -> f64 {
         match self {
             Self::All(n) => n,
             Self::Individual(intervals) => intervals.cpu,
         }
     }
 
-    const fn temps(self) -> u64 {
+    const fn temps(self) -> f64 {
         match self {
             Self::All(n) => n,
             Self::Individual(intervals) => intervals.temps,
         }
     }
 
-    const fn disks(self) -> u64 {
+    const fn disks(self) -> f64 {
         match self {
             Self::All(n) => n,
             Self::Individual(intervals) => intervals.disks,
         }
     }
 
-    const fn networks(self) -> u64 {
+    const fn networks(self) -> f64 {
         match self {
             Self::All(n) => n,
             Self::Individual(intervals) => intervals.networks,
         }
     }
 
-    const fn system(self) -> u64 {
+    const fn system(self) -> f64 {
         match self {
             Self::All(n) => n,
             Self::Individual(intervals) => intervals.system,
@@ -149,22 +194,115 @@ impl Interval {
     }
 }
 
-const fn default_interval() -> u64 {
-    5
+const fn default_interval() -> f64 {
+    5.0
 }
 
-#[derive(Debug)]
-enum RefreshType {
-    Memory,
+const fn default_history_length() -> usize {
+    60
+}
+
+#[derive(Debug, Deserialize, Copy, Clone, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum GraphMetric {
     Cpu,
-    Temps,
-    Disks,
-    Network,
-    System,
+    Memory,
+}
+
+impl GraphMetric {
+    const fn refresh_type(self) -> RefreshType {
+        match self {
+            Self::Cpu => RefreshType::Cpu,
+            Self::Memory => RefreshType::Memory,
+        }
+    }
+
+    const fn interval(self, interval: Interval) -> f64 {
+        match self {
+            Self::Cpu => interval.cpu(),
+            Self::Memory => interval.memory(),
+        }
+    }
+
+    /// The token holding the metric's percentage value.
+    const fn token(self) -> &'static str {
+        match self {
+            Self::Cpu => "cpu_percent",
+            Self::Memory => "memory_percent",
+        }
+    }
+
+    const fn class(self) -> &'static str {
+        match self {
+            Self::Cpu => "cpu",
+            Self::Memory => "memory",
+        }
+    }
+
+    const fn title(self) -> &'static str {
+        match self {
+            Self::Cpu => "CPU",
+            Self::Memory => "Memory",
+        }
+    }
+}
+
+/// The temperature level of the threshold sensor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum TempLevel {
+    #[default]
+    Normal,
+    Warm,
+    Hot,
+}
+
+impl TempLevel {
+    fn new(temp: Option<f32>, warm: Option<f32>, hot: Option<f32>) -> Self {
+        let Some(temp) = temp else {
+            return Self::Normal;
+        };
+
+        if hot.is_some_and(|hot| temp >= hot) {
+            Self::Hot
+        } else if warm.is_some_and(|warm| temp >= warm) {
+            Self::Warm
+        } else {
+            Self::Normal
+        }
+    }
+
+    const fn class(self) -> Option<&'static str> {
+        match self {
+            Self::Normal => None,
+            Self::Warm => Some("warm"),
+            Self::Hot => Some("hot"),
+        }
+    }
+}
+
+/// Checks whether the token is a temperature sensor token,
+/// which resolve to an empty string when the sensor is missing.
+///
+/// Tokens with a format spec are ignored,
+/// so the spec is still split off before lookup.
+fn is_temp_token(token: &str) -> bool {
+    token == "temp_cpu"
+        || token
+            .strip_prefix("temp:")
+            .is_some_and(|sensor| !sensor.contains(':'))
+}
+
+#[derive(Debug, Clone)]
+pub enum SysInfoEvent {
+    /// A snapshot of all current values.
+    Values(Values),
+    /// A new percentage sample for a graphed metric.
+    Sample(GraphMetric, f64),
 }
 
 impl Module<gtk::Box> for SysInfoModule {
-    type SendMessage = HashMap<String, String>;
+    type SendMessage = SysInfoEvent;
     type ReceiveMessage = ();
 
     module_impl!("sysinfo");
@@ -176,56 +314,54 @@ impl Module<gtk::Box> for SysInfoModule {
         _rx: mpsc::Receiver<Self::ReceiveMessage>,
     ) -> Result<()> {
         let interval = self.interval;
+        let client = context.client::<sys_info::Client>();
+        client.expose_mounts(&self.mounts);
+
+        let receivers = client.subscribe(
+            [
+                (RefreshType::Memory, interval.memory()),
+                (RefreshType::Cpu, interval.cpu()),
+                (RefreshType::Temps, interval.temps()),
+                (RefreshType::Disks, interval.disks()),
+                (RefreshType::Network, interval.networks()),
+                (RefreshType::System, interval.system()),
+            ]
+            .map(|(refresh_type, secs)| (refresh_type, sys_info::interval_from_secs(secs))),
+        );
 
-        let refresh_kind = RefreshKind::everything()
-            .without_processes()
-            .without_users_list();
-
-        let mut sys = System::new_with_specifics(refresh_kind);
-        sys.refresh_components_list();
-        sys.refresh_disks_list();
-        sys.refresh_networks_list();
+        for mut rx in receivers {
+            let tx = context.tx.clone();
+            spawn(async move {
+                while let Ok(values) = rx.recv().await {
+                    send_async!(tx, ModuleUpdateEvent::Update(SysInfoEvent::Values(values)));
+                }
+            });
+        }
 
-        let (refresh_tx, mut refresh_rx) = mpsc::channel(16);
+        // graphs are sampled on their own receivers,
+        // so each metric is only recorded when it is refreshed.
+        for metric in self.graphs.iter().copied() {
+            let metric_interval = sys_info::interval_from_secs(metric.interval(interval));
+            let receivers = client.subscribe([(metric.refresh_type(), metric_interval)]);
 
-        macro_rules! spawn_refresh {
-            ($refresh_type:expr, $func:ident) => {{
-                let tx = refresh_tx.clone();
+            for mut rx in receivers {
+                let tx = context.tx.clone();
                 spawn(async move {
-                    loop {
-                        send_async!(tx, $refresh_type);
-                        sleep(Duration::from_secs(interval.$func())).await;
+                    while let Ok(values) = rx.recv().await {
+                        let sample = values
+                            .get(metric.token())
+                            .and_then(|value| value.trim().parse().ok());
+
+                        if let Some(sample) = sample {
+                            send_async!(
+                                tx,
+                                ModuleUpdateEvent::Update(SysInfoEvent::Sample(metric, sample))
+                            );
+                        }
                     }
                 });
-            }};
-        }
-
-        spawn_refresh!(RefreshType::Memory, memory);
-        spawn_refresh!(RefreshType::Cpu, cpu);
-        spawn_refresh!(RefreshType::Temps, temps);
-        spawn_refresh!(RefreshType::Disks, disks);
-        spawn_refresh!(RefreshType::Network, networks);
-        spawn_refresh!(RefreshType::System, system);
-
-        let tx = context.tx.clone();
-        spawn(async move {
-            let mut format_info = HashMap::new();
-
-            while let Some(refresh) = refresh_rx.recv().await {
-                match refresh {
-                    RefreshType::Memory => refresh_memory_tokens(&mut format_info, &mut sys),
-                    RefreshType::Cpu => refresh_cpu_tokens(&mut format_info, &mut sys),
-                    RefreshType::Temps => refresh_temp_tokens(&mut format_info, &mut sys),
-                    RefreshType::Disks => refresh_disk_tokens(&mut format_info, &mut sys),
-                    RefreshType::Network => {
-                        refresh_network_tokens(&mut format_info, &mut sys, interval.networks());
-                    }
-                    RefreshType::System => refresh_system_tokens(&mut format_info, &sys),
-                };
-
-                send_async!(tx, ModuleUpdateEvent::Update(format_info.clone()));
             }
-        });
+        }
 
         Ok(())
     }
@@ -233,10 +369,8 @@ impl Module<gtk::Box> for SysInfoModule {
     fn into_widget(
         self,
         context: WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
-        _info: &ModuleInfo,
+        info: &ModuleInfo,
     ) -> Result<ModuleParts<gtk::Box>> {
-        let re = Regex::new(r"\{([^}]+)}")?;
-
         let layout = match self.direction {
             Some(orientation) => orientation,
             None => self.orientation,
@@ -244,6 +378,26 @@ impl Module<gtk::Box> for SysInfoModule {
 
         let container = gtk::Box::new(layout.into(), 10);
 
+        // labels are placed inside a button to open the graphs popup,
+        // or directly in the container otherwise.
+        let button = (!self.graphs.is_empty()).then(|| {
+            let button = Button::new();
+            let labels_container = gtk::Box::new(layout.into(), 10);
+            button.add(&labels_container);
+            container.add(&button);
+
+            let tx = context.tx.clone();
+            button.connect_clicked(move |button| {
+                try_send!(tx, ModuleUpdateEvent::TogglePopup(button.popup_id()));
+            });
+
+            (button, labels_container)
+        });
+
+        let labels_container = button
+            .as_ref()
+            .map_or(&container, |(_, labels_container)| labels_container);
+
         let mut labels = Vec::new();
 
         for format in &self.format {
@@ -252,200 +406,180 @@ impl Module<gtk::Box> for SysInfoModule {
             label.add_class("item");
             label.set_angle(self.orientation.to_angle());
 
-            container.add(&label);
+            labels_container.add(&label);
             labels.push(label);
         }
 
         {
-            let formats = self.format;
-            glib_recv!(context.subscribe(), info => {
+            let formats = self.format.clone();
+
+            let temp_token = self.temp_sensor.as_ref().map_or_else(
+                || String::from("temp_cpu"),
+                |sensor| format!("temp:{sensor}"),
+            );
+            let (temp_warm, temp_hot) = (self.temp_warm, self.temp_hot);
+            let temp_level = Cell::new(TempLevel::Normal);
+
+            let container = container.clone();
+            glib_recv!(context.subscribe(), event => {
+                let SysInfoEvent::Values(info) = event else {
+                    continue;
+                };
+
                 for (format, label) in formats.iter().zip(labels.clone()) {
-                    let format_compiled = re.replace_all(format, |caps: &Captures| {
-                        info.get(&caps[1])
-                            .unwrap_or(&caps[0].to_string())
-                            .to_string()
+                    let format_compiled = format::replace_tokens(format, |token| {
+                        info.get(token)
+                            .cloned()
+                            .or_else(|| is_temp_token(token).then(String::new))
                     });
+                    label.set_label_escaped(&format_compiled);
+                }
 
-                    label.set_label_escaped(format_compiled.as_ref());
+                // classes are only updated when the level changes
+                let temp = info.get(&temp_token).and_then(|temp| temp.parse().ok());
+                let level = TempLevel::new(temp, temp_warm, temp_hot);
+                let previous = temp_level.replace(level);
+
+                if level != previous {
+                    if let Some(class) = previous.class() {
+                        container.remove_class(class);
+                    }
+                    if let Some(class) = level.class() {
+                        container.add_class(class);
+                    }
                 }
             });
         }
 
+        let popup = button.and_then(|(button, _)| {
+            self.into_popup(
+                context.controller_tx.clone(),
+                context.subscribe(),
+                context,
+                info,
+            )
+            .into_popup_parts(vec![&button])
+        });
+
         Ok(ModuleParts {
             widget: container,
-            popup: None,
+            popup,
         })
     }
-}
-
-fn refresh_memory_tokens(format_info: &mut HashMap<String, String>, sys: &mut System) {
-    sys.refresh_memory();
-
-    let total_memory = sys.total_memory();
-    let available_memory = sys.available_memory();
-
-    let actual_used_memory = total_memory - available_memory;
-    let memory_percent = actual_used_memory as f64 / total_memory as f64 * 100.0;
-
-    format_info.insert(
-        String::from("memory_free"),
-        (bytes_to_gigabytes(available_memory)).to_string(),
-    );
-    format_info.insert(
-        String::from("memory_used"),
-        (bytes_to_gigabytes(actual_used_memory)).to_string(),
-    );
-    format_info.insert(
-        String::from("memory_total"),
-        (bytes_to_gigabytes(total_memory)).to_string(),
-    );
-    format_info.insert(
-        String::from("memory_percent"),
-        format!("{memory_percent:0>2.0}"),
-    );
-
-    let used_swap = sys.used_swap();
-    let total_swap = sys.total_swap();
-
-    format_info.insert(
-        String::from("swap_free"),
-        (bytes_to_gigabytes(sys.free_swap())).to_string(),
-    );
-    format_info.insert(
-        String::from("swap_used"),
-        (bytes_to_gigabytes(used_swap)).to_string(),
-    );
-    format_info.insert(
-        String::from("swap_total"),
-        (bytes_to_gigabytes(total_swap)).to_string(),
-    );
-    format_info.insert(
-        String::from("swap_percent"),
-        format!("{:0>2.0}", used_swap as f64 / total_swap as f64 * 100.0),
-    );
-}
 
-fn refresh_cpu_tokens(format_info: &mut HashMap<String, String>, sys: &mut System) {
-    sys.refresh_cpu();
-
-    let cpu_info = sys.global_cpu_info();
-    let cpu_percent = cpu_info.cpu_usage();
-
-    format_info.insert(String::from("cpu_percent"), format!("{cpu_percent:0>2.0}"));
-}
+    fn into_popup(
+        self,
+        _tx: mpsc::Sender<Self::ReceiveMessage>,
+        rx: broadcast::Receiver<Self::SendMessage>,
+        _context: WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
+        _info: &ModuleInfo,
+    ) -> Option<gtk::Box> {
+        let container = gtk::Box::new(Orientation::Vertical, 5);
+
+        let history_length = self.history_length.max(2);
+
+        let graphs = self
+            .graphs
+            .iter()
+            .map(|&metric| {
+                let title = Label::new(Some(metric.title()));
+                title.add_class("title");
+                title.add_class(metric.class());
+                container.add(&title);
+
+                let samples = rc_mut!(VecDeque::with_capacity(history_length));
+
+                let area = DrawingArea::new();
+                area.add_class("graph");
+                area.add_class(metric.class());
+                area.set_size_request(200, 60);
+
+                {
+                    let samples = samples.clone();
+                    area.connect_draw(move |area, cr| {
+                        draw_graph(area, cr, &samples.borrow(), history_length);
+                        Propagation::Proceed
+                    });
+                }
 
-fn refresh_temp_tokens(format_info: &mut HashMap<String, String>, sys: &mut System) {
-    sys.refresh_components();
+                container.add(&area);
 
-    let components = sys.components();
-    for component in components {
-        let key = component.label().replace(' ', "-");
-        let temp = component.temperature();
+                (metric, title, samples, area)
+            })
+            .collect::<Vec<_>>();
 
-        format_info.insert(format!("temp_c:{key}"), format!("{temp:.0}"));
-        format_info.insert(format!("temp_f:{key}"), format!("{:.0}", c_to_f(temp)));
-    }
-}
+        glib_recv!(rx, event => {
+            let SysInfoEvent::Sample(metric, sample) = event else {
+                continue;
+            };
 
-fn refresh_disk_tokens(format_info: &mut HashMap<String, String>, sys: &mut System) {
-    sys.refresh_disks();
-
-    for disk in sys.disks() {
-        // replace braces to avoid conflict with regex
-        let key = disk
-            .mount_point()
-            .to_str()
-            .map(|s| s.replace(['{', '}'], ""));
-
-        if let Some(key) = key {
-            let total = disk.total_space();
-            let available = disk.available_space();
-            let used = total - available;
+            for (_, title, samples, area) in graphs.iter().filter(|graph| graph.0 == metric) {
+                {
+                    let mut samples = samples.borrow_mut();
+                    if samples.len() == history_length {
+                        samples.pop_front();
+                    }
+                    samples.push_back(sample);
+                }
 
-            format_info.insert(
-                format!("disk_free:{key}"),
-                bytes_to_gigabytes(available).to_string(),
-            );
+                title.set_label(&format!("{} {sample:.0}%", metric.title()));
 
-            format_info.insert(
-                format!("disk_used:{key}"),
-                bytes_to_gigabytes(used).to_string(),
-            );
+                // only redraw while the popup is open
+                if area.is_mapped() {
+                    area.queue_draw();
+                }
+            }
+        });
 
-            format_info.insert(
-                format!("disk_total:{key}"),
-                bytes_to_gigabytes(total).to_string(),
-            );
+        container.show_all();
 
-            format_info.insert(
-                format!("disk_percent:{key}"),
-                format!("{:0>2.0}", used as f64 / total as f64 * 100.0),
-            );
-        }
+        Some(container)
     }
 }
 
-fn refresh_network_tokens(
-    format_info: &mut HashMap<String, String>,
-    sys: &mut System,
-    interval: u64,
-) {
-    sys.refresh_networks();
-
-    for (iface, network) in sys.networks() {
-        format_info.insert(
-            format!("net_down:{iface}"),
-            format!("{:0>2.0}", bytes_to_megabits(network.received()) / interval),
-        );
-
-        format_info.insert(
-            format!("net_up:{iface}"),
-            format!(
-                "{:0>2.0}",
-                bytes_to_megabits(network.transmitted()) / interval
-            ),
-        );
+/// Draws the samples as a filled line graph,
+/// with the newest sample at the right edge.
+///
+/// Samples are percentages, drawn from `0` at the bottom to `100` at the top.
+fn draw_graph(area: &DrawingArea, cr: &cairo::Context, samples: &VecDeque<f64>, capacity: usize) {
+    if samples.is_empty() {
+        return;
     }
-}
 
-fn refresh_system_tokens(format_info: &mut HashMap<String, String>, sys: &System) {
-    // no refresh required for these tokens
+    let width = f64::from(area.allocated_width());
+    let height = f64::from(area.allocated_height());
 
-    let load_average = sys.load_average();
-    format_info.insert(
-        String::from("load_average:1"),
-        format!("{:.2}", load_average.one),
-    );
+    let step = width / (capacity - 1) as f64;
+    let offset = capacity - samples.len();
 
-    format_info.insert(
-        String::from("load_average:5"),
-        format!("{:.2}", load_average.five),
-    );
+    let points = samples.iter().enumerate().map(|(i, sample)| {
+        let x = (offset + i) as f64 * step;
+        let y = height - sample.clamp(0.0, 100.0) / 100.0 * height;
+        (x, y)
+    });
 
-    format_info.insert(
-        String::from("load_average:15"),
-        format!("{:.2}", load_average.fifteen),
-    );
+    let color = area.style_context().color(StateFlags::NORMAL);
 
-    let uptime = Duration::from_secs(sys.uptime()).as_secs();
-    let hours = uptime / 3600;
-    format_info.insert(
-        String::from("uptime"),
-        format!("{:0>2}:{:0>2}", hours, (uptime % 3600) / 60),
+    cr.move_to(offset as f64 * step, height);
+    for (x, y) in points.clone() {
+        cr.line_to(x, y);
+    }
+    cr.line_to(width, height);
+    cr.close_path();
+
+    cr.set_source_rgba(
+        color.red(),
+        color.green(),
+        color.blue(),
+        color.alpha() * 0.3,
     );
-}
+    cr.fill().ok();
 
-/// Converts celsius to fahrenheit.
-fn c_to_f(c: f32) -> f32 {
-    c * 9.0 / 5.0 + 32.0
-}
-
-const fn bytes_to_gigabytes(b: u64) -> u64 {
-    const BYTES_IN_GIGABYTE: u64 = 1_000_000_000;
-    b / BYTES_IN_GIGABYTE
-}
+    for (x, y) in points {
+        cr.line_to(x, y);
+    }
 
-const fn bytes_to_megabits(b: u64) -> u64 {
-    const BYTES_IN_MEGABIT: u64 = 125_000;
-    b / BYTES_IN_MEGABIT
+    cr.set_source_rgba(color.red(), color.green(), color.blue(), color.alpha());
+    cr.set_line_width(1.5);
+    cr.stroke().ok();
 }
@@ -62,7 +62,8 @@ fn get_image_from_icon_name(item: &TrayMenu, icon_theme: &IconTheme, size: u32)
     }
 
     let icon_info = item.icon_name.as_ref().and_then(|icon_name| {
-        icon_theme.lookup_icon(icon_name, size as i32, IconLookupFlags::empty())
+        // force the size so that non-square theme icons are still rendered square
+        icon_theme.lookup_icon(icon_name, size as i32, IconLookupFlags::FORCE_SIZE)
     });
 
     if let Some(icon_info) = icon_info {
@@ -1,17 +1,29 @@
-use glib::{Propagation, SignalHandlerId};
-use gtk::gdk::Gravity;
+use super::LeftClick;
+use crate::config::BarPosition;
+use crate::try_send;
+use glib::Propagation;
+use gtk::gdk::{Gravity, BUTTON_PRIMARY};
 use gtk::prelude::*;
 use gtk::{EventBox, Image, Label, MenuItem};
+use std::cell::RefCell;
+use std::rc::Rc;
+use system_tray::client::ActivateRequest;
+use system_tray::gtk_menu::Menu;
 use system_tray::item::{IconPixmap, StatusNotifierItem, Tooltip};
+use tokio::sync::mpsc;
 
 /// Main tray icon to show on the bar
 pub(crate) struct TrayMenu {
     pub event_box: EventBox,
-    button_handler: Option<SignalHandlerId>,
+    menu: Rc<RefCell<Option<Menu>>>,
     widget: MenuItem,
     image_widget: Option<Image>,
     label_widget: Option<Label>,
 
+    /// The item's position in the configured order.
+    /// Items not in the order share the lowest priority.
+    pub rank: usize,
+
     pub title: Option<String>,
     pub icon_name: Option<String>,
     pub icon_theme_path: Option<String>,
@@ -19,21 +31,82 @@ pub(crate) struct TrayMenu {
 }
 
 impl TrayMenu {
-    pub fn new(address: &str, item: StatusNotifierItem) -> Self {
+    pub fn new(
+        address: &str,
+        item: StatusNotifierItem,
+        bar_position: BarPosition,
+        rank: usize,
+        left_click: LeftClick,
+        tx: mpsc::Sender<ActivateRequest>,
+    ) -> Self {
         let event_box = EventBox::new();
 
         let widget = MenuItem::new();
         widget.style_context().add_class("item");
+        widget.style_context().add_class("tray-item");
+
+        // allow styling individual items by their ID, or title if they have none
+        let name = if item.id.is_empty() {
+            item.title.as_deref().unwrap_or_default()
+        } else {
+            &item.id
+        };
+
+        if !name.is_empty() {
+            widget.style_context().add_class(&class_name(name));
+        }
         event_box.add(&widget);
 
         event_box.show_all();
 
+        let menu = Rc::new(RefCell::new(None::<Menu>));
+
+        // items which set `ItemIsMenu` only support showing their menu
+        let activate = left_click == LeftClick::Activate && !item.item_is_menu;
+
+        {
+            let menu = menu.clone();
+            let address = address.to_string();
+
+            // open the menu away from the bar edge, towards the screen center
+            let (widget_anchor, menu_anchor) = match bar_position {
+                BarPosition::Top => (Gravity::South, Gravity::North),
+                BarPosition::Bottom => (Gravity::North, Gravity::South),
+                BarPosition::Left => (Gravity::East, Gravity::West),
+                BarPosition::Right => (Gravity::West, Gravity::East),
+            };
+
+            event_box.connect_button_press_event(move |event_box, event| {
+                let menu = menu.borrow();
+
+                // items without a menu can only be activated
+                if event.button() == BUTTON_PRIMARY && (activate || menu.is_none()) {
+                    let (x, y) = event.root();
+                    try_send!(
+                        tx,
+                        ActivateRequest::Default {
+                            address: address.clone(),
+                            x: x as i32,
+                            y: y as i32,
+                        }
+                    );
+                } else if let Some(menu) = menu.as_ref() {
+                    menu.popup_at_widget(event_box, widget_anchor, menu_anchor, None);
+                }
+
+                // the click is consumed by the item,
+                // so stop it reaching the module's `on_click_*` scripts.
+                Propagation::Stop
+            });
+        }
+
         let mut slf = Self {
             event_box,
-            button_handler: None,
+            menu,
             widget,
             image_widget: None,
             label_widget: None,
+            rank,
             title: item.title,
             icon_name: item.icon_name,
             icon_theme_path: item.icon_theme_path,
@@ -114,15 +187,31 @@ impl TrayMenu {
         }
     }
 
-    pub fn set_menu_widget(&mut self, menu: system_tray::gtk_menu::Menu) {
-        let button_handler = self
-            .event_box
-            .connect_button_press_event(move |event_box, _event| {
-                menu.popup_at_widget(event_box, Gravity::North, Gravity::South, None);
-                Propagation::Proceed
-            });
-        if let Some(handler) = self.button_handler.replace(button_handler) {
-            self.event_box.disconnect(handler);
-        }
+    pub fn set_menu_widget(&mut self, menu: Menu) {
+        self.menu.replace(Some(menu));
+    }
+}
+
+/// Converts an item name into a valid CSS class name,
+/// replacing any unsupported characters with `-`.
+///
+/// Class names cannot start with a digit,
+/// so these are prefixed with `_`.
+fn class_name(name: &str) -> String {
+    let class = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect::<String>();
+
+    if class.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("_{class}")
+    } else {
+        class
     }
 }
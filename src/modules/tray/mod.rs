@@ -1,18 +1,23 @@
 mod icon;
 mod interface;
 
-use crate::clients::tray;
-use crate::config::{CommonConfig, ModuleOrientation};
-use crate::modules::{Module, ModuleInfo, ModuleParts, ModuleUpdateEvent, WidgetContext};
-use crate::{glib_recv, lock, module_impl, send_async, spawn};
-use color_eyre::{Report, Result};
+use crate::clients::{tray, LazyClient};
+use crate::config::{BarPosition, CommonConfig, ModuleOrientation};
+use crate::gtk_helpers::IronbarGtkExt;
+use crate::modules::{
+    Module, ModuleInfo, ModuleParts, ModulePopupParts, ModuleUpdateEvent, PopupButton,
+    WidgetContext,
+};
+use crate::{glib_recv, lock, module_impl, send_async, spawn, try_send};
+use color_eyre::Result;
 use gtk::prelude::*;
-use gtk::{IconTheme, Orientation};
+use gtk::{Button, IconTheme, Orientation};
 use interface::TrayMenu;
 use serde::Deserialize;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use system_tray::client::Event;
 use system_tray::client::{ActivateRequest, UpdateEvent};
+use system_tray::item::StatusNotifierItem;
 use tokio::sync::mpsc;
 use tracing::{debug, error, trace, warn};
 
@@ -39,6 +44,65 @@ pub struct TrayModule {
     /// **Default**: `horizontal` for horizontal bars, `vertical` for vertical bars
     #[serde(default)]
     direction: Option<ModuleOrientation>,
+
+    /// The space in pixels between each tray item.
+    /// This applies along the tray's `direction`,
+    /// so works for both horizontal and vertical trays.
+    /// Negative values are treated as `0`.
+    ///
+    /// **Default**: `10`
+    #[serde(default = "default_spacing")]
+    spacing: i32,
+
+    /// The space in pixels between the tray items
+    /// and the edges of the module.
+    ///
+    /// **Default**: `0`
+    #[serde(default)]
+    padding: u32,
+
+    /// A list of items to hide from the tray.
+    /// Items are matched by their ID or title.
+    ///
+    /// **Default**: `[]`
+    #[serde(default)]
+    hidden: Vec<String>,
+
+    /// A list of items to place first in the tray, in the given order.
+    /// Items are matched by their ID or title.
+    /// Any other items are placed after these, in the order they appear.
+    ///
+    /// **Default**: `[]`
+    #[serde(default)]
+    order: Vec<String>,
+
+    /// The action to take when an item is left-clicked.
+    /// Right-clicking an item always opens its menu.
+    ///
+    /// Items which do not support activation,
+    /// or which do not have a menu, fall back to the other action.
+    ///
+    /// **Valid options**: `activate`, `menu`, `context_menu`, `context-menu`
+    /// <br>
+    /// **Default**: `menu`
+    #[serde(default)]
+    left_click: LeftClick,
+
+    /// The maximum number of items to show on the bar.
+    /// Any further items are placed in a popup,
+    /// opened by a button after the shown items.
+    ///
+    /// Leave unset to show all items on the bar.
+    ///
+    /// **Default**: `null`
+    overflow: Option<usize>,
+
+    /// The label of the button which opens the overflow popup.
+    ///
+    /// **Default**: `󰇘`
+    #[serde(default = "default_overflow_icon")]
+    overflow_icon: String,
+
     /// See [common options](module-level-options#common-options).
     #[serde(flatten)]
     pub common: Option<CommonConfig>,
@@ -48,10 +112,32 @@ const fn default_icon_size() -> u32 {
     16
 }
 
+const fn default_spacing() -> i32 {
+    10
+}
+
+fn default_overflow_icon() -> String {
+    String::from("󰇘")
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum LeftClick {
+    /// Activates the item, which usually toggles the application window.
+    Activate,
+    /// Opens the item's menu.
+    #[default]
+    #[serde(alias = "context_menu", alias = "context-menu")]
+    Menu,
+}
+
 impl Module<gtk::Box> for TrayModule {
     type SendMessage = Event;
     type ReceiveMessage = ActivateRequest;
 
+    const REPORTS_READY: bool = true;
+
     module_impl!("tray");
 
     fn spawn_controller(
@@ -61,15 +147,42 @@ impl Module<gtk::Box> for TrayModule {
         mut rx: mpsc::Receiver<Self::ReceiveMessage>,
     ) -> Result<()> {
         let tx = context.tx.clone();
+        let hidden = self.hidden.clone();
 
-        let client = context.try_client::<tray::Client>()?;
-        let mut tray_rx = client.subscribe();
+        let client = context.client::<LazyClient<tray::Client>>();
 
-        let initial_items = lock!(client.items()).clone();
-
-        // listen to tray updates
         spawn(async move {
+            let Some(client) = client.ready().await else {
+                return;
+            };
+
+            let mut tray_rx = client.subscribe();
+            let initial_items = lock!(client.items()).clone();
+
+            // send tray commands
+            {
+                let client = client.clone();
+                spawn(async move {
+                    while let Some(cmd) = rx.recv().await {
+                        if let Err(err) = client.activate(cmd).await {
+                            error!("{err:?}");
+                        };
+                    }
+                });
+            }
+
+            // addresses of items which match the hidden list,
+            // so that their later events can be ignored.
+            let mut hidden_addresses = HashSet::new();
+
+            // listen to tray updates
             for (key, (item, menu)) in initial_items {
+                if matches_any(&item, &hidden) {
+                    debug!("Hiding tray item at '{key}'");
+                    hidden_addresses.insert(key);
+                    continue;
+                }
+
                 send_async!(
                     tx,
                     ModuleUpdateEvent::Update(Event::Add(key.clone(), item.into()))
@@ -83,20 +196,22 @@ impl Module<gtk::Box> for TrayModule {
                 }
             }
 
+            send_async!(tx, ModuleUpdateEvent::Ready);
+
             while let Ok(message) = tray_rx.recv().await {
-                send_async!(tx, ModuleUpdateEvent::Update(message));
-            }
-        });
+                match &message {
+                    Event::Add(address, item) if matches_any(item, &hidden) => {
+                        debug!("Hiding tray item at '{address}'");
+                        hidden_addresses.insert(address.clone());
+                        continue;
+                    }
+                    Event::Update(address, _) if hidden_addresses.contains(address) => continue,
+                    Event::Remove(address) if hidden_addresses.remove(address) => continue,
+                    _ => {}
+                }
 
-        // send tray commands
-        spawn(async move {
-            while let Some(cmd) = rx.recv().await {
-                if let Err(err) = client.activate(cmd).await {
-                    error!("{err:?}");
-                };
+                send_async!(tx, ModuleUpdateEvent::Update(message));
             }
-
-            Ok::<_, Report>(())
         });
 
         Ok(())
@@ -115,42 +230,157 @@ impl Module<gtk::Box> for TrayModule {
         // as the latter has issues on Sway with menus focus-stealing from the bar.
         //
         // Each widget is wrapped in an EventBox, copying what Waybar does here.
-        let container = gtk::Box::new(orientation, 10);
+        // GTK rejects negative spacing, so clamp rather than fall back to its default.
+        let spacing = self.spacing.max(0);
+
+        let container = gtk::Box::new(orientation, spacing);
+        container.set_border_width(self.padding);
+
+        let items = gtk::Box::new(orientation, spacing);
+        container.add(&items);
+
+        // items past the overflow limit are moved into a popup,
+        // opened by a button placed after the shown items.
+        let overflow = self.overflow.map(|_| {
+            let button = Button::with_label(&self.overflow_icon);
+            button.add_class("overflow");
+            button.set_no_show_all(true);
+            container.add(&button);
+
+            let tx = context.tx.clone();
+            button.connect_clicked(move |button| {
+                try_send!(tx, ModuleUpdateEvent::TogglePopup(button.popup_id()));
+            });
+
+            let popup_items = gtk::Box::new(orientation, spacing);
+            popup_items.add_class("overflow-items");
+
+            (button, popup_items)
+        });
+
+        let popup = overflow.clone().map(|(button, popup_items)| {
+            let popup_container = gtk::Box::new(Orientation::Vertical, 0);
+            popup_container.add(&popup_items);
+            popup_container.show_all();
+
+            ModulePopupParts {
+                container: popup_container,
+                buttons: vec![button],
+            }
+        });
 
         {
-            let container = container.clone();
+            let mut layout = TrayLayout {
+                items,
+                overflow,
+                limit: self.overflow.unwrap_or(usize::MAX),
+                order: Vec::new(),
+            };
+
             let mut menus = HashMap::new();
             let icon_theme = info.icon_theme.clone();
+            let bar_position = info.bar_position;
+            let tx = context.controller_tx.clone();
 
             // listen for UI updates
             glib_recv!(context.subscribe(), update =>
-                on_update(update, &container, &mut menus, &icon_theme, self.icon_size, self.prefer_theme_icons)
+                on_update(update, &mut layout, &mut menus, &icon_theme, bar_position, &tx, &self)
             );
         };
 
         Ok(ModuleParts {
             widget: container,
-            popup: None,
+            popup,
         })
     }
 }
 
+/// Places tray items either on the bar or in the overflow popup.
+#[derive(Debug)]
+struct TrayLayout {
+    /// Container for items shown on the bar.
+    items: gtk::Box,
+    /// Button to open the overflow popup, and the popup's item container.
+    overflow: Option<(Button, gtk::Box)>,
+    /// The maximum number of items to show on the bar.
+    limit: usize,
+    /// Item addresses, in display order.
+    order: Vec<Box<str>>,
+}
+
+impl TrayLayout {
+    /// Moves each item into the correct container and position,
+    /// showing the overflow button if any items overflow.
+    ///
+    /// Items only move between containers when crossing the limit,
+    /// so the order of the rest is kept stable.
+    fn rebalance(&self, menus: &HashMap<Box<str>, TrayMenu>) {
+        for (index, address) in self.order.iter().enumerate() {
+            let Some(menu) = menus.get(address) else {
+                continue;
+            };
+
+            let (target, position) = match &self.overflow {
+                Some((_, overflow)) if index >= self.limit => (overflow, index - self.limit),
+                _ => (&self.items, index),
+            };
+
+            let widget = &menu.event_box;
+            let parent = widget.parent();
+
+            if parent.as_ref() != Some(target.upcast_ref()) {
+                if let Some(parent) = parent.and_then(|parent| parent.downcast::<gtk::Box>().ok()) {
+                    parent.remove(widget);
+                }
+
+                target.pack_start(widget, true, true, 0);
+            }
+
+            target.reorder_child(widget, position as i32);
+        }
+
+        if let Some((button, _)) = &self.overflow {
+            button.set_visible(self.order.len() > self.limit);
+        }
+    }
+}
+
 /// Handles UI updates as callback,
 /// getting the diff since the previous update and applying it to the menu.
 fn on_update(
     update: Event,
-    container: &gtk::Box,
+    layout: &mut TrayLayout,
     menus: &mut HashMap<Box<str>, TrayMenu>,
     icon_theme: &IconTheme,
-    icon_size: u32,
-    prefer_icons: bool,
+    bar_position: BarPosition,
+    tx: &mpsc::Sender<ActivateRequest>,
+    module: &TrayModule,
 ) {
+    let icon_size = module.icon_size;
+    let prefer_icons = module.prefer_theme_icons;
+
     match update {
         Event::Add(address, item) => {
             debug!("Received new tray item at '{address}': {item:?}");
 
-            let mut menu_item = TrayMenu::new(&address, *item);
-            container.pack_start(&menu_item.event_box, true, true, 0);
+            let rank = module
+                .order
+                .iter()
+                .position(|name| matches(&item, name))
+                .unwrap_or(module.order.len());
+
+            // existing items are already sorted,
+            // so the new item goes after any with an equal or higher priority.
+            let position = menus.values().filter(|menu| menu.rank <= rank).count();
+
+            let mut menu_item = TrayMenu::new(
+                &address,
+                *item,
+                bar_position,
+                rank,
+                module.left_click,
+                tx.clone(),
+            );
 
             if let Ok(image) = icon::get_image(&menu_item, icon_theme, icon_size, prefer_icons) {
                 menu_item.set_image(&image);
@@ -160,7 +390,12 @@ fn on_update(
             };
 
             menu_item.event_box.show();
-            menus.insert(address.into(), menu_item);
+
+            let address: Box<str> = address.into();
+            layout.order.insert(position, address.clone());
+            menus.insert(address, menu_item);
+
+            layout.rebalance(menus);
         }
         Event::Update(address, update) => {
             debug!("Received tray update for '{address}'");
@@ -208,9 +443,28 @@ fn on_update(
         Event::Remove(address) => {
             debug!("Removing tray item at '{address}'");
 
-            if let Some(menu) = menus.get(address.as_str()) {
-                container.remove(&menu.event_box);
+            if let Some(menu) = menus.remove(address.as_str()) {
+                if let Some(parent) = menu
+                    .event_box
+                    .parent()
+                    .and_then(|parent| parent.downcast::<gtk::Box>().ok())
+                {
+                    parent.remove(&menu.event_box);
+                }
+
+                layout.order.retain(|item| **item != *address);
+                layout.rebalance(menus);
             }
         }
     };
 }
+
+/// Checks whether the item's ID or title matches the given name.
+fn matches(item: &StatusNotifierItem, name: &str) -> bool {
+    item.id == name || item.title.as_deref() == Some(name)
+}
+
+/// Checks whether the item matches any of the given names.
+fn matches_any(item: &StatusNotifierItem, names: &[String]) -> bool {
+    names.iter().any(|name| matches(item, name))
+}
@@ -8,7 +8,9 @@ use upower_dbus::BatteryState;
 use zbus;
 use zbus::fdo::PropertiesProxy;
 
-use crate::config::CommonConfig;
+use crate::clients::LazyClient;
+use crate::config::{CommonConfig, IconRamp};
+use crate::format;
 use crate::gtk_helpers::{IronbarGtkExt, IronbarLabelExt};
 use crate::image::ImageProvider;
 use crate::modules::PopupButton;
@@ -37,6 +39,14 @@ pub struct UpowerModule {
     #[serde(default = "default_icon_size")]
     icon_size: i32,
 
+    /// Icons to use for the `{icon}` token, from lowest to highest charge.
+    ///
+    /// See [icon ramps](formatting-tokens#icon-ramps).
+    ///
+    /// **Default**: `null`
+    #[serde(default)]
+    icons: Option<IconRamp>,
+
     /// See [common options](module-level-options#common-options).
     #[serde(flatten)]
     pub common: Option<CommonConfig>,
@@ -63,6 +73,8 @@ impl Module<gtk::Button> for UpowerModule {
     type SendMessage = UpowerProperties;
     type ReceiveMessage = ();
 
+    const REPORTS_READY: bool = true;
+
     module_impl!("upower");
 
     fn spawn_controller(
@@ -73,9 +85,13 @@ impl Module<gtk::Button> for UpowerModule {
     ) -> Result<()> {
         let tx = context.tx.clone();
 
-        let display_proxy = context.try_client::<PropertiesProxy>()?;
+        let display_proxy = context.client::<LazyClient<PropertiesProxy>>();
 
         spawn(async move {
+            let Some(display_proxy) = display_proxy.ready().await else {
+                return Ok(());
+            };
+
             let mut prop_changed_stream = display_proxy.receive_properties_changed().await?;
 
             let device_interface_name =
@@ -112,6 +128,7 @@ impl Module<gtk::Button> for UpowerModule {
             };
 
             send_async!(tx, ModuleUpdateEvent::Update(properties.clone()));
+            send_async!(tx, ModuleUpdateEvent::Ready);
 
             while let Some(signal) = prop_changed_stream.next().await {
                 let args = signal.args().expect("Invalid signal arguments");
@@ -191,6 +208,7 @@ impl Module<gtk::Button> for UpowerModule {
         });
 
         let format = self.format.clone();
+        let icons = self.icons.clone();
 
         let rx = context.subscribe();
         glib_recv!(rx, properties => {
@@ -202,9 +220,18 @@ impl Module<gtk::Button> for UpowerModule {
             else {
                 seconds_to_string(properties.time_to_empty)
             };
-            let format = format.replace("{percentage}", &properties.percentage.to_string())
-                .replace("{time_remaining}", &time_remaining)
-                .replace("{state}", battery_state_to_string(state));
+            let format = format::replace_tokens(&format, |token| match token {
+                "percentage" => Some(properties.percentage.to_string()),
+                "time_remaining" => Some(time_remaining.clone()),
+                "state" => Some(battery_state_to_string(state).to_string()),
+                "icon" => Some(
+                    icons
+                        .as_ref()
+                        .map(|icons| icons.get(properties.percentage).to_string())
+                        .unwrap_or_default(),
+                ),
+                _ => None,
+            });
 
             let mut icon_name = String::from("icon:");
             icon_name.push_str(&properties.icon_name);
@@ -1,16 +1,23 @@
-use crate::clients::volume::{self, Event};
-use crate::config::CommonConfig;
+use crate::clients::volume::{Event, SinkInput, VolumeClient};
+use crate::config::{CommonConfig, IconRamp};
+use crate::format;
 use crate::gtk_helpers::IronbarGtkExt;
+use crate::image::ImageProvider;
 use crate::modules::{
     Module, ModuleInfo, ModuleParts, ModulePopup, ModuleUpdateEvent, PopupButton, WidgetContext,
 };
-use crate::{glib_recv, lock, module_impl, send_async, spawn, try_send};
+use crate::{glib_recv, lock, module_impl, rc_mut, send_async, spawn, try_send};
 use glib::Propagation;
 use gtk::pango::EllipsizeMode;
 use gtk::prelude::*;
-use gtk::{Button, CellRendererText, ComboBoxText, Label, Orientation, Scale, ToggleButton};
+use gtk::{
+    Button, CellRendererText, ComboBoxText, IconTheme, Image, Label, Orientation, Scale,
+    ToggleButton,
+};
 use serde::Deserialize;
+use std::cell::RefCell;
 use std::collections::HashMap;
+use std::rc::Rc;
 use tokio::sync::mpsc;
 
 #[derive(Debug, Clone, Deserialize)]
@@ -36,6 +43,12 @@ pub struct VolumeModule {
     #[serde(default)]
     icons: Icons,
 
+    /// The size to render application icons at in the popup.
+    ///
+    /// **Default**: `24`
+    #[serde(default = "default_icon_size")]
+    icon_size: i32,
+
     /// See [common options](module-level-options#common-options).
     #[serde(flatten)]
     pub common: Option<CommonConfig>,
@@ -45,9 +58,23 @@ fn default_format() -> String {
     String::from("{icon} {percentage}%")
 }
 
+const fn default_icon_size() -> i32 {
+    24
+}
+
 #[derive(Debug, Clone, Deserialize)]
 #[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Icons {
+    /// Icons to show across the volume range, from lowest to highest.
+    /// When set, this takes priority over the
+    /// `volume_high`, `volume_medium` and `volume_low` icons.
+    ///
+    /// See [icon ramps](formatting-tokens#icon-ramps).
+    ///
+    /// **Default**: `null`
+    #[serde(default)]
+    volume: Option<IconRamp>,
+
     /// Icon to show for high volume levels.
     ///
     /// **Default**: `󰕾`
@@ -75,6 +102,10 @@ pub struct Icons {
 
 impl Icons {
     fn volume_icon(&self, volume_percent: f64) -> &str {
+        if let Some(ramp) = &self.volume {
+            return ramp.get(volume_percent);
+        }
+
         match volume_percent as u32 {
             0..=33 => &self.volume_low,
             34..=66 => &self.volume_medium,
@@ -86,6 +117,7 @@ impl Icons {
 impl Default for Icons {
     fn default() -> Self {
         Self {
+            volume: None,
             volume_high: default_icon_volume_high(),
             volume_medium: default_icon_volume_medium(),
             volume_low: default_icon_volume_low(),
@@ -120,8 +152,10 @@ pub enum Update {
     SinkVolume(String, f64),
     SinkMute(String, bool),
 
-    InputVolume(u32, f64),
-    InputMute(u32, bool),
+    /// Sets the volume of all the given sink inputs.
+    InputVolume(Vec<u32>, f64),
+    /// Sets the mute state of all the given sink inputs.
+    InputMute(Vec<u32>, bool),
 }
 
 impl Module<Button> for VolumeModule {
@@ -139,7 +173,7 @@ impl Module<Button> for VolumeModule {
     where
         <Self as Module<Button>>::SendMessage: Clone,
     {
-        let client = context.client::<volume::Client>();
+        let client = context.client::<dyn VolumeClient>();
 
         {
             let client = client.clone();
@@ -185,8 +219,16 @@ impl Module<Button> for VolumeModule {
                     Update::SinkChange(name) => client.set_default_sink(&name),
                     Update::SinkVolume(name, volume) => client.set_sink_volume(&name, volume),
                     Update::SinkMute(name, muted) => client.set_sink_muted(&name, muted),
-                    Update::InputVolume(index, volume) => client.set_input_volume(index, volume),
-                    Update::InputMute(index, muted) => client.set_input_muted(index, muted),
+                    Update::InputVolume(indexes, volume) => {
+                        for index in indexes {
+                            client.set_input_volume(index, volume);
+                        }
+                    }
+                    Update::InputMute(indexes, muted) => {
+                        for index in indexes {
+                            client.set_input_muted(index, muted);
+                        }
+                    }
                 }
             }
         });
@@ -222,10 +264,12 @@ impl Module<Button> for VolumeModule {
             glib_recv!(rx, event => {
                 match event {
                     Event::AddSink(sink) | Event::UpdateSink(sink) if sink.active => {
-                        let label = format
-                            .replace("{icon}", if sink.muted { &icons.muted } else { icons.volume_icon(sink.volume) })
-                            .replace("{percentage}", &sink.volume.to_string())
-                            .replace("{name}", &sink.description);
+                        let label = format::replace_tokens(&format, |token| match token {
+                            "icon" => Some(if sink.muted { icons.muted.clone() } else { icons.volume_icon(sink.volume).to_string() }),
+                            "percentage" => Some(sink.volume.to_string()),
+                            "name" => Some(sink.description.clone()),
+                            _ => None,
+                        });
 
                         button.set_label(&label);
                     },
@@ -251,7 +295,7 @@ impl Module<Button> for VolumeModule {
         tx: mpsc::Sender<Self::ReceiveMessage>,
         rx: tokio::sync::broadcast::Receiver<Self::SendMessage>,
         _context: WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
-        _info: &ModuleInfo,
+        info: &ModuleInfo,
     ) -> Option<gtk::Box>
     where
         Self: Sized,
@@ -267,6 +311,43 @@ impl Module<Button> for VolumeModule {
         container.add(&sink_container);
         container.add(&input_container);
 
+        // the master slider always controls the default sink,
+        // regardless of which device is selected in the dropdown.
+        let master_container = gtk::Box::new(Orientation::Vertical, 0);
+        master_container.add_class("master-box");
+
+        let master_label = Label::new(Some("Master"));
+        master_label.add_class("title");
+        master_container.add(&master_label);
+
+        let master_slider = Scale::builder()
+            .orientation(Orientation::Horizontal)
+            .build();
+
+        master_slider.add_class("master-slider");
+        master_slider.set_range(0.0, self.max_volume);
+        master_slider.set_value(50.0);
+        master_container.add(&master_slider);
+
+        sink_container.add(&master_container);
+
+        let default_sink = rc_mut!(None::<String>);
+
+        {
+            let tx = tx.clone();
+            let default_sink = default_sink.clone();
+
+            master_slider.connect_button_release_event(move |scale, _| {
+                if let Some(sink) = default_sink.borrow().as_ref() {
+                    // GTK will send values outside min/max range
+                    let val = scale.value().clamp(0.0, self.max_volume);
+                    try_send!(tx, Update::SinkVolume(sink.clone(), val));
+                }
+
+                Propagation::Proceed
+            });
+        }
+
         let sink_selector = ComboBoxText::new();
         sink_selector.add_class("device-selector");
 
@@ -337,7 +418,13 @@ impl Module<Button> for VolumeModule {
 
         container.show_all();
 
-        let mut inputs = HashMap::new();
+        let icon_theme = info.icon_theme.clone();
+
+        // streams are grouped by application,
+        // so these track the group key for each input index,
+        // and the UI for each group.
+        let mut input_groups = HashMap::<u32, String>::new();
+        let mut groups = HashMap::<String, InputUi>::new();
 
         {
             let input_container = input_container.clone();
@@ -350,6 +437,9 @@ impl Module<Button> for VolumeModule {
                         sink_selector.append(Some(&info.name), &info.description);
 
                         if info.active {
+                            default_sink.borrow_mut().replace(info.name.clone());
+                            master_slider.set_value(info.volume);
+
                             sink_selector.set_active(Some(sinks.len() as u32));
                             slider.set_value(info.volume);
 
@@ -361,6 +451,9 @@ impl Module<Button> for VolumeModule {
                     }
                     Event::UpdateSink(info) => {
                         if info.active {
+                            default_sink.borrow_mut().replace(info.name.clone());
+                            master_slider.set_value(info.volume);
+
                             if let Some(pos) = sinks.iter().position(|s| s.name == info.name) {
                                 sink_selector.set_active(Some(pos as u32));
                                 slider.set_value(info.volume);
@@ -378,69 +471,46 @@ impl Module<Button> for VolumeModule {
                     }
 
                     Event::AddInput(info) => {
-                        let index = info.index;
-
-                        let item_container = gtk::Box::new(Orientation::Vertical, 0);
-                        item_container.add_class("app-box");
-
-                        let label = Label::new(Some(&info.name));
-                        label.add_class("title");
-
-                        let slider = Scale::builder().sensitive(info.can_set_volume).build();
-                        slider.set_range(0.0, self.max_volume);
-                        slider.set_value(info.volume);
-                        slider.add_class("slider");
-
-                        {
-                            let tx = tx.clone();
-                            slider.connect_button_release_event(move |scale, _| {
-                                // GTK will send values outside min/max range
-                                let val = scale.value().clamp(0.0, self.max_volume);
-                                try_send!(tx, Update::InputVolume(index, val));
-
-                                Propagation::Proceed
-                            });
+                        let key = group_key(&info);
+                        input_groups.insert(info.index, key.clone());
+
+                        if let Some(ui) = groups.get(&key) {
+                            ui.inputs.borrow_mut().push(info);
+                            ui.update(&self.icons);
+                        } else {
+                            let ui = InputUi::new(&info, &tx, self.max_volume, &icon_theme, self.icon_size);
+                            ui.inputs.borrow_mut().push(info);
+                            ui.update(&self.icons);
+
+                            input_container.add(&ui.container);
+                            groups.insert(key, ui);
                         }
-
-                        let btn_mute = ToggleButton::new();
-                        btn_mute.add_class("btn-mute");
-
-                        btn_mute.set_active(info.muted);
-                        btn_mute.set_label(if info.muted { &self.icons.muted } else { self.icons.volume_icon(info.volume) });
-
-                        {
-                            let tx = tx.clone();
-                            btn_mute.connect_toggled(move |btn| {
-                                let muted = btn.is_active();
-                                try_send!(tx, Update::InputMute(index, muted));
-                            });
-                        }
-
-                        item_container.add(&label);
-                        item_container.add(&slider);
-                        item_container.add(&btn_mute);
-                        item_container.show_all();
-
-                        input_container.add(&item_container);
-
-                        inputs.insert(info.index, InputUi {
-                            container: item_container,
-                            label,
-                            slider,
-                            btn_mute
-                        });
                     }
                     Event::UpdateInput(info) => {
-                        if let Some(ui) = inputs.get(&info.index) {
-                            ui.label.set_label(&info.name);
-                            ui.slider.set_value(info.volume);
-                            ui.slider.set_sensitive(info.can_set_volume);
-                            ui.btn_mute.set_label(if info.muted { &self.icons.muted } else { self.icons.volume_icon(info.volume) });
+                        if let Some(ui) = input_groups.get(&info.index).and_then(|key| groups.get(key)) {
+                            if let Some(input) = ui.inputs.borrow_mut().iter_mut().find(|input| input.index == info.index) {
+                                *input = info;
+                            }
+
+                            ui.update(&self.icons);
                         }
                     }
                     Event::RemoveInput(index) => {
-                        if let Some(ui) = inputs.remove(&index) {
+                        let Some(key) = input_groups.remove(&index) else {
+                            continue;
+                        };
+
+                        let Some(ui) = groups.get(&key) else {
+                            continue;
+                        };
+
+                        ui.inputs.borrow_mut().retain(|input| input.index != index);
+
+                        if ui.inputs.borrow().is_empty() {
                             input_container.remove(&ui.container);
+                            groups.remove(&key);
+                        } else {
+                            ui.update(&self.icons);
                         }
                     }
                 }
@@ -451,9 +521,147 @@ impl Module<Button> for VolumeModule {
     }
 }
 
+/// Gets the key used to group a sink input in the popup.
+/// Streams from unknown applications are not grouped.
+fn group_key(input: &SinkInput) -> String {
+    input.app_id().map_or_else(
+        || format!("input-{}", input.index),
+        |app_id| format!("app-{app_id}"),
+    )
+}
+
+/// Popup controls for a group of sink inputs,
+/// which belong to the same application.
 struct InputUi {
     container: gtk::Box,
     label: Label,
     slider: Scale,
     btn_mute: ToggleButton,
+
+    inputs: Rc<RefCell<Vec<SinkInput>>>,
+}
+
+impl InputUi {
+    fn new(
+        info: &SinkInput,
+        tx: &mpsc::Sender<Update>,
+        max_volume: f64,
+        icon_theme: &IconTheme,
+        icon_size: i32,
+    ) -> Self {
+        let inputs = rc_mut!(Vec::<SinkInput>::new());
+
+        let indexes = {
+            let inputs = inputs.clone();
+            move || {
+                inputs
+                    .borrow()
+                    .iter()
+                    .map(|input| input.index)
+                    .collect::<Vec<_>>()
+            }
+        };
+
+        let item_container = gtk::Box::new(Orientation::Vertical, 0);
+        item_container.add_class("app-box");
+
+        let header = gtk::Box::new(Orientation::Horizontal, 5);
+
+        let icon = Image::new();
+        icon.add_class("icon");
+
+        // applications may set an icon name,
+        // otherwise try to find their desktop file.
+        let icon_input = info
+            .app_icon
+            .as_ref()
+            .map(|icon| format!("icon:{icon}"))
+            .or_else(|| info.app_id().map(ToString::to_string));
+
+        let has_icon = icon_input
+            .and_then(|input| ImageProvider::parse(&input, icon_theme, false, icon_size))
+            .is_some_and(|provider| provider.load_into_image(&icon).is_ok());
+
+        if has_icon {
+            header.add(&icon);
+        }
+
+        let label = Label::new(None);
+        label.add_class("title");
+        header.add(&label);
+
+        let slider = Scale::builder().build();
+        slider.set_range(0.0, max_volume);
+        slider.add_class("slider");
+
+        {
+            let tx = tx.clone();
+            let indexes = indexes.clone();
+
+            slider.connect_button_release_event(move |scale, _| {
+                // GTK will send values outside min/max range
+                let val = scale.value().clamp(0.0, max_volume);
+                try_send!(tx, Update::InputVolume(indexes(), val));
+
+                Propagation::Proceed
+            });
+        }
+
+        let btn_mute = ToggleButton::new();
+        btn_mute.add_class("btn-mute");
+        btn_mute.set_active(info.muted);
+
+        {
+            let tx = tx.clone();
+            btn_mute.connect_toggled(move |btn| {
+                let muted = btn.is_active();
+                try_send!(tx, Update::InputMute(indexes(), muted));
+            });
+        }
+
+        item_container.add(&header);
+        item_container.add(&slider);
+        item_container.add(&btn_mute);
+        item_container.show_all();
+
+        Self {
+            container: item_container,
+            label,
+            slider,
+            btn_mute,
+            inputs,
+        }
+    }
+
+    /// Updates the controls to reflect the current state of the group.
+    ///
+    /// The group shows the loudest stream's volume,
+    /// and is only shown as muted when all its streams are muted.
+    fn update(&self, icons: &Icons) {
+        let inputs = self.inputs.borrow();
+
+        let Some(first) = inputs.first() else {
+            return;
+        };
+
+        // single streams from unknown apps keep their stream name
+        let name = first
+            .app_name
+            .as_ref()
+            .or(first.app_binary.as_ref())
+            .unwrap_or(&first.name);
+
+        let volume = inputs.iter().map(|input| input.volume).fold(0.0, f64::max);
+        let muted = inputs.iter().all(|input| input.muted);
+        let can_set_volume = inputs.iter().any(|input| input.can_set_volume);
+
+        self.label.set_label(name);
+        self.slider.set_value(volume);
+        self.slider.set_sensitive(can_set_volume);
+        self.btn_mute.set_label(if muted {
+            &icons.muted
+        } else {
+            icons.volume_icon(volume)
+        });
+    }
 }
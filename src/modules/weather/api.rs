@@ -0,0 +1,439 @@
+use color_eyre::{Report, Result};
+use serde::Deserialize;
+use std::fmt::{Display, Formatter};
+
+/// The weather API which the configured URL points to.
+/// This determines how the response is parsed.
+#[derive(Debug, Default, Deserialize, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum WeatherApi {
+    /// [wttr.in](https://wttr.in), using its JSON format (`?format=j1`).
+    #[default]
+    Wttr,
+    /// [Open-Meteo](https://open-meteo.com),
+    /// requesting `current=temperature_2m,weather_code`
+    /// and `daily=weather_code,temperature_2m_max,temperature_2m_min`.
+    OpenMeteo,
+}
+
+impl WeatherApi {
+    /// Parses a response body from this API.
+    ///
+    /// If the API responded with an error,
+    /// its message is returned as the error.
+    pub fn parse(self, body: &str) -> Result<Weather> {
+        match self {
+            Self::Wttr => {
+                if let Ok(error) = serde_json::from_str::<wttr::ErrorResponse>(body) {
+                    return Err(Report::msg(error.to_string()));
+                }
+
+                serde_json::from_str::<wttr::Response>(body)?.try_into()
+            }
+            Self::OpenMeteo => {
+                if let Ok(error) = serde_json::from_str::<open_meteo::ErrorResponse>(body) {
+                    return Err(Report::msg(error.reason));
+                }
+
+                serde_json::from_str::<open_meteo::Response>(body)?.try_into()
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Weather {
+    pub temperature: f64,
+    pub condition: Condition,
+    pub forecast: Vec<Forecast>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Forecast {
+    pub date: String,
+    pub min: f64,
+    pub max: f64,
+    pub condition: Condition,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Condition {
+    Clear,
+    PartlyCloudy,
+    Cloudy,
+    Fog,
+    Rain,
+    Snow,
+    Storm,
+    Unknown,
+}
+
+impl Condition {
+    /// Gets the name of the freedesktop icon for this condition.
+    pub const fn icon_name(self) -> &'static str {
+        match self {
+            Self::Clear => "weather-clear",
+            Self::PartlyCloudy => "weather-few-clouds",
+            Self::Cloudy => "weather-overcast",
+            Self::Fog => "weather-fog",
+            Self::Rain => "weather-showers",
+            Self::Snow => "weather-snow",
+            Self::Storm => "weather-storm",
+            Self::Unknown => "weather-severe-alert",
+        }
+    }
+
+    /// Gets a symbol for this condition, for use in text.
+    pub const fn symbol(self) -> &'static str {
+        match self {
+            Self::Clear => "☀",
+            Self::PartlyCloudy => "⛅",
+            Self::Cloudy => "☁",
+            Self::Fog => "🌫",
+            Self::Rain => "🌧",
+            Self::Snow => "❄",
+            Self::Storm => "⛈",
+            Self::Unknown => "?",
+        }
+    }
+
+    /// Maps a [WWO weather code](https://www.worldweatheronline.com/weather-api/api/docs/weather-icons.aspx),
+    /// as used by wttr.in.
+    fn from_wwo_code(code: u32) -> Self {
+        match code {
+            113 => Self::Clear,
+            116 => Self::PartlyCloudy,
+            119 | 122 => Self::Cloudy,
+            143 | 248 | 260 => Self::Fog,
+            176 | 263 | 266 | 293..=314 | 353..=359 => Self::Rain,
+            179..=185 | 227 | 230 | 281 | 284 | 317..=350 | 362..=377 => Self::Snow,
+            200 | 386..=395 => Self::Storm,
+            _ => Self::Unknown,
+        }
+    }
+
+    /// Maps a [WMO weather code](https://open-meteo.com/en/docs#weathervariables),
+    /// as used by Open-Meteo.
+    fn from_wmo_code(code: u32) -> Self {
+        match code {
+            0 => Self::Clear,
+            1 | 2 => Self::PartlyCloudy,
+            3 => Self::Cloudy,
+            45 | 48 => Self::Fog,
+            51..=67 | 80..=82 => Self::Rain,
+            71..=77 | 85 | 86 => Self::Snow,
+            95..=99 => Self::Storm,
+            _ => Self::Unknown,
+        }
+    }
+}
+
+impl Display for Condition {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::Clear => "Clear",
+                Self::PartlyCloudy => "Partly cloudy",
+                Self::Cloudy => "Cloudy",
+                Self::Fog => "Fog",
+                Self::Rain => "Rain",
+                Self::Snow => "Snow",
+                Self::Storm => "Storm",
+                Self::Unknown => "Unknown",
+            }
+        )
+    }
+}
+
+mod wttr {
+    use super::{Condition, Forecast, Weather};
+    use color_eyre::{Report, Result};
+    use serde::Deserialize;
+    use std::fmt::{Display, Formatter};
+
+    // wttr.in returns all values as strings
+
+    /// Body returned for invalid requests,
+    /// such as an unknown location.
+    #[derive(Debug, Deserialize)]
+    pub struct ErrorResponse {
+        data: ErrorData,
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct ErrorData {
+        error: Vec<ErrorMessage>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct ErrorMessage {
+        msg: String,
+    }
+
+    impl Display for ErrorResponse {
+        fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+            let messages = self
+                .data
+                .error
+                .iter()
+                .map(|error| error.msg.as_str())
+                .collect::<Vec<_>>();
+
+            write!(f, "{}", messages.join(", "))
+        }
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct Response {
+        current_condition: Vec<Current>,
+        weather: Vec<Day>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Current {
+        #[serde(rename = "temp_C")]
+        temp_c: String,
+        weather_code: String,
+    }
+
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Day {
+        date: String,
+        #[serde(rename = "mintempC")]
+        min_temp_c: String,
+        #[serde(rename = "maxtempC")]
+        max_temp_c: String,
+        hourly: Vec<Hour>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Hour {
+        weather_code: String,
+    }
+
+    fn condition(code: &str) -> Condition {
+        code.parse()
+            .map_or(Condition::Unknown, Condition::from_wwo_code)
+    }
+
+    impl TryFrom<Response> for Weather {
+        type Error = Report;
+
+        fn try_from(response: Response) -> Result<Self> {
+            let current = response
+                .current_condition
+                .first()
+                .ok_or_else(|| Report::msg("Response has no current conditions"))?;
+
+            let forecast = response
+                .weather
+                .into_iter()
+                .map(|day| {
+                    // use the midday reading for the day's condition
+                    let condition = day
+                        .hourly
+                        .get(day.hourly.len() / 2)
+                        .map_or(Condition::Unknown, |hour| condition(&hour.weather_code));
+
+                    Ok(Forecast {
+                        date: day.date,
+                        min: day.min_temp_c.parse()?,
+                        max: day.max_temp_c.parse()?,
+                        condition,
+                    })
+                })
+                .collect::<Result<_>>()?;
+
+            Ok(Self {
+                temperature: current.temp_c.parse()?,
+                condition: condition(&current.weather_code),
+                forecast,
+            })
+        }
+    }
+}
+
+mod open_meteo {
+    use super::{Condition, Forecast, Weather};
+    use color_eyre::{Report, Result};
+    use serde::Deserialize;
+
+    #[derive(Debug, Deserialize)]
+    pub struct Response {
+        current: Current,
+        daily: Option<Daily>,
+    }
+
+    /// Body returned for invalid requests,
+    /// such as unknown variables.
+    #[derive(Debug, Deserialize)]
+    pub struct ErrorResponse {
+        pub reason: String,
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct Current {
+        temperature_2m: f64,
+        weather_code: u32,
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct Daily {
+        time: Vec<String>,
+        weather_code: Vec<u32>,
+        temperature_2m_max: Vec<f64>,
+        temperature_2m_min: Vec<f64>,
+    }
+
+    impl TryFrom<Response> for Weather {
+        type Error = Report;
+
+        fn try_from(response: Response) -> Result<Self> {
+            let forecast = response
+                .daily
+                .map(|daily| {
+                    daily
+                        .time
+                        .into_iter()
+                        .zip(daily.weather_code)
+                        .zip(daily.temperature_2m_min)
+                        .zip(daily.temperature_2m_max)
+                        .map(|(((date, code), min), max)| Forecast {
+                            date,
+                            min,
+                            max,
+                            condition: Condition::from_wmo_code(code),
+                        })
+                        .collect()
+                })
+                .unwrap_or_default();
+
+            Ok(Self {
+                temperature: response.current.temperature_2m,
+                condition: Condition::from_wmo_code(response.current.weather_code),
+                forecast,
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WTTR_RESPONSE: &str = r#"{
+        "current_condition": [
+            { "temp_C": "14", "weatherCode": "116", "humidity": "71" }
+        ],
+        "weather": [
+            {
+                "date": "2024-05-01",
+                "mintempC": "9",
+                "maxtempC": "17",
+                "hourly": [
+                    { "time": "0", "weatherCode": "113" },
+                    { "time": "1200", "weatherCode": "296" },
+                    { "time": "2100", "weatherCode": "113" }
+                ]
+            },
+            {
+                "date": "2024-05-02",
+                "mintempC": "-2",
+                "maxtempC": "4",
+                "hourly": [
+                    { "time": "0", "weatherCode": "338" }
+                ]
+            }
+        ]
+    }"#;
+
+    const WTTR_ERROR: &str = r#"{
+        "data": {
+            "error": [
+                { "msg": "Unable to find any matching weather location to the query submitted!" }
+            ]
+        }
+    }"#;
+
+    const OPEN_METEO_RESPONSE: &str = r#"{
+        "latitude": 52.52,
+        "longitude": 13.42,
+        "current": { "time": "2024-05-01T12:00", "temperature_2m": 13.6, "weather_code": 3 },
+        "daily": {
+            "time": ["2024-05-01", "2024-05-02"],
+            "weather_code": [61, 95],
+            "temperature_2m_max": [18.2, 21.0],
+            "temperature_2m_min": [8.1, 11.4]
+        }
+    }"#;
+
+    const OPEN_METEO_ERROR: &str = r#"{
+        "error": true,
+        "reason": "Cannot initialize WeatherVariable from invalid String value temperature for key current"
+    }"#;
+
+    #[test]
+    fn test_parse_wttr() {
+        let weather = WeatherApi::Wttr.parse(WTTR_RESPONSE).expect("to parse");
+
+        assert_eq!(weather.temperature, 14.0);
+        assert_eq!(weather.condition, Condition::PartlyCloudy);
+
+        assert_eq!(weather.forecast.len(), 2);
+        assert_eq!(weather.forecast[0].date, "2024-05-01");
+        assert_eq!(weather.forecast[0].min, 9.0);
+        assert_eq!(weather.forecast[0].max, 17.0);
+        assert_eq!(weather.forecast[0].condition, Condition::Rain);
+        assert_eq!(weather.forecast[1].min, -2.0);
+        assert_eq!(weather.forecast[1].condition, Condition::Snow);
+    }
+
+    #[test]
+    fn test_parse_wttr_error() {
+        let err = WeatherApi::Wttr.parse(WTTR_ERROR).expect_err("to fail");
+        assert_eq!(
+            err.to_string(),
+            "Unable to find any matching weather location to the query submitted!"
+        );
+    }
+
+    #[test]
+    fn test_parse_open_meteo() {
+        let weather = WeatherApi::OpenMeteo
+            .parse(OPEN_METEO_RESPONSE)
+            .expect("to parse");
+
+        assert_eq!(weather.temperature, 13.6);
+        assert_eq!(weather.condition, Condition::Cloudy);
+
+        assert_eq!(weather.forecast.len(), 2);
+        assert_eq!(weather.forecast[0].date, "2024-05-01");
+        assert_eq!(weather.forecast[0].min, 8.1);
+        assert_eq!(weather.forecast[0].max, 18.2);
+        assert_eq!(weather.forecast[0].condition, Condition::Rain);
+        assert_eq!(weather.forecast[1].condition, Condition::Storm);
+    }
+
+    #[test]
+    fn test_parse_open_meteo_error() {
+        let err = WeatherApi::OpenMeteo
+            .parse(OPEN_METEO_ERROR)
+            .expect_err("to fail");
+
+        assert!(err
+            .to_string()
+            .starts_with("Cannot initialize WeatherVariable"));
+    }
+
+    #[test]
+    fn test_parse_invalid_body() {
+        assert!(WeatherApi::Wttr.parse("not json").is_err());
+        assert!(WeatherApi::OpenMeteo.parse("{}").is_err());
+    }
+}
@@ -0,0 +1,248 @@
+mod api;
+
+use self::api::{Weather, WeatherApi};
+use crate::config::CommonConfig;
+use crate::format;
+use crate::gtk_helpers::{IronbarGtkExt, IronbarLabelExt};
+use crate::image::ImageProvider;
+use crate::modules::{
+    Module, ModuleInfo, ModuleParts, ModulePopup, ModuleUpdateEvent, PopupButton, WidgetContext,
+};
+use crate::{glib_recv, module_impl, send_async, spawn, try_send};
+use color_eyre::Result;
+use gtk::prelude::*;
+use gtk::{Button, Label, Orientation};
+use serde::Deserialize;
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc};
+use tokio::time::sleep;
+use tracing::warn;
+
+#[derive(Debug, Deserialize, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct WeatherModule {
+    /// The URL to fetch weather data from.
+    /// This must return JSON in the format of the chosen `api`.
+    ///
+    /// **Required**
+    url: String,
+
+    /// The API which the URL points to.
+    ///
+    /// **Valid options**: `wttr`, `open_meteo`
+    /// <br>
+    /// **Default**: `wttr`
+    #[serde(default)]
+    api: WeatherApi,
+
+    /// Number of seconds between refreshing.
+    /// Values below `300` are raised to `300`,
+    /// to avoid flooding the weather service with requests.
+    ///
+    /// **Default**: `900`
+    #[serde(default = "default_interval")]
+    interval: u64,
+
+    /// The format string to use for the widget button label.
+    /// For available tokens, see [below](#formatting-tokens).
+    ///
+    /// **Default**: `{temp}°`
+    #[serde(default = "default_format")]
+    format: String,
+
+    /// The size to render the condition icon at, in pixels.
+    ///
+    /// **Default**: `24`
+    #[serde(default = "default_icon_size")]
+    icon_size: i32,
+
+    /// See [common options](module-level-options#common-options).
+    #[serde(flatten)]
+    pub common: Option<CommonConfig>,
+}
+
+const fn default_interval() -> u64 {
+    900
+}
+
+/// The shortest allowed refresh interval, in seconds.
+const MIN_INTERVAL: u64 = 300;
+
+fn default_format() -> String {
+    String::from("{temp}°")
+}
+
+const fn default_icon_size() -> i32 {
+    24
+}
+
+#[derive(Debug, Clone)]
+pub enum WeatherEvent {
+    Update(Weather),
+    /// The latest fetch failed,
+    /// so the last value is out of date.
+    Stale,
+}
+
+async fn fetch(url: &str, api: WeatherApi) -> Result<Weather> {
+    let body = reqwest::get(url).await?.error_for_status()?.text().await?;
+
+    api.parse(&body)
+}
+
+impl Module<Button> for WeatherModule {
+    type SendMessage = WeatherEvent;
+    type ReceiveMessage = ();
+
+    module_impl!("weather");
+
+    fn spawn_controller(
+        &self,
+        _info: &ModuleInfo,
+        context: &WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
+        _rx: mpsc::Receiver<Self::ReceiveMessage>,
+    ) -> Result<()> {
+        let tx = context.tx.clone();
+        let url = self.url.clone();
+        let api = self.api;
+
+        let interval = if self.interval < MIN_INTERVAL {
+            warn!(
+                "Weather refresh interval '{}' is too short, using minimum of {MIN_INTERVAL}s",
+                self.interval
+            );
+            MIN_INTERVAL
+        } else {
+            self.interval
+        };
+
+        let interval = Duration::from_secs(interval);
+
+        spawn(async move {
+            loop {
+                let event = match fetch(&url, api).await {
+                    Ok(weather) => WeatherEvent::Update(weather),
+                    Err(err) => {
+                        warn!("Failed to fetch weather: {err:?}");
+                        WeatherEvent::Stale
+                    }
+                };
+
+                send_async!(tx, ModuleUpdateEvent::Update(event));
+                sleep(interval).await;
+            }
+        });
+
+        Ok(())
+    }
+
+    fn into_widget(
+        self,
+        context: WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
+        info: &ModuleInfo,
+    ) -> Result<ModuleParts<Button>> {
+        let icon_theme = info.icon_theme.clone();
+
+        let icon = gtk::Image::new();
+        icon.add_class("icon");
+
+        let label = Label::builder().use_markup(true).build();
+        label.add_class("label");
+
+        let container = gtk::Box::new(info.bar_position.orientation(), 5);
+        container.add_class("contents");
+        container.add(&icon);
+        container.add(&label);
+
+        let button = Button::new();
+        button.add(&container);
+
+        let tx = context.tx.clone();
+        button.connect_clicked(move |button| {
+            try_send!(tx, ModuleUpdateEvent::TogglePopup(button.popup_id()));
+        });
+
+        {
+            let button = button.clone();
+            let format = self.format.clone();
+            let icon_size = self.icon_size;
+
+            glib_recv!(context.subscribe(), event => {
+                match event {
+                    WeatherEvent::Update(weather) => {
+                        button.remove_class("stale");
+
+                        let text = format::replace_tokens(&format, |token| match token {
+                            "temp" => Some(format!("{:.0}", weather.temperature)),
+                            "condition" => Some(weather.condition.to_string()),
+                            "icon" => Some(weather.condition.symbol().to_string()),
+                            _ => None,
+                        });
+                        label.set_label_escaped(&text);
+
+                        let icon_name = format!("icon:{}", weather.condition.icon_name());
+                        ImageProvider::parse(&icon_name, &icon_theme, false, icon_size)
+                            .map(|provider| provider.load_into_image(&icon));
+                    }
+                    // keep showing the last value
+                    WeatherEvent::Stale => button.add_class("stale"),
+                }
+            });
+        }
+
+        let rx = context.subscribe();
+        let popup = self
+            .into_popup(context.controller_tx.clone(), rx, context, info)
+            .into_popup_parts(vec![&button]);
+
+        Ok(ModuleParts::new(button, popup))
+    }
+
+    fn into_popup(
+        self,
+        _tx: mpsc::Sender<Self::ReceiveMessage>,
+        rx: broadcast::Receiver<Self::SendMessage>,
+        _context: WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
+        _info: &ModuleInfo,
+    ) -> Option<gtk::Box>
+    where
+        Self: Sized,
+    {
+        let container = gtk::Box::new(Orientation::Vertical, 5);
+
+        let forecast = gtk::Box::new(Orientation::Vertical, 0);
+        forecast.add_class("forecast");
+        container.add(&forecast);
+
+        glib_recv!(rx, event => {
+            let WeatherEvent::Update(weather) = event else {
+                continue;
+            };
+
+            for child in forecast.children() {
+                forecast.remove(&child);
+            }
+
+            for day in weather.forecast {
+                let text = format!(
+                    "{}  {} {}  {:.0}° / {:.0}°",
+                    day.date,
+                    day.condition.symbol(),
+                    day.condition,
+                    day.min,
+                    day.max
+                );
+
+                let label = Label::new(Some(&text));
+                label.add_class("day");
+                forecast.add(&label);
+            }
+
+            forecast.show_all();
+        });
+
+        container.show_all();
+
+        Some(container)
+    }
+}
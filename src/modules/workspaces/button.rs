@@ -1,41 +1,155 @@
 use super::open_state::OpenState;
+use crate::clients::compositor::CompositorCapabilities;
 use crate::gtk_helpers::IronbarGtkExt;
-use crate::image::new_icon_button;
-use crate::modules::workspaces::WorkspaceItemContext;
-use crate::try_send;
+use crate::image::ImageProvider;
+#[cfg(feature = "launcher")]
+use crate::modules::launcher;
+use crate::modules::workspaces::{WorkspaceEvent, WorkspaceItemContext};
+use crate::{rc_mut, try_send};
+use glib::{ControlFlow, SourceId};
+#[cfg(feature = "launcher")]
+use gtk::gdk::DragAction;
+use gtk::pango::EllipsizeMode;
 use gtk::prelude::*;
-use gtk::Button as GtkButton;
+use gtk::{Button as GtkButton, Image, Label};
+#[cfg(feature = "launcher")]
+use gtk::{DestDefaults, TargetEntry, TargetFlags};
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use std::time::Duration;
+use tracing::warn;
+
+/// How often the pulse class is toggled while a workspace is urgent.
+const URGENT_PULSE_INTERVAL: Duration = Duration::from_millis(500);
+const URGENT_PULSE_CLASS: &str = "urgent-pulse";
 
 #[derive(Debug, Clone)]
 pub struct Button {
     button: GtkButton,
     workspace_id: i64,
+    name: String,
+    windows: Cell<Option<u32>>,
+    urgent_animation: bool,
+    urgent_pulse: Rc<RefCell<Option<SourceId>>>,
 }
 
 impl Button {
     pub fn new(id: i64, name: &str, open_state: OpenState, context: &WorkspaceItemContext) -> Self {
-        let label = context.name_map.get(name).map_or(name, String::as_str);
-
-        let button = new_icon_button(label, &context.icon_theme, context.icon_size);
+        let button = GtkButton::new();
         button.set_widget_name(name);
         button.add_class("item");
 
-        let tx = context.tx.clone();
+        if let Some(min_width) = context.button_min_width {
+            button.set_size_request(min_width, -1);
+        }
+
+        // accept launcher items dropped onto the button,
+        // moving their window to this workspace.
+        #[cfg(feature = "launcher")]
+        if context
+            .capabilities
+            .contains(CompositorCapabilities::MOVE_WINDOW)
+        {
+            let targets = [TargetEntry::new(
+                launcher::DRAG_TARGET,
+                TargetFlags::SAME_APP,
+                0,
+            )];
+            button.drag_dest_set(DestDefaults::ALL, &targets, DragAction::MOVE);
+
+            let tx = context.tx.clone();
+            button.connect_drag_data_received(move |button, _, _, _, data, _, _| {
+                if let Some(app_id) = data.text() {
+                    try_send!(
+                        tx,
+                        WorkspaceEvent::MoveWindow {
+                            app_id: app_id.to_string(),
+                            workspace: button.widget_name().to_string(),
+                        }
+                    );
+                }
+            });
+        }
 
-        let name = name.to_string();
-        button.connect_clicked(move |_item| {
-            try_send!(tx, name.clone());
-        });
+        if context
+            .capabilities
+            .contains(CompositorCapabilities::FOCUS_WORKSPACE)
+        {
+            let tx = context.tx.clone();
+
+            // the widget name is kept in sync with the workspace name,
+            // so this is read on click to handle renames.
+            button.connect_clicked(move |item| {
+                try_send!(tx, WorkspaceEvent::Focus(item.widget_name().to_string()));
+            });
+        }
 
         let btn = Self {
             button,
             workspace_id: id,
+            name: name.to_string(),
+            windows: Cell::new(None),
+            urgent_animation: context.urgent_animation,
+            urgent_pulse: rc_mut!(None),
         };
 
+        btn.update_contents(context);
         btn.set_open_state(open_state);
         btn
     }
 
+    /// Sets the button's label, or its image if the workspace is mapped to one.
+    ///
+    /// Images are rendered at the configured icon size.
+    /// If an image cannot be loaded, the workspace's formatted name is shown instead.
+    fn update_contents(&self, context: &WorkspaceItemContext) {
+        let windows = self.windows.get();
+        let label = context.format_label(&self.name, windows);
+
+        let label = if ImageProvider::is_definitely_image_input(&label) {
+            let image = Image::new();
+            image.add_class("image");
+            image.add_class("icon");
+
+            match ImageProvider::parse(&label, &context.icon_theme, false, context.icon_size)
+                .map(|provider| provider.load_into_image(&image))
+            {
+                Some(Ok(())) => {
+                    self.button.set_property("label", None::<&str>);
+                    self.button.set_image(Some(&image));
+                    self.button.set_always_show_image(true);
+                    return;
+                }
+                Some(Err(err)) => {
+                    warn!(
+                        "Failed to load image for workspace '{}': {err:?}",
+                        self.name
+                    );
+                }
+                None => warn!("Failed to resolve image for workspace '{}'", self.name),
+            }
+
+            context.format(&self.name, &self.name, windows)
+        } else {
+            label
+        };
+
+        self.button.set_image(None::<&Image>);
+        self.button.set_label(&label);
+
+        // the button creates its own label child for text
+        if let Some(max_width) = context.button_max_width {
+            if let Some(label) = self
+                .button
+                .child()
+                .and_then(|child| child.downcast::<Label>().ok())
+            {
+                label.set_ellipsize(EllipsizeMode::End);
+                label.set_max_width_chars(max_width);
+            }
+        }
+    }
+
     pub fn button(&self) -> &GtkButton {
         &self.button
     }
@@ -63,8 +177,83 @@ impl Button {
     pub fn set_urgent(&self, urgent: bool) {
         if urgent {
             self.button.add_class("urgent");
+
+            if self.urgent_animation {
+                self.start_urgent_pulse();
+            }
         } else {
             self.button.remove_class("urgent");
+            self.stop_urgent_pulse();
+        }
+    }
+
+    /// Starts toggling the pulse class on a timer,
+    /// if it is not already running.
+    fn start_urgent_pulse(&self) {
+        let mut pulse = self.urgent_pulse.borrow_mut();
+        if pulse.is_some() {
+            return;
+        }
+
+        let button = self.button.downgrade();
+
+        *pulse = Some(glib::timeout_add_local(URGENT_PULSE_INTERVAL, move || {
+            let Some(button) = button.upgrade() else {
+                return ControlFlow::Break;
+            };
+
+            if button.style_context().has_class(URGENT_PULSE_CLASS) {
+                button.remove_class(URGENT_PULSE_CLASS);
+            } else {
+                button.add_class(URGENT_PULSE_CLASS);
+            }
+
+            ControlFlow::Continue
+        }));
+    }
+
+    /// Cancels the pulse timer and removes the pulse class.
+    fn stop_urgent_pulse(&self) {
+        if let Some(source) = self.urgent_pulse.borrow_mut().take() {
+            source.remove();
+        }
+
+        self.button.remove_class(URGENT_PULSE_CLASS);
+    }
+
+    /// Updates the label and occupied state with the workspace's window count.
+    /// Buttons displaying an image are left untouched.
+    pub fn set_windows(&self, windows: Option<u32>, context: &WorkspaceItemContext) {
+        self.windows.set(windows);
+
+        if self.button.image().is_none() {
+            self.update_contents(context);
+        }
+
+        self.set_occupied(windows.map(|count| count > 0));
+    }
+
+    /// Updates the workspace name,
+    /// re-resolving the button's label or image.
+    pub fn set_name(&mut self, name: &str, context: &WorkspaceItemContext) {
+        name.clone_into(&mut self.name);
+        self.button.set_widget_name(name);
+        self.update_contents(context);
+    }
+
+    /// Sets the `occupied` or `empty` class.
+    /// If occupancy is unknown, neither class is set.
+    pub fn set_occupied(&self, occupied: Option<bool>) {
+        if occupied == Some(true) {
+            self.button.add_class("occupied");
+        } else {
+            self.button.remove_class("occupied");
+        }
+
+        if occupied == Some(false) {
+            self.button.add_class("empty");
+        } else {
+            self.button.remove_class("empty");
         }
     }
 
@@ -3,15 +3,27 @@ mod button_map;
 mod open_state;
 
 use self::button::Button;
-use crate::clients::compositor::{Workspace, WorkspaceClient, WorkspaceUpdate};
+#[cfg(feature = "launcher")]
+use crate::clients::compositor::WindowTarget;
+use crate::clients::compositor::{
+    CompositorCapabilities, Workspace, WorkspaceClient, WorkspaceUpdate,
+};
+#[cfg(feature = "launcher")]
+use crate::clients::wayland;
 use crate::config::CommonConfig;
+use crate::format;
+use crate::gtk_helpers::IronbarGtkExt;
+use crate::image::ImageProvider;
 use crate::modules::workspaces::button_map::{ButtonMap, Identifier};
 use crate::modules::workspaces::open_state::OpenState;
 use crate::modules::{Module, ModuleInfo, ModuleParts, ModuleUpdateEvent, WidgetContext};
 use crate::{glib_recv, module_impl, send_async, spawn};
+use color_eyre::eyre::WrapErr;
 use color_eyre::{Report, Result};
 use gtk::prelude::*;
 use gtk::IconTheme;
+use indexmap::IndexMap;
+use regex::Regex;
 use serde::Deserialize;
 use std::cmp::Ordering;
 use std::collections::HashMap;
@@ -62,12 +74,46 @@ impl Default for Favorites {
 pub struct WorkspacesModule {
     /// Map of actual workspace names to custom names.
     ///
-    /// Custom names can be [images](images).
+    /// Custom names can be [images](images),
+    /// which are rendered at `icon_size`.
+    /// If an image fails to load, the workspace's name is shown instead.
     ///
     /// If a workspace is not present in the map,
-    /// it will fall back to using its actual name.
+    /// it will fall back to checking `name_map_regex`,
+    /// and then to using its actual name.
     name_map: Option<HashMap<String, String>>,
 
+    /// Map of regular expressions to custom names.
+    /// This allows a family of workspaces to share a custom name.
+    ///
+    /// Patterns are only checked if the workspace is not present in `name_map`,
+    /// and are tried in the order they are defined.
+    /// The first pattern to match the workspace's actual name is used.
+    ///
+    /// Custom names can be [images](images).
+    ///
+    /// **Default**: `{}`
+    ///
+    /// # Example
+    ///
+    /// ```corn
+    /// {
+    ///   type = "workspaces"
+    ///   name_map_regex.'^work-.*' = "icon:briefcase"
+    /// }
+    /// ```
+    #[serde(default)]
+    name_map_regex: IndexMap<String, String>,
+
+    /// The format string to use for each workspace's label.
+    /// For available tokens, see [below](#formatting-tokens).
+    ///
+    /// This is ignored for workspaces mapped to an image in `name_map`.
+    ///
+    /// **Default**: `{label}`
+    #[serde(default = "default_format")]
+    format: String,
+
     /// Workspaces which should always be shown.
     /// This can either be an array of workspace names,
     /// or a map of monitor names to arrays of workspace names.
@@ -126,21 +172,114 @@ pub struct WorkspacesModule {
     #[serde(default = "default_icon_size")]
     icon_size: i32,
 
+    /// The minimum width of each workspace button, in pixels.
+    ///
+    /// This applies to both text and image buttons,
+    /// and helps to prevent the bar shifting as labels change.
+    ///
+    /// **Default**: `null`
+    button_min_width: Option<i32>,
+
+    /// The maximum width of each workspace button's label, in characters.
+    /// Longer labels are ellipsized.
+    ///
+    /// Image buttons are unaffected, and are always rendered at `icon_size`.
+    ///
+    /// **Default**: `null`
+    button_max_width: Option<i32>,
+
+    /// Whether to pulse urgent workspaces.
+    ///
+    /// When enabled, the `urgent-pulse` class is toggled on a timer
+    /// while a workspace is urgent, which can be used to animate it.
+    /// The `urgent` class is set regardless.
+    ///
+    /// **Default**: `false`
+    #[serde(default = "crate::config::default_false")]
+    urgent_animation: bool,
+
+    /// Whether to also focus the most recently focused window
+    /// on a workspace when switching to it by clicking.
+    ///
+    /// This is only supported on Sway and Hyprland,
+    /// and has no effect on other compositors.
+    ///
+    /// **Default**: `false`
+    #[serde(default = "crate::config::default_false")]
+    focus_window_on_switch: bool,
+
     /// See [common options](module-level-options#common-options).
     #[serde(flatten)]
     pub common: Option<CommonConfig>,
 }
 
+fn default_format() -> String {
+    String::from("{label}")
+}
+
 const fn default_icon_size() -> i32 {
     32
 }
 
+#[derive(Debug, Clone)]
+pub enum WorkspaceEvent {
+    /// Focuses the workspace with this name.
+    Focus(String),
+    /// Moves the focused (or first) window of the app with this ID
+    /// to the workspace with this name.
+    #[cfg(feature = "launcher")]
+    MoveWindow { app_id: String, workspace: String },
+}
+
 #[derive(Debug, Clone)]
 pub struct WorkspaceItemContext {
     name_map: HashMap<String, String>,
+    name_patterns: Vec<(Regex, String)>,
+    format: String,
     icon_theme: IconTheme,
     icon_size: i32,
-    tx: mpsc::Sender<String>,
+    button_min_width: Option<i32>,
+    button_max_width: Option<i32>,
+    urgent_animation: bool,
+    capabilities: CompositorCapabilities,
+    tx: mpsc::Sender<WorkspaceEvent>,
+}
+
+impl WorkspaceItemContext {
+    /// Gets the label to display for a workspace.
+    ///
+    /// Exact matches in the name map take priority over patterns.
+    ///
+    /// Images from the name map are returned as-is,
+    /// otherwise the label is formatted using the configured format string.
+    fn format_label(&self, name: &str, windows: Option<u32>) -> String {
+        let label = self
+            .name_map
+            .get(name)
+            .or_else(|| {
+                self.name_patterns
+                    .iter()
+                    .find(|(pattern, _)| pattern.is_match(name))
+                    .map(|(_, label)| label)
+            })
+            .map_or(name, String::as_str);
+
+        if ImageProvider::is_definitely_image_input(label) {
+            return label.to_string();
+        }
+
+        self.format(label, name, windows)
+    }
+
+    /// Replaces the formatting tokens in the configured format string.
+    fn format(&self, label: &str, name: &str, windows: Option<u32>) -> String {
+        format::replace_tokens(&self.format, |token| match token {
+            "label" => Some(label.to_string()),
+            "name" => Some(name.to_string()),
+            "windows" => Some(windows.map(|count| count.to_string()).unwrap_or_default()),
+            _ => None,
+        })
+    }
 }
 
 /// Re-orders the container children alphabetically,
@@ -182,13 +321,13 @@ fn reorder_workspaces(container: &gtk::Box, sort_order: SortOrder) {
 
 impl Module<gtk::Box> for WorkspacesModule {
     type SendMessage = WorkspaceUpdate;
-    type ReceiveMessage = String;
+    type ReceiveMessage = WorkspaceEvent;
 
     module_impl!("workspaces");
 
     fn spawn_controller(
         &self,
-        _info: &ModuleInfo,
+        info: &ModuleInfo,
         context: &WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
         mut rx: mpsc::Receiver<Self::ReceiveMessage>,
     ) -> Result<()> {
@@ -208,14 +347,67 @@ impl Module<gtk::Box> for WorkspacesModule {
 
         let client = context.try_client::<dyn WorkspaceClient>()?;
 
+        let focus_window = self.focus_window_on_switch
+            && client
+                .capabilities()
+                .contains(CompositorCapabilities::FOCUS_LAST_WINDOW);
+
+        if self.focus_window_on_switch && !focus_window {
+            warn!("`focus_window_on_switch` is not supported by this compositor");
+        }
+
+        #[cfg(feature = "launcher")]
+        let wl = context.client::<wayland::Client>();
+
+        // workspaces are focused on this bar's output,
+        // for compositors with a set of workspaces per output.
+        let output_name = info.output_name.to_string();
+
         // Change workspace focus
         spawn(async move {
             trace!("Setting up UI event handler");
 
-            while let Some(name) = rx.recv().await {
-                if let Err(e) = client.focus(name.clone()) {
-                    warn!("Couldn't focus workspace '{name}': {e:#}");
-                };
+            while let Some(event) = rx.recv().await {
+                match event {
+                    WorkspaceEvent::Focus(name) => {
+                        if let Err(e) = client.focus_on_output(name.clone(), &output_name) {
+                            warn!("Couldn't focus workspace '{name}': {e:#}");
+                            continue;
+                        };
+
+                        if focus_window {
+                            if let Err(e) = client.focus_last_window(name.clone()) {
+                                warn!("Couldn't focus window on workspace '{name}': {e:#}");
+                            }
+                        }
+                    }
+                    #[cfg(feature = "launcher")]
+                    WorkspaceEvent::MoveWindow { app_id, workspace } => {
+                        let windows = wl
+                            .toplevel_info_all()
+                            .into_iter()
+                            .filter(|info| info.app_id == app_id)
+                            .collect::<Vec<_>>();
+
+                        let Some(window) = windows
+                            .iter()
+                            .find(|info| info.focused)
+                            .or_else(|| windows.first())
+                        else {
+                            warn!("Couldn't move window: no open windows for '{app_id}'");
+                            continue;
+                        };
+
+                        let target = WindowTarget {
+                            app_id: window.app_id.clone(),
+                            title: window.title.clone(),
+                        };
+
+                        if let Err(e) = client.move_window_to(&target, workspace.clone()) {
+                            warn!("Couldn't move window to workspace '{workspace}': {e:#}");
+                        }
+                    }
+                }
             }
 
             Ok::<(), Report>(())
@@ -233,12 +425,30 @@ impl Module<gtk::Box> for WorkspacesModule {
 
         let name_map = self.name_map.clone().unwrap_or_default();
 
+        let name_patterns = self
+            .name_map_regex
+            .iter()
+            .map(|(pattern, label)| {
+                Regex::new(pattern)
+                    .map(|regex| (regex, label.clone()))
+                    .wrap_err_with(|| format!("Invalid name_map_regex pattern '{pattern}'"))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
         let mut button_map = ButtonMap::new();
 
+        let capabilities = context.try_client::<dyn WorkspaceClient>()?.capabilities();
+
         let item_context = WorkspaceItemContext {
             name_map,
+            name_patterns,
+            format: self.format.clone(),
             icon_theme: info.icon_theme.clone(),
             icon_size: self.icon_size,
+            button_min_width: self.button_min_width,
+            button_max_width: self.button_max_width,
+            urgent_animation: self.urgent_animation,
+            capabilities,
             tx: context.controller_tx.clone(),
         };
 
@@ -251,6 +461,7 @@ impl Module<gtk::Box> for WorkspacesModule {
 
         for favorite in &favorites {
             let btn = Button::new(-1, favorite, OpenState::Closed, &item_context);
+            btn.set_occupied(Some(false));
             container.add(btn.button());
             button_map.insert(Identifier::Name(favorite.clone()), btn);
         }
@@ -260,11 +471,14 @@ impl Module<gtk::Box> for WorkspacesModule {
             let output_name = info.output_name.to_string();
 
             // keep track of whether init event has fired previously
-            // since it fires for every workspace subscriber
+            // since it fires for every workspace subscriber.
+            // this is reset when the client disconnects,
+            // so that the next init event resyncs the full state.
             let mut has_initialized = false;
 
             let add_workspace = {
                 let container = container.clone();
+                let item_context = item_context.clone();
                 move |workspace: Workspace, button_map: &mut ButtonMap| {
                     if favorites.contains(&workspace.name) {
                         let btn = button_map
@@ -274,6 +488,7 @@ impl Module<gtk::Box> for WorkspacesModule {
                         // set an ID to track the open workspace for the favourite
                         btn.set_workspace_id(workspace.id);
                         btn.set_open_state(workspace.visibility.into());
+                        btn.set_windows(workspace.windows, &item_context);
                     } else {
                         let btn = Button::new(
                             workspace.id,
@@ -281,6 +496,7 @@ impl Module<gtk::Box> for WorkspacesModule {
                             workspace.visibility.into(),
                             &item_context,
                         );
+                        btn.set_windows(workspace.windows, &item_context);
                         container.add(btn.button());
                         btn.button().show();
 
@@ -295,12 +511,16 @@ impl Module<gtk::Box> for WorkspacesModule {
                     // since favourites use name identifiers,
                     // we can safely remove using ID here and favourites will remain
                     if let Some(button) = button_map.remove(&Identifier::Id(id)) {
+                        // cancels the urgent pulse timer
+                        button.set_urgent(false);
                         container.remove(button.button());
                     } else {
                         // otherwise we do a deep search and use the button's cached ID
                         if let Some(button) = button_map.find_button_by_id_mut(id) {
+                            button.set_urgent(false);
                             button.set_workspace_id(-1);
                             button.set_open_state(OpenState::Closed);
+                            button.set_occupied(Some(false));
                         }
                     }
                 }
@@ -320,6 +540,19 @@ impl Module<gtk::Box> for WorkspacesModule {
                         return;
                     }
 
+                    container.style_context().remove_class("disconnected");
+
+                    // clear any workspaces left over from before a reconnect
+                    let stale_ids = button_map
+                        .values()
+                        .map(Button::workspace_id)
+                        .filter(|&id| id != -1)
+                        .collect::<Vec<_>>();
+
+                    for id in stale_ids {
+                        remove_workspace(id, &mut button_map);
+                    }
+
                     trace!("Creating workspace buttons");
 
                     for workspace in workspaces
@@ -378,23 +611,31 @@ impl Module<gtk::Box> for WorkspacesModule {
                     }
                 }
                 WorkspaceUpdate::Rename { id, name } => {
+                    if let Some(button) = button_map.find_button_by_id_mut(id) {
+                        button.set_name(&name, &item_context);
+                    }
+                }
+                WorkspaceUpdate::Urgent { id, urgent } => {
                     if let Some(button) = button_map
                         .get(&Identifier::Id(id))
-                        .or_else(|| button_map.get(&Identifier::Name(name.clone())))
-                        .map(Button::button)
+                        .or_else(|| button_map.find_button_by_id(id))
                     {
-                        button.set_label(&name);
-                        button.set_widget_name(&name);
+                        button.set_urgent(urgent);
                     }
                 }
-                WorkspaceUpdate::Urgent { id, urgent } => {
+                WorkspaceUpdate::WindowCount { id, count } => {
                     if let Some(button) = button_map
                         .get(&Identifier::Id(id))
                         .or_else(|| button_map.find_button_by_id(id))
                     {
-                        button.set_urgent(urgent);
+                        button.set_windows(Some(count), &item_context);
                     }
                 }
+                WorkspaceUpdate::Disconnected => {
+                    debug!("Workspace client disconnected");
+                    container.add_class("disconnected");
+                    has_initialized = false;
+                }
                 WorkspaceUpdate::Unknown => warn!("received unknown type workspace event"),
             };
 
@@ -1,22 +1,31 @@
-use glib::Propagation;
+use glib::{Propagation, SourceId};
 use std::cell::RefCell;
 use std::collections::HashMap;
 use std::rc::Rc;
+use std::time::Duration;
 
 use crate::clients::wayland::{OutputEvent, OutputEventType};
 use crate::config::BarPosition;
 use crate::gtk_helpers::{IronbarGtkExt, WidgetGeometry};
 use crate::modules::{ModuleInfo, ModulePopupParts, PopupButton};
 use crate::{glib_recv, rc_mut, Ironbar};
+use gtk::gdk::keys::constants as key;
+use gtk::gdk::{Monitor, NotifyType};
 use gtk::prelude::*;
-use gtk::{ApplicationWindow, Button, Orientation};
+use gtk::{ApplicationWindow, Button, DirectionType, EventBox, Orientation};
 use gtk_layer_shell::LayerShell;
 use tracing::{debug, trace};
 
+/// How long to wait after the cursor leaves a hover-triggered module
+/// before closing its popup, allowing the cursor to move onto the popup.
+const HOVER_HIDE_DELAY: Duration = Duration::from_millis(300);
+
 #[derive(Debug, Clone)]
 pub struct PopupCacheValue {
     pub name: String,
     pub content: ModulePopupParts,
+    /// The keyboard interactivity to use while this content is shown.
+    pub keyboard_mode: gtk_layer_shell::KeyboardMode,
 }
 
 #[derive(Debug, Clone)]
@@ -25,8 +34,13 @@ pub struct Popup {
     pub container_cache: Rc<RefCell<HashMap<usize, PopupCacheValue>>>,
     pub button_cache: Rc<RefCell<Vec<Button>>>,
     pos: BarPosition,
-    current_widget: Rc<RefCell<Option<(usize, usize)>>>,
+    gap: i32,
+    monitor: Monitor,
+    /// The widget the popup is open for,
+    /// and the button it is positioned against, if any.
+    current_widget: Rc<RefCell<Option<(usize, Option<usize>)>>>,
     output_size: Rc<RefCell<(i32, i32)>>,
+    pending_hide: Rc<RefCell<Option<SourceId>>>,
 }
 
 impl Popup {
@@ -38,6 +52,7 @@ impl Popup {
         module_info: &ModuleInfo,
         output_size: (i32, i32),
         gap: i32,
+        layer: gtk_layer_shell::Layer,
     ) -> Self {
         let pos = module_info.bar_position;
         let orientation = pos.orientation();
@@ -48,7 +63,7 @@ impl Popup {
 
         win.init_layer_shell();
         win.set_monitor(module_info.monitor);
-        win.set_layer(gtk_layer_shell::Layer::Overlay);
+        win.set_layer(layer);
         win.set_namespace(env!("CARGO_PKG_NAME"));
 
         win.set_layer_shell_margin(
@@ -109,6 +124,58 @@ impl Popup {
             Propagation::Proceed
         });
 
+        let current_widget = rc_mut!(None);
+
+        // keep hover-triggered popups open while the cursor is over them
+        let pending_hide = rc_mut!(None::<SourceId>);
+
+        {
+            let pending_hide = pending_hide.clone();
+
+            win.connect_enter_notify_event(move |_, _| {
+                if let Some(source) = pending_hide.borrow_mut().take() {
+                    source.remove();
+                }
+
+                Propagation::Proceed
+            });
+        }
+
+        // allow navigating the popup with the keyboard once it has focus.
+        // arrow keys move between widgets geometrically where possible,
+        // falling back to tab order so lists can be navigated in either orientation.
+        // the keyboard mode itself is set per module when shown.
+
+        {
+            let current_widget = current_widget.clone();
+
+            win.connect_key_press_event(move |win, event| {
+                let direction = match event.keyval() {
+                    key::Escape => {
+                        *current_widget.borrow_mut() = None;
+                        win.hide();
+                        return Propagation::Stop;
+                    }
+                    key::Up => DirectionType::Up,
+                    key::Down => DirectionType::Down,
+                    key::Left => DirectionType::Left,
+                    key::Right => DirectionType::Right,
+                    _ => return Propagation::Proceed,
+                };
+
+                if !win.child_focus(direction) {
+                    let fallback = match direction {
+                        DirectionType::Up | DirectionType::Left => DirectionType::TabBackward,
+                        _ => DirectionType::TabForward,
+                    };
+
+                    win.child_focus(fallback);
+                }
+
+                Propagation::Stop
+            });
+        }
+
         let output_size = rc_mut!(output_size);
 
         // respond to resolution changes
@@ -135,12 +202,21 @@ impl Popup {
             container_cache: rc_mut!(HashMap::new()),
             button_cache: rc_mut!(vec![]),
             pos,
-            current_widget: rc_mut!(None),
+            gap,
+            monitor: module_info.monitor.clone(),
+            current_widget,
             output_size,
+            pending_hide,
         }
     }
 
-    pub fn register_content(&self, key: usize, name: String, content: ModulePopupParts) {
+    pub fn register_content(
+        &self,
+        key: usize,
+        name: String,
+        content: ModulePopupParts,
+        keyboard_mode: gtk_layer_shell::KeyboardMode,
+    ) {
         debug!("Registered popup content for #{}", key);
 
         for button in &content.buttons {
@@ -162,7 +238,7 @@ impl Popup {
                 if container.is_visible() {
                     trace!("Resized:  {}x{}", rect.width(), rect.height());
 
-                    if let Some((widget_id, button_id)) = *current_widget.borrow() {
+                    if let Some((widget_id, Some(button_id))) = *current_widget.borrow() {
                         if let Some(PopupCacheValue { .. }) = cache.borrow().get(&widget_id) {
                             Self::set_position(
                                 &button_cache.borrow(),
@@ -180,21 +256,34 @@ impl Popup {
             .borrow_mut()
             .append(&mut content.buttons.clone());
 
-        self.container_cache
-            .borrow_mut()
-            .insert(key, PopupCacheValue { name, content });
+        self.container_cache.borrow_mut().insert(
+            key,
+            PopupCacheValue {
+                name,
+                content,
+                keyboard_mode,
+            },
+        );
     }
 
     pub fn show(&self, widget_id: usize, button_id: usize) {
         self.clear_window();
 
-        if let Some(PopupCacheValue { content, .. }) = self.container_cache.borrow().get(&widget_id)
+        if let Some(PopupCacheValue {
+            content,
+            keyboard_mode,
+            ..
+        }) = self.container_cache.borrow().get(&widget_id)
         {
-            *self.current_widget.borrow_mut() = Some((widget_id, button_id));
+            self.window.set_keyboard_mode(*keyboard_mode);
+            *self.current_widget.borrow_mut() = Some((widget_id, Some(button_id)));
 
             content.container.add_class("popup");
             self.window.add(&content.container);
 
+            self.window.set_exclusive_zone(0);
+            self.set_bar_margin(self.gap);
+
             self.window.show();
 
             Self::set_position(
@@ -210,11 +299,19 @@ impl Popup {
     pub fn show_at(&self, widget_id: usize, geometry: WidgetGeometry) {
         self.clear_window();
 
-        if let Some(PopupCacheValue { content, .. }) = self.container_cache.borrow().get(&widget_id)
+        if let Some(PopupCacheValue {
+            content,
+            keyboard_mode,
+            ..
+        }) = self.container_cache.borrow().get(&widget_id)
         {
+            self.window.set_keyboard_mode(*keyboard_mode);
             content.container.add_class("popup");
             self.window.add(&content.container);
 
+            self.window.set_exclusive_zone(0);
+            self.set_bar_margin(self.gap);
+
             self.window.show();
             Self::set_pos(
                 geometry,
@@ -225,6 +322,95 @@ impl Popup {
         }
     }
 
+    /// Shows the popup content for the widget at the current pointer position,
+    /// rather than next to its button.
+    ///
+    /// The popup is kept within the bounds of the output.
+    /// If the pointer is not over one of Ironbar's windows,
+    /// the popup is shown next to the widget's button instead.
+    pub fn show_at_pointer(&self, widget_id: usize) {
+        let button_id = self
+            .container_cache
+            .borrow()
+            .get(&widget_id)
+            .and_then(|value| value.content.buttons.first().map(PopupButton::popup_id));
+
+        // Wayland only reports the pointer position while it is over one of our surfaces,
+        // so the position is stale otherwise.
+        let pointer = self
+            .window
+            .display()
+            .default_seat()
+            .and_then(|seat| seat.pointer())
+            .filter(|pointer| pointer.window_at_position().0.is_some());
+
+        let Some(pointer) = pointer else {
+            if let Some(button_id) = button_id {
+                self.show(widget_id, button_id);
+            }
+
+            return;
+        };
+
+        self.clear_window();
+
+        if let Some(PopupCacheValue {
+            content,
+            keyboard_mode,
+            ..
+        }) = self.container_cache.borrow().get(&widget_id)
+        {
+            self.window.set_keyboard_mode(*keyboard_mode);
+            *self.current_widget.borrow_mut() = Some((widget_id, None));
+
+            content.container.add_class("popup");
+            self.window.add(&content.container);
+
+            // margins are relative to the screen edges rather than the bar,
+            // so ignore other surfaces' exclusive zones.
+            self.window.set_exclusive_zone(-1);
+
+            self.window.show();
+
+            let (_, x, y) = pointer.position();
+            let monitor = self.monitor.geometry();
+
+            let (width, height) = self.window.size();
+            let (screen_width, screen_height) = *self.output_size.borrow();
+
+            let x = (x - monitor.x()).clamp(0, (screen_width - width).max(0));
+            let y = (y - monitor.y()).clamp(0, (screen_height - height).max(0));
+
+            let (offset_edge, offset) = if self.pos.orientation() == Orientation::Horizontal {
+                (gtk_layer_shell::Edge::Left, x)
+            } else {
+                (gtk_layer_shell::Edge::Top, y)
+            };
+
+            self.window.set_layer_shell_margin(offset_edge, offset);
+
+            // distance from the bar's screen edge
+            self.set_bar_margin(match self.pos {
+                BarPosition::Top => y,
+                BarPosition::Bottom => screen_height - height - y,
+                BarPosition::Left => x,
+                BarPosition::Right => screen_width - width - x,
+            });
+        }
+    }
+
+    /// Sets the margin between the popup and the screen edge the bar is on.
+    fn set_bar_margin(&self, margin: i32) {
+        let edge = match self.pos {
+            BarPosition::Top => gtk_layer_shell::Edge::Top,
+            BarPosition::Bottom => gtk_layer_shell::Edge::Bottom,
+            BarPosition::Left => gtk_layer_shell::Edge::Left,
+            BarPosition::Right => gtk_layer_shell::Edge::Right,
+        };
+
+        self.window.set_layer_shell_margin(edge, margin);
+    }
+
     fn set_position(
         buttons: &[Button],
         button_id: usize,
@@ -248,10 +434,65 @@ impl Popup {
         }
     }
 
+    /// Shows the widget's popup when the cursor enters the container,
+    /// rather than waiting for a click.
+    ///
+    /// The popup is hidden after a short delay once the cursor leaves the container,
+    /// unless it moves onto the popup in the meantime.
+    pub fn connect_hover(&self, container: &EventBox, widget_id: usize, button_id: usize) {
+        {
+            let popup = self.clone();
+
+            container.connect_enter_notify_event(move |_, _| {
+                if let Some(source) = popup.pending_hide.borrow_mut().take() {
+                    source.remove();
+                }
+
+                if !popup.visible() || popup.current_widget() != Some(widget_id) {
+                    popup.hide();
+                    popup.show(widget_id, button_id);
+                }
+
+                Propagation::Proceed
+            });
+        }
+
+        let popup = self.clone();
+
+        container.connect_leave_notify_event(move |_, event| {
+            // moving onto a child widget is still hovering the container
+            if event.detail() == NotifyType::Inferior {
+                return Propagation::Proceed;
+            }
+
+            let source = {
+                let popup = popup.clone();
+
+                glib::timeout_add_local_once(HOVER_HIDE_DELAY, move || {
+                    popup.pending_hide.borrow_mut().take();
+
+                    if popup.current_widget() == Some(widget_id) {
+                        popup.hide();
+                    }
+                })
+            };
+
+            if let Some(source) = popup.pending_hide.borrow_mut().replace(source) {
+                source.remove();
+            }
+
+            Propagation::Proceed
+        });
+    }
+
     /// Hides the popup
     pub fn hide(&self) {
         *self.current_widget.borrow_mut() = None;
         self.window.hide();
+
+        // release any keyboard grab while nothing is shown
+        self.window
+            .set_keyboard_mode(gtk_layer_shell::KeyboardMode::None);
     }
 
     /// Checks if the popup is currently visible
@@ -1,40 +1,51 @@
+#[cfg(feature = "ipc")]
+use crate::ironvar::VariableManager;
 use crate::{glib_recv_mpsc, spawn, try_send};
 use color_eyre::{Help, Report};
 use gtk::ffi::GTK_STYLE_PROVIDER_PRIORITY_USER;
 use gtk::prelude::*;
 use gtk::{gdk, gio, Application, CssProvider, StyleContext};
+#[cfg(feature = "ipc")]
+use indexmap::IndexMap;
 use notify::event::ModifyKind;
 use notify::{recommended_watcher, Event, EventKind, RecursiveMode, Result, Watcher};
 use std::env;
-use std::path::PathBuf;
+use std::ffi::OsStr;
+use std::fs;
+use std::path::{Path, PathBuf};
 use std::time::Duration;
 use tokio::sync::mpsc;
 use tokio::time::sleep;
 use tracing::{debug, error, info};
 
-/// Attempts to load CSS file at the given path
-/// and attach if to the current GTK application.
+/// Attempts to load the stylesheets at the given paths
+/// and attach them to the current GTK application.
+///
+/// Each path can either be a CSS file,
+/// or a directory in which case every `.css` file inside it is loaded
+/// in alphabetical order.
+/// Stylesheets are loaded in the order given,
+/// so rules in later sheets take priority.
 ///
 /// Installs a file watcher and reloads CSS when
-/// write changes are detected on the file.
-pub fn load_css(style_path: PathBuf, application: Application) {
-    // file watcher requires absolute path
-    let style_path = if style_path.is_absolute() {
-        style_path
-    } else {
-        env::current_dir().expect("to exist").join(style_path)
-    };
+/// write changes are detected on any of the stylesheets.
+///
+/// Returns the provider the stylesheets were loaded into.
+pub fn load_css(style_paths: Vec<PathBuf>, application: Application) -> CssProvider {
+    // file watcher requires absolute paths
+    let style_paths = style_paths
+        .into_iter()
+        .map(|path| {
+            if path.is_absolute() {
+                path
+            } else {
+                env::current_dir().expect("to exist").join(path)
+            }
+        })
+        .collect::<Vec<_>>();
 
     let provider = CssProvider::new();
-
-    match provider.load_from_file(&gio::File::for_path(&style_path)) {
-        Ok(()) => debug!("Loaded css from '{}'", style_path.display()),
-        Err(err) => error!("{:?}", Report::new(err)
-                    .wrap_err("Failed to load CSS")
-                    .suggestion("Check the CSS file for errors")
-                    .suggestion("GTK CSS uses a subset of the full CSS spec and many properties are not available. Ensure you are not using any unsupported property.")
-                )
-    };
+    load_from_paths(&provider, &style_paths);
 
     let screen = gdk::Screen::default().expect("Failed to get default GTK screen");
     StyleContext::add_provider_for_screen(
@@ -45,45 +56,341 @@ pub fn load_css(style_path: PathBuf, application: Application) {
 
     let (tx, rx) = mpsc::channel(8);
 
-    spawn(async move {
-        let style_path2 = style_path.clone();
-        let mut watcher = recommended_watcher(move |res: Result<Event>| match res {
-            Ok(event) if matches!(event.kind, EventKind::Modify(ModifyKind::Data(_))) => {
-                debug!("{event:?}");
-                if event.paths.first().is_some_and(|p| p == &style_path2) {
-                    try_send!(tx, style_path2.clone());
+    {
+        let style_paths = style_paths.clone();
+
+        spawn(async move {
+            let style_paths2 = style_paths.clone();
+            let mut watcher = recommended_watcher(move |res: Result<Event>| match res {
+                Ok(event) if matches!(event.kind, EventKind::Modify(ModifyKind::Data(_))) => {
+                    debug!("{event:?}");
+                    if event
+                        .paths
+                        .iter()
+                        .any(|path| is_stylesheet(path, &style_paths2))
+                    {
+                        try_send!(tx, ());
+                    }
                 }
+                Err(e) => error!("Error occurred when watching stylesheet: {:?}", e),
+                _ => {}
+            })
+            .expect("Failed to create CSS file watcher");
+
+            for style_path in &style_paths {
+                let dir_path = if style_path.is_dir() {
+                    style_path.as_path()
+                } else {
+                    style_path.parent().expect("to exist")
+                };
+
+                watcher
+                    .watch(dir_path, RecursiveMode::NonRecursive)
+                    .expect("Failed to start CSS file watcher");
+                debug!("Installed CSS file watcher on '{}'", style_path.display());
             }
-            Err(e) => error!("Error occurred when watching stylesheet: {:?}", e),
-            _ => {}
+
+            // avoid watcher from dropping
+            loop {
+                sleep(Duration::from_secs(1)).await;
+            }
+        });
+    }
+
+    {
+        let provider = provider.clone();
+        glib_recv_mpsc!(rx, _event => reload_css(&provider, &style_paths, &application));
+    }
+
+    provider
+}
+
+/// Reloads the stylesheets at the given paths into an existing provider,
+/// and redraws all windows to apply the changes.
+pub fn reload_css(provider: &CssProvider, style_paths: &[PathBuf], application: &Application) {
+    info!("Reloading CSS");
+    if load_from_paths(provider, style_paths) {
+        for win in application.windows() {
+            win.queue_draw();
+        }
+    }
+}
+
+/// Checks whether the file has a supported stylesheet extension.
+///
+/// SCSS partials, whose names start with `_`, are not treated as stylesheets
+/// since they are only used through imports.
+fn is_stylesheet_extension(path: &Path) -> bool {
+    match path.extension().and_then(OsStr::to_str) {
+        Some("css") => true,
+        #[cfg(feature = "scss")]
+        Some("scss") => !path
+            .file_name()
+            .and_then(OsStr::to_str)
+            .is_some_and(|name| name.starts_with('_')),
+        _ => false,
+    }
+}
+
+/// Gets the stylesheet files for a path.
+///
+/// For directories, this is every stylesheet inside, sorted by name.
+fn stylesheet_files(style_path: &Path) -> Vec<PathBuf> {
+    if !style_path.is_dir() {
+        return vec![style_path.to_path_buf()];
+    }
+
+    let mut files = fs::read_dir(style_path)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| path.is_file() && is_stylesheet_extension(path))
+                .collect::<Vec<_>>()
         })
-        .expect("Failed to create CSS file watcher");
+        .unwrap_or_else(|err| {
+            error!(
+                "Failed to read CSS directory '{}': {err}",
+                style_path.display()
+            );
+            vec![]
+        });
+
+    files.sort();
+    files
+}
+
+/// Checks whether the path is one of the stylesheets,
+/// or a stylesheet inside one of the directories.
+///
+/// Changes to any SCSS file next to an SCSS stylesheet are included,
+/// as these are likely to be imported by it.
+fn is_stylesheet(path: &Path, style_paths: &[PathBuf]) -> bool {
+    let is_scss =
+        |path: &Path| cfg!(feature = "scss") && path.extension() == Some(OsStr::new("scss"));
+
+    style_paths.iter().any(|style_path| {
+        let dir = if style_path.is_dir() {
+            Some(style_path.as_path())
+        } else {
+            style_path.parent().filter(|_| is_scss(style_path))
+        };
 
-        let dir_path = style_path.parent().expect("to exist");
+        path == style_path
+            || (dir.is_some_and(|dir| path.parent() == Some(dir))
+                && (is_stylesheet_extension(path) || is_scss(path)))
+    })
+}
+
+/// Loads the stylesheets at the given paths into the provider,
+/// logging any errors against the file they occurred in.
+///
+/// Invalid files are skipped, so the remaining sheets still apply.
+///
+/// Returns `true` if loading succeeded.
+fn load_from_paths(provider: &CssProvider, style_paths: &[PathBuf]) -> bool {
+    let files = style_paths
+        .iter()
+        .flat_map(|style_path| stylesheet_files(style_path))
+        .collect::<Vec<_>>();
+
+    #[cfg(feature = "scss")]
+    let files = {
+        let compiled = files
+            .iter()
+            .map(|file| compile_scss(file))
+            .collect::<Option<Vec<_>>>();
 
-        watcher
-            .watch(dir_path, RecursiveMode::NonRecursive)
-            .expect("Failed to start CSS file watcher");
-        debug!("Installed CSS file watcher on '{}'", style_path.display());
+        // keep the previous stylesheet active until every file compiles
+        let Some(files) = compiled else {
+            return false;
+        };
 
-        // avoid watcher from dropping
-        loop {
-            sleep(Duration::from_secs(1)).await;
+        files
+    };
+
+    // a single file is loaded directly to avoid parsing it twice
+    if let [file] = files.as_slice() {
+        return load_from_file(provider, file);
+    }
+
+    // each file is checked on its own first,
+    // as errors from the combined sheet do not say which file they came from.
+    // the valid files are then imported in order,
+    // so relative urls continue to resolve against each file.
+    let css = files
+        .iter()
+        .filter(|file| load_from_file(&CssProvider::new(), file))
+        .map(|file| format!("@import url(\"{}\");\n", gio::File::for_path(file).uri()))
+        .collect::<String>();
+
+    match provider.load_from_data(css.as_bytes()) {
+        Ok(()) => true,
+        Err(err) => {
+            error!("{:?}", Report::new(err).wrap_err("Failed to load CSS"));
+            false
         }
-    });
+    }
+}
 
-    glib_recv_mpsc!(rx, path => {
-        info!("Reloading CSS");
-        if let Err(err) = provider.load_from_file(&gio::File::for_path(path)) {
+/// Compiles the file if it is an SCSS stylesheet,
+/// writing the output to a CSS file in the runtime directory.
+///
+/// Returns the path to the CSS file to load,
+/// or `None` if compilation failed.
+#[cfg(feature = "scss")]
+fn compile_scss(style_path: &Path) -> Option<PathBuf> {
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{Hash, Hasher};
+
+    if style_path.extension() != Some(OsStr::new("scss")) {
+        return Some(style_path.to_path_buf());
+    }
+
+    let css = match grass::from_path(style_path, &grass::Options::default()) {
+        Ok(css) => css,
+        Err(err) => {
+            error!(
+                "{:?}",
+                Report::msg(err.to_string()).wrap_err(format!(
+                    "Failed to compile SCSS file '{}'",
+                    style_path.display()
+                ))
+            );
+            return None;
+        }
+    };
+
+    // name the output after the source path so multiple sheets do not collide
+    let mut hasher = DefaultHasher::new();
+    style_path.hash(&mut hasher);
+
+    let out_dir = dirs::runtime_dir()
+        .unwrap_or_else(env::temp_dir)
+        .join("ironbar");
+    let out_path = out_dir.join(format!("{:x}.css", hasher.finish()));
+
+    match fs::create_dir_all(&out_dir).and_then(|()| fs::write(&out_path, css)) {
+        Ok(()) => {
+            debug!(
+                "Compiled '{}' to '{}'",
+                style_path.display(),
+                out_path.display()
+            );
+            Some(out_path)
+        }
+        Err(err) => {
+            error!(
+                "{:?}",
+                Report::new(err).wrap_err(format!(
+                    "Failed to write compiled SCSS to '{}'",
+                    out_path.display()
+                ))
+            );
+            None
+        }
+    }
+}
+
+/// Loads the CSS file at the given path into the provider,
+/// logging any errors.
+///
+/// Returns `true` if loading succeeded.
+fn load_from_file(provider: &CssProvider, style_path: &Path) -> bool {
+    match provider.load_from_file(&gio::File::for_path(style_path)) {
+        Ok(()) => {
+            debug!("Loaded css from '{}'", style_path.display());
+            true
+        }
+        Err(err) => {
             error!("{:?}", Report::new(err)
-                .wrap_err("Failed to load CSS")
+                .wrap_err(format!("Failed to load CSS file '{}'", style_path.display()))
                 .suggestion("Check the CSS file for errors")
                 .suggestion("GTK CSS uses a subset of the full CSS spec and many properties are not available. Ensure you are not using any unsupported property.")
             );
-        } else {
-            for win in application.windows() {
-                win.queue_draw();
-            }
+            false
+        }
+    }
+}
+
+/// Named colours which can be set at runtime over IPC.
+///
+/// These are written as `@define-color` rules into their own provider,
+/// which takes priority over the user's stylesheets.
+/// Stylesheets can reference them as normal, for example `@accent`.
+#[cfg(feature = "ipc")]
+#[derive(Debug)]
+pub struct StyleVariables {
+    provider: CssProvider,
+    colors: IndexMap<String, String>,
+}
+
+#[cfg(feature = "ipc")]
+impl Default for StyleVariables {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "ipc")]
+impl StyleVariables {
+    pub fn new() -> Self {
+        let provider = CssProvider::new();
+
+        let screen = gdk::Screen::default().expect("Failed to get default GTK screen");
+        StyleContext::add_provider_for_screen(
+            &screen,
+            &provider,
+            GTK_STYLE_PROVIDER_PRIORITY_USER as u32 + 1,
+        );
+
+        Self {
+            provider,
+            colors: IndexMap::new(),
+        }
+    }
+
+    /// Sets the colour with this name, or removes it if no value is given,
+    /// and reloads the provider.
+    ///
+    /// If the new value is invalid, the previous value is kept.
+    pub fn set(&mut self, name: String, value: Option<String>) -> color_eyre::Result<()> {
+        if name.is_empty() || !VariableManager::is_identifier(&name) {
+            return Err(Report::msg("Invalid colour name"));
+        }
+
+        if value
+            .as_deref()
+            .is_some_and(|value| value.contains([';', '{', '}']))
+        {
+            return Err(Report::msg("Invalid colour value"));
+        }
+
+        let previous = match value {
+            Some(value) => self.colors.insert(name.clone(), value),
+            None => self.colors.shift_remove(&name),
+        };
+
+        if let Err(err) = self.provider.load_from_data(self.to_css().as_bytes()) {
+            match previous {
+                Some(previous) => self.colors.insert(name, previous),
+                None => self.colors.shift_remove(&name),
+            };
+
+            // restore the last valid state
+            self.provider
+                .load_from_data(self.to_css().as_bytes())
+                .map_err(Report::new)?;
+
+            return Err(Report::new(err).wrap_err("Failed to set colour"));
         }
-    });
+
+        Ok(())
+    }
+
+    fn to_css(&self) -> String {
+        self.colors
+            .iter()
+            .map(|(name, value)| format!("@define-color {name} {value};\n"))
+            .collect()
+    }
 }